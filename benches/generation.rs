@@ -0,0 +1,149 @@
+//! Throughput of the generation strategies.
+//!
+//! Run with `cargo bench`, adding `--features tokio` for the async group.
+//! Every generator here reads the system clock, so single-ID throughput tops
+//! out at one tick's sequence space per millisecond; the numbers show how
+//! close each strategy gets to that ceiling and how it degrades under
+//! contention.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use snowflake_id::{ShardedSnowflakeGenerator, SnowflakeGenerator, SnowflakeGenerator128};
+use std::hint::black_box;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const THREADS: [usize; 3] = [2, 4, 8];
+const IDS_PER_THREAD: u64 = 1_000;
+
+fn single_thread(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single_thread");
+    group.throughput(Throughput::Elements(1));
+
+    let generator = SnowflakeGenerator::new(1).unwrap();
+    group.bench_function("mutex", |b| {
+        b.iter(|| black_box(generator.generate().unwrap()))
+    });
+
+    let sharded = ShardedSnowflakeGenerator::new(1, 4).unwrap();
+    group.bench_function("sharded", |b| {
+        b.iter(|| black_box(sharded.next_id(thread::sleep)))
+    });
+
+    let wide = SnowflakeGenerator128::new(1);
+    group.bench_function("128_bit", |b| {
+        b.iter(|| black_box(wide.generate().unwrap()))
+    });
+
+    group.finish();
+}
+
+fn bulk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bulk");
+    let generator = SnowflakeGenerator::new(1).unwrap();
+
+    for count in [100, 10_000] {
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new("generate_bulk", count), &count, |b, &n| {
+            b.iter(|| black_box(generator.generate_bulk(n).unwrap()))
+        });
+        group.bench_with_input(BenchmarkId::new("reserve_block", count), &count, |b, &n| {
+            b.iter(|| black_box(generator.reserve_block(n, thread::sleep).unwrap()))
+        });
+    }
+
+    group.finish();
+}
+
+/// Times `threads` threads each calling `next` [`IDS_PER_THREAD`] times
+fn contended_run(iters: u64, threads: usize, next: impl Fn() + Sync) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..iters {
+        let start = Instant::now();
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| (0..IDS_PER_THREAD).for_each(|_| next()));
+            }
+        });
+        total += start.elapsed();
+    }
+    total
+}
+
+fn contended(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contended");
+
+    for threads in THREADS {
+        group.throughput(Throughput::Elements(threads as u64 * IDS_PER_THREAD));
+
+        let generator = SnowflakeGenerator::new(1).unwrap();
+        group.bench_with_input(BenchmarkId::new("mutex", threads), &threads, |b, &n| {
+            b.iter_custom(|iters| {
+                contended_run(iters, n, || {
+                    black_box(generator.generate().unwrap());
+                })
+            })
+        });
+
+        let sharded = ShardedSnowflakeGenerator::new(1, threads).unwrap();
+        group.bench_with_input(BenchmarkId::new("sharded", threads), &threads, |b, &n| {
+            b.iter_custom(|iters| {
+                contended_run(iters, n, || {
+                    black_box(sharded.next_id(thread::sleep));
+                })
+            })
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(feature = "tokio")]
+fn async_generation(c: &mut Criterion) {
+    use snowflake_id::AsyncSnowflakeGenerator;
+    use std::sync::Arc;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("async");
+
+    let generator = Arc::new(AsyncSnowflakeGenerator::new(1).unwrap());
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("next_id", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { black_box(generator.next_id().await) })
+    });
+
+    for tasks in THREADS {
+        group.throughput(Throughput::Elements(tasks as u64 * IDS_PER_THREAD));
+        group.bench_with_input(BenchmarkId::new("contended", tasks), &tasks, |b, &n| {
+            b.to_async(&runtime).iter(|| {
+                let generator = Arc::clone(&generator);
+                async move {
+                    let handles: Vec<_> = (0..n)
+                        .map(|_| {
+                            let generator = Arc::clone(&generator);
+                            tokio::spawn(async move {
+                                for _ in 0..IDS_PER_THREAD {
+                                    black_box(generator.next_id().await);
+                                }
+                            })
+                        })
+                        .collect();
+                    for handle in handles {
+                        handle.await.unwrap();
+                    }
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(not(feature = "tokio"))]
+fn async_generation(_: &mut Criterion) {}
+
+criterion_group!(benches, single_thread, bulk, contended, async_generation);
+criterion_main!(benches);
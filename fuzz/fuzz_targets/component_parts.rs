@@ -0,0 +1,32 @@
+//! Builds IDs from arbitrary components and checks that components within
+//! the layout come back out unchanged.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snowflake_id::{Snowflake, SnowflakeId, SnowflakeId128};
+
+fn mask(value: u64, bits: u64) -> u64 {
+    value & ((1u64 << bits) - 1)
+}
+
+fuzz_target!(|data: [u64; 3]| {
+    let [timestamp, machine_id, sequence] = data;
+
+    // Out-of-range components must not panic, whatever they produce
+    let _ = SnowflakeId::from_component_parts(timestamp, machine_id, sequence);
+
+    let timestamp = mask(timestamp, SnowflakeId::timestamp_bits());
+    let machine_id = mask(machine_id, SnowflakeId::machine_id_bits());
+    let sequence = mask(sequence, SnowflakeId::sequence_bits());
+    let id = SnowflakeId::from_component_parts(timestamp, machine_id, sequence);
+    assert!(id.is_valid());
+    assert_eq!(Snowflake::timestamp(&id), timestamp);
+    assert_eq!(Snowflake::machine_id(&id), machine_id);
+    assert_eq!(Snowflake::sequence(&id), sequence);
+
+    let wide = SnowflakeId128::from_parts(timestamp, machine_id as u32, sequence as u32);
+    assert_eq!(wide.timestamp(), timestamp);
+    assert_eq!(wide.machine_id(), machine_id as u32);
+    assert_eq!(wide.sequence(), sequence as u32);
+});
@@ -0,0 +1,31 @@
+//! Deserializes arbitrary JSON through the ID types and the `serde` field
+//! adapters, which see untrusted request bodies.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde::Deserialize;
+use snowflake_id::{SnowflakeId, SnowflakeId128};
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Adapters {
+    #[serde(with = "snowflake_id::serde::exact_number")]
+    exact: SnowflakeId,
+    #[serde(with = "snowflake_id::serde::as_i64")]
+    number: SnowflakeId,
+    #[serde(with = "snowflake_id::serde::as_string")]
+    string: SnowflakeId,
+    #[serde(with = "snowflake_id::serde::fixed_le")]
+    bytes: SnowflakeId,
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(id) = serde_json::from_slice::<SnowflakeId>(data) {
+        let json = serde_json::to_vec(&id).unwrap();
+        assert_eq!(serde_json::from_slice::<SnowflakeId>(&json).unwrap(), id);
+    }
+    let _ = serde_json::from_slice::<SnowflakeId128>(data);
+    let _ = serde_json::from_slice::<Vec<SnowflakeId>>(data);
+    let _ = serde_json::from_slice::<Adapters>(data);
+});
@@ -0,0 +1,28 @@
+//! Parses arbitrary text as every ID type and checks that accepted input
+//! survives a Display round-trip.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snowflake_id::obfuscate::ObfuscatedSnowflakeId;
+use snowflake_id::{Epoch, SnowflakeId, SnowflakeId128, TypedSnowflakeId};
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+fn round_trip<T: FromStr + Display + PartialEq + Debug>(input: &str) {
+    if let Ok(value) = input.parse::<T>() {
+        let reparsed = value.to_string().parse::<T>().ok();
+        assert_eq!(reparsed.as_ref(), Some(&value));
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    round_trip::<SnowflakeId>(input);
+    round_trip::<SnowflakeId128>(input);
+    round_trip::<TypedSnowflakeId<()>>(input);
+    round_trip::<ObfuscatedSnowflakeId>(input);
+    round_trip::<Epoch>(input);
+});
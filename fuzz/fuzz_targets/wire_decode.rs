@@ -0,0 +1,18 @@
+//! Decodes arbitrary bytes as a wire batch and checks that accepted batches
+//! re-encode to something that decodes the same.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snowflake_id::wire;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = wire::batch_fingerprint(data);
+    if let Ok((fingerprint, ids)) = wire::decode_batch_with_fingerprint(data) {
+        let encoded = match fingerprint {
+            Some(fingerprint) => wire::encode_batch_with_fingerprint(&ids, &fingerprint),
+            None => wire::encode_batch(&ids),
+        };
+        assert_eq!(wire::decode_batch(&encoded).unwrap(), ids);
+    }
+});
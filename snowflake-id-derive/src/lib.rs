@@ -0,0 +1,209 @@
+//! `#[derive(Snowflake)]` for `snowflake-id`. Use it through the `derive`
+//! feature of `snowflake-id`, which re-exports the macro; the generated code
+//! refers to that crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitInt, Type};
+
+const DEFAULT_TIMESTAMP_BITS: u64 = 41;
+const DEFAULT_MACHINE_ID_BITS: u64 = 10;
+const DEFAULT_SEQUENCE_BITS: u64 = 12;
+
+/// Implements `Snowflake`, `Display`, `FromStr`, serde and conversions to and
+/// from the inner integer for a newtype over `i64` or `u64`.
+///
+/// Bit widths default to the standard 41/10/12 layout and can be set with
+/// `#[snowflake(timestamp_bits = .., machine_id_bits = .., sequence_bits = ..)]`.
+#[proc_macro_derive(Snowflake, attributes(snowflake))]
+pub fn derive_snowflake(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct Layout {
+    timestamp_bits: u64,
+    machine_id_bits: u64,
+    sequence_bits: u64,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let inner = newtype_field(&input)?;
+    let signed = if inner_is(inner, "i64") {
+        true
+    } else if inner_is(inner, "u64") {
+        false
+    } else {
+        return Err(Error::new_spanned(
+            inner,
+            "#[derive(Snowflake)] needs a newtype over i64 or u64",
+        ));
+    };
+
+    let layout = parse_layout(&input)?;
+    let total = layout.timestamp_bits + layout.machine_id_bits + layout.sequence_bits;
+    let available = if signed { 63 } else { 64 };
+    if total > available {
+        return Err(Error::new(
+            Span::call_site(),
+            format!(
+                "snowflake layout needs {} bits but {} has {} available",
+                total,
+                if signed { "i64" } else { "u64" },
+                available
+            ),
+        ));
+    }
+
+    let Layout {
+        timestamp_bits,
+        machine_id_bits,
+        sequence_bits,
+    } = layout;
+    let krate = quote!(::snowflake_id);
+
+    Ok(quote! {
+        impl #krate::Snowflake for #name {
+            fn from_component_parts(timestamp_offset: u64, machine_id: u64, sequence: u64) -> Self {
+                let id = (timestamp_offset << (#machine_id_bits + #sequence_bits))
+                    | (machine_id << #sequence_bits)
+                    | sequence;
+                #name(id as #inner)
+            }
+
+            fn id(&self) -> u64 {
+                self.0 as u64
+            }
+
+            fn timestamp_bits() -> u64 {
+                #timestamp_bits
+            }
+
+            fn machine_id_bits() -> u64 {
+                #machine_id_bits
+            }
+
+            fn sequence_bits() -> u64 {
+                #sequence_bits
+            }
+        }
+
+        impl ::core::fmt::Display for #name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::core::fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl ::core::convert::TryFrom<#inner> for #name {
+            type Error = #krate::error::SnowflakeError;
+
+            fn try_from(value: #inner) -> ::core::result::Result<Self, Self::Error> {
+                let id = #name(value);
+                if #krate::Snowflake::is_valid(&id) {
+                    Ok(id)
+                } else {
+                    Err(#krate::error::SnowflakeError::InvalidId(format!(
+                        "{} doesn't fit a {}/{}/{}-bit snowflake layout",
+                        value, #timestamp_bits, #machine_id_bits, #sequence_bits
+                    )))
+                }
+            }
+        }
+
+        impl ::core::convert::From<#name> for #inner {
+            fn from(id: #name) -> Self {
+                id.0
+            }
+        }
+
+        impl ::core::str::FromStr for #name {
+            type Err = #krate::error::SnowflakeError;
+
+            fn from_str(s: &str) -> ::core::result::Result<Self, Self::Err> {
+                let value = s.parse::<#inner>().map_err(|e| {
+                    #krate::error::SnowflakeError::Unparseable {
+                        input: s.to_string(),
+                        reason: e.to_string(),
+                    }
+                })?;
+                <Self as ::core::convert::TryFrom<#inner>>::try_from(value)
+            }
+        }
+
+        impl #krate::derive_support::serde::Serialize for #name {
+            fn serialize<S>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error>
+            where
+                S: #krate::derive_support::serde::Serializer,
+            {
+                #krate::derive_support::serialize(self.0, serializer)
+            }
+        }
+
+        impl<'de> #krate::derive_support::serde::Deserialize<'de> for #name {
+            fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>
+            where
+                D: #krate::derive_support::serde::Deserializer<'de>,
+            {
+                let value: #inner = #krate::derive_support::deserialize(deserializer)?;
+                <Self as ::core::convert::TryFrom<#inner>>::try_from(value)
+                    .map_err(#krate::derive_support::serde::de::Error::custom)
+            }
+        }
+    })
+}
+
+fn newtype_field(input: &DeriveInput) -> syn::Result<&Type> {
+    if let Data::Struct(data) = &input.data {
+        if let Fields::Unnamed(fields) = &data.fields {
+            if fields.unnamed.len() == 1 {
+                return Ok(&fields.unnamed[0].ty);
+            }
+        }
+    }
+    Err(Error::new_spanned(
+        &input.ident,
+        "#[derive(Snowflake)] needs a tuple struct with a single field",
+    ))
+}
+
+fn inner_is(ty: &Type, name: &str) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident(name))
+}
+
+fn parse_layout(input: &DeriveInput) -> syn::Result<Layout> {
+    let mut layout = Layout {
+        timestamp_bits: DEFAULT_TIMESTAMP_BITS,
+        machine_id_bits: DEFAULT_MACHINE_ID_BITS,
+        sequence_bits: DEFAULT_SEQUENCE_BITS,
+    };
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("snowflake"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let field = if meta.path.is_ident("timestamp_bits") {
+                &mut layout.timestamp_bits
+            } else if meta.path.is_ident("machine_id_bits") {
+                &mut layout.machine_id_bits
+            } else if meta.path.is_ident("sequence_bits") {
+                &mut layout.sequence_bits
+            } else {
+                return Err(
+                    meta.error("expected `timestamp_bits`, `machine_id_bits` or `sequence_bits`")
+                );
+            };
+            let bits: LitInt = meta.value()?.parse()?;
+            *field = bits.base10_parse()?;
+            if *field >= 64 {
+                return Err(Error::new_spanned(bits, "bit widths must be below 64"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(layout)
+}
@@ -0,0 +1,109 @@
+//! actix-web integration for [`SnowflakeId`] and [`SnowflakeError`].
+//!
+//! `SnowflakeId` deserializes from path segments and query strings as-is, so
+//! `web::Path<SnowflakeId>` and `web::Query<T>` work without wrappers.
+//! actix-web answers a path that fails to deserialize with `404`; register
+//! [`path_config`] to reject malformed IDs with a `400` instead:
+//!
+//! ```
+//! use actix_web::{web, App, HttpResponse};
+//! use snowflake_id::{actix_extras, SnowflakeId};
+//!
+//! async fn get_event(id: web::Path<SnowflakeId>) -> HttpResponse {
+//!     HttpResponse::Ok().body(id.to_string())
+//! }
+//!
+//! let app = App::new()
+//!     .app_data(actix_extras::path_config())
+//!     .route("/events/{id}", web::get().to(get_event));
+//! ```
+//!
+//! `SnowflakeError` implements `ResponseError`, so handlers can return it
+//! with `?`: invalid input maps to `400`, transient generator conditions to
+//! `503` and everything else to `500`.
+//!
+//! [`SnowflakeId`]: crate::SnowflakeId
+
+use crate::error::SnowflakeError;
+use actix_web::error::{self, ResponseError};
+use actix_web::http::StatusCode;
+use actix_web::web::PathConfig;
+
+impl ResponseError for SnowflakeError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.http_status()).expect("valid status code")
+    }
+}
+
+/// Returns a [`PathConfig`] that rejects path segments that fail to
+/// deserialize, such as malformed IDs, with `400 Bad Request`
+pub fn path_config() -> PathConfig {
+    PathConfig::default().error_handler(|err, _| error::ErrorBadRequest(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeId;
+
+    use actix_web::{test, web, App};
+
+    #[derive(::serde::Deserialize)]
+    struct Page {
+        after: Option<SnowflakeId>,
+    }
+
+    async fn call(uri: &str) -> (StatusCode, String) {
+        let app = test::init_service(
+            App::new()
+                .app_data(crate::actix_extras::path_config())
+                .route(
+                    "/events/{id}",
+                    web::get().to(|id: web::Path<SnowflakeId>| async move { id.to_string() }),
+                )
+                .route(
+                    "/events",
+                    web::get()
+                        .to(|page: web::Query<Page>| async move { format!("{:?}", page.after) }),
+                )
+                .route(
+                    "/fail",
+                    web::get()
+                        .to(|| async { Err::<String, _>(SnowflakeError::InvalidId("bad".into())) }),
+                ),
+        )
+        .await;
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        let status = response.status();
+        let body = test::read_body(response).await;
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[actix_web::test]
+    async fn test_extractors() {
+        assert_eq!(
+            call("/events/123456789012345678").await,
+            (StatusCode::OK, "123456789012345678".to_string())
+        );
+        assert_eq!(
+            call("/events?after=42").await,
+            (StatusCode::OK, "Some(SnowflakeId(42))".to_string())
+        );
+
+        let (status, body) = call("/events/abc").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("\"abc\""), "{}", body);
+
+        let (status, body) = call("/events?after=-1").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("cannot be negative"), "{}", body);
+    }
+
+    #[actix_web::test]
+    async fn test_error_response() {
+        let (status, body) = call("/fail").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, SnowflakeError::InvalidId("bad".into()).to_string());
+    }
+}
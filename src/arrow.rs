@@ -0,0 +1,88 @@
+//! Moving ID columns in and out of Apache Arrow.
+//!
+//! IDs are stored as Arrow's `Int64` type, which Parquet writes as a plain
+//! `INT64` column. [`to_int64_array`] and [`from_int64_array`] convert
+//! between that and `Vec<SnowflakeId>`; [`timestamp_array`] derives a
+//! millisecond creation-time column from an ID column so the dataset can be
+//! partitioned or filtered by time without decoding IDs row by row.
+//!
+//! # Example
+//! ```
+//! use arrow_array::Array;
+//! use snowflake_id::arrow::{from_int64_array, timestamp_array, to_int64_array};
+//! use snowflake_id::{Epoch, SnowflakeId};
+//!
+//! let ids = vec![SnowflakeId::new(175928847299117063).unwrap()];
+//! let column = to_int64_array(&ids);
+//! assert_eq!(from_int64_array(&column).unwrap(), ids);
+//!
+//! let created = timestamp_array(&column, Epoch::DISCORD);
+//! assert_eq!(created.value(0), 1462015105796);
+//! assert_eq!(created.timezone(), Some("UTC"));
+//! ```
+
+use crate::defs::TIMESTAMP_SHIFT;
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::SnowflakeId;
+use arrow_array::{Array, Int64Array, TimestampMillisecondArray};
+
+/// Builds an `Int64` column from `ids`
+pub fn to_int64_array(ids: &[SnowflakeId]) -> Int64Array {
+    ids.iter().map(|id| id.id()).collect::<Vec<_>>().into()
+}
+
+/// Reads `array` back into IDs, failing on nulls and negative values
+pub fn from_int64_array(array: &Int64Array) -> Result<Vec<SnowflakeId>, SnowflakeError> {
+    if array.null_count() > 0 {
+        return Err(SnowflakeError::InvalidId(format!(
+            "ID column has {} nulls",
+            array.null_count()
+        )));
+    }
+    array
+        .values()
+        .iter()
+        .map(|&v| SnowflakeId::new(v))
+        .collect()
+}
+
+/// Derives a UTC `Timestamp(Millisecond)` column from an ID column,
+/// resolving timestamps against `epoch`. Null IDs give null timestamps.
+pub fn timestamp_array(ids: &Int64Array, epoch: impl Into<Epoch>) -> TimestampMillisecondArray {
+    let epoch = epoch.into().as_millis();
+    let mask = SnowflakeId::timestamp_mask();
+    ids.unary::<_, arrow_array::types::TimestampMillisecondType>(|id| {
+        ((id as u64 >> TIMESTAMP_SHIFT) & mask) as i64 + epoch
+    })
+    .with_timezone("UTC")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompose_batch;
+    use crate::SnowflakeGenerator;
+
+    #[test]
+    fn test_arrow_columns() {
+        use arrow_array::{Array, Int64Array};
+
+        let generator = SnowflakeGenerator::new(9).unwrap();
+        let ids = generator.generate_bulk(100).unwrap();
+        let column = to_int64_array(&ids);
+        assert_eq!(column.len(), ids.len());
+        assert_eq!(from_int64_array(&column).unwrap(), ids);
+
+        let created = timestamp_array(&column, Epoch::TWITTER);
+        assert_eq!(
+            created.values().to_vec(),
+            decompose_batch(&ids, Epoch::TWITTER).timestamps
+        );
+
+        let with_null = Int64Array::from(vec![Some(ids[0].id()), None]);
+        assert!(from_int64_array(&with_null).is_err());
+        assert!(timestamp_array(&with_null, Epoch::TWITTER).is_null(1));
+        assert!(from_int64_array(&Int64Array::from(vec![-1])).is_err());
+    }
+}
@@ -1,22 +1,39 @@
-use crate::defs::CLOCK_BACKWARDS_TOLERANCE_MS;
+use crate::clock::{Clock, ClockHealth, SystemClock};
+use crate::epoch::Epoch;
 use crate::error::SnowflakeError;
-use crate::generator::SnowflakeOperation;
+use crate::generator::{
+    CheckpointStore, Checkpointing, ClockCache, GeneratorCore, GeneratorState, SnowflakeOperation,
+};
+use crate::parts::SnowflakeParts;
+use crate::rt::Mutex;
 use crate::snowflake::Snowflake;
-use chrono::Utc;
-use std::marker::PhantomData;
+#[cfg(feature = "stats")]
+use crate::stats::GeneratorStats;
+use crate::wait::{SleepWait, WaitStrategy};
+use futures_core::Stream;
+use futures_util::future::{self, Either};
+use std::pin::pin;
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Mutex;
+#[cfg(feature = "stats")]
+use std::time::Instant;
 
-struct GeneratorState {
-    last_timestamp: i64,
-    sequence: u64,
+/// Request priority used for load shedding, see
+/// [`AsyncSnowflakeGenerator::with_load_shedding`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Priority {
+    /// Always served, waiting through sequence exhaustion if necessary
+    #[default]
+    High,
+    /// Rejected with [`SnowflakeError::Overloaded`] while the generator is saturated
+    Low,
 }
 
 pub struct AsyncSnowflakeGenerator<S: Snowflake> {
-    machine_id: u64,
+    pub(crate) core: GeneratorCore<S>,
     state: Mutex<GeneratorState>,
-    epoch: i64,
-    _marker: PhantomData<S>,
+    shed_after_ticks: Option<u32>,
+    wait: Arc<dyn WaitStrategy>,
 }
 
 impl<S: Snowflake> AsyncSnowflakeGenerator<S> {
@@ -24,91 +41,544 @@ impl<S: Snowflake> AsyncSnowflakeGenerator<S> {
         Self::with_epoch(machine_id, crate::defs::SNOWFLAKE_ID_EPOCH)
     }
 
-    pub fn with_epoch(machine_id: u64, epoch: i64) -> Result<Self, SnowflakeError> {
-        if machine_id > S::max_machine_id() {
-            return Err(SnowflakeError::InvalidMachineId(
-                machine_id,
-                S::max_machine_id(),
-            ));
+    pub fn with_epoch(machine_id: u64, epoch: impl Into<Epoch>) -> Result<Self, SnowflakeError> {
+        Self::with_clock(machine_id, epoch, SystemClock)
+    }
+
+    pub fn with_clock(
+        machine_id: u64,
+        epoch: impl Into<Epoch>,
+        clock: impl Clock + 'static,
+    ) -> Result<Self, SnowflakeError> {
+        Ok(Self::from_core(
+            GeneratorCore::new(machine_id, epoch.into(), Arc::new(clock))?,
+            Arc::new(SleepWait),
+        ))
+    }
+
+    pub(crate) fn from_core(core: GeneratorCore<S>, wait: Arc<dyn WaitStrategy>) -> Self {
+        AsyncSnowflakeGenerator {
+            state: Mutex::new(core.initial_state()),
+            core,
+            shed_after_ticks: None,
+            wait,
         }
+    }
 
-        Ok(AsyncSnowflakeGenerator {
-            machine_id,
-            state: Mutex::new(GeneratorState {
-                last_timestamp: 0,
-                sequence: 0,
-            }),
-            epoch,
-            _marker: PhantomData,
-        })
+    /// Enables priority shedding: once the sequence space has run out for more
+    /// than `ticks` consecutive milliseconds, [`Priority::Low`] requests are
+    /// rejected with [`SnowflakeError::Overloaded`] instead of queueing behind
+    /// the 1ms waits, while [`Priority::High`] requests keep being served.
+    pub fn with_load_shedding(mut self, ticks: u32) -> Self {
+        self.shed_after_ticks = Some(ticks);
+        self
     }
 
     pub fn epoch(&self) -> i64 {
-        self.epoch
+        self.core.epoch
+    }
+
+    pub fn machine_id(&self) -> u64 {
+        self.core.machine_id
+    }
+
+    /// Returns the length of one timestamp unit
+    pub fn tick(&self) -> Duration {
+        self.core.ticks_duration(1)
+    }
+
+    /// Splits `id` into its fields, see
+    /// [`crate::generator::SnowflakeGenerator::decompose`]
+    pub fn decompose(&self, id: S) -> SnowflakeParts {
+        self.core.decompose(id)
+    }
+
+    /// Returns a digest of the layout, epoch, tick length and clock policy,
+    /// see [`crate::generator::SnowflakeGenerator::fingerprint`]
+    pub fn fingerprint(&self) -> [u8; 32] {
+        self.core.fingerprint()
+    }
+
+    /// Reports on the generator's clock, see
+    /// [`crate::generator::SnowflakeGenerator::clock_health`]
+    pub async fn clock_health(&self) -> ClockHealth {
+        let state = self.state.lock().await;
+        self.core.clock_health(&state)
     }
 
     pub async fn try_next_id(&self) -> Result<SnowflakeOperation<S>, SnowflakeError> {
         let mut state = self.state.lock().await;
-        let timestamp = Self::current_timestamp();
-
-        if timestamp < state.last_timestamp {
-            let drift = state.last_timestamp - timestamp;
-            if drift <= CLOCK_BACKWARDS_TOLERANCE_MS {
-                return Ok(SnowflakeOperation::Pending(Duration::from_millis(
-                    drift as u64,
-                )));
-            } else {
-                return Err(SnowflakeError::ClockMovedBackwards);
-            }
-        }
+        self.store_checkpoint(&mut state).await?;
+        self.core.try_next(&mut state)
+    }
 
-        if timestamp == state.last_timestamp {
-            let next_seq = (state.sequence + 1) & S::max_sequence();
-            if next_seq == 0 {
-                return Ok(SnowflakeOperation::Pending(Duration::from_millis(1)));
-            }
-            state.sequence = next_seq;
-        } else {
-            state.sequence = 0;
+    /// Stores the checkpoint mark the next claim needs, if the checkpoint
+    /// is an [`AsyncCheckpoint`](crate::checkpoint::AsyncCheckpoint)
+    async fn store_checkpoint(&self, state: &mut GeneratorState) -> Result<(), SnowflakeError> {
+        let Some(Checkpointing {
+            store: CheckpointStore::Async(store),
+            ..
+        }) = &self.core.checkpoint
+        else {
+            return Ok(());
+        };
+        // The latest tick the next claim can take, barring a clock that moves
+        // on meanwhile, which just brings the claim back here
+        let timestamp = self
+            .core
+            .current_timestamp()
+            .max(state.last_timestamp.saturating_add(1));
+        if let Some((_, mark, until)) = self.core.checkpoint_due(timestamp, state) {
+            store
+                .store(mark)
+                .await
+                .map_err(|err| SnowflakeError::CheckpointFailed(Arc::new(err)))?;
+            state.checkpointed_until = until;
         }
+        Ok(())
+    }
 
-        state.last_timestamp = timestamp;
+    /// Generates the next ID, waiting through any pending ticks with the
+    /// generator's [`WaitStrategy`].
+    ///
+    /// # Cancel safety
+    /// This method is cancel safe. The sequence only advances in the same poll
+    /// that hands the ID back, so dropping the future mid-wait, e.g. when it
+    /// loses a `select!`, loses no ID and leaves the generator as it was. The
+    /// same holds for [`AsyncSnowflakeGenerator::next_id_checked`],
+    /// [`AsyncSnowflakeGenerator::next_id_with_priority`] and
+    /// [`AsyncSnowflakeGenerator::next_id_timeout`].
+    ///
+    /// # Panics
+    /// Panics if generation fails; use [`AsyncSnowflakeGenerator::next_id_checked`]
+    /// to handle errors instead.
+    pub async fn next_id(&self) -> S {
+        self.next_id_checked()
+            .await
+            .expect("snowflake generation failed")
+    }
 
-        let timestamp_offset = timestamp - self.epoch;
-        if timestamp_offset < 0 || timestamp_offset > S::max_timestamp() {
-            return Err(SnowflakeError::TimestampOverflow);
-        }
+    /// Generates the next ID, returning an error instead of panicking on
+    /// clock failures
+    pub async fn next_id_checked(&self) -> Result<S, SnowflakeError> {
+        #[cfg(feature = "stats")]
+        let started = Instant::now();
 
-        let masked_timestamp =
-            (timestamp_offset as u64) & ((1u64 << S::timestamp_bits()) - 1);
+        let mut attempt = 0;
+        loop {
+            match self.try_next_id().await? {
+                SnowflakeOperation::Ready(id) => {
+                    #[cfg(feature = "stats")]
+                    self.core.stats.record_latency(started.elapsed());
+                    return Ok(id);
+                }
+                SnowflakeOperation::Pending(wait) => {
+                    self.wait.wait_async(wait, attempt).await;
+                    attempt = attempt.saturating_add(1);
+                }
+            }
+        }
+    }
 
-        Ok(SnowflakeOperation::Ready(S::from_component_parts(
-            masked_timestamp,
-            self.machine_id,
-            state.sequence,
-        )))
+    /// Generates the next ID, giving up with [`SnowflakeError::Timeout`] if
+    /// none is issued within `timeout`, e.g. because the clock is stalled or
+    /// stepped back within tolerance.
+    ///
+    /// # Example
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use snowflake_id::AsyncSnowflakeGenerator;
+    /// use std::time::Duration;
+    ///
+    /// let generator = AsyncSnowflakeGenerator::new(1).unwrap();
+    /// let id = generator.next_id_timeout(Duration::from_millis(50)).await.unwrap();
+    /// # let _: snowflake_id::SnowflakeId = id;
+    /// # }
+    /// ```
+    pub async fn next_id_timeout(&self, timeout: Duration) -> Result<S, SnowflakeError> {
+        let generate = pin!(self.next_id_checked());
+        let expire = pin!(crate::rt::sleep(timeout));
+        match future::select(generate, expire).await {
+            Either::Left((result, _)) => result,
+            Either::Right(((), _)) => Err(SnowflakeError::Timeout(timeout)),
+        }
     }
 
-    pub async fn next_id(&self) -> S {
+    /// Generates the next ID at the given priority.
+    ///
+    /// Low-priority requests fail fast with [`SnowflakeError::Overloaded`] while
+    /// the generator is saturated (see [`AsyncSnowflakeGenerator::with_load_shedding`]),
+    /// including if it becomes saturated while they are waiting.
+    pub async fn next_id_with_priority(&self, priority: Priority) -> Result<S, SnowflakeError> {
+        let mut attempt = 0;
         loop {
-            match self.try_next_id().await.expect("snowflake generation failed") {
-                SnowflakeOperation::Ready(id) => return id,
+            let operation = {
+                let mut state = self.state.lock().await;
+                if priority == Priority::Low && self.is_saturated(&state) {
+                    return Err(SnowflakeError::Overloaded);
+                }
+                self.store_checkpoint(&mut state).await?;
+                self.core.try_next(&mut state)?
+            };
+
+            match operation {
+                SnowflakeOperation::Ready(id) => return Ok(id),
                 SnowflakeOperation::Pending(wait) => {
-                    tokio::time::sleep(wait).await;
+                    self.wait.wait_async(wait, attempt).await;
+                    attempt = attempt.saturating_add(1);
                 }
             }
         }
     }
 
+    fn is_saturated(&self, state: &GeneratorState) -> bool {
+        self.shed_after_ticks
+            .is_some_and(|ticks| state.exhaustion_streak(self.core.current_timestamp()) > ticks)
+    }
+
+    #[deprecated(note = "panics on clock errors; use `next_id_bulk_checked` instead")]
     pub async fn next_id_bulk(&self, count: usize) -> Vec<S> {
+        self.next_id_bulk_checked(count)
+            .await
+            .expect("snowflake generation failed")
+    }
+
+    /// Generates `count` IDs, returning an error instead of panicking on
+    /// clock failures.
+    ///
+    /// The generator lock is acquired once for the whole batch and only
+    /// released while waiting through a sequence rollover or clock wait.
+    ///
+    /// # Cancel safety
+    /// Dropping the future mid-wait discards the IDs generated so far. They
+    /// are never issued again, so this leaves a gap but no duplicates.
+    pub async fn next_id_bulk_checked(&self, count: usize) -> Result<Vec<S>, SnowflakeError> {
+        #[cfg(feature = "stats")]
+        let started = Instant::now();
         let mut ids = Vec::with_capacity(count);
-        for _ in 0..count {
-            ids.push(self.next_id().await);
+
+        let mut attempt = 0;
+        let mut clock = ClockCache::default();
+        let mut state = self.state.lock().await;
+        self.store_checkpoint(&mut state).await?;
+        while ids.len() < count {
+            match self.core.try_next_cached(&mut state, &mut clock)? {
+                SnowflakeOperation::Ready(id) => ids.push(id),
+                // A checkpoint mark is due
+                SnowflakeOperation::Pending(Duration::ZERO) => {
+                    self.store_checkpoint(&mut state).await?;
+                }
+                SnowflakeOperation::Pending(wait) => {
+                    // Let other tasks in while we wait
+                    drop(state);
+                    self.wait.wait_async(wait, attempt).await;
+                    attempt = attempt.saturating_add(1);
+                    state = self.state.lock().await;
+                }
+            }
         }
-        ids
+        drop(state);
+
+        #[cfg(feature = "stats")]
+        self.core.stats.record_latency(started.elapsed());
+
+        Ok(ids)
+    }
+
+    /// Returns a snapshot of this generator's latency statistics
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> GeneratorStats {
+        self.core.stats.snapshot()
+    }
+
+    /// Returns an endless stream of IDs, awaiting any pending waits internally.
+    ///
+    /// IDs are generated lazily as the stream is polled, so it composes with
+    /// `StreamExt` combinators and backpressure-aware consumers.
+    ///
+    /// # Example
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use futures_util::StreamExt;
+    /// use snowflake_id::AsyncSnowflakeGenerator;
+    ///
+    /// let generator = AsyncSnowflakeGenerator::new(1).unwrap();
+    /// let ids: Vec<_> = generator.stream().take(3).collect().await;
+    /// assert_eq!(ids.len(), 3);
+    /// # }
+    /// ```
+    pub fn stream(&self) -> impl Stream<Item = S> + '_ {
+        futures_util::stream::unfold(self, |generator| async move {
+            Some((generator.next_id().await, generator))
+        })
     }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use crate::test_support::manual_clock;
+    use crate::AsyncSnowflakeGenerator;
+    use crate::Profile;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+    use crate::MAX_SEQUENCE;
+    use crate::SNOWFLAKE_ID_EPOCH;
+
+    #[tokio::test]
+    async fn test_builder_build_async() {
+        let generator = SnowflakeGenerator::builder(9)
+            .profile(Profile::ClockParanoid)
+            .build_async()
+            .unwrap();
+        let a = generator.next_id().await;
+        let b = generator.next_id().await;
+        assert!(a < b);
+        assert_eq!(b.machine_id(), 9);
+    }
+
+    #[tokio::test]
+    async fn test_async_wait_strategies() {
+        use crate::wait::{BackoffWait, SpinWait, YieldWait};
+
+        for generator in [
+            SnowflakeGenerator::builder(1).wait(SpinWait).build_async(),
+            SnowflakeGenerator::builder(1).wait(YieldWait).build_async(),
+            SnowflakeGenerator::builder(1)
+                .wait(BackoffWait::new(2, 2))
+                .build_async(),
+        ] {
+            let ids = generator
+                .unwrap()
+                .next_id_bulk_checked(10_000)
+                .await
+                .unwrap();
+            assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_generate() {
+        let generator = AsyncSnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
+        let id1 = generator.next_id().await;
+        let id2 = generator.next_id().await;
+
+        assert_ne!(id1, id2);
+        assert!(id1.id() < id2.id());
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn test_async_generate_bulk() {
+        let generator = AsyncSnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
+        let ids = generator.next_id_bulk(100).await;
+
+        assert_eq!(ids.len(), 100);
+
+        for i in 1..ids.len() {
+            assert!(ids[i - 1].id() < ids[i].id());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_stream() {
+        use futures_util::StreamExt;
+
+        let generator = AsyncSnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
+        let ids: Vec<SnowflakeId> = generator.stream().take(50).collect().await;
+
+        assert_eq!(ids.len(), 50);
+        for i in 1..ids.len() {
+            assert!(ids[i - 1].id() < ids[i].id());
+        }
+    }
+
+    #[cfg(feature = "stats")]
+    #[tokio::test]
+    async fn test_async_latency_stats() {
+        let generator = AsyncSnowflakeGenerator::new(1).unwrap();
+        generator.next_id_bulk_checked(20).await.unwrap();
+
+        assert_eq!(generator.stats().latency_percentiles().count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_async_checked_generation() {
+        let clock = manual_clock();
+        let generator =
+            AsyncSnowflakeGenerator::with_clock(1, Epoch::TWITTER, clock.clone()).unwrap();
+
+        assert_eq!(generator.next_id_bulk_checked(5).await.unwrap().len(), 5);
+
+        clock.set(SNOWFLAKE_ID_EPOCH);
+        assert!(matches!(
+            generator.next_id_checked().await,
+            Err(SnowflakeError::ClockMovedBackwards { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_async_priority_shedding() {
+        let clock = manual_clock();
+        let generator = AsyncSnowflakeGenerator::with_clock(1, Epoch::TWITTER, clock.clone())
+            .unwrap()
+            .with_load_shedding(2);
+
+        // Exhaust the sequence space on three consecutive ticks
+        for _ in 0..3 {
+            for _ in 0..=MAX_SEQUENCE {
+                generator
+                    .next_id_with_priority(Priority::High)
+                    .await
+                    .unwrap();
+            }
+            assert!(matches!(
+                generator.try_next_id().await.unwrap(),
+                SnowflakeOperation::Pending(_)
+            ));
+            clock.advance(Duration::from_millis(1));
+        }
+
+        assert!(matches!(
+            generator.next_id_with_priority(Priority::Low).await,
+            Err(SnowflakeError::Overloaded)
+        ));
+        assert!(generator
+            .next_id_with_priority(Priority::High)
+            .await
+            .is_ok());
+
+        // A quiet tick ends the saturation streak
+        clock.advance(Duration::from_millis(5));
+        assert!(generator.next_id_with_priority(Priority::Low).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_async_no_shedding_by_default() {
+        let generator = AsyncSnowflakeGenerator::new(1).unwrap();
+        for _ in 0..10_000 {
+            generator
+                .next_id_with_priority(Priority::Low)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_bulk_spans_sequence_rollover() {
+        let generator = AsyncSnowflakeGenerator::new(1).unwrap();
+        let count = (MAX_SEQUENCE as usize + 1) * 3;
+        let ids = generator.next_id_bulk_checked(count).await.unwrap();
+
+        assert_eq!(ids.len(), count);
+        for i in 1..ids.len() {
+            assert!(ids[i - 1].id() < ids[i].id());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_async_bulk_concurrent_unique() {
+        use std::collections::HashSet;
+        use std::sync::Arc;
+
+        let generator = Arc::new(AsyncSnowflakeGenerator::new(1).unwrap());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let generator = generator.clone();
+                tokio::spawn(async move { generator.next_id_bulk_checked(5000).await.unwrap() })
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        for handle in handles {
+            for id in handle.await.unwrap() {
+                assert!(seen.insert(id));
+            }
+        }
+        assert_eq!(seen.len(), 20_000);
+    }
+
+    #[tokio::test]
+    async fn test_async_next_id_cancel_safe() {
+        use std::time::Duration;
+
+        let clock = manual_clock();
+        let generator =
+            AsyncSnowflakeGenerator::with_clock(1, Epoch::TWITTER, clock.clone()).unwrap();
+
+        let mut last = None;
+        while let SnowflakeOperation::Ready(id) = generator.try_next_id().await.unwrap() {
+            last = Some(id);
+        }
+
+        // The tick is exhausted and the clock stalled, so the timer wins
+        tokio::select! {
+            id = generator.next_id() => panic!("unexpected id {:?}", id),
+            _ = tokio::time::sleep(Duration::from_millis(20)) => {}
+        }
+        assert!(matches!(
+            generator.next_id_timeout(Duration::from_millis(5)).await,
+            Err(SnowflakeError::Timeout(timeout)) if timeout == Duration::from_millis(5)
+        ));
+
+        clock.advance(Duration::from_millis(1));
+        let next = generator
+            .next_id_timeout(Duration::from_millis(5))
+            .await
+            .unwrap();
+        assert!(next > last.unwrap());
+        assert_eq!(next.sequence(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_async_try_next_id() {
+        let generator = AsyncSnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
+        let result = generator.try_next_id().await.unwrap();
+
+        match result {
+            SnowflakeOperation::Ready(id) => {
+                assert!(id.id() > 0);
+                assert_eq!(id.machine_id(), 1);
+            }
+            SnowflakeOperation::Pending(_) => panic!("Expected Ready, got Pending"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_checkpoint() {
+        use crate::test_support::MemoryCheckpoint;
+
+        let start = SNOWFLAKE_ID_EPOCH + 1_000;
+        let clock = manual_clock();
+        let checkpoint = MemoryCheckpoint::default();
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .build_async_with_checkpoint(checkpoint.clone(), Duration::from_millis(100))
+            .await
+            .unwrap();
+        let last = generator.next_id_checked().await.unwrap();
+        assert_eq!(checkpoint.mark(), Some(start + 100));
+        clock.advance(Duration::from_millis(100));
+        generator.next_id_bulk_checked(3).await.unwrap();
+        assert_eq!(checkpoint.mark(), Some(start + 200));
 
-    fn current_timestamp() -> i64 {
-        Utc::now().timestamp_millis()
+        // After a restart with the clock set back, nothing is issued, or
+        // stored, until it passes the stored mark
+        clock.set(start);
+        let restarted = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .build_async_with_checkpoint(checkpoint.clone(), Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert!(matches!(
+            restarted.try_next_id().await,
+            Ok(SnowflakeOperation::Pending(wait)) if wait == Duration::from_millis(201)
+        ));
+        assert_eq!(checkpoint.mark(), Some(start + 200));
+        clock.set(start + 201);
+        assert!(restarted.next_id_checked().await.unwrap() > last);
+        assert_eq!(checkpoint.mark(), Some(start + 301));
     }
 }
@@ -0,0 +1,264 @@
+//! Finding duplicate IDs in large streams, e.g. log replays.
+//!
+//! Holding every ID of a long replay in a set takes more memory than the
+//! replay is worth, so [`CollisionChecker`] works in two passes. The first
+//! feeds every ID through a Bloom filter sized for the expected count, and
+//! keeps only the IDs it may have seen before as suspects. The second
+//! replays the stream and counts the suspects exactly, so false positives
+//! drop out and the report lists only real duplicates.
+//!
+//! Duplicates point at two generators sharing a machine ID, or one whose
+//! clock went back further than it noticed; the report groups them by
+//! machine ID and timestamp so the culprit is easy to find.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::audit::CollisionChecker;
+//! use snowflake_id::SnowflakeGenerator;
+//!
+//! let ids = SnowflakeGenerator::new(1).unwrap().generate_bulk(10_000).unwrap();
+//!
+//! let mut checker = CollisionChecker::new(ids.len(), 0.001);
+//! ids.iter().for_each(|&id| checker.ingest(id));
+//! ids.iter().for_each(|&id| checker.verify(id));
+//! assert!(checker.report().is_clean());
+//! ```
+
+use crate::snowflake::Snowflake;
+use crate::SnowflakeId;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::hash::BuildHasher;
+
+/// Two-pass duplicate detector, see the [module docs](self)
+pub struct CollisionChecker<S = SnowflakeId> {
+    bloom: BloomFilter,
+    /// IDs the filter may have seen before, with their exact count from the
+    /// verification pass
+    suspects: HashMap<S, u64>,
+    ingested: u64,
+}
+
+impl<S: Snowflake> CollisionChecker<S> {
+    /// Sizes the filter for `expected_ids` IDs with the given false positive
+    /// rate, clamped to `1e-9..=0.5`. Lower rates cost more filter memory
+    /// but leave fewer suspects to hold.
+    pub fn new(expected_ids: usize, false_positive_rate: f64) -> Self {
+        CollisionChecker {
+            bloom: BloomFilter::new(expected_ids.max(1), false_positive_rate.clamp(1e-9, 0.5)),
+            suspects: HashMap::new(),
+            ingested: 0,
+        }
+    }
+
+    /// Records `id` in the first pass
+    pub fn ingest(&mut self, id: S) {
+        self.ingested += 1;
+        if self.bloom.insert(self.bloom.hasher.hash_one(id)) {
+            self.suspects.entry(id).or_insert(0);
+        }
+    }
+
+    /// Counts `id` in the second pass, which must replay the same stream
+    pub fn verify(&mut self, id: S) {
+        if let Some(count) = self.suspects.get_mut(&id) {
+            *count += 1;
+        }
+    }
+
+    /// Returns how many IDs the first pass has seen
+    pub fn ingested(&self) -> u64 {
+        self.ingested
+    }
+
+    /// Returns how many IDs the filter flagged, duplicates and false
+    /// positives together
+    pub fn suspects(&self) -> usize {
+        self.suspects.len()
+    }
+
+    /// Returns the duplicates confirmed by the verification pass
+    pub fn report(&self) -> AuditReport<S> {
+        let mut collisions: Vec<_> = self
+            .suspects
+            .iter()
+            .filter(|(_, &occurrences)| occurrences > 1)
+            .map(|(&id, &occurrences)| Collision { id, occurrences })
+            .collect();
+        collisions.sort_by_key(|collision| collision.id);
+
+        let mut buckets = BTreeMap::new();
+        for collision in &collisions {
+            *buckets
+                .entry((collision.id.machine_id(), collision.id.timestamp()))
+                .or_insert(0) += collision.occurrences - 1;
+        }
+
+        AuditReport {
+            ids_scanned: self.ingested,
+            suspects: self.suspects.len(),
+            collisions,
+            buckets: buckets
+                .into_iter()
+                .map(|((machine_id, timestamp), duplicates)| CollisionBucket {
+                    machine_id,
+                    timestamp,
+                    duplicates,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// An ID seen more than once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collision<S> {
+    pub id: S,
+    pub occurrences: u64,
+}
+
+/// Duplicates minted by one machine ID in one tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionBucket {
+    pub machine_id: u64,
+    /// Timestamp offset from the epoch, as stored in the IDs
+    pub timestamp: u64,
+    /// Extra copies beyond the first, over all IDs in the bucket
+    pub duplicates: u64,
+}
+
+/// Result of [`CollisionChecker::report`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditReport<S> {
+    pub ids_scanned: u64,
+    /// IDs the filter flagged for verification
+    pub suspects: usize,
+    /// Confirmed duplicates, in ID order
+    pub collisions: Vec<Collision<S>>,
+    /// Confirmed duplicates grouped by machine ID and timestamp
+    pub buckets: Vec<CollisionBucket>,
+}
+
+impl<S> AuditReport<S> {
+    /// Returns true if no duplicates were found
+    pub fn is_clean(&self) -> bool {
+        self.collisions.is_empty()
+    }
+
+    /// Returns the machine IDs that minted duplicates, likely shared by more
+    /// than one generator
+    pub fn conflicting_machine_ids(&self) -> BTreeSet<u64> {
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.machine_id)
+            .collect()
+    }
+}
+
+impl<S> fmt::Display for AuditReport<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ids scanned, {} suspects, {} duplicated",
+            self.ids_scanned,
+            self.suspects,
+            self.collisions.len()
+        )?;
+        for bucket in &self.buckets {
+            write!(
+                f,
+                "\nmachine_id={} timestamp={}: {} duplicates",
+                bucket.machine_id, bucket.timestamp, bucket.duplicates
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct BloomFilter {
+    bits: Vec<u64>,
+    len: u64,
+    hashes: u32,
+    hasher: RandomState,
+}
+
+impl BloomFilter {
+    fn new(expected: usize, false_positive_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let len = (-(expected as f64) * false_positive_rate.ln() / (ln2 * ln2)).ceil() as u64;
+        let len = len.max(64);
+        let hashes = ((len as f64 / expected as f64) * ln2)
+            .round()
+            .clamp(1.0, 32.0) as u32;
+        BloomFilter {
+            bits: vec![0; len.div_ceil(64) as usize],
+            len,
+            hashes,
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Sets the bits for `hash`, returning true if they were all set already
+    fn insert(&mut self, hash: u64) -> bool {
+        // Double hashing: bit i is h1 + i * h2
+        let (h1, h2) = (hash, hash.rotate_left(32) | 1);
+        let mut present = true;
+        for i in 0..self.hashes as u64 {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % self.len;
+            let (word, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+            present &= self.bits[word] & mask != 0;
+            self.bits[word] |= mask;
+        }
+        present
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collision_checker() {
+        let mut ids: Vec<SnowflakeId> = (0..20_000)
+            .map(|i| SnowflakeId::from_component_parts(1_000 + i / 4_096, 1, i % 4_096))
+            .collect();
+        // A second process sharing machine ID 7 at offset 2_000
+        let shared: Vec<_> = (0..5)
+            .map(|seq| SnowflakeId::from_component_parts(2_000, 7, seq))
+            .collect();
+        ids.extend(&shared);
+        ids.extend(&shared[..3]);
+        ids.push(ids[0]);
+
+        let mut checker = CollisionChecker::new(ids.len(), 0.01);
+        ids.iter().for_each(|&id| checker.ingest(id));
+        ids.iter().for_each(|&id| checker.verify(id));
+        assert_eq!(checker.ingested(), ids.len() as u64);
+        let report = checker.report();
+        assert!(!report.is_clean());
+        assert_eq!(report.collisions.len(), 4);
+        assert!(report.collisions.iter().all(|c| c.occurrences == 2));
+        assert!(report.suspects >= 4);
+        assert_eq!(
+            report
+                .conflicting_machine_ids()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [1, 7]
+        );
+        let bucket = report.buckets.iter().find(|b| b.machine_id == 7).unwrap();
+        assert_eq!((bucket.timestamp, bucket.duplicates), (2_000, 3));
+        assert!(report
+            .to_string()
+            .contains("machine_id=7 timestamp=2000: 3 duplicates"));
+
+        // An undersized filter flags plenty, but verification clears them
+        let unique = &ids[..20_000];
+        let mut small = CollisionChecker::new(10, 0.5);
+        unique.iter().for_each(|&id| small.ingest(id));
+        unique.iter().for_each(|&id| small.verify(id));
+        assert!(small.suspects() > 1_000);
+        assert!(small.report().is_clean());
+    }
+}
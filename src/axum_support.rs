@@ -0,0 +1,111 @@
+//! axum integration for [`SnowflakeId`] and [`SnowflakeError`].
+//!
+//! `SnowflakeId` deserializes from path segments and query strings as-is, so
+//! `Path<SnowflakeId>` and `Query<T>` work without wrappers; malformed IDs are
+//! rejected with a `400` naming the offending value. `SnowflakeError`
+//! implements `IntoResponse`, so handlers can return it with `?`.
+
+use crate::error::SnowflakeError;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+impl SnowflakeError {
+    /// Returns the HTTP status a handler failing with this error should send
+    pub fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.http_status()).expect("valid status code")
+    }
+}
+
+impl IntoResponse for SnowflakeError {
+    fn into_response(self) -> Response {
+        (self.status_code(), self.to_string()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeId;
+
+    use axum::body::Body;
+
+    use axum::extract::{Path, Query};
+
+    use axum::http::Request;
+
+    use axum::routing::get;
+
+    use axum::Router;
+
+    use http_body_util::BodyExt;
+
+    use tower::ServiceExt;
+
+    #[derive(::serde::Deserialize)]
+    struct Page {
+        after: Option<SnowflakeId>,
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/events/{id}",
+                get(|Path(id): Path<SnowflakeId>| async move { id.to_string() }),
+            )
+            .route(
+                "/events",
+                get(|Query(page): Query<Page>| async move { format!("{:?}", page.after) }),
+            )
+            .route(
+                "/fail",
+                get(|| async {
+                    Err::<String, _>(SnowflakeError::ClockMovedBackwards { drift_ms: 7 })
+                }),
+            )
+    }
+
+    async fn call(uri: &str) -> (StatusCode, String) {
+        let response = app()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_extractors() {
+        assert_eq!(
+            call("/events/123456789012345678").await,
+            (StatusCode::OK, "123456789012345678".to_string())
+        );
+        assert_eq!(
+            call("/events?after=42").await,
+            (StatusCode::OK, "Some(SnowflakeId(42))".to_string())
+        );
+
+        let (status, body) = call("/events/abc").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("\"abc\""), "{}", body);
+        assert!(body.contains("snowflake id"), "{}", body);
+
+        let (status, body) = call("/events?after=-1").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("cannot be negative"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn test_error_response() {
+        let (status, body) = call("/fail").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            body,
+            SnowflakeError::ClockMovedBackwards { drift_ms: 7 }.to_string()
+        );
+        assert_eq!(
+            SnowflakeError::InvalidId("x".into()).status_code(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! `snowflake`: mint and inspect snowflake IDs from the shell.
+//!
+//! ```text
+//! snowflake generate --machine-id 7 --count 3
+//! snowflake decode 175928847299117063 --epoch discord
+//! snowflake range --from 2024-01-01T00:00:00Z --to 2024-02-01T00:00:00Z
+//! ```
+
+use chrono::DateTime;
+use clap::{Parser, Subcommand};
+use snowflake_id::clap_extras::parse_machine_id;
+use snowflake_id::error::SnowflakeError;
+use snowflake_id::{Epoch, SnowflakeGenerator, SnowflakeId};
+use std::process::ExitCode;
+use std::thread;
+
+#[derive(Parser)]
+#[command(
+    name = "snowflake",
+    version,
+    about = "Generate and inspect snowflake IDs"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Mint new IDs, one per line
+    Generate {
+        #[arg(long, value_parser = parse_machine_id)]
+        machine_id: u64,
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+        /// Preset name (twitter, discord, unix) or milliseconds since the
+        /// Unix epoch
+        #[arg(long, default_value = "twitter")]
+        epoch: Epoch,
+    },
+    /// Print the fields of existing IDs
+    Decode {
+        #[arg(required = true)]
+        ids: Vec<SnowflakeId>,
+        #[arg(long, default_value = "twitter")]
+        epoch: Epoch,
+    },
+    /// Print the smallest and largest IDs minted in `[from, to)`, for use
+    /// in `BETWEEN` filters
+    Range {
+        /// RFC 3339 time or milliseconds since the Unix epoch
+        #[arg(long, value_parser = parse_time)]
+        from: i64,
+        /// RFC 3339 time or milliseconds since the Unix epoch, exclusive
+        #[arg(long, value_parser = parse_time)]
+        to: i64,
+        #[arg(long, default_value = "twitter")]
+        epoch: Epoch,
+    },
+}
+
+fn parse_time(value: &str) -> Result<i64, String> {
+    value
+        .parse()
+        .or_else(|_| DateTime::parse_from_rfc3339(value).map(|time| time.timestamp_millis()))
+        .map_err(|_| format!("expected an RFC 3339 time or Unix milliseconds, got {value}"))
+}
+
+/// Runs `command`, returning the lines to print
+fn run(command: Command) -> Result<Vec<String>, SnowflakeError> {
+    let lines = match command {
+        Command::Generate {
+            machine_id,
+            count,
+            epoch,
+        } => {
+            let generator = SnowflakeGenerator::with_epoch(machine_id, epoch)?;
+            let ids = generator.next_id_bulk_checked(count, thread::sleep)?;
+            ids.iter().map(ToString::to_string).collect()
+        }
+        Command::Decode { ids, epoch } => ids
+            .iter()
+            .map(|id| format!("{} {}", id, id.decompose(epoch)))
+            .collect(),
+        Command::Range { from, to, epoch } => {
+            let range = SnowflakeId::range_for(from..to, epoch)?;
+            vec![format!("{} {}", range.start(), range.end())]
+        }
+    };
+    Ok(lines)
+}
+
+/// Prints the outcome of [`run`] and picks the exit code; argument errors
+/// exit with clap's code `2` before getting here
+fn report(result: Result<Vec<String>, SnowflakeError>) -> ExitCode {
+    match result {
+        Ok(lines) => {
+            for line in lines {
+                println!("{}", line);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    report(run(Cli::parse().command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::ErrorKind;
+    use snowflake_id::MAX_MACHINE_ID;
+
+    fn parse(args: &[&str]) -> Result<Command, clap::Error> {
+        Cli::try_parse_from(["snowflake"].iter().chain(args)).map(|cli| cli.command)
+    }
+
+    fn parse_err(args: &[&str]) -> clap::Error {
+        match parse(args) {
+            Ok(_) => panic!("expected {:?} to be rejected", args),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let command = parse(&["generate", "--machine-id", "7", "--count", "3"]).unwrap();
+        assert!(matches!(
+            command,
+            Command::Generate { machine_id: 7, count: 3, epoch } if epoch == Epoch::TWITTER
+        ));
+        let lines = run(command).unwrap();
+        assert_eq!(lines.len(), 3);
+        for line in lines {
+            assert_eq!(line.parse::<SnowflakeId>().unwrap().machine_id(), 7);
+        }
+
+        let command = parse(&["generate", "--machine-id", "0", "--epoch", "discord"]).unwrap();
+        assert!(matches!(
+            command,
+            Command::Generate { count: 1, epoch, .. } if epoch == Epoch::DISCORD
+        ));
+
+        let overflow = parse_err(&[
+            "generate",
+            "--machine-id",
+            &(MAX_MACHINE_ID + 1).to_string(),
+        ]);
+        assert_eq!(overflow.kind(), ErrorKind::ValueValidation);
+        assert_eq!(overflow.exit_code(), 2);
+        assert_eq!(
+            parse_err(&["generate"]).kind(),
+            ErrorKind::MissingRequiredArgument
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        let command = parse(&["decode", "175928847299117063", "--epoch", "twitter"]).unwrap();
+        let lines = run(command).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("175928847299117063 "));
+
+        assert_eq!(
+            parse_err(&["decode"]).kind(),
+            ErrorKind::MissingRequiredArgument
+        );
+        let invalid = parse_err(&["decode", "abc"]);
+        assert_eq!(invalid.kind(), ErrorKind::ValueValidation);
+        assert_eq!(invalid.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_range() {
+        let command = parse(&[
+            "range",
+            "--from",
+            "2024-01-01T00:00:00Z",
+            "--to",
+            "1704067201000",
+        ])
+        .unwrap();
+        assert!(matches!(
+            command,
+            Command::Range {
+                from: 1_704_067_200_000,
+                to: 1_704_067_201_000,
+                ..
+            }
+        ));
+        let lines = run(command).unwrap();
+        let bounds: Vec<SnowflakeId> = lines[0].split(' ').map(|id| id.parse().unwrap()).collect();
+        assert!(bounds[0] < bounds[1]);
+
+        let invalid = parse_err(&["range", "--from", "yesterday", "--to", "0"]);
+        assert_eq!(invalid.kind(), ErrorKind::ValueValidation);
+        assert_eq!(invalid.exit_code(), 2);
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(parse_err(&[]).exit_code(), 2);
+        assert_eq!(parse_err(&["unknown"]).kind(), ErrorKind::InvalidSubcommand);
+        assert_eq!(parse_err(&["--version"]).exit_code(), 0);
+        assert_eq!(parse_err(&["--help"]).exit_code(), 0);
+
+        // Failures while running exit with 1
+        let before_epoch = parse(&["range", "--from", "0", "--to", "1000"]).unwrap();
+        let result = run(before_epoch);
+        assert!(result.is_err());
+        assert_eq!(report(result), ExitCode::FAILURE);
+        assert_eq!(report(Ok(Vec::new())), ExitCode::SUCCESS);
+    }
+}
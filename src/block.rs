@@ -0,0 +1,301 @@
+use crate::error::SnowflakeError;
+use crate::generator::{SnowflakeGenerator, SnowflakeOperation};
+use crate::snowflake::Snowflake;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// A contiguous run of sequence numbers within a single tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRun {
+    /// Timestamp offset from the generator's epoch
+    pub timestamp_offset: u64,
+    pub first_sequence: u64,
+    /// Inclusive
+    pub last_sequence: u64,
+}
+
+/// A pre-allocated set of IDs that can be handed out without touching the
+/// generator again, see [`SnowflakeGenerator::reserve_block`].
+///
+/// Within each tick the reserved sequence numbers are contiguous, so a block
+/// is stored as a handful of runs regardless of how many IDs it holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnowflakeBlock<S> {
+    machine_id: u64,
+    runs: Vec<BlockRun>,
+    len: usize,
+    _marker: PhantomData<S>,
+}
+
+impl<S: Snowflake> SnowflakeBlock<S> {
+    /// Returns the reserved runs in ascending order
+    pub fn runs(&self) -> &[BlockRun] {
+        &self.runs
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the smallest ID in the block
+    pub fn first(&self) -> Option<S> {
+        let run = self.runs.first()?;
+        Some(S::from_component_parts(
+            run.timestamp_offset,
+            self.machine_id,
+            run.first_sequence,
+        ))
+    }
+
+    /// Returns the largest ID in the block
+    pub fn last(&self) -> Option<S> {
+        let run = self.runs.last()?;
+        Some(S::from_component_parts(
+            run.timestamp_offset,
+            self.machine_id,
+            run.last_sequence,
+        ))
+    }
+
+    /// Returns true if `id` was reserved as part of this block
+    pub fn contains(&self, id: &S) -> bool {
+        id.machine_id() == self.machine_id
+            && self.runs.iter().any(|run| {
+                run.timestamp_offset == id.timestamp()
+                    && (run.first_sequence..=run.last_sequence).contains(&id.sequence())
+            })
+    }
+
+    /// Iterates over the reserved IDs in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = S> + '_ {
+        self.runs.iter().flat_map(move |run| {
+            (run.first_sequence..=run.last_sequence).map(move |sequence| {
+                S::from_component_parts(run.timestamp_offset, self.machine_id, sequence)
+            })
+        })
+    }
+}
+
+impl<S: Snowflake> IntoIterator for SnowflakeBlock<S> {
+    type Item = S;
+    type IntoIter = BlockIter<S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        BlockIter {
+            machine_id: self.machine_id,
+            runs: self.runs.into_iter(),
+            run: None,
+            remaining: self.len,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Owning iterator over a [`SnowflakeBlock`], building each ID as it goes
+#[derive(Debug, Clone)]
+pub struct BlockIter<S> {
+    machine_id: u64,
+    runs: std::vec::IntoIter<BlockRun>,
+    /// Run being handed out, with `first_sequence` as the next sequence
+    run: Option<BlockRun>,
+    remaining: usize,
+    _marker: PhantomData<S>,
+}
+
+impl<S: Snowflake> Iterator for BlockIter<S> {
+    type Item = S;
+
+    fn next(&mut self) -> Option<S> {
+        loop {
+            if let Some(run) = &mut self.run {
+                if run.first_sequence <= run.last_sequence {
+                    let id = S::from_component_parts(
+                        run.timestamp_offset,
+                        self.machine_id,
+                        run.first_sequence,
+                    );
+                    run.first_sequence += 1;
+                    self.remaining -= 1;
+                    return Some(id);
+                }
+            }
+            self.run = Some(self.runs.next()?);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<S: Snowflake> ExactSizeIterator for BlockIter<S> {}
+
+impl<S: Snowflake> FusedIterator for BlockIter<S> {}
+
+impl<S: Snowflake> SnowflakeGenerator<S> {
+    /// Reserves `count` IDs up front and returns them as a [`SnowflakeBlock`]
+    /// the caller can iterate locally without further locking.
+    ///
+    /// The block is carved out under the same rules as
+    /// [`SnowflakeGenerator::generate`]: it waits out restored checkpoints
+    /// and floors, borrows ticks with
+    /// [`OverflowStrategy::BorrowFuture`](crate::OverflowStrategy::BorrowFuture),
+    /// starts each tick at the configured
+    /// [`SequenceStart`](crate::SequenceStart) and stores checkpoint marks
+    /// for the ticks it spans. The generator lock is held throughout; whenever
+    /// the generator would have to wait, `on_pending` is called with how long
+    /// until the next attempt.
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::SnowflakeGenerator;
+    /// use std::thread;
+    ///
+    /// let generator = SnowflakeGenerator::new(1).unwrap();
+    /// let block = generator.reserve_block(10_000, thread::sleep).unwrap();
+    /// assert_eq!(block.iter().count(), 10_000);
+    /// ```
+    pub fn reserve_block(
+        &self,
+        count: usize,
+        mut on_pending: impl FnMut(Duration),
+    ) -> Result<SnowflakeBlock<S>, SnowflakeError> {
+        let mut runs = Vec::new();
+        let mut remaining = count as u64;
+
+        let mut state = self.lock_state();
+        while remaining > 0 {
+            match self.core.try_claim(&mut state, remaining)? {
+                SnowflakeOperation::Ready(claim) => {
+                    let first_sequence = claim.first.sequence();
+                    remaining -= claim.last_sequence - first_sequence + 1;
+                    runs.push(BlockRun {
+                        timestamp_offset: claim.first.timestamp(),
+                        first_sequence,
+                        last_sequence: claim.last_sequence,
+                    });
+                }
+                SnowflakeOperation::Pending(wait) => on_pending(wait),
+            }
+        }
+
+        Ok(SnowflakeBlock {
+            machine_id: self.core.machine_id,
+            runs,
+            len: count,
+            _marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::manual_clock;
+    use crate::Epoch;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+    use crate::MAX_SEQUENCE;
+    use crate::SNOWFLAKE_ID_EPOCH;
+    use std::thread;
+
+    #[test]
+    fn test_reserve_block() {
+        let generator = SnowflakeGenerator::new(3).unwrap();
+        let before = generator.next_id(|_| thread::yield_now());
+
+        let count = MAX_SEQUENCE as usize * 2 + 100;
+        let block = generator.reserve_block(count, thread::sleep).unwrap();
+        let after = generator.next_id(|_| thread::yield_now());
+
+        assert_eq!(block.len(), count);
+        assert!(block.runs().len() >= 3);
+        let ids: Vec<SnowflakeId> = block.iter().collect();
+        assert_eq!(ids.len(), count);
+        for i in 1..ids.len() {
+            assert!(ids[i - 1] < ids[i]);
+        }
+        assert_eq!(block.first(), Some(ids[0]));
+        assert_eq!(block.last(), Some(ids[count - 1]));
+        assert!(ids
+            .iter()
+            .all(|id| block.contains(id) && id.machine_id() == 3));
+
+        // Reserved IDs never overlap what the generator hands out afterwards
+        assert!(before < ids[0]);
+        assert!(ids[count - 1] < after);
+        assert!(!block.contains(&after));
+        assert_eq!(block.into_iter().count(), count);
+    }
+
+    #[test]
+    fn test_reserve_block_clock_backwards() {
+        let clock = manual_clock();
+        let generator = SnowflakeGenerator::with_clock(1, Epoch::TWITTER, clock.clone()).unwrap();
+
+        let block = generator.reserve_block(10, |_| unreachable!()).unwrap();
+        assert_eq!(block.runs().len(), 1);
+
+        clock.set(SNOWFLAKE_ID_EPOCH);
+        assert!(matches!(
+            generator.reserve_block(10, |_| unreachable!()),
+            Err(SnowflakeError::ClockMovedBackwards { .. })
+        ));
+        assert!(generator
+            .reserve_block(0, |_| unreachable!())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_reserve_block_checkpoint() {
+        use crate::test_support::MemoryCheckpoint;
+
+        // A mark left by a previous process half a second ahead of the clock
+        let start = SNOWFLAKE_ID_EPOCH + 1_000;
+        let checkpoint = MemoryCheckpoint::new(start + 500);
+        let clock = manual_clock();
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .checkpoint(checkpoint.clone(), Duration::from_millis(100))
+            .build()
+            .unwrap();
+
+        let mut waited = Duration::ZERO;
+        let block = generator
+            .reserve_block(10, |wait| {
+                waited += wait;
+                clock.advance(wait);
+            })
+            .unwrap();
+        assert_eq!(waited, Duration::from_millis(501));
+        assert_eq!(block.first().unwrap().timestamp(), 1_501);
+        assert_eq!(checkpoint.mark(), Some(start + 601));
+    }
+
+    #[test]
+    fn test_reserve_block_borrows_future_ticks() {
+        use crate::OverflowStrategy;
+
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(manual_clock())
+            .overflow(OverflowStrategy::BorrowFuture { max_ahead_ms: 10 })
+            .build()
+            .unwrap();
+
+        // Eight ticks ahead of the clock, further than the backwards tolerance
+        let burst = generator
+            .reserve_block((MAX_SEQUENCE as usize + 1) * 9, |_| unreachable!())
+            .unwrap();
+        assert_eq!(burst.runs().len(), 9);
+        assert_eq!(burst.last().unwrap().timestamp(), 1_008);
+
+        let next = generator.reserve_block(10, |_| unreachable!()).unwrap();
+        assert!(next.first() > burst.last());
+    }
+}
@@ -0,0 +1,37 @@
+//! borsh serialization for [`SnowflakeId`].
+//!
+//! IDs are written as borsh's `i64`: 8 little-endian bytes. Decoding rejects
+//! negative values, which no valid ID can have.
+
+use crate::SnowflakeId;
+use borsh::io::{Error, ErrorKind, Read, Result, Write};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+impl BorshSerialize for SnowflakeId {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.0.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for SnowflakeId {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let value = i64::deserialize_reader(reader)?;
+        SnowflakeId::new(value).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borsh_roundtrip() {
+        let id = SnowflakeId::new(123456789012345678).unwrap();
+        let bytes = borsh::to_vec(&id).unwrap();
+        assert_eq!(bytes, 123456789012345678_i64.to_le_bytes());
+        assert_eq!(borsh::from_slice::<SnowflakeId>(&bytes).unwrap(), id);
+
+        assert!(borsh::from_slice::<SnowflakeId>(&(-1_i64).to_le_bytes()).is_err());
+        assert!(borsh::from_slice::<SnowflakeId>(&bytes[..7]).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+//! BSON conversions storing [`SnowflakeId`] as a native `Int64`.
+//!
+//! For `#[derive(Serialize)]` documents, annotate ID fields with
+//! `#[serde(with = "snowflake_id::serde::as_i64")]` so they are stored as
+//! `Int64` rather than strings.
+
+use crate::error::SnowflakeError;
+use crate::SnowflakeId;
+use bson::Bson;
+
+impl From<SnowflakeId> for Bson {
+    fn from(id: SnowflakeId) -> Self {
+        Bson::Int64(id.0)
+    }
+}
+
+impl TryFrom<Bson> for SnowflakeId {
+    type Error = SnowflakeError;
+
+    /// Accepts `Int64`, `Int32` and digit strings
+    fn try_from(value: Bson) -> Result<Self, Self::Error> {
+        match value {
+            Bson::Int64(value) => SnowflakeId::new(value),
+            Bson::Int32(value) => SnowflakeId::new(i64::from(value)),
+            Bson::String(value) => value.parse(),
+            other => Err(SnowflakeError::InvalidId(format!(
+                "unsupported BSON type {:?}",
+                other.element_type()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::serde::{Deserialize, Serialize};
+
+    #[test]
+    fn test_bson_int64() {
+        use bson::{doc, Bson};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Document {
+            #[serde(with = "crate::serde::as_i64")]
+            id: SnowflakeId,
+        }
+
+        let id = SnowflakeId::new(123456789012345678).unwrap();
+        assert_eq!(Bson::from(id), Bson::Int64(123456789012345678));
+        assert_eq!(
+            SnowflakeId::try_from(Bson::Int64(123456789012345678)).unwrap(),
+            id
+        );
+        assert_eq!(SnowflakeId::try_from(Bson::Int32(7)).unwrap().id(), 7);
+        assert_eq!(
+            SnowflakeId::try_from(Bson::String("123456789012345678".into())).unwrap(),
+            id
+        );
+        assert!(SnowflakeId::try_from(Bson::Double(1.0)).is_err());
+        assert!(SnowflakeId::try_from(Bson::Int64(-1)).is_err());
+
+        let document = bson::to_document(&Document { id }).unwrap();
+        assert_eq!(document, doc! { "id": 123456789012345678_i64 });
+        assert_eq!(bson::from_document::<Document>(document).unwrap().id, id);
+
+        // Strings arriving from JSON APIs still deserialize
+        let document = doc! { "id": "123456789012345678" };
+        assert_eq!(bson::from_document::<Document>(document).unwrap().id, id);
+    }
+}
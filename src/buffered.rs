@@ -0,0 +1,205 @@
+//! IDs generated ahead of time by a background task.
+//!
+//! A [`BufferedGenerator`] keeps a bounded buffer of IDs topped up from a
+//! tokio task, so taking one is a channel receive rather than a trip through
+//! the generator lock. Sequence-exhaustion and clock waits are absorbed by the
+//! task while the buffer lasts, instead of stalling the request that happened
+//! to hit them.
+//!
+//! The task refills the buffer to the high watermark, then sleeps until
+//! consumers have drained it down to the low watermark, so it runs in bursts
+//! rather than once per ID.
+//!
+//! # Example
+//! ```
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! use snowflake_id::buffered::BufferedGenerator;
+//! use snowflake_id::AsyncSnowflakeGenerator;
+//!
+//! let generator = AsyncSnowflakeGenerator::new(1).unwrap();
+//! let buffered = BufferedGenerator::new(generator, 256, 1024).unwrap();
+//! let a = buffered.recv().await.unwrap();
+//! let b = buffered.recv().await.unwrap();
+//! assert!(a < b);
+//! # }
+//! ```
+
+use crate::async_generator::AsyncSnowflakeGenerator;
+use crate::error::SnowflakeError;
+use crate::rt::Mutex;
+use crate::snowflake::Snowflake;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+
+/// Hands out IDs from a buffer kept filled by a background task, see the
+/// [module docs](self)
+///
+/// IDs come out in generation order. Dropping the `BufferedGenerator` stops
+/// the task; IDs still in the buffer are discarded and never reissued.
+pub struct BufferedGenerator<S: Snowflake> {
+    generator: Arc<AsyncSnowflakeGenerator<S>>,
+    buffer: Mutex<mpsc::Receiver<Result<S, SnowflakeError>>>,
+    sender: mpsc::WeakSender<Result<S, SnowflakeError>>,
+    refill: Arc<Notify>,
+    low_watermark: usize,
+    task: JoinHandle<()>,
+}
+
+impl<S: Snowflake + Send + Sync + 'static> BufferedGenerator<S> {
+    /// Starts filling a buffer of up to `high_watermark` IDs from `generator`,
+    /// refilling whenever it drains to `low_watermark` or below.
+    ///
+    /// Returns [`SnowflakeError::InvalidConfig`] unless
+    /// `low_watermark < high_watermark`.
+    ///
+    /// # Panics
+    /// Panics if called outside a tokio runtime.
+    pub fn new(
+        generator: AsyncSnowflakeGenerator<S>,
+        low_watermark: usize,
+        high_watermark: usize,
+    ) -> Result<Self, SnowflakeError> {
+        if low_watermark >= high_watermark {
+            return Err(SnowflakeError::InvalidConfig(format!(
+                "low watermark {} must be below high watermark {}",
+                low_watermark, high_watermark
+            )));
+        }
+
+        let generator = Arc::new(generator);
+        let refill = Arc::new(Notify::new());
+        let (sender, receiver) = mpsc::channel(high_watermark);
+        let weak_sender = sender.downgrade();
+        let task = tokio::spawn(fill(generator.clone(), sender, refill.clone()));
+
+        Ok(BufferedGenerator {
+            generator,
+            buffer: Mutex::new(receiver),
+            sender: weak_sender,
+            refill,
+            low_watermark,
+            task,
+        })
+    }
+
+    /// Takes the next ID, waiting for the background task if the buffer is
+    /// empty.
+    ///
+    /// Generation errors hit by the task, such as
+    /// [`SnowflakeError::ClockMovedBackwards`], are passed on in order.
+    pub async fn recv(&self) -> Result<S, SnowflakeError> {
+        let mut buffer = self.buffer.lock().await;
+        let next = buffer.recv().await;
+        self.after_take(&buffer);
+        drop(buffer);
+
+        match next {
+            Some(result) => result,
+            // The task only stops by panicking; keep serving directly
+            None => self.generator.next_id_checked().await,
+        }
+    }
+
+    /// Takes the next ID if one is buffered, without waiting
+    pub fn try_recv(&self) -> Option<Result<S, SnowflakeError>> {
+        let mut buffer = self.buffer.try_lock()?;
+        let next = buffer.try_recv().ok();
+        self.after_take(&buffer);
+        next
+    }
+
+    /// Returns how many IDs are buffered right now
+    pub fn buffered(&self) -> usize {
+        self.sender
+            .upgrade()
+            .map_or(0, |sender| sender.max_capacity() - sender.capacity())
+    }
+
+    /// Returns the underlying generator
+    pub fn generator(&self) -> &AsyncSnowflakeGenerator<S> {
+        &self.generator
+    }
+
+    fn after_take(&self, buffer: &mpsc::Receiver<Result<S, SnowflakeError>>) {
+        if buffer.len() <= self.low_watermark {
+            self.refill.notify_one();
+        }
+    }
+}
+
+impl<S: Snowflake> Drop for BufferedGenerator<S> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Fills the buffer to capacity, then waits to be told it has drained
+async fn fill<S: Snowflake + Send + Sync>(
+    generator: Arc<AsyncSnowflakeGenerator<S>>,
+    sender: mpsc::Sender<Result<S, SnowflakeError>>,
+    refill: Arc<Notify>,
+) {
+    loop {
+        while sender.capacity() > 0 {
+            let result = generator.next_id_checked().await;
+            let failed = result.is_err();
+            if sender.send(result).await.is_err() {
+                return;
+            }
+            if failed {
+                // Don't flood the buffer with the same error
+                tokio::time::sleep(generator.tick()).await;
+            }
+        }
+        refill.notified().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::SnowflakeOperation;
+    use crate::test_support::manual_clock;
+    use crate::AsyncSnowflakeGenerator;
+    use crate::Epoch;
+
+    #[tokio::test]
+    async fn test_buffered_generator() {
+        use std::time::Duration;
+
+        let generator = AsyncSnowflakeGenerator::new(1).unwrap();
+        assert!(matches!(
+            BufferedGenerator::new(generator, 8, 8),
+            Err(SnowflakeError::InvalidConfig(_))
+        ));
+
+        let clock = manual_clock();
+        let generator =
+            AsyncSnowflakeGenerator::with_clock(1, Epoch::TWITTER, clock.clone()).unwrap();
+        let buffered = BufferedGenerator::new(generator, 2, 8).unwrap();
+        while buffered.buffered() < 8 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        // Stall the generator: the buffered IDs still come out straight away
+        while let SnowflakeOperation::Ready(_) = buffered.generator().try_next_id().await.unwrap() {
+        }
+        let mut ids = Vec::new();
+        for _ in 0..8 {
+            ids.push(buffered.try_recv().unwrap().unwrap());
+        }
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(buffered.try_recv().is_none());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(20), buffered.recv())
+                .await
+                .is_err()
+        );
+
+        clock.advance(Duration::from_millis(1));
+        let next = buffered.recv().await.unwrap();
+        assert!(next > ids[7]);
+    }
+}
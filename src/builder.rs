@@ -0,0 +1,669 @@
+use crate::checkpoint::{Checkpoint, StrictMonotonic};
+use crate::clock::{Clock, MonotonicClock, SystemClock};
+use crate::defs::{CLOCK_BACKWARDS_TOLERANCE_MS, SNOWFLAKE_ID_EPOCH};
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::generator::{CheckpointStore, Checkpointing, GeneratorCore, SnowflakeGenerator};
+use crate::sharded::ShardedSnowflakeGenerator;
+use crate::snapshot::GeneratorSnapshot;
+use crate::snowflake::Snowflake;
+use crate::wait::{BackoffWait, SleepWait, SpinWait, WaitStrategy};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use crate::async_generator::AsyncSnowflakeGenerator;
+#[cfg(feature = "async")]
+use crate::checkpoint::AsyncCheckpoint;
+
+/// Preset combinations of generator settings for common deployments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Profile {
+    /// Busy-spins through sequence exhaustion for the lowest tail latency,
+    /// at the cost of burning a core while waiting
+    LowLatency,
+    /// Yields the thread while waiting so other work keeps running, and
+    /// retries as soon as the scheduler comes back. Sleeps once a call has
+    /// yielded a few dozen times, so a long wait such as a clock step back
+    /// doesn't keep a core busy.
+    HighThroughput,
+    /// Reads time from a [`MonotonicClock`] and refuses any backwards clock
+    /// movement, trading timestamp accuracy for a hard monotonicity guarantee.
+    /// Checkpoints every 100ms, so the guarantee survives restarts at the
+    /// cost of frequent writes.
+    ClockParanoid,
+    /// Sleeps while waiting and tolerates larger clock steps, for devices
+    /// with coarse RTCs and intermittent NTP. Checkpoints every 10 seconds to
+    /// spare flash storage, pausing for up to that long after a restart.
+    Embedded,
+}
+
+/// What the generator does when a millisecond's sequence space runs out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OverflowStrategy {
+    /// Waits for the clock to reach the next millisecond
+    #[default]
+    Wait,
+    /// Moves on to the next millisecond straight away, letting the
+    /// generator's logical clock run up to `max_ahead_ms` ahead of real time
+    /// before waiting. Keeps generating through bursts at the cost of
+    /// timestamps that may be slightly in the future.
+    ///
+    /// While ahead, the generator keeps issuing from its logical clock, so
+    /// backwards clock steps no larger than `max_ahead_ms` are absorbed too.
+    BorrowFuture { max_ahead_ms: i64 },
+}
+
+/// Where each millisecond's sequence starts.
+///
+/// Starting at zero puts most IDs' low bits near zero, which skews sharding
+/// by `id % n`. A nonzero start spreads them out, at the cost of fewer IDs
+/// per millisecond: the sequence still counts up to the layout's maximum, so
+/// IDs stay ordered within a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SequenceStart {
+    #[default]
+    Zero,
+    /// Always starts at the given offset
+    Fixed(u64),
+    /// Starts at an offset drawn each millisecond from `0..=max`
+    Random { max: u64 },
+}
+
+/// Backwards clock movement tolerated by [`Profile::Embedded`]
+const EMBEDDED_TOLERANCE: Duration = Duration::from_millis(100);
+
+/// Yields the [`Profile::HighThroughput`] wait makes per call before sleeping
+const HIGH_THROUGHPUT_YIELDS: u32 = 32;
+
+/// Checkpoint interval used by [`GeneratorBuilder::persist`] unless a
+/// [`Profile`] picks another
+const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Checkpoint interval of [`Profile::ClockParanoid`]
+const CLOCK_PARANOID_CHECKPOINT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Checkpoint interval of [`Profile::Embedded`]
+const EMBEDDED_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Step-by-step configuration for the sync and async generators.
+///
+/// Start from a [`Profile`] for sensible defaults, then override individual
+/// settings; later calls win.
+///
+/// # Example
+/// ```
+/// use snowflake_id::builder::Profile;
+/// use snowflake_id::{Epoch, SnowflakeGenerator};
+///
+/// let generator = SnowflakeGenerator::builder(1)
+///     .profile(Profile::LowLatency)
+///     .epoch(Epoch::DISCORD)
+///     .build()
+///     .unwrap();
+/// let id = generator.generate().unwrap();
+/// ```
+pub struct GeneratorBuilder<S> {
+    machine_id: u64,
+    epoch: Epoch,
+    clock: Option<Arc<dyn Clock>>,
+    tolerance: Duration,
+    wait: Arc<dyn WaitStrategy>,
+    reserve_tombstone_bit: bool,
+    checkpoint: Option<(Arc<dyn Checkpoint>, Duration)>,
+    /// Interval for [`GeneratorBuilder::persist`]
+    checkpoint_interval: Duration,
+    overflow: OverflowStrategy,
+    sequence_start: SequenceStart,
+    tick: Duration,
+    coarse_clock: bool,
+    floor: Option<S>,
+    restore: Option<GeneratorSnapshot>,
+    _marker: PhantomData<S>,
+}
+
+impl<S: Snowflake> GeneratorBuilder<S> {
+    /// Starts a builder with the same defaults as [`SnowflakeGenerator::new`]
+    pub fn new(machine_id: u64) -> Self {
+        GeneratorBuilder {
+            machine_id,
+            epoch: Epoch::custom(SNOWFLAKE_ID_EPOCH),
+            clock: None,
+            tolerance: Duration::from_millis(CLOCK_BACKWARDS_TOLERANCE_MS as u64),
+            wait: Arc::new(SleepWait),
+            reserve_tombstone_bit: false,
+            checkpoint: None,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            overflow: OverflowStrategy::Wait,
+            sequence_start: SequenceStart::Zero,
+            tick: Duration::from_millis(1),
+            coarse_clock: false,
+            floor: None,
+            restore: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Starts a builder that carries on after the last ID recorded in
+    /// `snapshot`, taking its machine ID, epoch and tick. Other settings
+    /// can be added as usual; changing the machine ID, epoch or tick makes
+    /// building fail with [`SnowflakeError::InvalidConfig`].
+    pub fn restore(snapshot: GeneratorSnapshot) -> Self {
+        let mut builder = Self::new(snapshot.machine_id)
+            .epoch(Epoch::custom(snapshot.epoch))
+            .tick(Duration::from_micros(snapshot.tick_micros));
+        builder.restore = Some(snapshot);
+        builder
+    }
+
+    /// Applies the wait, clock, tolerance and checkpoint interval settings
+    /// of `profile`. The interval takes effect through
+    /// [`GeneratorBuilder::persist`].
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.clock = None;
+        self.tolerance = Duration::from_millis(CLOCK_BACKWARDS_TOLERANCE_MS as u64);
+        self.checkpoint_interval = DEFAULT_CHECKPOINT_INTERVAL;
+        match profile {
+            Profile::LowLatency => self.wait = Arc::new(SpinWait),
+            Profile::HighThroughput => {
+                self.wait = Arc::new(BackoffWait::new(0, HIGH_THROUGHPUT_YIELDS))
+            }
+            Profile::ClockParanoid => {
+                self.wait = Arc::new(SleepWait);
+                self.clock = Some(Arc::new(MonotonicClock::new()));
+                self.tolerance = Duration::ZERO;
+                self.checkpoint_interval = CLOCK_PARANOID_CHECKPOINT_INTERVAL;
+            }
+            Profile::Embedded => {
+                self.wait = Arc::new(SleepWait);
+                self.tolerance = EMBEDDED_TOLERANCE;
+                self.checkpoint_interval = EMBEDDED_CHECKPOINT_INTERVAL;
+            }
+        }
+        self
+    }
+
+    pub fn epoch(mut self, epoch: impl Into<Epoch>) -> Self {
+        self.epoch = epoch.into();
+        self
+    }
+
+    /// Reads time from `clock` instead of the system clock
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Sets how far the clock may move backwards before generation fails;
+    /// smaller movements are waited out. Truncated to whole milliseconds.
+    pub fn clock_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets how the generator waits for the clock, see [`crate::wait`]
+    pub fn wait(mut self, wait: impl WaitStrategy + 'static) -> Self {
+        self.wait = Arc::new(wait);
+        self
+    }
+
+    /// Rejects machine IDs that overlap the layout's tombstone bit, so every
+    /// ID the generator mints can later be turned into a tombstone with
+    /// [`Snowflake::tombstone_of`]. Generators from an
+    /// [`EpochRotation`](crate::rotation::EpochRotation) always reserve it,
+    /// and the rotation's era bit is a different one.
+    pub fn reserve_tombstone_bit(mut self) -> Self {
+        self.reserve_tombstone_bit = true;
+        self
+    }
+
+    /// Sets what happens when a tick's sequence space runs out
+    pub fn overflow(mut self, overflow: OverflowStrategy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Sets where each tick's sequence starts. Building fails with
+    /// [`SnowflakeError::InvalidSequenceStart`] if the offset exceeds the
+    /// layout's maximum sequence.
+    pub fn sequence_start(mut self, start: SequenceStart) -> Self {
+        self.sequence_start = start;
+        self
+    }
+
+    /// Sets the length of one timestamp unit, one millisecond by default.
+    ///
+    /// Longer ticks, like Sonyflake's 10 ms, stretch the timestamp bits over
+    /// a longer lifetime at the cost of fewer IDs per second; microsecond
+    /// ticks do the opposite. Building fails with
+    /// [`SnowflakeError::InvalidTick`] unless the tick is a nonzero whole
+    /// number of microseconds.
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::SnowflakeGenerator;
+    /// use std::time::Duration;
+    ///
+    /// let generator = SnowflakeGenerator::builder(1)
+    ///     .tick(Duration::from_millis(10))
+    ///     .build()
+    ///     .unwrap();
+    /// let id = generator.generate().unwrap();
+    /// assert_eq!(generator.decompose(id).machine_id, 1);
+    /// ```
+    pub fn tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Makes bulk calls read the clock once every
+    /// [`COARSE_CLOCK_READ_EVERY`](crate::COARSE_CLOCK_READ_EVERY) IDs, and again whenever they have to wait,
+    /// instead of once per ID. Off by default.
+    ///
+    /// IDs stay unique and ordered; a batch's timestamps may just trail the
+    /// clock by the time it takes to mint that many IDs. Single-ID calls
+    /// always read the clock.
+    pub fn coarse_clock(mut self, enabled: bool) -> Self {
+        self.coarse_clock = enabled;
+        self
+    }
+
+    /// Persists a timestamp mark `interval` ahead of the clock to
+    /// `checkpoint`, and waits for the clock to pass any mark stored by a
+    /// previous process before generating. See [`crate::checkpoint`].
+    ///
+    /// Building fails with [`SnowflakeError::CheckpointFailed`] if the stored
+    /// mark can't be read.
+    pub fn checkpoint(mut self, checkpoint: impl Checkpoint + 'static, interval: Duration) -> Self {
+        self.checkpoint = Some((Arc::new(checkpoint), interval));
+        self
+    }
+
+    /// Checkpoints to `checkpoint` like [`GeneratorBuilder::checkpoint`], at
+    /// the interval chosen by the last [`Profile`] applied, or every second
+    /// without one
+    pub fn persist(self, checkpoint: impl Checkpoint + 'static) -> Self {
+        let interval = self.checkpoint_interval;
+        self.checkpoint(checkpoint, interval)
+    }
+
+    /// Checkpoints like [`GeneratorBuilder::checkpoint`] and refuses to
+    /// issue IDs at or below the floor, see [`StrictMonotonic`]
+    pub fn strict_monotonic(mut self, strict: StrictMonotonic<S>) -> Self {
+        self.checkpoint = Some((strict.checkpoint, strict.interval));
+        self.floor = strict.floor;
+        self
+    }
+
+    /// Builds a [`SnowflakeGenerator`]
+    pub fn build(self) -> Result<SnowflakeGenerator<S>, SnowflakeError> {
+        let wait = self.wait.clone();
+        Ok(SnowflakeGenerator::from_core(self.core()?, wait))
+    }
+
+    /// Builds a [`ShardedSnowflakeGenerator`] splitting the sequence space
+    /// into `shards` slices. Its callers pass their own wait to
+    /// [`ShardedSnowflakeGenerator::next_id`], so the builder's wait strategy
+    /// goes unused.
+    pub fn build_sharded(
+        self,
+        shards: usize,
+    ) -> Result<ShardedSnowflakeGenerator<S>, SnowflakeError> {
+        ShardedSnowflakeGenerator::from_core(self.core()?, shards)
+    }
+
+    /// Builds an [`AsyncSnowflakeGenerator`]
+    #[cfg(feature = "async")]
+    pub fn build_async(self) -> Result<AsyncSnowflakeGenerator<S>, SnowflakeError> {
+        let wait = self.wait.clone();
+        Ok(AsyncSnowflakeGenerator::from_core(self.core()?, wait))
+    }
+
+    /// Builds an [`AsyncSnowflakeGenerator`] that checkpoints to
+    /// `checkpoint` like [`GeneratorBuilder::checkpoint`], awaiting each
+    /// store instead of blocking the executor on it. Replaces any checkpoint
+    /// set on the builder.
+    ///
+    /// Fails with [`SnowflakeError::CheckpointFailed`] if the stored mark
+    /// can't be read.
+    #[cfg(feature = "async")]
+    pub async fn build_async_with_checkpoint(
+        mut self,
+        checkpoint: impl AsyncCheckpoint + 'static,
+        interval: Duration,
+    ) -> Result<AsyncSnowflakeGenerator<S>, SnowflakeError> {
+        let restored = checkpoint
+            .load()
+            .await
+            .map_err(|err| SnowflakeError::CheckpointFailed(Arc::new(err)))?;
+        self.checkpoint = None;
+        let wait = self.wait.clone();
+        let mut core = self.core()?;
+        core.checkpoint = Some(Checkpointing {
+            store: CheckpointStore::Async(Arc::new(checkpoint)),
+            interval_ms: i64::try_from(interval.as_millis()).unwrap_or(i64::MAX),
+            restored,
+        });
+        Ok(AsyncSnowflakeGenerator::from_core(core, wait))
+    }
+
+    pub(crate) fn core(self) -> Result<GeneratorCore<S>, SnowflakeError> {
+        let tombstone_bit = S::tombstone_mask() >> S::sequence_bits();
+        if self.reserve_tombstone_bit && self.machine_id & tombstone_bit != 0 {
+            return Err(SnowflakeError::InvalidMachineId(
+                self.machine_id,
+                S::max_machine_id() & !tombstone_bit,
+            ));
+        }
+        let clock = self.clock.unwrap_or_else(|| Arc::new(SystemClock));
+        let mut core = GeneratorCore::new(self.machine_id, self.epoch, clock)?;
+        core.tick_micros = i64::try_from(self.tick.as_micros())
+            .ok()
+            .filter(|&micros| micros > 0 && self.tick.subsec_nanos().is_multiple_of(1_000))
+            .ok_or(SnowflakeError::InvalidTick(self.tick))?;
+        core.tolerance_ms = i64::try_from(self.tolerance.as_millis()).unwrap_or(i64::MAX);
+        core.overflow = self.overflow;
+        core.coarse_clock = self.coarse_clock;
+        if let Some(snapshot) = self.restore {
+            if snapshot.sequence > S::max_sequence() {
+                return Err(SnowflakeError::InvalidConfig(format!(
+                    "snapshot sequence {} exceeds the layout maximum {}",
+                    snapshot.sequence,
+                    S::max_sequence()
+                )));
+            }
+            if (snapshot.machine_id, snapshot.epoch, snapshot.tick_micros)
+                != (core.machine_id, core.epoch, core.tick_micros as u64)
+            {
+                return Err(SnowflakeError::InvalidConfig(
+                    "a restored generator must keep the snapshot's machine id, epoch and tick"
+                        .to_string(),
+                ));
+            }
+            core.resume = Some((snapshot.last_timestamp, snapshot.sequence));
+        }
+        core.floor = self.floor.map(|id| {
+            core.epoch_ticks()
+                .saturating_add(Snowflake::timestamp(&id) as i64)
+        });
+        match self.sequence_start {
+            SequenceStart::Fixed(offset) | SequenceStart::Random { max: offset }
+                if offset > S::max_sequence() =>
+            {
+                return Err(SnowflakeError::InvalidSequenceStart(
+                    offset,
+                    S::max_sequence(),
+                ));
+            }
+            start => core.sequence_start = start,
+        }
+        if let Some((store, interval)) = self.checkpoint {
+            let restored = store
+                .load()
+                .map_err(|err| SnowflakeError::CheckpointFailed(Arc::new(err)))?;
+            core.checkpoint = Some(Checkpointing {
+                store: CheckpointStore::Blocking(store),
+                interval_ms: i64::try_from(interval.as_millis()).unwrap_or(i64::MAX),
+                restored,
+            });
+        }
+        Ok(core)
+    }
+}
+
+impl<S: Snowflake> SnowflakeGenerator<S> {
+    /// Returns a [`GeneratorBuilder`] for `machine_id`
+    pub fn builder(machine_id: u64) -> GeneratorBuilder<S> {
+        GeneratorBuilder::new(machine_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::manual_clock;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+    use crate::MAX_MACHINE_ID;
+    use crate::MAX_SEQUENCE;
+
+    #[test]
+    fn test_overflow_borrow_future() {
+        use crate::clock::ManualClock;
+        use crate::generator::SnowflakeOperation;
+
+        let start = SNOWFLAKE_ID_EPOCH + 1_000;
+        let clock = ManualClock::new(start);
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .overflow(OverflowStrategy::BorrowFuture { max_ahead_ms: 2 })
+            .build()
+            .unwrap();
+        let per_tick = MAX_SEQUENCE as usize + 1;
+
+        // Three ticks' worth without the clock moving, then a wait
+        let ids = generator
+            .next_id_bulk_checked(3 * per_tick, |_| panic!("waited"))
+            .unwrap();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(ids[ids.len() - 1].timestamp(), 1_002);
+        assert!(matches!(
+            generator.try_next_id(),
+            Ok(SnowflakeOperation::Pending(wait)) if wait == Duration::from_millis(1)
+        ));
+
+        clock.advance(Duration::from_millis(1));
+        let next = generator.generate().unwrap();
+        assert_eq!(next.timestamp(), 1_003);
+
+        // Backwards steps within the lead are absorbed
+        clock.set(start + 1);
+        assert!(generator.generate().unwrap() > next);
+
+        let waiting = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+        assert_ne!(generator.fingerprint(), waiting.fingerprint());
+    }
+
+    #[test]
+    fn test_sequence_start() {
+        use crate::generator::SnowflakeOperation;
+        use std::collections::HashSet;
+
+        let clock = manual_clock();
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .sequence_start(SequenceStart::Fixed(4000))
+            .build()
+            .unwrap();
+        let ids = generator
+            .next_id_bulk_checked(96, |_| panic!("waited"))
+            .unwrap();
+        assert_eq!(ids[0].sequence(), 4000);
+        assert_eq!(ids[95].sequence(), MAX_SEQUENCE);
+        assert!(matches!(
+            generator.try_next_id(),
+            Ok(SnowflakeOperation::Pending(_))
+        ));
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(generator.generate().unwrap().sequence(), 4000);
+
+        // Random starts vary between ticks without breaking uniqueness or order
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .sequence_start(SequenceStart::Random { max: 1000 })
+            .wait(move |_| clock.advance(Duration::from_millis(1)))
+            .build()
+            .unwrap();
+        let ids = generator.generate_bulk(50_000).unwrap();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+        let starts: HashSet<u64> = ids
+            .windows(2)
+            .filter(|pair| pair[0].timestamp() != pair[1].timestamp())
+            .map(|pair| pair[1].sequence())
+            .collect();
+        assert!(starts.len() > 1);
+        assert!(starts.iter().all(|&start| start <= 1000));
+
+        assert!(matches!(
+            SnowflakeGenerator::builder(1)
+                .sequence_start(SequenceStart::Random {
+                    max: MAX_SEQUENCE + 1
+                })
+                .build(),
+            Err(SnowflakeError::InvalidSequenceStart(4096, 4095))
+        ));
+    }
+
+    #[test]
+    fn test_tick() {
+        use crate::clock::ManualClock;
+        use crate::generator::SnowflakeOperation;
+
+        let clock = ManualClock::new(Epoch::DISCORD.as_millis() + 1_234);
+        let generator = SnowflakeGenerator::builder(1)
+            .epoch(Epoch::DISCORD)
+            .tick(Duration::from_millis(10))
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+        assert_eq!(generator.tick(), Duration::from_millis(10));
+        let id = generator.generate().unwrap();
+        assert_eq!(id.timestamp(), 123);
+        let parts = generator.decompose(id);
+        assert_eq!(
+            parts.datetime.timestamp_millis(),
+            Epoch::DISCORD.as_millis() + 1_230
+        );
+        assert_eq!((parts.raw_offset, parts.machine_id), (123, 1));
+
+        // The default 5ms tolerance rounds up to one tick
+        clock.rewind(Duration::from_millis(10));
+        assert!(matches!(
+            generator.try_next_id(),
+            Ok(SnowflakeOperation::Pending(wait)) if wait == Duration::from_millis(10)
+        ));
+        clock.rewind(Duration::from_millis(20));
+        assert!(matches!(
+            generator.try_next_id(),
+            Err(SnowflakeError::ClockMovedBackwards { .. })
+        ));
+
+        clock.advance(Duration::from_millis(30));
+        for _ in 0..MAX_SEQUENCE {
+            generator.try_next_id().unwrap();
+        }
+        assert!(matches!(
+            generator.try_next_id(),
+            Ok(SnowflakeOperation::Pending(wait)) if wait == Duration::from_millis(10)
+        ));
+
+        struct MicroClock(i64);
+        impl Clock for MicroClock {
+            fn now_millis(&self) -> i64 {
+                self.0 / 1_000
+            }
+            fn now_micros(&self) -> i64 {
+                self.0
+            }
+        }
+        let micros = SnowflakeGenerator::builder(1)
+            .tick(Duration::from_micros(1))
+            .clock(MicroClock(SNOWFLAKE_ID_EPOCH * 1_000 + 1_234_567))
+            .build()
+            .unwrap();
+        assert_eq!(micros.generate().unwrap().timestamp(), 1_234_567);
+        assert_ne!(
+            micros.fingerprint(),
+            SnowflakeGenerator::new(1).unwrap().fingerprint()
+        );
+
+        for tick in [Duration::ZERO, Duration::from_nanos(1_500)] {
+            assert!(matches!(
+                SnowflakeGenerator::builder(1).tick(tick).build(),
+                Err(SnowflakeError::InvalidTick(t)) if t == tick
+            ));
+        }
+    }
+
+    #[test]
+    fn test_profile_checkpoint_interval() {
+        use crate::test_support::MemoryCheckpoint;
+
+        let start = SNOWFLAKE_ID_EPOCH + 1_000;
+        for (profile, interval) in [
+            (None, 1_000),
+            (Some(Profile::HighThroughput), 1_000),
+            (Some(Profile::ClockParanoid), 100),
+            (Some(Profile::Embedded), 10_000),
+        ] {
+            let checkpoint = MemoryCheckpoint::default();
+            let mut builder = SnowflakeGenerator::builder(1);
+            if let Some(profile) = profile {
+                builder = builder.profile(profile);
+            }
+            let generator = builder
+                .clock(manual_clock())
+                .persist(checkpoint.clone())
+                .build()
+                .unwrap();
+            generator.generate().unwrap();
+            assert_eq!(checkpoint.mark(), Some(start + interval), "{:?}", profile);
+        }
+    }
+
+    #[test]
+    fn test_builder_profiles() {
+        for profile in [
+            Profile::LowLatency,
+            Profile::HighThroughput,
+            Profile::ClockParanoid,
+            Profile::Embedded,
+        ] {
+            let generator = SnowflakeGenerator::builder(7)
+                .profile(profile)
+                .epoch(Epoch::DISCORD)
+                .build()
+                .unwrap();
+            assert_eq!(generator.epoch(), Epoch::DISCORD.as_millis());
+            assert!(generator.self_test().passed(), "{:?}", profile);
+
+            let ids: Vec<SnowflakeId> = generator.iter().take(10_000).collect();
+            assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(generator.generate().unwrap() > ids[ids.len() - 1]);
+        }
+
+        // Tolerance is configurable, and zero rejects any backwards step
+        let clock = manual_clock();
+        let generator = SnowflakeGenerator::builder(1)
+            .profile(Profile::ClockParanoid)
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+        generator.generate().unwrap();
+        clock.rewind(Duration::from_millis(1));
+        assert!(matches!(
+            generator.try_next_id(),
+            Err(SnowflakeError::ClockMovedBackwards { .. })
+        ));
+
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .clock_tolerance(Duration::from_millis(50))
+            .build()
+            .unwrap();
+        generator.generate().unwrap();
+        clock.rewind(Duration::from_millis(50));
+        assert!(matches!(
+            generator.try_next_id(),
+            Ok(crate::generator::SnowflakeOperation::Pending(wait)) if wait == Duration::from_millis(50)
+        ));
+        assert!(GeneratorBuilder::<SnowflakeId>::new(MAX_MACHINE_ID + 1)
+            .build()
+            .is_err());
+    }
+}
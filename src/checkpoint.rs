@@ -0,0 +1,510 @@
+//! Persisting the generator's timestamp so restarts can't reissue IDs.
+//!
+//! A generator only remembers its last timestamp in memory. If a process
+//! restarts while the wall clock is behind where it was, the new process
+//! can mint IDs that were already handed out. With a [`Checkpoint`]
+//! configured via
+//! [`GeneratorBuilder::checkpoint`](crate::builder::GeneratorBuilder::checkpoint),
+//! the generator stores a high-water mark one interval ahead of the clock,
+//! refreshing it whenever the clock reaches it. On startup it restores the
+//! mark and waits until the wall clock passes it before generating, so every
+//! new ID sorts after every ID minted before the restart.
+//!
+//! A restart therefore pauses generation for up to one interval; shorter
+//! intervals mean shorter pauses but more frequent writes.
+//!
+//! The mark is stored while the generator's lock is held, so the call that
+//! reaches it, and every caller queued behind that call, blocks until
+//! [`Checkpoint::store`] returns. [`FileCheckpoint`] syncs the file to disk
+//! each time, which costs one disk flush per interval.
+//!
+//! [`AsyncCheckpoint`] is the same store for async services, awaited
+//! rather than blocked on (`async` feature).
+//!
+//! Containers without a durable local disk can keep the mark in Redis with
+//! `RedisCheckpoint` (`redis` feature) or in S3, GCS or another object store
+//! with [`ObjectStoreCheckpoint`].
+//!
+//! [`StrictMonotonic`] adds a floor on top, typically the `MAX(id)` of the
+//! table the IDs go into, for when the checkpoint alone can't be trusted,
+//! e.g. after restoring the host from an older image.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::checkpoint::FileCheckpoint;
+//! use snowflake_id::SnowflakeGenerator;
+//! use std::time::Duration;
+//!
+//! let path = std::env::temp_dir().join("snowflake-doc-checkpoint");
+//! let generator = SnowflakeGenerator::builder(1)
+//!     .checkpoint(FileCheckpoint::new(&path), Duration::from_millis(50))
+//!     .build()
+//!     .unwrap();
+//! let id = generator.generate().unwrap();
+//! # std::fs::remove_file(path).ok();
+//! ```
+
+use crate::sync::Mutex;
+use std::fmt;
+use std::fs::{self, File};
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Durable storage for a generator's timestamp high-water mark
+pub trait Checkpoint: Send + Sync {
+    /// Returns the stored mark in milliseconds since the Unix epoch, or
+    /// `None` if nothing has been stored yet
+    fn load(&self) -> io::Result<Option<i64>>;
+
+    /// Durably replaces the stored mark
+    fn store(&self, millis: i64) -> io::Result<()>;
+}
+
+/// Future returned by [`AsyncCheckpoint`]'s methods
+#[cfg(feature = "async")]
+pub type CheckpointFuture<'a, T> = Pin<Box<dyn Future<Output = io::Result<T>> + Send + 'a>>;
+
+/// A [`Checkpoint`] reached over the network, for
+/// [`GeneratorBuilder::build_async_with_checkpoint`](crate::builder::GeneratorBuilder::build_async_with_checkpoint)
+///
+/// The async generator awaits each store instead of blocking its executor
+/// thread on it. Callers still queue behind a pending store, as they do
+/// behind [`Checkpoint::store`].
+#[cfg(feature = "async")]
+pub trait AsyncCheckpoint: Send + Sync {
+    /// Returns the stored mark in milliseconds since the Unix epoch, or
+    /// `None` if nothing has been stored yet
+    fn load(&self) -> CheckpointFuture<'_, Option<i64>>;
+
+    /// Durably replaces the stored mark
+    fn store(&self, millis: i64) -> CheckpointFuture<'_, ()>;
+}
+
+/// Stores the mark as decimal text in a file, replacing it atomically
+#[derive(Debug, Clone)]
+pub struct FileCheckpoint {
+    path: PathBuf,
+}
+
+impl FileCheckpoint {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileCheckpoint { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Checkpoint for FileCheckpoint {
+    fn load(&self) -> io::Result<Option<i64>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        parse_mark(&contents, self.path.display()).map(Some)
+    }
+
+    fn store(&self, millis: i64) -> io::Result<()> {
+        // Write then rename, so a crash mid-write leaves the old mark intact
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let mut file = File::create(&temp)?;
+        file.write_all(format_mark(millis).as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp, &self.path)
+    }
+}
+
+/// Stores the mark under a key in Redis
+///
+/// Each store is a single `SET`, which Redis applies atomically, so a reader
+/// sees the old mark or the new one and never a mix. A connection is opened
+/// per call, as calls come once per checkpoint interval.
+#[cfg(feature = "redis")]
+#[derive(Debug, Clone)]
+pub struct RedisCheckpoint {
+    client: redis::Client,
+    key: String,
+}
+
+#[cfg(feature = "redis")]
+impl RedisCheckpoint {
+    /// Stores the mark under `key` on the server `client` connects to
+    pub fn new(client: redis::Client, key: impl Into<String>) -> Self {
+        RedisCheckpoint {
+            client,
+            key: key.into(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    fn connection(&self) -> io::Result<redis::Connection> {
+        self.client.get_connection().map_err(io::Error::other)
+    }
+}
+
+#[cfg(feature = "redis")]
+impl Checkpoint for RedisCheckpoint {
+    fn load(&self) -> io::Result<Option<i64>> {
+        use redis::Commands;
+
+        self.connection()?.get(&self.key).map_err(io::Error::other)
+    }
+
+    fn store(&self, millis: i64) -> io::Result<()> {
+        use redis::Commands;
+
+        self.connection()?
+            .set(&self.key, millis)
+            .map_err(io::Error::other)
+    }
+}
+
+/// A bucket in S3, GCS or another object store, for
+/// [`ObjectStoreCheckpoint`]
+///
+/// Implement it over the store's client library. Both calls act on whole
+/// objects, and `put_if` must be a conditional write: S3's `If-Match` and
+/// `If-None-Match` headers, or GCS's `ifGenerationMatch`.
+pub trait ObjectStore: Send + Sync {
+    /// Returns the object's contents and version, such as its ETag or
+    /// generation, or `None` if it doesn't exist
+    fn get(&self, key: &str) -> io::Result<Option<(Vec<u8>, String)>>;
+
+    /// Replaces the object only if its version is still `version`, or
+    /// creates it only if it doesn't exist when `version` is `None`, and
+    /// returns the new version. Fails if the condition doesn't hold.
+    fn put_if(&self, key: &str, contents: Vec<u8>, version: Option<&str>) -> io::Result<String>;
+}
+
+/// Stores the mark as a small text object in an [`ObjectStore`]
+///
+/// Object stores replace whole objects at once, so the mark is never seen
+/// half-written. Each store is conditional on the version this checkpoint
+/// last loaded or stored: if another process wrote the mark in between, the
+/// store fails and generation stops with
+/// [`SnowflakeError::CheckpointFailed`](crate::error::SnowflakeError::CheckpointFailed)
+/// instead of the two overwriting each other's marks.
+pub struct ObjectStoreCheckpoint<B> {
+    store: B,
+    key: String,
+    /// Version of the object as last seen, `None` if it didn't exist
+    version: Mutex<Option<String>>,
+}
+
+impl<B: ObjectStore> ObjectStoreCheckpoint<B> {
+    /// Stores the mark as the object `key` in `store`
+    pub fn new(store: B, key: impl Into<String>) -> Self {
+        ObjectStoreCheckpoint {
+            store,
+            key: key.into(),
+            version: Mutex::new(None),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl<B: ObjectStore> Checkpoint for ObjectStoreCheckpoint<B> {
+    fn load(&self) -> io::Result<Option<i64>> {
+        let mut version = self.version.lock();
+        let Some((contents, current)) = self.store.get(&self.key)? else {
+            *version = None;
+            return Ok(None);
+        };
+        let contents = String::from_utf8_lossy(&contents);
+        let mark = parse_mark(&contents, format_args!("object {}", self.key))?;
+        *version = Some(current);
+        Ok(Some(mark))
+    }
+
+    fn store(&self, millis: i64) -> io::Result<()> {
+        let mut version = self.version.lock();
+        let contents = format_mark(millis).into_bytes();
+        *version = Some(self.store.put_if(&self.key, contents, version.as_deref())?);
+        Ok(())
+    }
+}
+
+fn format_mark(millis: i64) -> String {
+    format!("{}\n", millis)
+}
+
+fn parse_mark(contents: &str, source: impl fmt::Display) -> io::Result<i64> {
+    contents.trim().parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("malformed checkpoint in {}", source),
+        )
+    })
+}
+
+/// Settings for
+/// [`GeneratorBuilder::strict_monotonic`](crate::builder::GeneratorBuilder::strict_monotonic):
+/// a checkpoint plus an optional floor ID.
+///
+/// The generator never issues an ID at or below the floor, from single IDs,
+/// bulk calls and reserved blocks alike: it skips the floor's tick entirely,
+/// so this holds whichever machine minted the floor.
+/// If the clock is behind the floor by more than the clock tolerance,
+/// generation fails with
+/// [`SnowflakeError::ClockMovedBackwards`](crate::error::SnowflakeError::ClockMovedBackwards)
+/// instead of waiting.
+///
+/// # Example
+/// ```
+/// use snowflake_id::checkpoint::{FileCheckpoint, StrictMonotonic};
+/// use snowflake_id::{SnowflakeGenerator, SnowflakeId};
+/// use std::time::Duration;
+///
+/// // e.g. SELECT MAX(id) FROM orders
+/// let max_id = SnowflakeGenerator::new(7).unwrap().generate().unwrap();
+///
+/// let path = std::env::temp_dir().join("snowflake-doc-strict");
+/// let strict = StrictMonotonic::new(FileCheckpoint::new(&path), Duration::from_millis(50))
+///     .floor(max_id);
+/// let generator = SnowflakeGenerator::builder(1)
+///     .strict_monotonic(strict)
+///     .build()
+///     .unwrap();
+/// assert!(generator.generate().unwrap() > max_id);
+/// # std::fs::remove_file(path).ok();
+/// ```
+pub struct StrictMonotonic<S> {
+    pub(crate) checkpoint: Arc<dyn Checkpoint>,
+    pub(crate) interval: Duration,
+    pub(crate) floor: Option<S>,
+}
+
+impl<S> StrictMonotonic<S> {
+    /// Checkpoints to `checkpoint` every `interval`, as
+    /// [`GeneratorBuilder::checkpoint`](crate::builder::GeneratorBuilder::checkpoint) does
+    pub fn new(checkpoint: impl Checkpoint + 'static, interval: Duration) -> Self {
+        StrictMonotonic {
+            checkpoint: Arc::new(checkpoint),
+            interval,
+            floor: None,
+        }
+    }
+
+    /// Never issues an ID at or below `id`
+    pub fn floor(mut self, id: S) -> Self {
+        self.floor = Some(id);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::SnowflakeError;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+    use crate::SNOWFLAKE_ID_EPOCH;
+
+    #[test]
+    fn test_checkpoint() {
+        use crate::clock::ManualClock;
+        use crate::generator::SnowflakeOperation;
+
+        let path =
+            std::env::temp_dir().join(format!("snowflake-checkpoint-{}", std::process::id()));
+        let checkpoint = FileCheckpoint::new(&path);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(checkpoint.load().unwrap(), None);
+
+        let start = SNOWFLAKE_ID_EPOCH + 1_000;
+        let clock = ManualClock::new(start);
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .checkpoint(checkpoint.clone(), Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let last = generator.generate().unwrap();
+        assert_eq!(checkpoint.load().unwrap(), Some(start + 100));
+        clock.advance(Duration::from_millis(100));
+        generator.generate().unwrap();
+        assert_eq!(checkpoint.load().unwrap(), Some(start + 200));
+
+        // After a restart with the clock set back, nothing is issued until it
+        // passes the stored mark
+        clock.set(start);
+        let restarted = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .checkpoint(checkpoint.clone(), Duration::from_millis(100))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            restarted.try_next_id(),
+            Ok(SnowflakeOperation::Pending(wait)) if wait == Duration::from_millis(201)
+        ));
+        clock.set(start + 201);
+        assert!(restarted.generate().unwrap() > last);
+
+        std::fs::write(&path, "not a timestamp").unwrap();
+        assert!(matches!(
+            SnowflakeGenerator::builder(1)
+                .checkpoint(checkpoint, Duration::from_millis(100))
+                .build(),
+            Err(SnowflakeError::CheckpointFailed(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_object_store_checkpoint() {
+        use std::collections::HashMap;
+
+        /// Versions objects with a counter, as GCS does with generations
+        type Objects = HashMap<String, (Vec<u8>, u64)>;
+
+        #[derive(Clone)]
+        struct MemoryBucket(Arc<Mutex<Objects>>);
+
+        impl ObjectStore for MemoryBucket {
+            fn get(&self, key: &str) -> io::Result<Option<(Vec<u8>, String)>> {
+                let objects = self.0.lock();
+                Ok(objects
+                    .get(key)
+                    .map(|(contents, version)| (contents.clone(), version.to_string())))
+            }
+
+            fn put_if(
+                &self,
+                key: &str,
+                contents: Vec<u8>,
+                version: Option<&str>,
+            ) -> io::Result<String> {
+                let mut objects = self.0.lock();
+                let current = objects.get(key).map(|(_, version)| version.to_string());
+                if current.as_deref() != version {
+                    return Err(io::Error::other("precondition failed"));
+                }
+                let next = current.map_or(1, |version| version.parse::<u64>().unwrap() + 1);
+                objects.insert(key.to_string(), (contents, next));
+                Ok(next.to_string())
+            }
+        }
+
+        let bucket = MemoryBucket(Arc::new(Mutex::new(HashMap::new())));
+        let clock = crate::test_support::manual_clock();
+        let start = SNOWFLAKE_ID_EPOCH + 1_000;
+        let build = || {
+            SnowflakeGenerator::builder(1)
+                .clock(clock.clone())
+                .checkpoint(
+                    ObjectStoreCheckpoint::new(bucket.clone(), "ids/orders"),
+                    Duration::from_millis(100),
+                )
+                .build()
+        };
+        let generator = build().unwrap();
+        generator.generate().unwrap();
+        let (contents, version) = bucket.get("ids/orders").unwrap().unwrap();
+        assert_eq!(contents, format!("{}\n", start + 100).into_bytes());
+        assert_eq!(version, "1");
+        clock.advance(Duration::from_millis(100));
+        generator.generate().unwrap();
+        let checkpoint = ObjectStoreCheckpoint::new(bucket.clone(), "ids/orders");
+        assert_eq!(checkpoint.load().unwrap(), Some(start + 200));
+
+        // A second writer loses its claim to the mark
+        let restarted = build().unwrap();
+        clock.advance(Duration::from_millis(200));
+        restarted.generate().unwrap();
+        clock.advance(Duration::from_millis(100));
+        assert!(matches!(
+            generator.generate(),
+            Err(SnowflakeError::CheckpointFailed(_))
+        ));
+
+        bucket
+            .put_if("ids/orders", b"oops".to_vec(), Some("3"))
+            .unwrap();
+        assert!(matches!(build(), Err(SnowflakeError::CheckpointFailed(_))));
+    }
+
+    #[test]
+    fn test_strict_monotonic() {
+        use crate::clock::ManualClock;
+        use crate::generator::SnowflakeOperation;
+
+        let path = std::env::temp_dir().join(format!("snowflake-strict-{}", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let start = SNOWFLAKE_ID_EPOCH + 1_000;
+        let clock = ManualClock::new(start);
+        let strict = |floor| {
+            SnowflakeGenerator::builder(1)
+                .clock(clock.clone())
+                .strict_monotonic(
+                    StrictMonotonic::new(FileCheckpoint::new(&path), Duration::from_millis(100))
+                        .floor(floor),
+                )
+                .build()
+                .unwrap()
+        };
+
+        // A floor minted this tick by a higher machine id: skip the whole tick
+        let floor = SnowflakeId::from_component_parts(1_000, 9, 0);
+        let generator = strict(floor);
+        assert!(matches!(
+            generator.try_next_id(),
+            Ok(SnowflakeOperation::Pending(_))
+        ));
+        clock.advance(Duration::from_millis(1));
+        let id = generator.generate().unwrap();
+        assert!(id > floor);
+        assert_eq!(id.machine_id(), 1);
+
+        // A floor far ahead of the clock is refused rather than waited out
+        std::fs::remove_file(&path).ok();
+        let generator = strict(SnowflakeId::from_component_parts(60_000, 9, 0));
+        assert!(matches!(
+            generator.try_next_id(),
+            Err(SnowflakeError::ClockMovedBackwards { .. })
+        ));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_strict_monotonic_bulk_paths() {
+        use crate::test_support::{manual_clock, MemoryCheckpoint};
+
+        let floor = SnowflakeId::from_component_parts(1_000, 9, 0);
+        let clock = manual_clock();
+        let strict = || {
+            let waiter = clock.clone();
+            SnowflakeGenerator::builder(1)
+                .clock(clock.clone())
+                .wait(move |wait| waiter.advance(wait))
+                .strict_monotonic(
+                    StrictMonotonic::new(MemoryCheckpoint::default(), Duration::from_millis(100))
+                        .floor(floor),
+                )
+                .build()
+                .unwrap()
+        };
+
+        let block = strict()
+            .reserve_block(10, |wait| clock.advance(wait))
+            .unwrap();
+        assert!(block.first().unwrap() > floor);
+
+        clock.set(SNOWFLAKE_ID_EPOCH + 1_000);
+        let chunk: Vec<_> = strict().next_id_chunks(10).unwrap().collect();
+        assert!(chunk[0] > floor);
+    }
+}
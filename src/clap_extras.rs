@@ -0,0 +1,110 @@
+//! clap argument parsing for snowflake IDs and machine ids.
+//!
+//! [`SnowflakeId`] implements `ValueParserFactory`, so `SnowflakeId` fields
+//! in `#[derive(Parser)]` structs parse without any attributes. Machine ids
+//! are plain `u64`s; point their `value_parser` at [`parse_machine_id`] to
+//! reject values above [`MAX_MACHINE_ID`] before a generator is built.
+//!
+//! ```
+//! use clap::Parser;
+//! use snowflake_id::clap_extras::parse_machine_id;
+//! use snowflake_id::SnowflakeId;
+//!
+//! #[derive(Parser)]
+//! struct Args {
+//!     #[arg(long)]
+//!     id: SnowflakeId,
+//!     #[arg(long, value_parser = parse_machine_id)]
+//!     machine_id: u64,
+//! }
+//!
+//! let args = Args::try_parse_from(["tool", "--id", "42", "--machine-id", "7"]).unwrap();
+//! assert_eq!(args.id.id(), 42);
+//!
+//! let err = Args::try_parse_from(["tool", "--id", "42", "--machine-id", "4096"])
+//!     .err()
+//!     .unwrap();
+//! assert!(err.to_string().contains("must be between 0 and 1023"));
+//! ```
+
+use crate::defs::MAX_MACHINE_ID;
+use crate::SnowflakeId;
+use clap::builder::ValueParserFactory;
+use std::num::IntErrorKind;
+
+impl ValueParserFactory for SnowflakeId {
+    type Parser = fn(&str) -> Result<SnowflakeId, String>;
+
+    fn value_parser() -> Self::Parser {
+        parse_id
+    }
+}
+
+/// Parses a [`SnowflakeId`], explaining what was wrong with a rejected value
+pub fn parse_id(value: &str) -> Result<SnowflakeId, String> {
+    let out_of_range = || format!("out of range; the largest snowflake ID is {}", i64::MAX);
+    SnowflakeId::new(parse_integer(value, out_of_range)?)
+        .map_err(|_| "snowflake IDs can't be negative".to_string())
+}
+
+/// Parses a machine id, rejecting values above [`MAX_MACHINE_ID`]
+pub fn parse_machine_id(value: &str) -> Result<u64, String> {
+    let out_of_range = || format!("must be between 0 and {}", MAX_MACHINE_ID);
+    let machine_id =
+        u64::try_from(parse_integer(value, out_of_range)?).map_err(|_| out_of_range())?;
+    if machine_id > MAX_MACHINE_ID {
+        return Err(out_of_range());
+    }
+    Ok(machine_id)
+}
+
+/// Parses an `i64`, describing the accepted range with `out_of_range` when
+/// the value doesn't fit
+fn parse_integer(value: &str, out_of_range: impl Fn() -> String) -> Result<i64, String> {
+    value.parse::<i64>().map_err(|e| match e.kind() {
+        IntErrorKind::Empty => "expected a number, got an empty value".to_string(),
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => out_of_range(),
+        _ => "expected a number".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clap_parsers() {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Args {
+            #[arg(long)]
+            id: SnowflakeId,
+            #[arg(long, value_parser = parse_machine_id)]
+            machine_id: u64,
+        }
+
+        // `=` so clap doesn't take negative values for flags
+        let parse = |id: &str, machine_id: &str| {
+            Args::try_parse_from([
+                "tool".to_string(),
+                format!("--id={}", id),
+                format!("--machine-id={}", machine_id),
+            ])
+        };
+        let args = parse("175928847299117063", "1023").unwrap();
+        assert_eq!(args.id.id(), 175928847299117063);
+        assert_eq!(args.machine_id, MAX_MACHINE_ID);
+
+        let message = |result: Result<Args, clap::Error>| match result {
+            Ok(_) => panic!("invalid arguments accepted"),
+            Err(err) => err.to_string(),
+        };
+        assert!(message(parse("-1", "0")).contains("snowflake IDs can't be negative"));
+        assert!(message(parse("abc", "0")).contains("invalid value 'abc' for '--id <ID>'"));
+        assert!(message(parse("99999999999999999999", "0")).contains("out of range"));
+        assert!(message(parse("1", "1024")).contains("must be between 0 and 1023"));
+        assert!(message(parse("1", "-1")).contains("must be between 0 and 1023"));
+        assert!(message(parse("1", "99999999999999999999")).contains("must be between 0 and 1023"));
+    }
+}
@@ -0,0 +1,276 @@
+//! Time sources for the generators, and how they cope with leap seconds
+//! and clock adjustments.
+//!
+//! # Leap seconds and smeared clocks
+//! Unix time has no leap seconds, so the system clock has to absorb them.
+//! A kernel that steps the clock replays the last second of the day, which
+//! a generator sees as the clock moving back one second: beyond the default
+//! tolerance, so generation fails with `ClockMovedBackwards` until the clock
+//! catches up. Raising
+//! [`GeneratorBuilder::clock_tolerance`](crate::builder::GeneratorBuilder::clock_tolerance)
+//! to a little over a second waits it out instead, and a [`MonotonicClock`]
+//! never sees it at all.
+//!
+//! Hosts that smear the leap second, as the big cloud providers' time
+//! services do, slew the clock over many hours instead of stepping it, so it
+//! never moves backwards and IDs keep flowing; their timestamps are just off
+//! by up to half a second around the event. The gap between the wall and
+//! monotonic clocks shows up in
+//! [`SnowflakeGenerator::clock_health`](crate::generator::SnowflakeGenerator::clock_health),
+//! alongside whether the generator is currently waiting out a backwards step.
+
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A source of wall-clock time for the generators.
+///
+/// The default [`SystemClock`] reads the system clock; custom implementations
+/// let tests and special environments control what "now" means.
+pub trait Clock: Send + Sync {
+    /// Returns the current time in milliseconds since the Unix epoch
+    fn now_millis(&self) -> i64;
+
+    /// Returns the current time in microseconds since the Unix epoch.
+    /// Defaults to [`Clock::now_millis`] scaled up, for clocks without
+    /// finer resolution.
+    fn now_micros(&self) -> i64 {
+        self.now_millis().saturating_mul(1_000)
+    }
+}
+
+/// A generator's view of its clock, from
+/// [`SnowflakeGenerator::clock_health`](crate::generator::SnowflakeGenerator::clock_health)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClockHealth {
+    /// How far the generator's clock has moved relative to the monotonic
+    /// clock since the generator's first call, in microseconds to within a
+    /// tick; positive when it has gained. Zero before the first call and
+    /// where no monotonic clock is available.
+    pub drift_micros: i64,
+    /// Time since the generator last saw its clock behind the last tick it
+    /// issued from, or `None` if it never has
+    pub since_last_backwards: Option<Duration>,
+    /// Whether the clock is currently behind by no more than the tolerance,
+    /// so generation is waiting for it to catch up
+    pub in_tolerance_wait: bool,
+    /// Whether the clock is currently behind by more than the tolerance, so
+    /// generation fails with `ClockMovedBackwards`
+    pub rejecting: bool,
+}
+
+impl ClockHealth {
+    /// Returns whether the generator can issue IDs without waiting for the
+    /// clock, and its clock has drifted by at most `max_drift`
+    pub fn is_healthy(&self, max_drift: Duration) -> bool {
+        !self.in_tolerance_wait
+            && !self.rejecting
+            && u128::from(self.drift_micros.unsigned_abs()) <= max_drift.as_micros()
+    }
+}
+
+/// Reads the monotonic clock, which doesn't exist in the browser
+pub(crate) fn monotonic_now() -> Option<Instant> {
+    if cfg!(all(feature = "wasm", target_arch = "wasm32")) {
+        None
+    } else {
+        Some(Instant::now())
+    }
+}
+
+/// Reads time from the system clock via chrono
+///
+/// With the `wasm` feature on `wasm32` targets, time comes from
+/// `Date.now()` instead, as there is no system clock to read.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+    fn now_millis(&self) -> i64 {
+        Utc::now().timestamp_millis()
+    }
+
+    #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+    fn now_micros(&self) -> i64 {
+        Utc::now().timestamp_micros()
+    }
+
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    fn now_millis(&self) -> i64 {
+        js_sys::Date::now() as i64
+    }
+}
+
+/// Wall-clock time that never moves backwards.
+///
+/// Reads the system clock once at construction and advances from there with
+/// the monotonic [`Instant`] clock, so NTP steps and manual clock changes
+/// after startup are ignored. The trade-off is that drift accumulated by the
+/// monotonic clock is never corrected, which matters for long-lived processes
+/// that rely on IDs carrying accurate timestamps.
+///
+/// Not available in the browser: [`Instant`] panics on
+/// `wasm32-unknown-unknown`.
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    anchor_micros: i64,
+    anchor: Instant,
+}
+
+impl MonotonicClock {
+    /// Anchors a new clock to the current system time
+    pub fn new() -> Self {
+        MonotonicClock {
+            anchor_micros: SystemClock.now_micros(),
+            anchor: Instant::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now_millis(&self) -> i64 {
+        self.now_micros().div_euclid(1_000)
+    }
+
+    fn now_micros(&self) -> i64 {
+        self.anchor_micros + self.anchor.elapsed().as_micros() as i64
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now_millis(&self) -> i64 {
+        (**self).now_millis()
+    }
+
+    fn now_micros(&self) -> i64 {
+        (**self).now_micros()
+    }
+}
+
+mod manual {
+    use super::Clock;
+    use crate::defs::SNOWFLAKE_ID_EPOCH;
+    use std::sync::atomic::{AtomicI64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// A clock that only moves when told to, with microsecond resolution
+    ///
+    /// Clones share the same time, so keep one in the test and hand another
+    /// to the generator. Public with the `test-util` feature, see
+    /// [`crate::test_util`].
+    #[derive(Debug, Clone, Default)]
+    pub struct ManualClock {
+        micros: Arc<AtomicI64>,
+    }
+
+    #[cfg_attr(not(feature = "test-util"), allow(dead_code))]
+    impl ManualClock {
+        /// Starts at `millis` since the Unix epoch
+        pub fn new(millis: i64) -> Self {
+            ManualClock {
+                micros: Arc::new(AtomicI64::new(millis.saturating_mul(1_000))),
+            }
+        }
+
+        /// Starts `offset_ms` after the crate's default epoch
+        pub fn at_epoch_offset(offset_ms: i64) -> Self {
+            Self::new(SNOWFLAKE_ID_EPOCH + offset_ms)
+        }
+
+        /// Jumps to `millis` since the Unix epoch, forwards or backwards
+        pub fn set(&self, millis: i64) {
+            self.micros
+                .store(millis.saturating_mul(1_000), Ordering::SeqCst);
+        }
+
+        /// Moves the clock forwards by `by`
+        pub fn advance(&self, by: Duration) {
+            self.micros.fetch_add(micros(by), Ordering::SeqCst);
+        }
+
+        /// Moves the clock backwards by `by`, like an NTP step
+        pub fn rewind(&self, by: Duration) {
+            self.micros.fetch_sub(micros(by), Ordering::SeqCst);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now_millis(&self) -> i64 {
+            self.now_micros().div_euclid(1_000)
+        }
+
+        fn now_micros(&self) -> i64 {
+            self.micros.load(Ordering::SeqCst)
+        }
+    }
+
+    fn micros(duration: Duration) -> i64 {
+        i64::try_from(duration.as_micros()).unwrap_or(i64::MAX)
+    }
+}
+
+#[cfg(feature = "test-util")]
+pub use manual::ManualClock;
+#[cfg(not(feature = "test-util"))]
+pub(crate) use manual::ManualClock;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Epoch;
+    use crate::SnowflakeGenerator;
+    use crate::SNOWFLAKE_ID_EPOCH;
+    use std::thread;
+
+    #[test]
+    fn test_clock_health() {
+        let start = SNOWFLAKE_ID_EPOCH + 1_000;
+        let clock = ManualClock::new(start);
+        let generator = SnowflakeGenerator::with_clock(1, Epoch::TWITTER, clock.clone()).unwrap();
+        generator.generate().unwrap();
+
+        let health = generator.clock_health();
+        assert_eq!(health.since_last_backwards, None);
+        assert!(!health.in_tolerance_wait && !health.rejecting);
+
+        // The manual clock gains a minute on the monotonic one
+        clock.advance(Duration::from_millis(60_000));
+        let health = generator.clock_health();
+        assert!(health.drift_micros > 59_000_000);
+        assert!(!health.is_healthy(Duration::from_secs(1)));
+        assert!(health.is_healthy(Duration::from_secs(120)));
+
+        generator.generate().unwrap();
+        clock.rewind(Duration::from_millis(2));
+        assert!(generator.try_next_id().is_ok());
+        let health = generator.clock_health();
+        assert!(health.in_tolerance_wait);
+        assert!(health.since_last_backwards.is_some());
+
+        clock.rewind(Duration::from_millis(10_000));
+        let health = generator.clock_health();
+        assert!(health.rejecting && !health.in_tolerance_wait);
+
+        clock.advance(Duration::from_millis(10_003));
+        assert!(generator
+            .clock_health()
+            .is_healthy(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn test_monotonic_clock() {
+        let clock = MonotonicClock::new();
+        let first = clock.now_millis();
+        assert!((first - SystemClock.now_millis()).abs() < 1_000);
+        thread::sleep(Duration::from_millis(2));
+        assert!(clock.now_millis() >= first + 2);
+    }
+}
@@ -0,0 +1,221 @@
+//! Watching the clock for NTP misbehaviour.
+//!
+//! [`ClockGuard`] wraps any [`Clock`] and calls user callbacks when the time
+//! it reads moves backwards, when it catches up again, and when it jumps
+//! forward further than the monotonic clock says time has passed. Hand it to
+//! [`GeneratorBuilder::clock`](crate::builder::GeneratorBuilder::clock) to
+//! hear about the clock trouble behind `ClockMovedBackwards` errors and stalls
+//! as it happens, e.g. to page an operator or switch to a degraded mode.
+//!
+//! Callbacks run on the thread reading the clock, usually one generating an
+//! ID, so they should be quick.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::clock_guard::ClockGuard;
+//! use snowflake_id::{SnowflakeGenerator, SystemClock};
+//! use std::time::Duration;
+//!
+//! let clock = ClockGuard::new(SystemClock)
+//!     .on_backwards(|drift| eprintln!("clock moved back {:?}", drift))
+//!     .on_resume(|| eprintln!("clock caught up"))
+//!     .on_forward_jump(Duration::from_secs(1), |jump| eprintln!("clock jumped {:?}", jump));
+//! let generator = SnowflakeGenerator::builder(1).clock(clock).build().unwrap();
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+type Callback = Box<dyn Fn(Duration) + Send + Sync>;
+
+struct GuardState {
+    /// Latest time read so far
+    high_water: i64,
+    /// Whether the clock is currently behind `high_water`
+    behind: bool,
+    /// The previous reading and when it was taken
+    last_read: Option<(i64, Instant)>,
+}
+
+enum Event {
+    Backwards(Duration),
+    Resume,
+    ForwardJump(Duration),
+}
+
+/// A [`Clock`] wrapper that reports backwards movement and forward jumps,
+/// see the [module docs](self)
+///
+/// Forward jumps are measured with [`Instant`], so the guard isn't available
+/// in the browser, where `Instant` panics.
+pub struct ClockGuard<C = SystemClock> {
+    clock: C,
+    state: Mutex<GuardState>,
+    on_backwards: Option<Callback>,
+    on_resume: Option<Box<dyn Fn() + Send + Sync>>,
+    on_forward_jump: Option<(i64, Callback)>,
+}
+
+impl<C: Clock> ClockGuard<C> {
+    pub fn new(clock: C) -> Self {
+        ClockGuard {
+            clock,
+            state: Mutex::new(GuardState {
+                high_water: i64::MIN,
+                behind: false,
+                last_read: None,
+            }),
+            on_backwards: None,
+            on_resume: None,
+            on_forward_jump: None,
+        }
+    }
+
+    /// Calls `callback` with how far the clock fell behind the latest time
+    /// read, once each time it starts running behind
+    pub fn on_backwards(mut self, callback: impl Fn(Duration) + Send + Sync + 'static) -> Self {
+        self.on_backwards = Some(Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` when a clock that moved backwards catches up again
+    pub fn on_resume(mut self, callback: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_resume = Some(Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` with the size of the jump when the clock moves more
+    /// than `threshold` further forward between two reads than the monotonic
+    /// clock does
+    pub fn on_forward_jump(
+        mut self,
+        threshold: Duration,
+        callback: impl Fn(Duration) + Send + Sync + 'static,
+    ) -> Self {
+        let threshold = i64::try_from(threshold.as_millis()).unwrap_or(i64::MAX);
+        self.on_forward_jump = Some((threshold, Box::new(callback)));
+        self
+    }
+
+    /// Returns whether the clock is currently behind the latest time read
+    pub fn is_behind(&self) -> bool {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).behind
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.clock
+    }
+
+    fn observe(&self, now: i64) -> Option<Event> {
+        let read_at = Instant::now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let last_read = state.last_read.replace((now, read_at));
+
+        if now < state.high_water {
+            if state.behind {
+                return None;
+            }
+            state.behind = true;
+            return Some(Event::Backwards(Duration::from_millis(
+                (state.high_water - now) as u64,
+            )));
+        }
+        state.high_water = now;
+        if state.behind {
+            state.behind = false;
+            return Some(Event::Resume);
+        }
+
+        let (threshold, _) = self.on_forward_jump.as_ref()?;
+        let (last, last_at) = last_read?;
+        let elapsed = read_at.duration_since(last_at).as_millis() as i64;
+        let jump = (now - last).saturating_sub(elapsed);
+        (jump > *threshold).then(|| Event::ForwardJump(Duration::from_millis(jump as u64)))
+    }
+}
+
+impl<C: Clock> Clock for ClockGuard<C> {
+    fn now_millis(&self) -> i64 {
+        let now = self.clock.now_millis();
+        // Callbacks run after the state lock is released
+        match self.observe(now) {
+            Some(Event::Backwards(drift)) => {
+                if let Some(callback) = &self.on_backwards {
+                    callback(drift);
+                }
+            }
+            Some(Event::Resume) => {
+                if let Some(callback) = &self.on_resume {
+                    callback();
+                }
+            }
+            Some(Event::ForwardJump(jump)) => {
+                if let Some((_, callback)) = &self.on_forward_jump {
+                    callback(jump);
+                }
+            }
+            None => {}
+        }
+        now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+    use crate::SNOWFLAKE_ID_EPOCH;
+
+    #[test]
+    fn test_clock_guard() {
+        use crate::clock::ManualClock;
+        use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        let start = SNOWFLAKE_ID_EPOCH + 1_000;
+        let clock = ManualClock::new(start);
+        let drift = Arc::new(AtomicU64::new(0));
+        let resumes = Arc::new(AtomicU32::new(0));
+        let jump = Arc::new(AtomicU64::new(0));
+        let guard = ClockGuard::new(clock.clone())
+            .on_backwards({
+                let drift = drift.clone();
+                move |d| drift.store(d.as_millis() as u64, Ordering::SeqCst)
+            })
+            .on_resume({
+                let resumes = resumes.clone();
+                move || {
+                    resumes.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .on_forward_jump(Duration::from_secs(1), {
+                let jump = jump.clone();
+                move |j| jump.store(j.as_millis() as u64, Ordering::SeqCst)
+            });
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(guard)
+            .clock_tolerance(Duration::ZERO)
+            .build()
+            .unwrap();
+
+        generator.generate().unwrap();
+        clock.set(start - 50);
+        assert!(generator.generate().is_err());
+        assert_eq!(drift.load(Ordering::SeqCst), 50);
+        // Reported once per episode
+        clock.set(start - 80);
+        assert!(generator.generate().is_err());
+        assert_eq!(drift.load(Ordering::SeqCst), 50);
+        assert_eq!(resumes.load(Ordering::SeqCst), 0);
+
+        clock.set(start + 1);
+        generator.generate().unwrap();
+        assert_eq!(resumes.load(Ordering::SeqCst), 1);
+        assert_eq!(jump.load(Ordering::SeqCst), 0);
+
+        clock.advance(Duration::from_millis(60_000));
+        generator.generate().unwrap();
+        assert!(jump.load(Ordering::SeqCst) > 50_000);
+    }
+}
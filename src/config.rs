@@ -0,0 +1,226 @@
+//! Configuring generators from the environment or a config file.
+//!
+//! [`GeneratorConfig`] holds the settings services usually want to set per
+//! deployment. It deserializes with serde from any format, and
+//! [`GeneratorConfig::from_env`] reads it from these variables:
+//!
+//! | Variable | Value | Default |
+//! |----------|-------|---------|
+//! | `SNOWFLAKE_MACHINE_ID` | Machine ID | required |
+//! | `SNOWFLAKE_EPOCH` | Preset name (e.g. `discord`) or Unix milliseconds | Twitter's epoch |
+//! | `SNOWFLAKE_TOLERANCE_MS` | Backwards clock movement to wait out | `5` |
+//! | `SNOWFLAKE_COARSE_CLOCK` | `true` to share clock reads in bulk calls | `false` |
+//!
+//! # Example
+//! ```
+//! use snowflake_id::config::GeneratorConfig;
+//! use snowflake_id::SnowflakeGenerator;
+//!
+//! let config: GeneratorConfig =
+//!     serde_json::from_str(r#"{"machine_id": 7, "epoch": "discord"}"#).unwrap();
+//! let generator: SnowflakeGenerator = config.build().unwrap();
+//! assert_eq!(generator.machine_id(), 7);
+//! ```
+
+use crate::builder::GeneratorBuilder;
+use crate::defs::{CLOCK_BACKWARDS_TOLERANCE_MS, SNOWFLAKE_ID_EPOCH};
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::generator::SnowflakeGenerator;
+use crate::snowflake::Snowflake;
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::env::{self, VarError};
+use std::str::FromStr;
+use std::time::Duration;
+
+pub const MACHINE_ID_VAR: &str = "SNOWFLAKE_MACHINE_ID";
+pub const EPOCH_VAR: &str = "SNOWFLAKE_EPOCH";
+pub const TOLERANCE_VAR: &str = "SNOWFLAKE_TOLERANCE_MS";
+pub const COARSE_CLOCK_VAR: &str = "SNOWFLAKE_COARSE_CLOCK";
+
+/// Deployment settings for a generator, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeneratorConfig {
+    pub machine_id: u64,
+    /// Accepts a preset name or Unix milliseconds; serialized as milliseconds
+    #[serde(
+        default,
+        serialize_with = "serialize_epoch",
+        deserialize_with = "deserialize_epoch"
+    )]
+    pub epoch: Epoch,
+    #[serde(default = "default_tolerance_ms")]
+    pub tolerance_ms: u64,
+    /// See [`GeneratorBuilder::coarse_clock`]
+    #[serde(default)]
+    pub coarse_clock: bool,
+}
+
+impl GeneratorConfig {
+    /// Returns the default settings for `machine_id`
+    pub fn new(machine_id: u64) -> Self {
+        GeneratorConfig {
+            machine_id,
+            epoch: Epoch::custom(SNOWFLAKE_ID_EPOCH),
+            tolerance_ms: default_tolerance_ms(),
+            coarse_clock: false,
+        }
+    }
+
+    /// Turns the coarse clock on or off, see [`GeneratorBuilder::coarse_clock`]
+    pub fn coarse_clock(mut self, enabled: bool) -> Self {
+        self.coarse_clock = enabled;
+        self
+    }
+
+    /// Reads the settings from `SNOWFLAKE_*` environment variables, failing
+    /// with [`SnowflakeError::InvalidConfig`] naming the variable if one is
+    /// missing or malformed
+    pub fn from_env() -> Result<Self, SnowflakeError> {
+        Self::from_lookup(|name| match env::var(name) {
+            Ok(value) => Ok(Some(value)),
+            Err(VarError::NotPresent) => Ok(None),
+            Err(VarError::NotUnicode(_)) => Err(invalid(name, "is not valid UTF-8")),
+        })
+    }
+
+    pub(crate) fn from_lookup(
+        lookup: impl Fn(&str) -> Result<Option<String>, SnowflakeError>,
+    ) -> Result<Self, SnowflakeError> {
+        let machine_id =
+            lookup(MACHINE_ID_VAR)?.ok_or_else(|| invalid(MACHINE_ID_VAR, "is not set"))?;
+        let mut config = Self::new(parse(MACHINE_ID_VAR, &machine_id)?);
+        if let Some(epoch) = lookup(EPOCH_VAR)? {
+            config.epoch = parse(EPOCH_VAR, &epoch)?;
+        }
+        if let Some(tolerance) = lookup(TOLERANCE_VAR)? {
+            config.tolerance_ms = parse(TOLERANCE_VAR, &tolerance)?;
+        }
+        if let Some(coarse_clock) = lookup(COARSE_CLOCK_VAR)? {
+            config.coarse_clock = parse(COARSE_CLOCK_VAR, &coarse_clock)?;
+        }
+        Ok(config)
+    }
+
+    /// Returns a builder with these settings, for adjusting further
+    pub fn builder<S: Snowflake>(&self) -> GeneratorBuilder<S> {
+        GeneratorBuilder::new(self.machine_id)
+            .epoch(self.epoch)
+            .clock_tolerance(Duration::from_millis(self.tolerance_ms))
+            .coarse_clock(self.coarse_clock)
+    }
+
+    /// Builds a generator with these settings
+    pub fn build<S: Snowflake>(&self) -> Result<SnowflakeGenerator<S>, SnowflakeError> {
+        self.builder().build()
+    }
+}
+
+impl<S: Snowflake> SnowflakeGenerator<S> {
+    /// Creates a generator configured by `SNOWFLAKE_*` environment variables,
+    /// see [`crate::config`]
+    pub fn from_env() -> Result<Self, SnowflakeError> {
+        GeneratorConfig::from_env()?.build()
+    }
+}
+
+pub(crate) fn default_tolerance_ms() -> u64 {
+    CLOCK_BACKWARDS_TOLERANCE_MS as u64
+}
+
+fn parse<T: FromStr>(name: &str, value: &str) -> Result<T, SnowflakeError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| invalid(name, &format!("has invalid value {:?}", value)))
+}
+
+fn invalid(name: &str, problem: &str) -> SnowflakeError {
+    SnowflakeError::InvalidConfig(format!("{} {}", name, problem))
+}
+
+pub(crate) fn serialize_epoch<Ser: Serializer>(
+    epoch: &Epoch,
+    serializer: Ser,
+) -> Result<Ser::Ok, Ser::Error> {
+    serializer.serialize_i64(epoch.as_millis())
+}
+
+pub(crate) fn deserialize_epoch<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Epoch, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Millis(i64),
+        Name(String),
+    }
+
+    match Repr::deserialize(deserializer)? {
+        Repr::Millis(millis) => Ok(Epoch::custom(millis)),
+        Repr::Name(name) => name.parse().map_err(::serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeId;
+
+    #[test]
+    fn test_generator_config() {
+        use std::collections::HashMap;
+
+        let from_vars = |vars: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            GeneratorConfig::from_lookup(|name| Ok(vars.get(name).cloned()))
+        };
+
+        let config = from_vars(&[
+            ("SNOWFLAKE_MACHINE_ID", "7"),
+            ("SNOWFLAKE_EPOCH", "Discord"),
+            ("SNOWFLAKE_TOLERANCE_MS", " 20 "),
+            ("SNOWFLAKE_COARSE_CLOCK", "true"),
+        ])
+        .unwrap();
+        assert_eq!(config.machine_id, 7);
+        assert!(config.coarse_clock);
+        assert_eq!(config.epoch, Epoch::DISCORD);
+        assert_eq!(config.tolerance_ms, 20);
+        let generator = config.build::<SnowflakeId>().unwrap();
+        assert_eq!(generator.epoch(), Epoch::DISCORD.as_millis());
+
+        let defaults = from_vars(&[("SNOWFLAKE_MACHINE_ID", "7")]).unwrap();
+        assert_eq!(defaults, GeneratorConfig::new(7));
+        assert_eq!(defaults.tolerance_ms, CLOCK_BACKWARDS_TOLERANCE_MS as u64);
+
+        let message =
+            |result: Result<GeneratorConfig, SnowflakeError>| result.unwrap_err().to_string();
+        assert!(message(from_vars(&[])).contains("SNOWFLAKE_MACHINE_ID is not set"));
+        assert!(message(from_vars(&[("SNOWFLAKE_MACHINE_ID", "seven")])).contains("\"seven\""));
+        assert!(message(from_vars(&[
+            ("SNOWFLAKE_MACHINE_ID", "7"),
+            ("SNOWFLAKE_EPOCH", "mars"),
+        ]))
+        .contains("SNOWFLAKE_EPOCH"));
+        assert!(matches!(
+            from_vars(&[("SNOWFLAKE_MACHINE_ID", "5000")])
+                .unwrap()
+                .build::<SnowflakeId>(),
+            Err(SnowflakeError::InvalidMachineId(5000, _))
+        ));
+
+        let config: GeneratorConfig =
+            serde_json::from_str(r#"{"machine_id": 3, "epoch": 1420070400000}"#).unwrap();
+        assert_eq!(config.epoch, Epoch::DISCORD);
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            r#"{"machine_id":3,"epoch":1420070400000,"tolerance_ms":5,"coarse_clock":false}"#
+        );
+        assert!(serde_json::from_str::<GeneratorConfig>(r#"{"machine": 3}"#).is_err());
+    }
+}
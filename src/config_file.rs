@@ -0,0 +1,384 @@
+//! Building several named generators from one config file.
+//!
+//! Services with more than one ID domain, such as users, orders and events,
+//! describe each domain's generator in a [`GeneratorConfigFile`] and build
+//! them all at startup with [`GeneratorRegistry::from_config`]. Files ending
+//! in `.toml` are read as TOML and anything else as JSON:
+//!
+//! ```toml
+//! [[generators]]
+//! name = "users"
+//! machine_id = { fixed = 7 }
+//!
+//! [[generators]]
+//! name = "orders"
+//! machine_id = { env = "ORDERS_MACHINE_ID" }
+//! epoch = "discord"
+//!
+//! [[generators]]
+//! name = "events"
+//! machine_id = { datacenter = { datacenter_id = 2, worker_id = 5 } }
+//! layout = { timestamp_bits = 41, machine_id_bits = 10, sequence_bits = 12 }
+//! ```
+//!
+//! Every generator in a registry shares the ID type `S`. A `layout` entry
+//! doesn't change it; it pins the layout the file was written for, so
+//! loading the file into a registry of a different ID type fails instead of
+//! quietly minting differently shaped IDs.
+//!
+//! Generators sharing a machine id need different epochs, or they would mint
+//! the same IDs; the registry refuses to build such a file.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::config_file::{GeneratorConfigFile, GeneratorRegistry};
+//!
+//! let file: GeneratorConfigFile = toml::from_str(
+//!     r#"
+//!     [[generators]]
+//!     name = "users"
+//!     machine_id = { fixed = 1 }
+//!
+//!     [[generators]]
+//!     name = "orders"
+//!     machine_id = { fixed = 2 }
+//!     "#,
+//! )
+//! .unwrap();
+//!
+//! let registry: GeneratorRegistry = GeneratorRegistry::from_config_file(&file).unwrap();
+//! assert_eq!(registry.next_id("orders").unwrap().machine_id(), 2);
+//! assert!(registry.next_id("invoices").is_err());
+//! ```
+
+use crate::builder::GeneratorBuilder;
+use crate::config::{default_tolerance_ms, deserialize_epoch, serialize_epoch};
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::generator::SnowflakeGenerator;
+use crate::machine_id::from_datacenter;
+use crate::snowflake::Snowflake;
+use crate::SnowflakeId;
+use ::serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Generators to build, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeneratorConfigFile {
+    pub generators: Vec<NamedGeneratorConfig>,
+}
+
+/// Settings for one generator in a [`GeneratorConfigFile`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamedGeneratorConfig {
+    pub name: String,
+    pub machine_id: MachineIdSource,
+    /// Accepts a preset name or Unix milliseconds; serialized as milliseconds
+    #[serde(
+        default,
+        serialize_with = "serialize_epoch",
+        deserialize_with = "deserialize_epoch"
+    )]
+    pub epoch: Epoch,
+    #[serde(default = "default_tolerance_ms")]
+    pub tolerance_ms: u64,
+    /// See [`GeneratorBuilder::coarse_clock`]
+    #[serde(default)]
+    pub coarse_clock: bool,
+    /// Layout the file expects the registry's ID type to have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<LayoutConfig>,
+}
+
+/// Where a generator's machine id comes from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum MachineIdSource {
+    Fixed(u64),
+    /// Read from the named environment variable at build time
+    Env(String),
+    /// Combined as in Twitter's layout, see
+    /// [`machine_id::from_datacenter`](crate::machine_id::from_datacenter)
+    Datacenter {
+        datacenter_id: u64,
+        worker_id: u64,
+    },
+}
+
+/// Bit widths of an ID layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub timestamp_bits: u64,
+    pub machine_id_bits: u64,
+    pub sequence_bits: u64,
+}
+
+impl LayoutConfig {
+    /// Returns the layout of `S`
+    pub fn of<S: Snowflake>() -> Self {
+        LayoutConfig {
+            timestamp_bits: S::timestamp_bits(),
+            machine_id_bits: S::machine_id_bits(),
+            sequence_bits: S::sequence_bits(),
+        }
+    }
+}
+
+impl GeneratorConfigFile {
+    /// Reads `path` as TOML if it ends in `.toml`, otherwise as JSON
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, SnowflakeError> {
+        let path = path.as_ref();
+        let invalid = |problem: String| {
+            SnowflakeError::InvalidConfig(format!("{}: {}", path.display(), problem))
+        };
+        let text = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&text).map_err(|e| invalid(e.to_string()))
+        } else {
+            serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))
+        }
+    }
+}
+
+impl MachineIdSource {
+    /// Returns the machine id, reading the environment if needed
+    pub fn resolve(&self) -> Result<u64, SnowflakeError> {
+        match self {
+            MachineIdSource::Fixed(machine_id) => Ok(*machine_id),
+            MachineIdSource::Env(name) => {
+                let value = env::var(name)
+                    .map_err(|_| SnowflakeError::InvalidConfig(format!("{} is not set", name)))?;
+                value.trim().parse().map_err(|_| {
+                    SnowflakeError::InvalidConfig(format!("{} has invalid value {:?}", name, value))
+                })
+            }
+            MachineIdSource::Datacenter {
+                datacenter_id,
+                worker_id,
+            } => from_datacenter(*datacenter_id, *worker_id),
+        }
+    }
+}
+
+impl NamedGeneratorConfig {
+    /// Returns a builder with these settings, for adjusting further
+    pub fn builder<S: Snowflake>(&self) -> Result<GeneratorBuilder<S>, SnowflakeError> {
+        if let Some(layout) = self.layout {
+            if layout != LayoutConfig::of::<S>() {
+                return Err(SnowflakeError::InvalidConfig(format!(
+                    "expects layout {:?}, not {:?}",
+                    layout,
+                    LayoutConfig::of::<S>()
+                )));
+            }
+        }
+        Ok(GeneratorBuilder::new(self.machine_id.resolve()?)
+            .epoch(self.epoch)
+            .clock_tolerance(Duration::from_millis(self.tolerance_ms))
+            .coarse_clock(self.coarse_clock))
+    }
+}
+
+/// Generators built from a [`GeneratorConfigFile`], looked up by name
+pub struct GeneratorRegistry<S: Snowflake = SnowflakeId> {
+    generators: BTreeMap<String, SnowflakeGenerator<S>>,
+}
+
+impl<S: Snowflake> GeneratorRegistry<S> {
+    /// Reads the file at `path` and builds every generator in it
+    pub fn from_config(path: impl AsRef<Path>) -> Result<Self, SnowflakeError> {
+        Self::from_config_file(&GeneratorConfigFile::from_path(path)?)
+    }
+
+    /// Builds every generator in `file`, failing on the first that can't be
+    /// built or on a repeated name.
+    ///
+    /// Errors from building a generator come wrapped in
+    /// [`SnowflakeError::NamedGenerator`]. Two generators on the same machine
+    /// id and epoch would mint the same IDs, so the second fails with
+    /// [`SnowflakeError::EpochInUse`].
+    pub fn from_config_file(file: &GeneratorConfigFile) -> Result<Self, SnowflakeError> {
+        Self::from_config_file_with(file, |builder| builder)
+    }
+
+    /// Builds every generator in `file` like
+    /// [`GeneratorRegistry::from_config_file`], applying further settings
+    /// such as a clock or wait strategy to each, as
+    /// [`GeneratorPool::configure`](crate::pool::GeneratorPool::configure)
+    /// does for a pool. The builder arrives with the file's settings applied.
+    pub fn from_config_file_with(
+        file: &GeneratorConfigFile,
+        configure: impl Fn(GeneratorBuilder<S>) -> GeneratorBuilder<S>,
+    ) -> Result<Self, SnowflakeError> {
+        let mut generators = BTreeMap::new();
+        let mut id_spaces = HashSet::new();
+        for config in &file.generators {
+            let named = |source| SnowflakeError::NamedGenerator {
+                name: config.name.clone(),
+                source: Box::new(source),
+            };
+            let generator = config
+                .builder()
+                .and_then(|builder| configure(builder).build())
+                .map_err(named)?;
+            // Checked on the built generator, in case `configure` set the epoch
+            if !id_spaces.insert((generator.machine_id(), generator.epoch())) {
+                return Err(named(SnowflakeError::EpochInUse(generator.epoch())));
+            }
+            if generators.insert(config.name.clone(), generator).is_some() {
+                return Err(SnowflakeError::InvalidConfig(format!(
+                    "generator {:?} is defined twice",
+                    config.name
+                )));
+            }
+        }
+        Ok(GeneratorRegistry { generators })
+    }
+
+    /// Returns the generator named `name`
+    pub fn get(&self, name: &str) -> Option<&SnowflakeGenerator<S>> {
+        self.generators.get(name)
+    }
+
+    /// Generates the next ID from the generator named `name`, failing with
+    /// [`SnowflakeError::UnknownGenerator`] if there is none
+    pub fn next_id(&self, name: &str) -> Result<S, SnowflakeError> {
+        self.get(name)
+            .ok_or_else(|| SnowflakeError::UnknownGenerator(name.to_string()))?
+            .generate()
+    }
+
+    /// Returns the generator names in sorted order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.generators.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.generators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.generators.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generator_config_file() {
+        let dir = std::env::temp_dir().join(format!("snowflake-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let toml_path = dir.join("generators.toml");
+        std::fs::write(
+            &toml_path,
+            r#"
+            [[generators]]
+            name = "users"
+            machine_id = { fixed = 7 }
+
+            [[generators]]
+            name = "events"
+            machine_id = { datacenter = { datacenter_id = 2, worker_id = 5 } }
+            epoch = "discord"
+            layout = { timestamp_bits = 41, machine_id_bits = 10, sequence_bits = 12 }
+            "#,
+        )
+        .unwrap();
+
+        let registry: GeneratorRegistry = GeneratorRegistry::from_config(&toml_path).unwrap();
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["events", "users"]);
+        assert_eq!(registry.next_id("users").unwrap().machine_id(), 7);
+        let events = registry.get("events").unwrap();
+        assert_eq!(events.machine_id(), (2 << 5) | 5);
+        assert_eq!(events.epoch(), Epoch::DISCORD.as_millis());
+        assert!(matches!(
+            registry.next_id("orders"),
+            Err(SnowflakeError::UnknownGenerator(name)) if name == "orders"
+        ));
+
+        // The same file as JSON round-trips through the serde types
+        let file = GeneratorConfigFile::from_path(&toml_path).unwrap();
+        let json_path = dir.join("generators.json");
+        std::fs::write(&json_path, serde_json::to_string(&file).unwrap()).unwrap();
+        assert_eq!(GeneratorConfigFile::from_path(&json_path).unwrap(), file);
+
+        let build = |json: &str| {
+            let file: GeneratorConfigFile = serde_json::from_str(json).unwrap();
+            GeneratorRegistry::<SnowflakeId>::from_config_file(&file).map(|r| r.len())
+        };
+        let message = |result: Result<usize, SnowflakeError>| match result {
+            Ok(_) => panic!("invalid config accepted"),
+            Err(err) => err.to_string(),
+        };
+        assert!(message(build(
+            r#"{"generators": [
+                {"name": "a", "machine_id": {"fixed": 1}},
+                {"name": "a", "machine_id": {"fixed": 2}}
+            ]}"#
+        ))
+        .contains("defined twice"));
+        let Err(err) = build(r#"{"generators": [{"name": "a", "machine_id": {"fixed": 5000}}]}"#)
+        else {
+            panic!("machine id 5000 accepted");
+        };
+        assert!(matches!(
+            &err,
+            SnowflakeError::NamedGenerator { name, source }
+                if name == "a" && matches!(**source, SnowflakeError::InvalidMachineId(5000, _))
+        ));
+        assert_eq!(err.code(), "invalid_machine_id");
+        assert!(err.is_invalid_input());
+        assert!(message(build(
+            r#"{"generators": [
+                {"name": "a", "machine_id": {"fixed": 1}},
+                {"name": "b", "machine_id": {"fixed": 1}}
+            ]}"#
+        ))
+        .contains("already used"));
+        assert_eq!(
+            build(
+                r#"{"generators": [
+                    {"name": "a", "machine_id": {"fixed": 1}},
+                    {"name": "b", "machine_id": {"fixed": 1}, "epoch": "discord"}
+                ]}"#
+            )
+            .unwrap(),
+            2
+        );
+
+        // Further settings apply to every generator
+        let clock = crate::test_support::manual_clock();
+        let registry = GeneratorRegistry::<SnowflakeId>::from_config_file_with(&file, |builder| {
+            builder.clock(clock.clone())
+        })
+        .unwrap();
+        let before = registry.next_id("users").unwrap();
+        clock.advance(Duration::from_millis(5));
+        assert_eq!(
+            registry.next_id("users").unwrap().timestamp(),
+            before.timestamp() + 5
+        );
+        assert!(message(build(
+            r#"{"generators": [{"name": "a", "machine_id": {"fixed": 1},
+                "layout": {"timestamp_bits": 42, "machine_id_bits": 8, "sequence_bits": 13}}]}"#
+        ))
+        .contains("expects layout"));
+        assert!(
+            MachineIdSource::Env("SNOWFLAKE_TEST_UNSET_MACHINE_ID".to_string())
+                .resolve()
+                .is_err()
+        );
+        assert!(GeneratorConfigFile::from_path(dir.join("missing.toml")).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,235 @@
+//! Integrity checks for consumers of snowflake-keyed streams.
+//!
+//! A single generator hands out sequence numbers contiguously within each
+//! tick and restarts from zero on every new tick. A consumer reading the IDs
+//! of one machine in order (for example a Kafka partition keyed by machine)
+//! can therefore spot dropped messages without any extra metadata.
+
+use crate::error::SnowflakeError;
+use crate::snowflake::Snowflake;
+use std::time::Duration;
+
+/// An anomaly reported by [`GapDetector::observe`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gap<S> {
+    /// Sequence numbers `first..=last` of the tick at `timestamp_offset` were
+    /// never observed
+    MissingSequences {
+        timestamp_offset: u64,
+        first: u64,
+        last: u64,
+    },
+    /// No ID was observed for longer than the configured maximum idle time
+    Idle {
+        previous: S,
+        id: S,
+        elapsed: Duration,
+    },
+    /// `id` is not greater than the previously observed ID, so it is either a
+    /// duplicate or was delivered out of order
+    OutOfOrder { previous: S, id: S },
+    /// `id` was minted by another machine, so it doesn't belong in this
+    /// stream at all
+    ForeignMachine { id: S },
+}
+
+impl<S> Gap<S> {
+    /// Returns how many IDs this gap accounts for as lost, if known
+    pub fn missing(&self) -> u64 {
+        match self {
+            Gap::MissingSequences { first, last, .. } => last - first + 1,
+            Gap::Idle { .. } | Gap::OutOfOrder { .. } | Gap::ForeignMachine { .. } => 0,
+        }
+    }
+}
+
+/// Detects missing sequence numbers and timestamp gaps in the IDs minted by
+/// a single machine.
+///
+/// Only gaps the ID structure can prove are reported: a tick that starts
+/// after sequence `0`, or a jump inside a tick. IDs lost at the *end* of a
+/// tick are indistinguishable from a generator that simply went quiet, which
+/// is what [`GapDetector::with_max_idle`] is for.
+///
+/// # Example
+/// ```
+/// use snowflake_id::consumer::{Gap, GapDetector};
+/// use snowflake_id::{Snowflake, SnowflakeId};
+///
+/// let mut detector = GapDetector::new(7).unwrap();
+/// assert_eq!(detector.observe(SnowflakeId::from_component_parts(100, 7, 0)), None);
+/// assert_eq!(
+///     detector.observe(SnowflakeId::from_component_parts(100, 7, 3)),
+///     Some(Gap::MissingSequences { timestamp_offset: 100, first: 1, last: 2 })
+/// );
+/// assert_eq!(detector.missing(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct GapDetector<S> {
+    machine_id: u64,
+    max_idle: Option<Duration>,
+    last: Option<S>,
+    missing: u64,
+}
+
+impl<S: Snowflake> GapDetector<S> {
+    /// Creates a detector for the IDs of `machine_id`
+    pub fn new(machine_id: u64) -> Result<Self, SnowflakeError> {
+        if machine_id > S::max_machine_id() {
+            return Err(SnowflakeError::InvalidMachineId(
+                machine_id,
+                S::max_machine_id(),
+            ));
+        }
+
+        Ok(GapDetector {
+            machine_id,
+            max_idle: None,
+            last: None,
+            missing: 0,
+        })
+    }
+
+    /// Reports [`Gap::Idle`] whenever consecutive IDs are more than
+    /// `max_idle` apart
+    pub fn with_max_idle(mut self, max_idle: Duration) -> Self {
+        self.max_idle = Some(max_idle);
+        self
+    }
+
+    pub fn machine_id(&self) -> u64 {
+        self.machine_id
+    }
+
+    /// Returns the last ID accepted in order, if any
+    pub fn last(&self) -> Option<S> {
+        self.last
+    }
+
+    /// Returns the total number of sequence numbers reported missing so far
+    pub fn missing(&self) -> u64 {
+        self.missing
+    }
+
+    /// Feeds the next ID of the stream and returns the gap it reveals, if any.
+    ///
+    /// A tick that both starts after sequence `0` and follows an idle period
+    /// is reported as [`Gap::MissingSequences`]. Out-of-order IDs and IDs
+    /// from other machines do not advance the detector.
+    pub fn observe(&mut self, id: S) -> Option<Gap<S>> {
+        if id.machine_id() != self.machine_id {
+            return Some(Gap::ForeignMachine { id });
+        }
+
+        let previous = match self.last {
+            Some(previous) if id <= previous => return Some(Gap::OutOfOrder { previous, id }),
+            Some(previous) => previous,
+            None => {
+                self.last = Some(id);
+                return None;
+            }
+        };
+        self.last = Some(id);
+
+        let expected = if id.timestamp() == previous.timestamp() {
+            previous.sequence() + 1
+        } else {
+            0
+        };
+        if id.sequence() > expected {
+            let gap = Gap::MissingSequences {
+                timestamp_offset: id.timestamp(),
+                first: expected,
+                last: id.sequence() - 1,
+            };
+            self.missing += gap.missing();
+            return Some(gap);
+        }
+
+        let elapsed = id.timestamp_duration() - previous.timestamp_duration();
+        match self.max_idle {
+            Some(max_idle) if elapsed > max_idle => Some(Gap::Idle {
+                previous,
+                id,
+                elapsed,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Forgets the last observed ID, e.g. after a consumer seeks
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+    use crate::MAX_MACHINE_ID;
+
+    #[test]
+    fn test_gap_detector() {
+        let id = |timestamp, sequence| SnowflakeId::from_component_parts(timestamp, 5, sequence);
+        let mut detector = GapDetector::new(5)
+            .unwrap()
+            .with_max_idle(Duration::from_secs(1));
+
+        assert_eq!(detector.observe(id(10, 4)), None);
+        assert_eq!(detector.observe(id(10, 5)), None);
+        assert_eq!(detector.observe(id(11, 0)), None);
+        assert_eq!(
+            detector.observe(id(11, 4)),
+            Some(Gap::MissingSequences {
+                timestamp_offset: 11,
+                first: 1,
+                last: 3
+            })
+        );
+        assert_eq!(
+            detector.observe(id(12, 2)),
+            Some(Gap::MissingSequences {
+                timestamp_offset: 12,
+                first: 0,
+                last: 1
+            })
+        );
+        assert_eq!(
+            detector.observe(id(12, 2)),
+            Some(Gap::OutOfOrder {
+                previous: id(12, 2),
+                id: id(12, 2)
+            })
+        );
+        assert_eq!(
+            detector.observe(id(2_000, 0)),
+            Some(Gap::Idle {
+                previous: id(12, 2),
+                id: id(2_000, 0),
+                elapsed: Duration::from_millis(1_988)
+            })
+        );
+        assert_eq!(detector.missing(), 5);
+        assert_eq!(detector.last(), Some(id(2_000, 0)));
+
+        let foreign = SnowflakeId::from_component_parts(2_001, 6, 0);
+        assert_eq!(
+            detector.observe(foreign),
+            Some(Gap::ForeignMachine { id: foreign })
+        );
+        assert_eq!(detector.last(), Some(id(2_000, 0)));
+        assert_eq!(detector.missing(), 5);
+
+        detector.reset();
+        assert_eq!(detector.observe(id(5, 9)), None);
+
+        let generator = SnowflakeGenerator::new(5).unwrap();
+        let mut detector = GapDetector::new(5).unwrap();
+        for id in generator.iter().take(10_000) {
+            assert_eq!(detector.observe(id), None);
+        }
+        assert!(GapDetector::<SnowflakeId>::new(MAX_MACHINE_ID + 1).is_err());
+    }
+}
@@ -5,5 +5,10 @@ pub const TIMESTAMP_SHIFT: u64 = MACHINE_ID_BITS + SEQUENCE_BITS; // 22
 pub const MAX_TIMESTAMP_MS: i64 = (1i64 << TIMESTAMP_BITS) - 1; // Maximum 41-bit value
 pub const MAX_MACHINE_ID: u64 = (1 << MACHINE_ID_BITS) - 1;
 pub const MAX_SEQUENCE: u64 = (1 << SEQUENCE_BITS) - 1;
+pub const DATACENTER_ID_BITS: u64 = 5; // Upper machine ID bits in Twitter's original layout
+pub const WORKER_ID_BITS: u64 = MACHINE_ID_BITS - DATACENTER_ID_BITS; // 5
+pub const MAX_DATACENTER_ID: u64 = (1 << DATACENTER_ID_BITS) - 1;
+pub const MAX_WORKER_ID: u64 = (1 << WORKER_ID_BITS) - 1;
 pub const SNOWFLAKE_ID_EPOCH: i64 = 1288834974657; // Default epoch (Twitter's epoch)
 pub const CLOCK_BACKWARDS_TOLERANCE_MS: i64 = 5; // Tolerate up to 5ms backwards clock movement
+pub const COARSE_CLOCK_READ_EVERY: u32 = 64; // IDs per clock read in bulk calls with a coarse clock
@@ -0,0 +1,124 @@
+//! Runtime support for `#[derive(Snowflake)]`; not a stable API.
+
+pub use ::serde;
+
+use ::serde::de::{self, Visitor};
+use ::serde::{Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+/// Integer types a derived ID can wrap
+pub trait Inner: Serialize + fmt::Display + FromStr + TryFrom<u64> + TryFrom<i64> {
+    const SIGNED: bool;
+}
+
+impl Inner for i64 {
+    const SIGNED: bool = true;
+}
+
+impl Inner for u64 {
+    const SIGNED: bool = false;
+}
+
+/// Writes a string for human-readable formats, so JavaScript clients keep
+/// full precision, and an integer otherwise, like
+/// [`SnowflakeId`](crate::SnowflakeId)
+pub fn serialize<T: Inner, S: Serializer>(value: T, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.collect_str(&value)
+    } else {
+        value.serialize(serializer)
+    }
+}
+
+/// Reads an integer or a digit string from human-readable formats, and the
+/// integer [`serialize`] writes from binary ones
+pub fn deserialize<'de, T: Inner, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    struct IdVisitor<T>(PhantomData<T>);
+
+    impl<T: Inner> Visitor<'_> for IdVisitor<T> {
+        type Value = T;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a string or integer representing a snowflake id")
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<T, E> {
+            T::try_from(value).map_err(|_| E::custom("snowflake id out of range"))
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<T, E> {
+            T::try_from(value).map_err(|_| E::custom("snowflake id out of range"))
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+            value
+                .parse()
+                .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+        }
+    }
+
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(IdVisitor(PhantomData))
+    } else if T::SIGNED {
+        deserializer.deserialize_i64(IdVisitor(PhantomData))
+    } else {
+        deserializer.deserialize_u64(IdVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::SnowflakeError;
+    use crate::Snowflake;
+    use crate::SnowflakeId;
+
+    #[test]
+    fn test_derive_snowflake() {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Snowflake)]
+        struct UserId(i64);
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Snowflake)]
+        #[snowflake(timestamp_bits = 44, machine_id_bits = 8, sequence_bits = 12)]
+        struct OrderId(u64);
+
+        assert_eq!(
+            (
+                UserId::timestamp_bits(),
+                UserId::machine_id_bits(),
+                UserId::sequence_bits()
+            ),
+            (41, 10, 12)
+        );
+        let user = UserId::from_component_parts(1_000, 7, 3);
+        assert_eq!(user.0, SnowflakeId::from_component_parts(1_000, 7, 3).id());
+        assert_eq!(user.to_string().parse::<UserId>().unwrap(), user);
+        assert_eq!(i64::from(user), user.0);
+        assert!(UserId::try_from(-1).is_err());
+        assert!(matches!(
+            "abc".parse::<UserId>(),
+            Err(SnowflakeError::Unparseable { input, .. }) if input == "abc"
+        ));
+
+        let order = crate::generator::SnowflakeGenerator::<OrderId>::new(255)
+            .unwrap()
+            .generate()
+            .unwrap();
+        assert_eq!(order.machine_id(), 255);
+        assert!(crate::generator::SnowflakeGenerator::<OrderId>::new(256).is_err());
+        // Bits above the 64-bit layout would be the 65th, so every u64 fits
+        assert_eq!(OrderId::try_from(u64::MAX).unwrap().0, u64::MAX);
+
+        let json = serde_json::to_string(&user).unwrap();
+        assert_eq!(json, format!("\"{}\"", user.0));
+        assert_eq!(serde_json::from_str::<UserId>(&json).unwrap(), user);
+        assert_eq!(
+            serde_json::from_str::<UserId>(&user.0.to_string()).unwrap(),
+            user
+        );
+        assert!(serde_json::from_str::<UserId>("-1").is_err());
+        let bytes = postcard::to_allocvec(&order).unwrap();
+        assert_eq!(postcard::from_bytes::<OrderId>(&bytes).unwrap(), order);
+    }
+}
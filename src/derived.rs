@@ -0,0 +1,107 @@
+use crate::error::SnowflakeError;
+use crate::SnowflakeId;
+use std::fmt;
+use std::str::FromStr;
+
+/// A deterministic child identifier derived from a parent snowflake.
+///
+/// The parent ID and a 32-bit child index are packed into a widened 128-bit
+/// value, so children of distinct parents can never collide and the same
+/// `(parent, index)` pair always yields the same ID. Children sort directly
+/// after their parent's other children, in index order.
+///
+/// # Example
+/// ```
+/// use snowflake_id::SnowflakeId;
+///
+/// let order = SnowflakeId::new(123456789).unwrap();
+/// let line_item = order.derive_child(2);
+///
+/// assert_eq!(line_item.parent(), order);
+/// assert_eq!(line_item.index(), 2);
+/// assert_eq!(line_item.to_string(), "123456789-2");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DerivedId {
+    parent: SnowflakeId,
+    index: u32,
+}
+
+impl DerivedId {
+    pub fn new(parent: SnowflakeId, index: u32) -> Self {
+        DerivedId { parent, index }
+    }
+
+    pub fn parent(&self) -> SnowflakeId {
+        self.parent
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Packs the ID as `parent << 32 | index`
+    pub fn to_u128(&self) -> u128 {
+        ((self.parent.id() as u128) << 32) | self.index as u128
+    }
+
+    /// Unpacks a value produced by [`DerivedId::to_u128`]
+    pub fn from_u128(value: u128) -> Result<Self, SnowflakeError> {
+        let parent = value >> 32;
+        if parent > i64::MAX as u128 {
+            return Err(SnowflakeError::InvalidId(
+                "Derived ID parent exceeds i64::MAX".to_string(),
+            ));
+        }
+        Ok(DerivedId {
+            parent: SnowflakeId::new_unchecked(parent as i64),
+            index: value as u32,
+        })
+    }
+}
+
+impl fmt::Display for DerivedId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.parent, self.index)
+    }
+}
+
+impl FromStr for DerivedId {
+    type Err = SnowflakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (parent, index) = s.split_once('-').ok_or_else(|| {
+            SnowflakeError::InvalidId("Derived ID must be of the form <parent>-<index>".to_string())
+        })?;
+        let index = index
+            .parse::<u32>()
+            .map_err(|e| SnowflakeError::InvalidId(format!("Failed to parse index: {}", e)))?;
+
+        Ok(DerivedId {
+            parent: parent.parse()?,
+            index,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_child() {
+        let parent = SnowflakeId::new(123456789012345678).unwrap();
+        let other = SnowflakeId::new(123456789012345679).unwrap();
+
+        assert_eq!(parent.derive_child(3), parent.derive_child(3));
+        assert_ne!(parent.derive_child(3), parent.derive_child(4));
+        assert_ne!(parent.derive_child(0), other.derive_child(0));
+        assert!(parent.derive_child(u32::MAX) < other.derive_child(0));
+
+        let child = parent.derive_child(42);
+        assert_eq!(DerivedId::from_u128(child.to_u128()).unwrap(), child);
+        assert_eq!(child.to_string().parse::<DerivedId>().unwrap(), child);
+        assert!("123".parse::<DerivedId>().is_err());
+        assert!("-1-2".parse::<DerivedId>().is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! LRU cache of formatted IDs for hot serialization paths.
+//!
+//! Services that re-serialize the same set of popular IDs over and over can
+//! skip the integer-to-string conversion by routing serialization through a
+//! [`DisplayCache`], either explicitly via [`DisplayCache::wrap`] or with the
+//! process-wide cache:
+//!
+//! ```
+//! use serde::Serialize;
+//! use snowflake_id::SnowflakeId;
+//!
+//! #[derive(Serialize)]
+//! struct User {
+//!     #[serde(serialize_with = "snowflake_id::display_cache::serialize")]
+//!     id: SnowflakeId,
+//! }
+//! ```
+
+use crate::SnowflakeId;
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+/// Capacity of the process-wide cache used by [`serialize`]
+pub const GLOBAL_CACHE_CAPACITY: usize = 4096;
+
+const NIL: usize = usize::MAX;
+
+struct Entry {
+    id: SnowflakeId,
+    text: Arc<str>,
+    prev: usize,
+    next: usize,
+}
+
+struct Lru {
+    map: HashMap<SnowflakeId, usize>,
+    entries: Vec<Entry>,
+    head: usize,
+    tail: usize,
+}
+
+impl Lru {
+    fn unlink(&mut self, index: usize) {
+        let (prev, next) = (self.entries[index].prev, self.entries[index].next);
+        match prev {
+            NIL => self.head = next,
+            prev => self.entries[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.entries[next].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, index: usize) {
+        self.entries[index].prev = NIL;
+        self.entries[index].next = self.head;
+        if self.head != NIL {
+            self.entries[self.head].prev = index;
+        }
+        self.head = index;
+        if self.tail == NIL {
+            self.tail = index;
+        }
+    }
+}
+
+/// A fixed-capacity, least-recently-used cache mapping IDs to their string form
+pub struct DisplayCache {
+    capacity: usize,
+    lru: Mutex<Lru>,
+}
+
+impl DisplayCache {
+    /// Creates a cache holding at most `capacity` formatted IDs
+    ///
+    /// # Panics
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "display cache capacity must be non-zero");
+        DisplayCache {
+            capacity,
+            lru: Mutex::new(Lru {
+                map: HashMap::with_capacity(capacity),
+                entries: Vec::with_capacity(capacity),
+                head: NIL,
+                tail: NIL,
+            }),
+        }
+    }
+
+    /// Returns the process-wide cache used by [`serialize`]
+    pub fn global() -> &'static DisplayCache {
+        static GLOBAL: OnceLock<DisplayCache> = OnceLock::new();
+        GLOBAL.get_or_init(|| DisplayCache::new(GLOBAL_CACHE_CAPACITY))
+    }
+
+    /// Returns the string form of `id`, formatting and caching it on a miss
+    pub fn get_or_format(&self, id: SnowflakeId) -> Arc<str> {
+        let mut lru = self.lock();
+
+        if let Some(&index) = lru.map.get(&id) {
+            if lru.head != index {
+                lru.unlink(index);
+                lru.push_front(index);
+            }
+            return lru.entries[index].text.clone();
+        }
+
+        let text: Arc<str> = id.to_string().into();
+        let index = if lru.entries.len() < self.capacity {
+            lru.entries.push(Entry {
+                id,
+                text: text.clone(),
+                prev: NIL,
+                next: NIL,
+            });
+            lru.entries.len() - 1
+        } else {
+            // Evict the least recently used entry and reuse its slot
+            let index = lru.tail;
+            lru.unlink(index);
+            let evicted = lru.entries[index].id;
+            lru.map.remove(&evicted);
+            lru.entries[index].id = id;
+            lru.entries[index].text = text.clone();
+            index
+        };
+        lru.push_front(index);
+        lru.map.insert(id, index);
+
+        text
+    }
+
+    /// Wraps `id` so that serializing it goes through this cache
+    pub fn wrap(&self, id: SnowflakeId) -> CachedDisplay<'_> {
+        CachedDisplay { cache: self, id }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        let mut lru = self.lock();
+        lru.map.clear();
+        lru.entries.clear();
+        lru.head = NIL;
+        lru.tail = NIL;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Lru> {
+        // Nothing inside the lock can panic after mutation starts, so a
+        // poisoned cache is still consistent
+        self.lru.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A [`SnowflakeId`] that serializes through a [`DisplayCache`].
+///
+/// Human-readable formats receive the cached string; binary formats still
+/// get the raw `i64`, matching the regular `Serialize` impl.
+pub struct CachedDisplay<'a> {
+    cache: &'a DisplayCache,
+    id: SnowflakeId,
+}
+
+impl Serialize for CachedDisplay<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.cache.get_or_format(self.id))
+        } else {
+            serializer.serialize_i64(self.id.id())
+        }
+    }
+}
+
+/// Serializes an ID through the process-wide [`DisplayCache`], for use with
+/// `#[serde(serialize_with = "snowflake_id::display_cache::serialize")]`
+pub fn serialize<S>(id: &SnowflakeId, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    DisplayCache::global().wrap(*id).serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_cache_lru() {
+        let cache = DisplayCache::new(2);
+        let a = SnowflakeId::new(1).unwrap();
+        let b = SnowflakeId::new(2).unwrap();
+        let c = SnowflakeId::new(3).unwrap();
+
+        let first = cache.get_or_format(a);
+        assert_eq!(&*first, "1");
+        assert!(std::sync::Arc::ptr_eq(&first, &cache.get_or_format(a)));
+
+        cache.get_or_format(b);
+        // Touch `a` so `b` becomes least recently used
+        cache.get_or_format(a);
+        cache.get_or_format(c);
+        assert_eq!(cache.len(), 2);
+        assert!(std::sync::Arc::ptr_eq(&first, &cache.get_or_format(a)));
+        assert_eq!(&*cache.get_or_format(c), "3");
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_display_cache_serialize() {
+        #[derive(Serialize)]
+        struct Wrapper {
+            #[serde(serialize_with = "crate::display_cache::serialize")]
+            id: SnowflakeId,
+        }
+
+        let id = SnowflakeId::new(123456789012345678).unwrap();
+        let json = serde_json::to_string(&Wrapper { id }).unwrap();
+        assert_eq!(json, r#"{"id":"123456789012345678"}"#);
+
+        let cache = DisplayCache::new(16);
+        let json = serde_json::to_string(&cache.wrap(id)).unwrap();
+        assert_eq!(json, serde_json::to_string(&id).unwrap());
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,249 @@
+//! Per-entity ordering for event-sourced aggregates.
+//!
+//! IDs from one generator are strictly increasing, but when an aggregate's
+//! stream fails over to another machine the new owner's IDs can sort before
+//! the events already written: its clock may lag, or it may share the last
+//! millisecond with a higher machine ID. [`EntitySequencer`] tracks the last
+//! ID of each entity and holds new IDs back until they sort after it.
+
+use crate::error::SnowflakeError;
+use crate::generator::SnowflakeGenerator;
+use crate::snowflake::Snowflake;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Default for [`EntitySequencer::with_max_wait`]
+pub const DEFAULT_MAX_ENTITY_WAIT: Duration = Duration::from_secs(1);
+
+/// Default for [`EntitySequencer::with_capacity`]
+pub const DEFAULT_ENTITY_CAPACITY: usize = 100_000;
+
+/// Wraps a generator to hand out IDs that are strictly increasing per entity,
+/// even across machine failover.
+///
+/// After taking over an entity, seed the sequencer with the last ID stored
+/// for it via [`EntitySequencer::observe`]; until then it can only order the
+/// IDs it minted itself.
+///
+/// Only entities whose last ID is still ahead of the generator need
+/// tracking: once the generator has passed it, every new ID sorts after it
+/// anyway. Beyond [`Self::with_capacity`] entities, those are evicted.
+///
+/// # Example
+/// ```
+/// use snowflake_id::entity::EntitySequencer;
+/// use snowflake_id::SnowflakeGenerator;
+/// use std::thread;
+///
+/// let sequencer = EntitySequencer::new(SnowflakeGenerator::new(1).unwrap());
+///
+/// // Last event written for aggregate 42 by the previous owner
+/// let previous = SnowflakeGenerator::new(1023).unwrap().next_id(thread::sleep);
+/// sequencer.observe(42, previous);
+///
+/// let id = sequencer.next_id_for_entity(42, thread::sleep).unwrap();
+/// assert!(id > previous);
+/// ```
+pub struct EntitySequencer<S: Snowflake> {
+    generator: SnowflakeGenerator<S>,
+    max_wait: Duration,
+    capacity: usize,
+    tracked: Mutex<Tracked<S>>,
+}
+
+struct Tracked<S> {
+    last: HashMap<u64, S>,
+    /// The latest ID minted here; entities whose last ID is older can't hold
+    /// back any future one
+    minted: Option<S>,
+}
+
+impl<S: Snowflake> Tracked<S> {
+    fn insert(&mut self, entity_key: u64, id: S, capacity: usize) {
+        self.last.insert(entity_key, id);
+        if self.last.len() > capacity {
+            if let Some(minted) = self.minted {
+                self.last.retain(|_, last| *last >= minted);
+            }
+        }
+    }
+}
+
+impl<S: Snowflake> EntitySequencer<S> {
+    pub fn new(generator: SnowflakeGenerator<S>) -> Self {
+        EntitySequencer {
+            generator,
+            max_wait: DEFAULT_MAX_ENTITY_WAIT,
+            capacity: DEFAULT_ENTITY_CAPACITY,
+            tracked: Mutex::new(Tracked {
+                last: HashMap::new(),
+                minted: None,
+            }),
+        }
+    }
+
+    /// Sets how far an entity's last ID may be ahead of this machine's clock
+    /// before [`Self::next_id_for_entity`] gives up instead of waiting
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Sets how many entities are tracked before those the generator has
+    /// already passed are evicted. Entities whose last ID is still ahead are
+    /// always kept, so the count can briefly exceed this.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn generator(&self) -> &SnowflakeGenerator<S> {
+        &self.generator
+    }
+
+    /// Returns the last ID known for `entity_key`, unless it has been
+    /// evicted
+    pub fn last(&self, entity_key: u64) -> Option<S> {
+        self.lock().last.get(&entity_key).copied()
+    }
+
+    /// Returns how many entities are being tracked
+    pub fn tracked(&self) -> usize {
+        self.lock().last.len()
+    }
+
+    /// Records `id` as written for `entity_key`, e.g. when loading the
+    /// entity's stream from the persistence layer. Older IDs are ignored.
+    pub fn observe(&self, entity_key: u64, id: S) {
+        let mut tracked = self.lock();
+        match tracked.last.get(&entity_key) {
+            Some(&last) if last >= id => {}
+            _ => tracked.insert(entity_key, id, self.capacity),
+        }
+    }
+
+    /// Stops tracking `entity_key`, e.g. when this machine hands it off
+    pub fn forget(&self, entity_key: u64) -> Option<S> {
+        self.lock().last.remove(&entity_key)
+    }
+
+    /// Generates an ID greater than every ID minted or observed for
+    /// `entity_key`, calling `on_pending` whenever it has to wait for the
+    /// clock to pass the entity's last ID.
+    ///
+    /// Returns [`SnowflakeError::ClockMovedBackwards`] if the last ID is more
+    /// than the configured maximum wait ahead of this machine's clock.
+    pub fn next_id_for_entity(
+        &self,
+        entity_key: u64,
+        mut on_pending: impl FnMut(Duration),
+    ) -> Result<S, SnowflakeError> {
+        loop {
+            let id = self.generator.next_id_checked(&mut on_pending)?;
+
+            let mut tracked = self.lock();
+            tracked.minted = tracked.minted.max(Some(id));
+            let previous = match tracked.last.get(&entity_key) {
+                Some(&previous) if id <= previous => previous,
+                _ => {
+                    tracked.insert(entity_key, id, self.capacity);
+                    return Ok(id);
+                }
+            };
+            drop(tracked);
+
+            // Only a later tick is guaranteed to sort after the previous ID
+            let lag =
+                previous.timestamp_duration() - id.timestamp_duration() + Duration::from_millis(1);
+            if lag > self.max_wait {
+                return Err(SnowflakeError::clock_moved_backwards(lag));
+            }
+            on_pending(lag);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Tracked<S>> {
+        self.tracked.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::manual_clock;
+    use crate::Epoch;
+    use crate::SnowflakeId;
+
+    #[test]
+    fn test_entity_sequencer() {
+        let clock = manual_clock();
+        let sequencer = EntitySequencer::new(
+            SnowflakeGenerator::with_clock(1, Epoch::TWITTER, clock.clone()).unwrap(),
+        );
+
+        let a = sequencer
+            .next_id_for_entity(7, |_| clock.advance(Duration::from_millis(1)))
+            .unwrap();
+        assert_eq!(sequencer.last(7), Some(a));
+
+        // The previous owner wrote in the same millisecond from a higher machine ID
+        let failover = SnowflakeId::from_component_parts(1_000, 1023, 0);
+        sequencer.observe(8, failover);
+        sequencer.observe(8, a);
+        assert_eq!(sequencer.last(8), Some(failover));
+        let mut waited = Duration::ZERO;
+        let b = sequencer
+            .next_id_for_entity(8, |wait| {
+                waited += wait;
+                clock.advance(wait);
+            })
+            .unwrap();
+        assert!(b > failover);
+        assert_eq!(b.timestamp(), 1_001);
+        assert_eq!(waited, Duration::from_millis(1));
+
+        // A previous owner whose clock ran far ahead can't be caught up with
+        sequencer.observe(9, SnowflakeId::from_component_parts(60_000, 3, 0));
+        assert!(matches!(
+            sequencer.next_id_for_entity(9, |_| clock.advance(Duration::from_millis(1))),
+            Err(SnowflakeError::ClockMovedBackwards { .. })
+        ));
+
+        assert_eq!(sequencer.forget(8), Some(b));
+        assert_eq!(sequencer.last(8), None);
+    }
+
+    #[test]
+    fn test_entity_sequencer_capacity() {
+        let clock = manual_clock();
+        let sequencer = EntitySequencer::new(
+            SnowflakeGenerator::with_clock(1, Epoch::TWITTER, clock.clone()).unwrap(),
+        )
+        .with_capacity(4);
+        let tick = || clock.advance(Duration::from_millis(1));
+
+        // An entity the generator hasn't caught up with yet is never evicted
+        let ahead = SnowflakeId::from_component_parts(1_050, 3, 0);
+        sequencer.observe(0, ahead);
+        for entity in 1..=100 {
+            sequencer.next_id_for_entity(entity, |_| tick()).unwrap();
+            assert!(sequencer.tracked() <= 5);
+        }
+        assert_eq!(sequencer.last(0), Some(ahead));
+        assert!(sequencer.last(100).is_some());
+
+        assert_eq!(sequencer.last(1), None);
+
+        let id = sequencer
+            .next_id_for_entity(0, |wait| clock.advance(wait))
+            .unwrap();
+        assert!(id > ahead);
+
+        // Once the generator has passed it, it goes like any other
+        for entity in 1..=5 {
+            sequencer.next_id_for_entity(entity, |_| tick()).unwrap();
+        }
+        assert_eq!(sequencer.last(0), None);
+    }
+}
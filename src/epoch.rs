@@ -0,0 +1,129 @@
+use crate::defs::SNOWFLAKE_ID_EPOCH;
+use crate::error::SnowflakeError;
+use std::fmt;
+use std::str::FromStr;
+
+/// A snowflake epoch, expressed in milliseconds since the Unix epoch.
+///
+/// Named presets cover the common third-party layouts so decoding their IDs
+/// doesn't require copying magic numbers around.
+///
+/// # Example
+/// ```
+/// use snowflake_id::{Epoch, SnowflakeGenerator};
+///
+/// let generator = SnowflakeGenerator::with_epoch(1, Epoch::DISCORD).unwrap();
+/// assert_eq!(generator.epoch(), Epoch::DISCORD.as_millis());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Epoch(i64);
+
+impl Epoch {
+    /// Twitter's epoch (2010-11-04T01:42:54.657Z), the crate default
+    pub const TWITTER: Epoch = Epoch(SNOWFLAKE_ID_EPOCH);
+    /// Discord's epoch (2015-01-01T00:00:00Z)
+    pub const DISCORD: Epoch = Epoch(1420070400000);
+    /// The Unix epoch (1970-01-01T00:00:00Z)
+    pub const UNIX: Epoch = Epoch(0);
+
+    /// All named presets, in the form accepted by [`Epoch::from_name`]
+    pub const PRESETS: &'static [(&'static str, Epoch)] = &[
+        ("twitter", Epoch::TWITTER),
+        ("discord", Epoch::DISCORD),
+        ("unix", Epoch::UNIX),
+    ];
+
+    /// Creates an epoch from milliseconds since the Unix epoch
+    pub const fn custom(millis: i64) -> Self {
+        Epoch(millis)
+    }
+
+    /// Looks up a named preset (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|(_, epoch)| *epoch)
+    }
+
+    /// Returns the epoch in milliseconds since the Unix epoch
+    pub const fn as_millis(&self) -> i64 {
+        self.0
+    }
+}
+
+impl Default for Epoch {
+    fn default() -> Self {
+        Epoch::TWITTER
+    }
+}
+
+impl From<i64> for Epoch {
+    fn from(millis: i64) -> Self {
+        Epoch(millis)
+    }
+}
+
+impl From<Epoch> for i64 {
+    fn from(epoch: Epoch) -> Self {
+        epoch.0
+    }
+}
+
+/// Parses a preset name (case-insensitive) or milliseconds since the Unix
+/// epoch
+impl FromStr for Epoch {
+    type Err = SnowflakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s)
+            .or_else(|| s.parse().ok().map(Epoch))
+            .ok_or_else(|| SnowflakeError::InvalidId(format!("Unknown epoch: {}", s)))
+    }
+}
+
+impl fmt::Display for Epoch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+    use std::thread;
+
+    #[test]
+    fn test_epoch_presets() {
+        assert_eq!(Epoch::TWITTER.as_millis(), SNOWFLAKE_ID_EPOCH);
+        assert_eq!(Epoch::DISCORD.as_millis(), 1420070400000);
+        assert_eq!(Epoch::UNIX.as_millis(), 0);
+        assert_eq!(Epoch::custom(42), Epoch::from(42i64));
+        assert_eq!(Epoch::default(), Epoch::TWITTER);
+
+        assert_eq!(Epoch::from_name("Discord"), Some(Epoch::DISCORD));
+        assert_eq!(Epoch::from_name("unknown"), None);
+
+        assert_eq!("twitter".parse::<Epoch>().unwrap(), Epoch::TWITTER);
+        assert_eq!("1420070400000".parse::<Epoch>().unwrap(), Epoch::DISCORD);
+        assert!("unknown".parse::<Epoch>().is_err());
+    }
+
+    #[test]
+    fn test_decode_discord_snowflake() {
+        // Example ID from the Discord API reference
+        let id = SnowflakeId::new(175928847299117063).unwrap();
+        assert_eq!(id.timestamp_with_epoch(Epoch::DISCORD), 1462015105796);
+    }
+
+    #[test]
+    fn test_generator_with_epoch_preset() {
+        let generator = SnowflakeGenerator::with_epoch(1, Epoch::DISCORD).unwrap();
+        assert_eq!(generator.epoch(), Epoch::DISCORD.as_millis());
+
+        let id = generator.next_id(|_| thread::yield_now());
+        assert!(id.timestamp_with_epoch(Epoch::DISCORD) > Epoch::DISCORD.as_millis());
+    }
+}
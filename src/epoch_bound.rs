@@ -0,0 +1,164 @@
+//! IDs that carry the epoch they were generated against.
+//!
+//! Decoding a snowflake's timestamp needs its epoch, so code that only
+//! passes IDs around ends up threading the epoch alongside. An
+//! [`EpochBound`] keeps the two together and answers time questions
+//! directly.
+//!
+//! # Example
+//! ```
+//! use chrono::Utc;
+//! use snowflake_id::{Epoch, SnowflakeGenerator};
+//!
+//! let generator = SnowflakeGenerator::with_epoch(1, Epoch::DISCORD).unwrap();
+//! let id = generator.next_bound_id().unwrap();
+//! assert!(id <= Utc::now());
+//! println!("{} is {:?} old", id.id(), id.age());
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::generator::SnowflakeGenerator;
+use crate::snowflake::Snowflake;
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+use std::time::Duration;
+
+/// A snowflake ID together with its epoch, see the [module docs](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EpochBound<S> {
+    id: S,
+    epoch: Epoch,
+    tick: Duration,
+}
+
+impl<S: Snowflake> EpochBound<S> {
+    /// Binds `id` to `epoch`, assuming the default millisecond tick
+    pub fn new(id: S, epoch: impl Into<Epoch>) -> Self {
+        EpochBound {
+            id,
+            epoch: epoch.into(),
+            tick: Duration::from_millis(1),
+        }
+    }
+
+    /// Sets the length of one timestamp unit, for IDs from generators
+    /// built with [`GeneratorBuilder::tick`](crate::builder::GeneratorBuilder::tick)
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    pub fn id(&self) -> S {
+        self.id
+    }
+
+    pub fn epoch(&self) -> Epoch {
+        self.epoch
+    }
+
+    pub fn tick(&self) -> Duration {
+        self.tick
+    }
+
+    /// Returns the creation time in microseconds since the Unix epoch
+    pub fn timestamp_micros(&self) -> i64 {
+        let tick_micros = i64::try_from(self.tick.as_micros()).unwrap_or(i64::MAX);
+        (self.id.timestamp() as i64)
+            .saturating_mul(tick_micros)
+            .saturating_add(self.epoch.as_millis().saturating_mul(1_000))
+    }
+
+    /// Returns the creation time
+    ///
+    /// # Panics
+    /// Panics if the timestamp is outside the range supported by chrono.
+    pub fn datetime(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_micros(self.timestamp_micros())
+            .expect("snowflake timestamp out of range for DateTime")
+    }
+
+    /// Returns the time elapsed since the ID was created, or zero if its
+    /// timestamp is in the future
+    pub fn age(&self) -> Duration {
+        let elapsed = SystemClock
+            .now_micros()
+            .saturating_sub(self.timestamp_micros());
+        Duration::from_micros(elapsed.max(0) as u64)
+    }
+
+    /// Returns true if the ID was created more than `duration` ago
+    pub fn is_older_than(&self, duration: Duration) -> bool {
+        self.age() > duration
+    }
+}
+
+impl<S: Snowflake> PartialEq<DateTime<Utc>> for EpochBound<S> {
+    fn eq(&self, other: &DateTime<Utc>) -> bool {
+        self.timestamp_micros() == other.timestamp_micros()
+    }
+}
+
+/// Compares the ID's creation time against a point in time
+impl<S: Snowflake> PartialOrd<DateTime<Utc>> for EpochBound<S> {
+    fn partial_cmp(&self, other: &DateTime<Utc>) -> Option<Ordering> {
+        Some(self.timestamp_micros().cmp(&other.timestamp_micros()))
+    }
+}
+
+impl<S: Snowflake> SnowflakeGenerator<S> {
+    /// Generates the next ID bound to this generator's epoch and tick
+    pub fn next_bound_id(&self) -> Result<EpochBound<S>, SnowflakeError> {
+        Ok(EpochBound::new(self.generate()?, Epoch::custom(self.epoch())).with_tick(self.tick()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+
+    #[test]
+    fn test_epoch_bound() {
+        use crate::clock::ManualClock;
+
+        let clock = ManualClock::new(Epoch::DISCORD.as_millis() + 5_000);
+        let generator = SnowflakeGenerator::builder(1)
+            .epoch(Epoch::DISCORD)
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+        let id = generator.next_bound_id().unwrap();
+        assert_eq!(id.epoch(), Epoch::DISCORD);
+        assert_eq!(id.datetime(), id.id().datetime_with_epoch(Epoch::DISCORD));
+        assert!(id == id.datetime());
+        assert!(id < id.datetime() + chrono::Duration::milliseconds(1));
+        assert!(id > id.datetime() - chrono::Duration::milliseconds(1));
+        // Created in 2015, so years old by now
+        assert!(id.age() > Duration::from_secs(86_400 * 365));
+
+        let ticked = SnowflakeGenerator::builder(1)
+            .epoch(Epoch::DISCORD)
+            .tick(Duration::from_millis(10))
+            .clock(clock.clone())
+            .build()
+            .unwrap()
+            .next_bound_id()
+            .unwrap();
+        assert_eq!(ticked.tick(), Duration::from_millis(10));
+        assert_eq!(ticked.datetime(), id.datetime());
+
+        let future = EpochBound::new(
+            SnowflakeId::first_for_timestamp(
+                Utc::now().timestamp_millis() + 60_000,
+                Epoch::TWITTER,
+            )
+            .unwrap(),
+            Epoch::TWITTER,
+        );
+        assert_eq!(future.age(), Duration::ZERO);
+        assert!(future > Utc::now());
+    }
+}
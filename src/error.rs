@@ -1,41 +1,210 @@
-use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Debug, Clone)]
+/// Everything that can go wrong creating, parsing or generating IDs.
+///
+/// Match on [`SnowflakeError::code`] rather than the display text to handle
+/// errors programmatically, and use [`SnowflakeError::is_retryable`] to tell
+/// transient conditions from bad input or configuration.
+#[derive(Debug, Clone, thiserror::Error)]
+#[non_exhaustive]
 pub enum SnowflakeError {
+    #[error("Invalid machine ID: {0}. Must be between 0 and {1}")]
     InvalidMachineId(u64, u64),
-    ClockMovedBackwards,
+    #[error("Clock moved backwards by {drift_ms}ms. Refusing to generate id")]
+    ClockMovedBackwards {
+        /// How far behind the last issued timestamp the clock was
+        drift_ms: u64,
+    },
+    #[error("Timestamp exceeds maximum.")]
     TimestampOverflow,
+    /// No longer returned: generator locks recover from a panicking holder
+    #[error("ID generator mutex was poisoned by a panicking thread")]
     GeneratorPoisoned,
+    #[error("Invalid snowflake ID: {0}")]
     InvalidId(String),
+    /// Text that isn't a number of the ID's type
+    #[error("Invalid snowflake ID: Failed to parse {input:?}: {reason}")]
+    Unparseable { input: String, reason: String },
+    #[error("Invalid or overlapping route: {start}..={end}")]
+    InvalidRoute { start: u64, end: u64 },
+    #[error("Invalid wire format: {0}")]
+    InvalidWireFormat(String),
+    #[error("Generator overloaded; low-priority request shed")]
+    Overloaded,
+    #[error("Global generator not initialised; call init_global first")]
+    NotInitialized,
+    #[error("Global generator is already initialised")]
+    AlreadyInitialized,
+    #[error("Invalid shard count: {0}. Must be between 1 and {1}")]
+    InvalidShardCount(usize, u64),
+    #[error("Failed to read or write checkpoint: {0}")]
+    CheckpointFailed(#[source] Arc<io::Error>),
+    #[error("Invalid sequence start: {0}. Must be at most {1}")]
+    InvalidSequenceStart(u64, u64),
+    #[error("Invalid datacenter ID: {0}. Must be between 0 and {1}")]
+    InvalidDatacenterId(u64, u64),
+    #[error("Invalid worker ID: {0}. Must be between 0 and {1}")]
+    InvalidWorkerId(u64, u64),
+    #[error("All machine IDs from 0 to {0} are leased")]
+    MachineIdsExhausted(u64),
+    #[error("Lease on machine ID {0} was lost to another claimant")]
+    LeaseLost(u64),
+    #[error("Machine ID registry failed: {0}")]
+    RegistryFailed(#[source] Arc<io::Error>),
+    #[error("Invalid generator configuration: {0}")]
+    InvalidConfig(String),
+    #[error("Invalid tick: {0:?}. Must be a nonzero whole number of microseconds")]
+    InvalidTick(Duration),
+    #[error("Timed out after {0:?} waiting for an ID")]
+    Timeout(Duration),
+    /// Another live generator on the same machine ID already uses the epoch
+    #[error("Epoch {0} is already used by another generator on this machine ID")]
+    EpochInUse(i64),
+    #[error("No generator named {0:?}")]
+    UnknownGenerator(String),
+    /// A named generator failed to build; [`SnowflakeError::code`] and the
+    /// other accessors report on `source`
+    #[error("Generator {name:?}: {source}")]
+    NamedGenerator {
+        name: String,
+        source: Box<SnowflakeError>,
+    },
 }
 
-impl fmt::Display for SnowflakeError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl SnowflakeError {
+    /// Returns a stable, machine-readable name for the kind of error, e.g.
+    /// `"clock_moved_backwards"`
+    pub fn code(&self) -> &'static str {
         match self {
-            SnowflakeError::InvalidMachineId(id, max) => {
-                write!(
-                    f,
-                    "Invalid machine ID: {}. Must be between 0 and {}",
-                    id, max
-                )
-            }
-            SnowflakeError::ClockMovedBackwards => {
-                write!(f, "Clock moved backwards. Refusing to generate id")
-            }
-            SnowflakeError::TimestampOverflow => {
-                write!(
-                    f,
-                    "Timestamp exceeds maximum."
-                )
-            }
-            SnowflakeError::GeneratorPoisoned => {
-                write!(f, "ID generator mutex was poisoned by a panicking thread")
-            }
-            SnowflakeError::InvalidId(msg) => {
-                write!(f, "Invalid snowflake ID: {}", msg)
-            }
+            SnowflakeError::InvalidMachineId(..) => "invalid_machine_id",
+            SnowflakeError::ClockMovedBackwards { .. } => "clock_moved_backwards",
+            SnowflakeError::TimestampOverflow => "timestamp_overflow",
+            SnowflakeError::GeneratorPoisoned => "generator_poisoned",
+            SnowflakeError::InvalidId(_) => "invalid_id",
+            SnowflakeError::Unparseable { .. } => "unparseable",
+            SnowflakeError::InvalidRoute { .. } => "invalid_route",
+            SnowflakeError::InvalidWireFormat(_) => "invalid_wire_format",
+            SnowflakeError::Overloaded => "overloaded",
+            SnowflakeError::NotInitialized => "not_initialized",
+            SnowflakeError::AlreadyInitialized => "already_initialized",
+            SnowflakeError::InvalidShardCount(..) => "invalid_shard_count",
+            SnowflakeError::CheckpointFailed(_) => "checkpoint_failed",
+            SnowflakeError::InvalidSequenceStart(..) => "invalid_sequence_start",
+            SnowflakeError::InvalidDatacenterId(..) => "invalid_datacenter_id",
+            SnowflakeError::InvalidWorkerId(..) => "invalid_worker_id",
+            SnowflakeError::MachineIdsExhausted(_) => "machine_ids_exhausted",
+            SnowflakeError::LeaseLost(_) => "lease_lost",
+            SnowflakeError::RegistryFailed(_) => "registry_failed",
+            SnowflakeError::InvalidConfig(_) => "invalid_config",
+            SnowflakeError::InvalidTick(_) => "invalid_tick",
+            SnowflakeError::Timeout(_) => "timeout",
+            SnowflakeError::EpochInUse(_) => "epoch_in_use",
+            SnowflakeError::UnknownGenerator(_) => "unknown_generator",
+            SnowflakeError::NamedGenerator { source, .. } => source.code(),
+        }
+    }
+
+    /// Returns true for transient conditions where the same call may succeed
+    /// if retried later, such as the clock catching up or load easing
+    pub fn is_retryable(&self) -> bool {
+        if let SnowflakeError::NamedGenerator { source, .. } = self {
+            return source.is_retryable();
+        }
+        matches!(
+            self,
+            SnowflakeError::ClockMovedBackwards { .. }
+                | SnowflakeError::Overloaded
+                | SnowflakeError::MachineIdsExhausted(_)
+                | SnowflakeError::Timeout(_)
+        )
+    }
+
+    /// Returns true if the error is down to the caller's input rather than
+    /// the generator or its environment
+    pub fn is_invalid_input(&self) -> bool {
+        if let SnowflakeError::NamedGenerator { source, .. } = self {
+            return source.is_invalid_input();
+        }
+        matches!(
+            self,
+            SnowflakeError::InvalidMachineId(..)
+                | SnowflakeError::InvalidId(_)
+                | SnowflakeError::Unparseable { .. }
+                | SnowflakeError::InvalidRoute { .. }
+                | SnowflakeError::InvalidWireFormat(_)
+                | SnowflakeError::InvalidShardCount(..)
+                | SnowflakeError::InvalidSequenceStart(..)
+                | SnowflakeError::InvalidDatacenterId(..)
+                | SnowflakeError::InvalidWorkerId(..)
+                | SnowflakeError::InvalidTick(_)
+                | SnowflakeError::UnknownGenerator(_)
+        )
+    }
+
+    /// Builds [`SnowflakeError::ClockMovedBackwards`] for a clock `drift`
+    /// behind
+    pub(crate) fn clock_moved_backwards(drift: Duration) -> Self {
+        SnowflakeError::ClockMovedBackwards {
+            drift_ms: u64::try_from(drift.as_millis()).unwrap_or(u64::MAX),
+        }
+    }
+
+    /// Status code for web framework integrations: `400` for bad input, `503`
+    /// for transient conditions a client can retry, `500` otherwise
+    #[cfg(any(feature = "axum", feature = "actix", feature = "server-grpc"))]
+    pub(crate) fn http_status(&self) -> u16 {
+        if self.is_invalid_input() {
+            400
+        } else if self.is_retryable() {
+            503
+        } else {
+            500
         }
     }
 }
 
-impl std::error::Error for SnowflakeError {}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Epoch;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+    use crate::test_support::manual_clock;
+
+    #[test]
+    fn test_error_codes_and_context() {
+        use std::error::Error;
+        use std::sync::Arc;
+
+        let clock = manual_clock();
+        let generator = SnowflakeGenerator::with_clock(1, Epoch::TWITTER, clock.clone()).unwrap();
+        generator.generate().unwrap();
+        clock.rewind(Duration::from_millis(250));
+        let err = generator.generate().unwrap_err();
+        assert!(matches!(
+            err,
+            SnowflakeError::ClockMovedBackwards { drift_ms: 250 }
+        ));
+        assert_eq!(err.code(), "clock_moved_backwards");
+        assert!(err.is_retryable() && !err.is_invalid_input());
+        assert!(err.to_string().contains("250ms"));
+
+        let err = "12x".parse::<SnowflakeId>().unwrap_err();
+        assert_eq!(err.code(), "unparseable");
+        assert!(err.is_invalid_input() && !err.is_retryable());
+        assert!(err.to_string().contains("\"12x\""));
+
+        let Err(err) = SnowflakeGenerator::new(4096) else {
+            panic!("machine id 4096 accepted");
+        };
+        assert!(matches!(err, SnowflakeError::InvalidMachineId(4096, 1023)));
+        assert_eq!(err.code(), "invalid_machine_id");
+
+        let io = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "read-only");
+        let err = SnowflakeError::CheckpointFailed(Arc::new(io));
+        assert_eq!(err.source().unwrap().to_string(), "read-only");
+        assert!(!err.is_retryable() && !err.is_invalid_input());
+    }
+}
@@ -1,43 +1,186 @@
-use crate::defs::CLOCK_BACKWARDS_TOLERANCE_MS;
+use crate::block::BlockIter;
+use crate::builder::{OverflowStrategy, SequenceStart};
+#[cfg(feature = "async")]
+use crate::checkpoint::AsyncCheckpoint;
+use crate::checkpoint::Checkpoint;
+use crate::clock::{monotonic_now, Clock, ClockHealth, SystemClock};
+use crate::defs::{CLOCK_BACKWARDS_TOLERANCE_MS, COARSE_CLOCK_READ_EVERY};
+use crate::epoch::Epoch;
 use crate::error::SnowflakeError;
+#[cfg(feature = "metrics")]
+use crate::generator_metrics::GeneratorMetrics;
+use crate::machine_id::from_datacenter;
+use crate::parts::SnowflakeParts;
 use crate::snowflake::Snowflake;
-use chrono::Utc;
+#[cfg(feature = "stats")]
+use crate::stats::{GeneratorStats, StatsRecorder};
+use crate::sync::{Mutex, MutexGuard};
+use crate::typed::TypedSnowflakeId;
+use crate::wait::{SleepWait, WaitStrategy};
+use crate::SnowflakeId;
+use chrono::DateTime;
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
 use std::marker::PhantomData;
-use std::sync::Mutex;
-use std::time::Duration;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub enum SnowflakeOperation<S> {
     Ready(S),
     Pending(Duration),
 }
 
-struct GeneratorState {
-    last_timestamp: i64,
-    sequence: u64,
+/// Run of sequence numbers claimed in one tick by [`GeneratorCore::try_claim`]
+pub(crate) struct Claim<S> {
+    /// The run's first, and smallest, ID
+    pub(crate) first: S,
+    pub(crate) last_sequence: u64,
 }
 
-pub struct SnowflakeGenerator<S: Snowflake> {
-    machine_id: u64,
-    state: Mutex<GeneratorState>,
-    epoch: i64,
+/// Part of each tick's sequence space a [`GeneratorState`] claims from:
+/// all of it, or one shard's slice, see
+/// [`crate::sharded::ShardedSnowflakeGenerator`]. The state's sequence
+/// counts from the slice's start.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SequenceSlice {
+    pub(crate) start: u64,
+    /// Highest sequence number within the slice
+    pub(crate) last: u64,
+}
+
+/// Clock reading shared by the IDs of one bulk call. It never outlives the
+/// call, so a cached tick is at most [`COARSE_CLOCK_READ_EVERY`] IDs old.
+/// Monotonicity doesn't depend on it: a reading behind the last issued tick
+/// is handled like any other clock lag.
+#[derive(Default)]
+pub(crate) struct ClockCache {
+    now: i64,
+    reads_left: u32,
+}
+
+pub(crate) struct GeneratorState {
+    pub(crate) last_timestamp: i64,
+    pub(crate) sequence: u64,
+    /// Number of consecutive ticks whose sequence space ran out
+    exhausted_ticks: u32,
+    last_exhausted_tick: i64,
+    /// Restored checkpoint; ticks up to it may already have been used
+    resume_after: i64,
+    /// Mark last written to the checkpoint
+    pub(crate) checkpointed_until: i64,
+    /// When the clock was last seen behind `last_timestamp`
+    last_backwards: Option<Instant>,
+    /// Monotonic clock and tick read together on the first call, to measure
+    /// drift against
+    anchor: Option<(Instant, i64)>,
+}
+
+impl GeneratorState {
+    pub(crate) fn new() -> Self {
+        GeneratorState {
+            last_timestamp: 0,
+            sequence: 0,
+            exhausted_ticks: 0,
+            last_exhausted_tick: i64::MIN,
+            resume_after: i64::MIN,
+            checkpointed_until: i64::MIN,
+            last_backwards: None,
+            anchor: None,
+        }
+    }
+
+    /// Returns how many consecutive ticks up to `now` ran out of sequence space
+    #[cfg(feature = "async")]
+    pub(crate) fn exhaustion_streak(&self, now: i64) -> u32 {
+        if now <= self.last_exhausted_tick.saturating_add(1) {
+            self.exhausted_ticks
+        } else {
+            0
+        }
+    }
+
+    fn record_exhaustion(&mut self, timestamp: i64) {
+        if timestamp == self.last_exhausted_tick {
+            return;
+        }
+        if self.last_exhausted_tick.checked_add(1) == Some(timestamp) {
+            self.exhausted_ticks += 1;
+        } else {
+            self.exhausted_ticks = 1;
+        }
+        self.last_exhausted_tick = timestamp;
+    }
+}
+
+/// Checkpoint settings, see [`crate::checkpoint`]
+pub(crate) struct Checkpointing {
+    pub(crate) store: CheckpointStore,
+    pub(crate) interval_ms: i64,
+    /// Mark loaded when the generator was built
+    pub(crate) restored: Option<i64>,
+}
+
+pub(crate) enum CheckpointStore {
+    Blocking(Arc<dyn Checkpoint>),
+    /// Stored by [`crate::async_generator::AsyncSnowflakeGenerator`] before
+    /// it claims, see [`GeneratorCore::checkpoint_due`]
+    #[cfg(feature = "async")]
+    Async(Arc<dyn AsyncCheckpoint>),
+}
+
+/// Configuration and generation logic shared by the sync and async generators
+pub(crate) struct GeneratorCore<S> {
+    pub(crate) machine_id: u64,
+    pub(crate) epoch: i64,
+    /// Length of one timestamp unit in microseconds
+    pub(crate) tick_micros: i64,
+    pub(crate) clock: Arc<dyn Clock>,
+    /// How far the clock may move backwards before generation fails
+    pub(crate) tolerance_ms: i64,
+    pub(crate) overflow: OverflowStrategy,
+    pub(crate) sequence_start: SequenceStart,
+    /// Keys the per-tick draw for [`SequenceStart::Random`]
+    sequence_seed: RandomState,
+    pub(crate) checkpoint: Option<Checkpointing>,
+    /// Reads the clock once per [`COARSE_CLOCK_READ_EVERY`] IDs in bulk calls
+    pub(crate) coarse_clock: bool,
+    /// Tick nothing may be issued at or before, see
+    /// [`crate::checkpoint::StrictMonotonic`]
+    pub(crate) floor: Option<i64>,
+    /// Tick and sequence of the last ID issued before a restart, see
+    /// [`crate::snapshot`]
+    pub(crate) resume: Option<(i64, u64)>,
+    #[cfg(feature = "stats")]
+    pub(crate) stats: StatsRecorder,
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: GeneratorMetrics,
     _marker: PhantomData<S>,
 }
 
-impl<S: Snowflake> SnowflakeGenerator<S> {
-    /// Creates a new SnowflakeGenerator with a custom epoch
-    ///
-    /// # Arguments
-    /// * `machine_id` - Unique machine/datacenter ID (0-1023)
-    /// * `epoch` - Custom epoch in milliseconds since Unix epoch
-    ///
-    /// # Example
-    /// ```
-    /// use snowflake_id::SnowflakeGenerator;
-    ///
-    /// // Use a custom epoch (e.g., Jan 1, 2024)
-    /// let generator = SnowflakeGenerator::with_epoch(1, 1704067200000).unwrap();
-    /// ```
-    pub fn with_epoch(machine_id: u64, epoch: i64) -> Result<Self, SnowflakeError> {
+impl<S: Snowflake> GeneratorCore<S> {
+    pub(crate) fn new(
+        machine_id: u64,
+        epoch: Epoch,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, SnowflakeError> {
+        #[cfg_attr(not(feature = "metrics"), allow(unused_mut))]
+        let mut core = Self::unmetered(machine_id, epoch, clock)?;
+        #[cfg(feature = "metrics")]
+        {
+            core.metrics = GeneratorMetrics::new(machine_id);
+        }
+        Ok(core)
+    }
+
+    /// Like [`GeneratorCore::new`], but registers no metrics, for throwaway
+    /// cores such as the one [`SnowflakeGenerator::self_test`] runs on
+    pub(crate) fn unmetered(
+        machine_id: u64,
+        epoch: Epoch,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, SnowflakeError> {
         if machine_id > S::max_machine_id() {
             return Err(SnowflakeError::InvalidMachineId(
                 machine_id,
@@ -45,71 +188,598 @@ impl<S: Snowflake> SnowflakeGenerator<S> {
             ));
         }
 
-        Ok(SnowflakeGenerator {
+        Ok(GeneratorCore {
             machine_id,
-            state: Mutex::new(GeneratorState {
-                last_timestamp: 0,
-                sequence: 0,
-            }),
-            epoch,
+            epoch: epoch.as_millis(),
+            tick_micros: 1_000,
+            clock,
+            tolerance_ms: CLOCK_BACKWARDS_TOLERANCE_MS,
+            overflow: OverflowStrategy::Wait,
+            sequence_start: SequenceStart::Zero,
+            sequence_seed: RandomState::new(),
+            checkpoint: None,
+            coarse_clock: false,
+            floor: None,
+            resume: None,
+            #[cfg(feature = "stats")]
+            stats: StatsRecorder::new(),
+            #[cfg(feature = "metrics")]
+            metrics: GeneratorMetrics::noop(),
             _marker: PhantomData,
         })
     }
 
-    /// Returns the epoch being used by this generator
-    pub fn epoch(&self) -> i64 {
+    /// Returns the current tick. Ticks are counted from the epoch and offset
+    /// by [`GeneratorCore::epoch_ticks`], so millisecond ticks are plain Unix
+    /// milliseconds.
+    pub(crate) fn current_timestamp(&self) -> i64 {
+        if self.tick_micros == 1_000 {
+            self.clock.now_millis()
+        } else {
+            self.tick_at(self.clock.now_micros())
+        }
+    }
+
+    /// Returns the tick containing `micros` since the Unix epoch
+    pub(crate) fn tick_at(&self, micros: i64) -> i64 {
+        let epoch_micros = self.epoch.saturating_mul(1_000);
+        micros
+            .saturating_sub(epoch_micros)
+            .div_euclid(self.tick_micros)
+            .saturating_add(self.epoch_ticks())
+    }
+
+    /// Returns the tick the epoch falls in
+    pub(crate) fn epoch_ticks(&self) -> i64 {
         self.epoch
+            .saturating_mul(1_000)
+            .div_euclid(self.tick_micros)
     }
 
-    pub fn try_next_id(&self) -> Result<SnowflakeOperation<S>, SnowflakeError> {
-        let mut state = self
-            .state
-            .lock()
-            .map_err(|_| SnowflakeError::GeneratorPoisoned)?;
+    /// Returns when `tick` starts, in microseconds since the Unix epoch
+    pub(crate) fn tick_start_micros(&self, tick: i64) -> i64 {
+        (tick - self.epoch_ticks())
+            .saturating_mul(self.tick_micros)
+            .saturating_add(self.epoch.saturating_mul(1_000))
+    }
+
+    /// Returns how long `ticks` ticks last
+    pub(crate) fn ticks_duration(&self, ticks: i64) -> Duration {
+        Duration::from_micros((ticks.max(0) as u64).saturating_mul(self.tick_micros as u64))
+    }
+
+    /// Backwards clock movement to wait out, in ticks, rounded up so a step
+    /// within the tolerance is never rejected
+    pub(crate) fn tolerance_ticks(&self) -> i64 {
+        let micros = self.tolerance_ms.saturating_mul(1_000);
+        micros.saturating_add(self.tick_micros - 1) / self.tick_micros
+    }
 
-        let timestamp = Self::current_timestamp();
+    /// How many ticks the logical clock may run ahead of real time
+    pub(crate) fn max_ahead_ticks(&self) -> i64 {
+        match self.overflow {
+            OverflowStrategy::Wait => 0,
+            OverflowStrategy::BorrowFuture { max_ahead_ms } => {
+                max_ahead_ms.max(0).saturating_mul(1_000) / self.tick_micros
+            }
+        }
+    }
+
+    /// Returns the first sequence number of the tick at `timestamp`
+    fn first_sequence(&self, timestamp: i64) -> u64 {
+        match self.sequence_start {
+            SequenceStart::Zero => 0,
+            SequenceStart::Fixed(offset) => offset,
+            SequenceStart::Random { max } => self.sequence_seed.hash_one(timestamp) % (max + 1),
+        }
+    }
+
+    /// Reports on the clock as seen against `state`
+    pub(crate) fn clock_health(&self, state: &GeneratorState) -> ClockHealth {
+        let now = self.current_timestamp();
+        let drift_micros = state.anchor.map_or(0, |(instant, tick)| {
+            let monotonic = i64::try_from(instant.elapsed().as_micros()).unwrap_or(i64::MAX);
+            (now - tick)
+                .saturating_mul(self.tick_micros)
+                .saturating_sub(monotonic)
+        });
+        let behind = state.last_timestamp.saturating_sub(now);
+        let held_up = behind > self.max_ahead_ticks();
+        let rejecting = held_up && behind > self.tolerance_ticks();
+        ClockHealth {
+            drift_micros,
+            since_last_backwards: state.last_backwards.map(|instant| instant.elapsed()),
+            in_tolerance_wait: held_up && !rejecting,
+            rejecting,
+        }
+    }
+
+    /// Returns a fresh state that honours any restored checkpoint, floor and
+    /// snapshot
+    pub(crate) fn initial_state(&self) -> GeneratorState {
+        let mut state = GeneratorState::new();
+        if let Some(restored) = self.checkpoint.as_ref().and_then(|c| c.restored) {
+            state.resume_after = self.tick_at(restored.saturating_mul(1_000));
+            // The stored mark already covers the ticks up to it, so nothing
+            // lower is ever written over it
+            state.checkpointed_until = state.resume_after;
+        }
+        if let Some(floor) = self.floor {
+            // As if the floor's tick had run dry here, so the clock has to
+            // pass it and falling behind it counts as moving backwards
+            state.last_timestamp = floor;
+            state.sequence = S::max_sequence();
+        }
+        if let Some((last_timestamp, sequence)) = self.resume {
+            if (last_timestamp, sequence) > (state.last_timestamp, state.sequence) {
+                state.last_timestamp = last_timestamp;
+                state.sequence = sequence;
+            }
+        }
+        state
+    }
+
+    /// Attempts to generate one ID against `state`
+    pub(crate) fn try_next(
+        &self,
+        state: &mut GeneratorState,
+    ) -> Result<SnowflakeOperation<S>, SnowflakeError> {
+        self.try_next_at(self.current_timestamp(), state)
+    }
+
+    /// Attempts to generate one ID as part of a bulk call, reusing the
+    /// clock reading in `cache` when the coarse clock is on
+    pub(crate) fn try_next_cached(
+        &self,
+        state: &mut GeneratorState,
+        cache: &mut ClockCache,
+    ) -> Result<SnowflakeOperation<S>, SnowflakeError> {
+        if !self.coarse_clock {
+            return self.try_next(state);
+        }
+        if cache.reads_left == 0 {
+            cache.now = self.current_timestamp();
+            cache.reads_left = COARSE_CLOCK_READ_EVERY;
+        }
+        cache.reads_left -= 1;
+        let result = self.try_next_at(cache.now, state);
+        // A stale reading would never see the wait end, so read again
+        if !matches!(result, Ok(SnowflakeOperation::Ready(_))) {
+            cache.reads_left = 0;
+        }
+        result
+    }
+
+    fn try_next_at(
+        &self,
+        now: i64,
+        state: &mut GeneratorState,
+    ) -> Result<SnowflakeOperation<S>, SnowflakeError> {
+        let id = match self.try_claim_at(now, state, 1, Self::whole_sequence())? {
+            SnowflakeOperation::Ready(claim) => claim.first,
+            SnowflakeOperation::Pending(wait) => return Ok(SnowflakeOperation::Pending(wait)),
+        };
+        #[cfg(feature = "metrics")]
+        self.metrics.record_generated();
+        Ok(SnowflakeOperation::Ready(id))
+    }
+
+    /// Attempts to claim up to `want` consecutive sequence numbers of one
+    /// tick against `state`, under the same clock, checkpoint and floor rules
+    /// as a single ID
+    pub(crate) fn try_claim(
+        &self,
+        state: &mut GeneratorState,
+        want: u64,
+    ) -> Result<SnowflakeOperation<Claim<S>>, SnowflakeError> {
+        self.try_claim_at(
+            self.current_timestamp(),
+            state,
+            want,
+            Self::whole_sequence(),
+        )
+    }
+
+    /// Claims like [`GeneratorCore::try_claim`], from `slice` of each tick's
+    /// sequence space
+    pub(crate) fn try_claim_in(
+        &self,
+        state: &mut GeneratorState,
+        want: u64,
+        slice: SequenceSlice,
+    ) -> Result<SnowflakeOperation<Claim<S>>, SnowflakeError> {
+        self.try_claim_at(self.current_timestamp(), state, want, slice)
+    }
+
+    fn whole_sequence() -> SequenceSlice {
+        SequenceSlice {
+            start: 0,
+            last: S::max_sequence(),
+        }
+    }
+
+    fn try_claim_at(
+        &self,
+        now: i64,
+        state: &mut GeneratorState,
+        want: u64,
+        slice: SequenceSlice,
+    ) -> Result<SnowflakeOperation<Claim<S>>, SnowflakeError> {
+        let mut timestamp = now;
+        if state.anchor.is_none() {
+            state.anchor = monotonic_now().map(|instant| (instant, now));
+        }
+
+        if timestamp <= state.resume_after {
+            let behind = state.resume_after - timestamp + 1;
+            return Ok(SnowflakeOperation::Pending(self.ticks_duration(behind)));
+        }
 
         if timestamp < state.last_timestamp {
             let drift = state.last_timestamp - timestamp;
-            if drift <= CLOCK_BACKWARDS_TOLERANCE_MS {
-                return Ok(SnowflakeOperation::Pending(Duration::from_millis(
-                    drift as u64,
-                )));
+            if drift > self.max_ahead_ticks() {
+                state.last_backwards = monotonic_now();
+            }
+            if drift <= self.max_ahead_ticks() {
+                // Still within the lead borrowed from the future
+                timestamp = state.last_timestamp;
+            } else if drift <= self.tolerance_ticks() {
+                let wait = self.ticks_duration(drift);
+                #[cfg(feature = "metrics")]
+                self.metrics.record_clock_wait(wait);
+                return Ok(SnowflakeOperation::Pending(wait));
             } else {
-                return Err(SnowflakeError::ClockMovedBackwards);
+                #[cfg(feature = "metrics")]
+                self.metrics.record_clock_rejected();
+                return Err(SnowflakeError::clock_moved_backwards(
+                    self.ticks_duration(drift),
+                ));
             }
         }
 
-        if timestamp == state.last_timestamp {
-            let next_seq = (state.sequence + 1) & S::max_sequence();
-            if next_seq == 0 {
-                return Ok(SnowflakeOperation::Pending(Duration::from_millis(1)));
+        // Nothing is written to `state` until the checkpoint has accepted
+        // the tick, so a failed store can't leave a sequence behind that was
+        // already issued
+        let first_sequence = if timestamp == state.last_timestamp {
+            if state.sequence < slice.last {
+                state.sequence + 1
+            } else if timestamp + 1 - now <= self.max_ahead_ticks() {
+                timestamp += 1;
+                self.first_sequence(timestamp) % (slice.last + 1)
+            } else {
+                state.record_exhaustion(timestamp);
+                let wait = self.ticks_duration(timestamp + 1 - now - self.max_ahead_ticks());
+                #[cfg(feature = "metrics")]
+                self.metrics.record_exhaustion(wait);
+                return Ok(SnowflakeOperation::Pending(wait));
             }
-            state.sequence = next_seq;
         } else {
-            state.sequence = 0;
+            self.first_sequence(timestamp) % (slice.last + 1)
+        };
+
+        let last_sequence =
+            first_sequence + want.saturating_sub(1).min(slice.last - first_sequence);
+        // Validates the timestamp fits the layout before anything is committed
+        let first = self.compose(timestamp, slice.start + first_sequence)?;
+        if !self.store_checkpoint(timestamp, state)? {
+            return Ok(SnowflakeOperation::Pending(Duration::ZERO));
         }
 
+        // A tick that started without the previous one running dry ends the streak
+        if timestamp != state.last_timestamp
+            && timestamp > state.last_exhausted_tick.saturating_add(1)
+        {
+            state.exhausted_ticks = 0;
+        }
         state.last_timestamp = timestamp;
+        state.sequence = last_sequence;
+        Ok(SnowflakeOperation::Ready(Claim {
+            first,
+            last_sequence,
+        }))
+    }
+
+    /// Writes a checkpoint mark covering `timestamp` if the last one doesn't.
+    /// Returns `false` if the mark is due but only the async generator can
+    /// store it.
+    pub(crate) fn store_checkpoint(
+        &self,
+        timestamp: i64,
+        state: &mut GeneratorState,
+    ) -> Result<bool, SnowflakeError> {
+        let Some((checkpoint, mark, until)) = self.checkpoint_due(timestamp, state) else {
+            return Ok(true);
+        };
+        match &checkpoint.store {
+            CheckpointStore::Blocking(store) => store
+                .store(mark)
+                .map_err(|err| SnowflakeError::CheckpointFailed(Arc::new(err)))?,
+            #[cfg(feature = "async")]
+            CheckpointStore::Async(_) => return Ok(false),
+        }
+        state.checkpointed_until = until;
+        Ok(true)
+    }
+
+    /// Returns the mark to store, and the last tick it covers, if the last
+    /// mark doesn't cover `timestamp`
+    pub(crate) fn checkpoint_due(
+        &self,
+        timestamp: i64,
+        state: &GeneratorState,
+    ) -> Option<(&Checkpointing, i64, i64)> {
+        let checkpoint = self.checkpoint.as_ref()?;
+        if timestamp < state.checkpointed_until {
+            return None;
+        }
+        let interval = checkpoint.interval_ms.saturating_mul(1_000) / self.tick_micros;
+        let until = timestamp.saturating_add(interval);
+        // The mark is the last millisecond of the tick
+        let mark = (self.tick_start_micros(until + 1) - 1).div_euclid(1_000);
+        Some((checkpoint, mark, until))
+    }
 
-        let timestamp_offset = timestamp - self.epoch;
+    /// Hashes everything that determines what an ID means: the layout, the
+    /// epoch, the tick length and the clock policy. The machine ID is left
+    /// out so every node of a service shares one fingerprint.
+    pub(crate) fn fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"snowflake-id fingerprint v1");
+        hasher.update(S::timestamp_bits().to_le_bytes());
+        hasher.update(S::machine_id_bits().to_le_bytes());
+        hasher.update(S::sequence_bits().to_le_bytes());
+        hasher.update(self.epoch.to_le_bytes());
+        // Tick length in milliseconds, or tagged microseconds for ticks that
+        // aren't whole milliseconds
+        if self.tick_micros % 1_000 == 0 {
+            hasher.update(((self.tick_micros / 1_000) as u64).to_le_bytes());
+        } else {
+            hasher.update(b"tick-micros");
+            hasher.update((self.tick_micros as u64).to_le_bytes());
+        }
+        hasher.update(self.tolerance_ms.to_le_bytes());
+        // Left out by default so fingerprints from before it existed still match
+        if let OverflowStrategy::BorrowFuture { max_ahead_ms } = self.overflow {
+            hasher.update(b"borrow-future");
+            hasher.update(max_ahead_ms.to_le_bytes());
+        }
+        hasher.finalize().into()
+    }
+
+    /// Splits `id` into its fields, resolving its timestamp with this core's
+    /// epoch and tick
+    pub(crate) fn decompose(&self, id: S) -> SnowflakeParts {
+        let micros = self.tick_start_micros(id.timestamp() as i64 + self.epoch_ticks());
+        SnowflakeParts {
+            datetime: DateTime::from_timestamp_micros(micros)
+                .expect("snowflake timestamp out of range for DateTime"),
+            machine_id: id.machine_id(),
+            sequence: id.sequence(),
+            raw_offset: id.timestamp(),
+        }
+    }
+
+    /// Builds an ID from an absolute timestamp, validating it fits the layout
+    pub(crate) fn compose(&self, timestamp: i64, sequence: u64) -> Result<S, SnowflakeError> {
+        let timestamp_offset = timestamp - self.epoch_ticks();
         if timestamp_offset < 0 || timestamp_offset > S::max_timestamp() {
             return Err(SnowflakeError::TimestampOverflow);
         }
 
+        // Mask to timestamp bits to ensure bit 63 is always 0 (keeping ID positive)
         let masked_timestamp = (timestamp_offset as u64) & ((1u64 << S::timestamp_bits()) - 1);
 
-        Ok(SnowflakeOperation::Ready(S::from_component_parts(
+        Ok(S::from_component_parts(
             masked_timestamp,
             self.machine_id,
-            state.sequence,
-        )))
+            sequence,
+        ))
+    }
+}
+
+/// Guard over a generator's state that, with the `stats` feature, records
+/// how long the lock was held when dropped
+pub(crate) struct StateGuard<'a> {
+    guard: MutexGuard<'a, GeneratorState>,
+    #[cfg(feature = "stats")]
+    stats: &'a StatsRecorder,
+    #[cfg(feature = "stats")]
+    acquired: Instant,
+}
+
+impl Deref for StateGuard<'_> {
+    type Target = GeneratorState;
+
+    fn deref(&self) -> &GeneratorState {
+        &self.guard
+    }
+}
+
+impl DerefMut for StateGuard<'_> {
+    fn deref_mut(&mut self) -> &mut GeneratorState {
+        &mut self.guard
+    }
+}
+
+#[cfg(feature = "stats")]
+impl Drop for StateGuard<'_> {
+    fn drop(&mut self) {
+        self.stats.record_lock_hold(self.acquired.elapsed());
+    }
+}
+
+pub struct SnowflakeGenerator<S: Snowflake> {
+    pub(crate) core: GeneratorCore<S>,
+    state: Mutex<GeneratorState>,
+    /// Wait used by [`SnowflakeGenerator::generate`], [`SnowflakeGenerator::generate_bulk`]
+    /// and [`SnowflakeGenerator::iter`]
+    wait: Arc<dyn WaitStrategy>,
+}
+
+impl<S: Snowflake> SnowflakeGenerator<S> {
+    /// Creates a new SnowflakeGenerator with a custom epoch
+    ///
+    /// # Arguments
+    /// * `machine_id` - Unique machine/datacenter ID (0-1023)
+    /// * `epoch` - Custom epoch, either an [`Epoch`] preset or milliseconds since Unix epoch
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::{Epoch, SnowflakeGenerator};
+    ///
+    /// // Use a custom epoch (e.g., Jan 1, 2024)
+    /// let generator = SnowflakeGenerator::with_epoch(1, 1704067200000).unwrap();
+    ///
+    /// // Or a named preset
+    /// let discord = SnowflakeGenerator::with_epoch(1, Epoch::DISCORD).unwrap();
+    /// ```
+    pub fn with_epoch(machine_id: u64, epoch: impl Into<Epoch>) -> Result<Self, SnowflakeError> {
+        Self::with_clock(machine_id, epoch, SystemClock)
+    }
+
+    /// Creates a new SnowflakeGenerator that reads time from `clock` instead of
+    /// the system clock
+    pub fn with_clock(
+        machine_id: u64,
+        epoch: impl Into<Epoch>,
+        clock: impl Clock + 'static,
+    ) -> Result<Self, SnowflakeError> {
+        Ok(Self::from_core(
+            GeneratorCore::new(machine_id, epoch.into(), Arc::new(clock))?,
+            Arc::new(SleepWait),
+        ))
+    }
+
+    pub(crate) fn from_core(core: GeneratorCore<S>, wait: Arc<dyn WaitStrategy>) -> Self {
+        SnowflakeGenerator {
+            state: Mutex::new(core.initial_state()),
+            core,
+            wait,
+        }
+    }
+
+    /// Returns the epoch being used by this generator
+    pub fn epoch(&self) -> i64 {
+        self.core.epoch
+    }
+
+    /// Returns the machine ID embedded in generated IDs
+    pub fn machine_id(&self) -> u64 {
+        self.core.machine_id
+    }
+
+    /// Returns the length of one timestamp unit
+    pub fn tick(&self) -> Duration {
+        self.core.ticks_duration(1)
+    }
+
+    /// Splits `id` into its fields, resolving its timestamp with this
+    /// generator's epoch and tick
+    ///
+    /// # Panics
+    /// Panics if the resulting timestamp is outside the range supported by chrono.
+    pub fn decompose(&self, id: S) -> SnowflakeParts {
+        self.core.decompose(id)
+    }
+
+    /// Returns a digest of the layout, epoch, tick length and clock policy.
+    ///
+    /// Services that exchange raw IDs can compare fingerprints at handshake
+    /// time to confirm they agree on how to decode them. The machine ID is
+    /// not included.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        self.core.fingerprint()
+    }
+
+    /// Reports drift between the generator's clock and the monotonic
+    /// clock, recent backwards movement, and whether generation is currently
+    /// held up by the clock, e.g. to gate a deployment on ID generation
+    /// being healthy. See [`crate::clock`] for how leap seconds show up.
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::SnowflakeGenerator;
+    /// use std::time::Duration;
+    ///
+    /// let generator = SnowflakeGenerator::new(1).unwrap();
+    /// generator.generate().unwrap();
+    /// assert!(generator.clock_health().is_healthy(Duration::from_millis(100)));
+    /// ```
+    pub fn clock_health(&self) -> ClockHealth {
+        let state = self.lock_state();
+        self.core.clock_health(&state)
+    }
+
+    pub fn try_next_id(&self) -> Result<SnowflakeOperation<S>, SnowflakeError> {
+        let mut state = self.lock_state();
+        self.core.try_next(&mut state)
+    }
+
+    pub(crate) fn lock_state(&self) -> StateGuard<'_> {
+        #[cfg(feature = "stats")]
+        let requested = Instant::now();
+
+        let guard = self.state.lock();
+
+        #[cfg(feature = "stats")]
+        let acquired = {
+            let acquired = Instant::now();
+            self.core.stats.record_lock_wait(acquired - requested);
+            acquired
+        };
+
+        StateGuard {
+            guard,
+            #[cfg(feature = "stats")]
+            stats: &self.core.stats,
+            #[cfg(feature = "stats")]
+            acquired,
+        }
+    }
+
+    /// Returns the end of the last tick this generator issued from, in
+    /// microseconds since the Unix epoch, or `None` if it hasn't issued yet
+    pub(crate) fn issued_until_micros(&self) -> Option<i64> {
+        let state = self.lock_state();
+        (state.last_timestamp != 0).then(|| self.core.tick_start_micros(state.last_timestamp + 1))
     }
 
-    pub fn next_id(&self, mut on_pending: impl FnMut(Duration)) -> S {
+    /// Holds generation back until the clock passes `micros` since the Unix
+    /// epoch, as if a checkpoint at that instant had been restored
+    pub(crate) fn resume_after_micros(&self, micros: i64) {
+        let mut state = self.lock_state();
+        let tick = self.core.tick_at(micros.saturating_sub(1));
+        state.resume_after = state.resume_after.max(tick);
+    }
+
+    /// Generates the next ID, calling `on_pending` whenever the generator has
+    /// to wait for the clock.
+    ///
+    /// # Panics
+    /// Panics if generation fails, e.g. when the clock moves backwards beyond
+    /// the tolerance. Use [`SnowflakeGenerator::next_id_checked`] to handle
+    /// these errors instead.
+    pub fn next_id(&self, on_pending: impl FnMut(Duration)) -> S {
+        self.next_id_checked(on_pending)
+            .expect("snowflake generation failed")
+    }
+
+    /// Generates the next ID, returning an error instead of panicking if the
+    /// clock moves backwards beyond the tolerance or the timestamp overflows
+    pub fn next_id_checked(
+        &self,
+        mut on_pending: impl FnMut(Duration),
+    ) -> Result<S, SnowflakeError> {
+        #[cfg(feature = "stats")]
+        let started = Instant::now();
+
         loop {
-            match self.try_next_id().expect("snowflake generation failed") {
-                SnowflakeOperation::Ready(id) => return id,
+            match self.try_next_id()? {
+                SnowflakeOperation::Ready(id) => {
+                    #[cfg(feature = "stats")]
+                    self.core.stats.record_latency(started.elapsed());
+                    return Ok(id);
+                }
                 SnowflakeOperation::Pending(wait) => {
                     on_pending(wait);
                 }
@@ -117,76 +787,597 @@ impl<S: Snowflake> SnowflakeGenerator<S> {
         }
     }
 
+    /// Generates the next ID using the generator's [`WaitStrategy`], which
+    /// is [`SleepWait`] unless chosen otherwise on the
+    /// [`GeneratorBuilder`](crate::builder::GeneratorBuilder)
+    pub fn generate(&self) -> Result<S, SnowflakeError> {
+        self.next_id_checked(self.wait_fn())
+    }
+
+    /// Generates `count` IDs like [`SnowflakeGenerator::next_id_bulk_checked`],
+    /// using the generator's [`WaitStrategy`]
+    pub fn generate_bulk(&self, count: usize) -> Result<Vec<S>, SnowflakeError> {
+        self.next_id_bulk_checked(count, self.wait_fn())
+    }
+
+    /// Adapts the wait strategy to the `on_pending` callbacks, counting
+    /// attempts from zero for each call
+    fn wait_fn(&self) -> impl FnMut(Duration) + '_ {
+        let mut attempt = 0;
+        move |wait| {
+            self.wait.wait(wait, attempt);
+            attempt = attempt.saturating_add(1);
+        }
+    }
+
+    /// Returns an endless iterator of IDs that waits through pending ticks
+    /// using the generator's [`WaitStrategy`].
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::SnowflakeGenerator;
+    ///
+    /// let generator = SnowflakeGenerator::new(1).unwrap();
+    /// let ids: Vec<_> = generator.iter().take(10).collect();
+    /// assert_eq!(ids.len(), 10);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = S> + '_ {
+        std::iter::repeat_with(move || self.generate().expect("snowflake generation failed"))
+    }
+
+    /// Returns an endless iterator of IDs, calling `on_pending` whenever the
+    /// generator has to wait for the clock
+    pub fn iter_with<'a>(
+        &'a self,
+        mut on_pending: impl FnMut(Duration) + 'a,
+    ) -> impl Iterator<Item = S> + 'a {
+        std::iter::repeat_with(move || self.next_id(&mut on_pending))
+    }
+
     pub fn new(machine_id: u64) -> Result<Self, SnowflakeError> {
         Self::with_epoch(machine_id, crate::defs::SNOWFLAKE_ID_EPOCH)
     }
 
-    pub fn next_id_bulk(
+    #[deprecated(note = "panics on clock errors; use `next_id_bulk_checked` instead")]
+    pub fn next_id_bulk(&self, count: usize, on_pending: impl FnMut(Duration)) -> Vec<S> {
+        self.next_id_bulk_checked(count, on_pending)
+            .expect("snowflake generation failed")
+    }
+
+    /// Generates `count` IDs while holding the generator lock once, returning
+    /// an error instead of panicking on clock failures
+    pub fn next_id_bulk_checked(
         &self,
         count: usize,
-        mut on_pending: impl FnMut(Duration),
-    ) -> Vec<S> {
+        on_pending: impl FnMut(Duration),
+    ) -> Result<Vec<S>, SnowflakeError> {
         let mut ids = Vec::with_capacity(count);
+        self.extend_bulk(count, &mut ids, on_pending)?;
+        Ok(ids)
+    }
+
+    /// Appends `count` IDs to `ids` like [`SnowflakeGenerator::generate_bulk`],
+    /// reusing its allocation across calls. On error, the IDs generated
+    /// before the failure stay in `ids`.
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::SnowflakeGenerator;
+    ///
+    /// let generator = SnowflakeGenerator::new(1).unwrap();
+    /// let mut ids = Vec::with_capacity(100);
+    /// for _ in 0..3 {
+    ///     ids.clear();
+    ///     generator.next_id_bulk_into(100, &mut ids).unwrap();
+    ///     assert_eq!(ids.len(), 100);
+    /// }
+    /// ```
+    pub fn next_id_bulk_into(&self, count: usize, ids: &mut Vec<S>) -> Result<(), SnowflakeError> {
+        self.extend_bulk(count, ids, self.wait_fn())
+    }
+
+    /// Reserves `count` IDs under one lock, like
+    /// [`SnowflakeGenerator::reserve_block`] with the generator's
+    /// [`WaitStrategy`], and returns them as an iterator that builds each ID
+    /// on the fly instead of collecting them
+    pub fn next_id_chunks(&self, count: usize) -> Result<BlockIter<S>, SnowflakeError> {
+        Ok(self.reserve_block(count, self.wait_fn())?.into_iter())
+    }
+
+    fn extend_bulk(
+        &self,
+        count: usize,
+        ids: &mut Vec<S>,
+        mut on_pending: impl FnMut(Duration),
+    ) -> Result<(), SnowflakeError> {
+        #[cfg(feature = "stats")]
+        let started = Instant::now();
+        ids.reserve(count);
+        let target = ids.len() + count;
 
         // Acquire lock once for the entire bulk operation
-        let mut state = self
-            .state
-            .lock()
-            .expect("snowflake generator mutex poisoned");
-
-        for _ in 0..count {
-            let mut timestamp = Self::current_timestamp();
-
-            // Handle clock moving backwards with tolerance
-            if timestamp < state.last_timestamp {
-                let drift = state.last_timestamp - timestamp;
-                if drift <= CLOCK_BACKWARDS_TOLERANCE_MS {
-                    // Wait for clock to catch up (small NTP adjustment)
-                    while timestamp < state.last_timestamp {
-                        on_pending(Duration::from_millis(drift as u64));
-                        timestamp = Self::current_timestamp();
-                    }
-                } else {
-                    // Large backwards movement - fail immediately
-                    panic!("clock moved backwards beyond tolerance");
-                }
+        let mut state = self.lock_state();
+        let mut clock = ClockCache::default();
+
+        while ids.len() < target {
+            match self.core.try_next_cached(&mut state, &mut clock)? {
+                SnowflakeOperation::Ready(id) => ids.push(id),
+                SnowflakeOperation::Pending(wait) => on_pending(wait),
             }
+        }
 
-            if timestamp == state.last_timestamp {
-                state.sequence = (state.sequence + 1) & S::max_sequence();
-                if state.sequence == 0 {
-                    while timestamp <= state.last_timestamp {
-                        on_pending(Duration::from_millis(1));
-                        timestamp = Self::current_timestamp();
-                    }
-                }
-            } else {
-                state.sequence = 0;
+        #[cfg(feature = "stats")]
+        self.core.stats.record_latency(started.elapsed());
+
+        Ok(())
+    }
+
+    /// Returns a snapshot of this generator's latency and lock contention
+    /// statistics
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> GeneratorStats {
+        self.core.stats.snapshot()
+    }
+}
+
+impl SnowflakeGenerator<SnowflakeId> {
+    /// Creates a generator for Twitter's original layout, where the machine
+    /// ID is split into a datacenter and a worker ID
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::{Epoch, SnowflakeGenerator};
+    ///
+    /// let generator = SnowflakeGenerator::with_datacenter(3, 7, Epoch::TWITTER).unwrap();
+    /// let id = generator.generate().unwrap();
+    /// assert_eq!((id.datacenter_id(), id.worker_id()), (3, 7));
+    /// ```
+    pub fn with_datacenter(
+        datacenter_id: u64,
+        worker_id: u64,
+        epoch: impl Into<Epoch>,
+    ) -> Result<Self, SnowflakeError> {
+        Self::with_epoch(from_datacenter(datacenter_id, worker_id)?, epoch)
+    }
+
+    /// Generates an ID tagged with the entity type `T`, see [`crate::typed`]
+    pub fn next_typed<T>(&self) -> Result<TypedSnowflakeId<T>, SnowflakeError> {
+        self.generate().map(TypedSnowflakeId::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::manual_clock;
+    use crate::SnowflakeGenerator;
+    use crate::MAX_MACHINE_ID;
+    use crate::MAX_SEQUENCE;
+    use crate::MAX_TIMESTAMP_MS;
+    use crate::SNOWFLAKE_ID_EPOCH;
+    use std::thread;
+
+    #[test]
+    fn test_snowflake_generator() {
+        let generator = SnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
+        let id1 = generator.next_id(|_| thread::yield_now());
+        let id2 = generator.next_id(|_| thread::yield_now());
+
+        assert_ne!(id1, id2);
+        assert!(id1.id() < id2.id());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_bulk_generation() {
+        let generator = SnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
+        let ids = generator.next_id_bulk(100, |_| thread::yield_now());
+
+        assert_eq!(ids.len(), 100);
+
+        for i in 1..ids.len() {
+            assert!(ids[i - 1].id() < ids[i].id());
+        }
+    }
+
+    #[test]
+    fn test_iter() {
+        let generator = SnowflakeGenerator::new(1).unwrap();
+        let ids: Vec<SnowflakeId> = generator.iter().take(5000).collect();
+
+        assert_eq!(ids.len(), 5000);
+        for i in 1..ids.len() {
+            assert!(ids[i - 1].id() < ids[i].id());
+        }
+
+        let more: Vec<SnowflakeId> = generator
+            .iter_with(|_| thread::yield_now())
+            .take(10)
+            .collect();
+        assert!(more[0] > ids[ids.len() - 1]);
+    }
+
+    #[test]
+    fn test_checked_generation_reports_clock_errors() {
+        use crate::clock::ManualClock;
+
+        let clock = manual_clock();
+        let generator = SnowflakeGenerator::with_clock(1, Epoch::TWITTER, clock.clone()).unwrap();
+
+        let ids = generator
+            .next_id_bulk_checked(3, |_| thread::yield_now())
+            .unwrap();
+        assert_eq!(ids.len(), 3);
+        assert!(generator.next_id_checked(|_| thread::yield_now()).is_ok());
+
+        clock.set(SNOWFLAKE_ID_EPOCH + 500);
+        assert!(matches!(
+            generator.next_id_checked(|_| thread::yield_now()),
+            Err(SnowflakeError::ClockMovedBackwards { .. })
+        ));
+        assert!(matches!(
+            generator.next_id_bulk_checked(3, |_| thread::yield_now()),
+            Err(SnowflakeError::ClockMovedBackwards { .. })
+        ));
+
+        // Timestamps before the epoch can't be encoded
+        let early = SnowflakeGenerator::with_clock(1, Epoch::TWITTER, ManualClock::new(1)).unwrap();
+        assert!(matches!(
+            early.next_id_checked(|_| thread::yield_now()),
+            Err(SnowflakeError::TimestampOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let a = SnowflakeGenerator::new(1).unwrap();
+        let b = SnowflakeGenerator::new(2).unwrap();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+
+        let discord = SnowflakeGenerator::with_epoch(1, Epoch::DISCORD).unwrap();
+        assert_ne!(a.fingerprint(), discord.fingerprint());
+        let paranoid = SnowflakeGenerator::builder(1)
+            .clock_tolerance(Duration::ZERO)
+            .build()
+            .unwrap();
+        assert_ne!(a.fingerprint(), paranoid.fingerprint());
+
+        let report = a.self_test();
+        assert_eq!(report.fingerprint, a.fingerprint());
+        assert!(report.to_string().contains("fingerprint "));
+    }
+
+    #[test]
+    fn test_coarse_clock() {
+        use crate::config::GeneratorConfig;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        struct CountingClock(AtomicUsize);
+
+        impl Clock for CountingClock {
+            fn now_millis(&self) -> i64 {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                SNOWFLAKE_ID_EPOCH + 1_000
             }
+        }
+
+        let build = |coarse: bool| {
+            let clock = Arc::new(CountingClock(AtomicUsize::new(0)));
+            let generator = SnowflakeGenerator::builder(1)
+                .clock(clock.clone())
+                .coarse_clock(coarse)
+                .build()
+                .unwrap();
+            (clock, generator)
+        };
+
+        let (clock, generator) = build(true);
+        let ids = generator.next_id_bulk_checked(1_000, |_| {}).unwrap();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            clock.0.load(Ordering::Relaxed),
+            1_000usize.div_ceil(COARSE_CLOCK_READ_EVERY as usize)
+        );
+        // Single IDs always read the clock
+        let next = generator.generate().unwrap();
+        assert!(next > ids[999]);
+        assert_eq!(clock.0.load(Ordering::Relaxed), 17);
+
+        let (clock, generator) = build(false);
+        generator.next_id_bulk_checked(1_000, |_| {}).unwrap();
+        assert_eq!(clock.0.load(Ordering::Relaxed), 1_000);
+
+        let config = GeneratorConfig::new(1).coarse_clock(true);
+        assert!(config.coarse_clock);
+        assert!(config.build::<SnowflakeId>().is_ok());
+    }
+
+    #[test]
+    fn test_bulk_into_and_chunks() {
+        let generator = SnowflakeGenerator::new(1).unwrap();
+
+        let mut ids = Vec::new();
+        generator.next_id_bulk_into(5_000, &mut ids).unwrap();
+        let capacity = ids.capacity();
+        let last = ids[4_999];
+        ids.clear();
+        generator.next_id_bulk_into(5_000, &mut ids).unwrap();
+        assert_eq!(ids.capacity(), capacity);
+        assert!(ids[0] > last);
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // Appends rather than overwriting
+        generator.next_id_bulk_into(10, &mut ids).unwrap();
+        assert_eq!(ids.len(), 5_010);
+
+        let mut chunk = generator.next_id_chunks(5_000).unwrap();
+        assert_eq!(chunk.len(), 5_000);
+        let first = chunk.next().unwrap();
+        assert!(first > ids[5_009]);
+        assert_eq!(chunk.len(), 4_999);
+        let rest: Vec<_> = chunk.collect();
+        assert_eq!(rest.len(), 4_999);
+        assert!(rest[0] > first);
+        assert!(rest.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let block = generator.reserve_block(100, std::thread::sleep).unwrap();
+        let expected: Vec<_> = block.iter().collect();
+        assert_eq!(block.into_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_invalid_machine_id() {
+        let result = SnowflakeGenerator::with_epoch(MAX_MACHINE_ID + 1, SNOWFLAKE_ID_EPOCH);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_custom_epoch() {
+        // Use Jan 1, 2024 as custom epoch
+        let custom_epoch = 1704067200000i64;
+        let generator = SnowflakeGenerator::with_epoch(5, custom_epoch).unwrap();
+
+        assert_eq!(generator.epoch(), custom_epoch);
+
+        let id = generator.next_id(|_| thread::yield_now());
+
+        // Verify machine ID is correct
+        assert_eq!(id.machine_id(), 5);
+
+        // Verify timestamp() returns offset (not Unix timestamp)
+        let offset = id.timestamp();
+        assert!(offset > 0);
+        // Offset should be reasonable (much less than MAX_TIMESTAMP_MS ~= 69 years)
+        assert!(offset < MAX_TIMESTAMP_MS);
+
+        // Verify timestamp_with_epoch() returns actual Unix timestamp
+        let timestamp = id.timestamp_with_epoch(custom_epoch);
+        assert!(timestamp >= custom_epoch);
+        // Verify it's not too far in the future (less than ~69 years from custom epoch)
+        assert!(timestamp < custom_epoch + MAX_TIMESTAMP_MS);
+
+        // Verify ID is positive
+        assert!(id.id() > 0);
+    }
+
+    #[test]
+    fn test_custom_epoch_vs_default() {
+        let custom_epoch = 1704067200000i64; // Jan 1, 2024
+        let generator_custom = SnowflakeGenerator::with_epoch(1, custom_epoch).unwrap();
+        let generator_default = SnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
+
+        let id_custom = generator_custom.next_id(|_| thread::yield_now());
+        let id_default = generator_default.next_id(|_| thread::yield_now());
 
-            state.last_timestamp = timestamp;
+        // Both should be positive
+        assert!(id_custom.id() > 0);
+        assert!(id_default.id() > 0);
 
-            // Calculate timestamp offset and validate it fits in timestamp bits
-            let timestamp_offset = timestamp - self.epoch;
-            if timestamp_offset < 0 || timestamp_offset > S::max_timestamp() {
-                panic!("snowflake timestamp overflow");
+        // Timestamps extracted with correct epochs should be recent
+        let ts_custom = id_custom.timestamp_with_epoch(custom_epoch);
+        let ts_default = id_default.timestamp_with_epoch(SNOWFLAKE_ID_EPOCH);
+
+        assert!(ts_custom >= custom_epoch);
+        assert!(ts_default >= SNOWFLAKE_ID_EPOCH);
+    }
+
+    #[test]
+    fn test_custom_epoch_uniqueness() {
+        let custom_epoch = 1704067200000i64;
+        let generator = SnowflakeGenerator::with_epoch(3, custom_epoch).unwrap();
+
+        let id1 = generator.next_id(|_| thread::yield_now());
+        let id2 = generator.next_id(|_| thread::yield_now());
+        let id3 = generator.next_id(|_| thread::yield_now());
+
+        // All IDs should be unique
+        assert_ne!(id1.id(), id2.id());
+        assert_ne!(id2.id(), id3.id());
+        assert_ne!(id1.id(), id3.id());
+
+        // IDs should be monotonically increasing
+        assert!(id1.id() < id2.id());
+        assert!(id2.id() < id3.id());
+    }
+
+    #[test]
+    fn test_generator_with_clock() {
+        use std::sync::atomic::{AtomicI64, Ordering};
+        use std::sync::Arc;
+
+        struct FixedClock(AtomicI64);
+
+        impl Clock for FixedClock {
+            fn now_millis(&self) -> i64 {
+                self.0.load(Ordering::SeqCst)
             }
+        }
 
-            // Mask to timestamp bits to ensure bit 63 is always 0 (keeping ID positive)
-            let masked_timestamp = (timestamp_offset as u64) & ((1u64 << S::timestamp_bits()) - 1);
+        let clock = Arc::new(FixedClock(AtomicI64::new(SNOWFLAKE_ID_EPOCH + 500)));
+        let generator = SnowflakeGenerator::with_clock(2, Epoch::TWITTER, clock.clone()).unwrap();
 
-            // Construct Id
-            ids.push(S::from_component_parts(
-                masked_timestamp,
-                self.machine_id,
-                state.sequence,
-            ));
+        let id = generator.next_id(|_| thread::yield_now());
+        assert_eq!(id.timestamp(), 500);
+
+        clock.0.store(SNOWFLAKE_ID_EPOCH + 501, Ordering::SeqCst);
+        let id = generator.next_id(|_| thread::yield_now());
+        assert_eq!((id.timestamp(), id.sequence()), (501, 0));
+    }
+
+    #[test]
+    fn test_next_id_chunks_follows_generator_settings() {
+        use crate::{OverflowStrategy, SequenceStart};
+
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(manual_clock())
+            .sequence_start(SequenceStart::Fixed(4_000))
+            .overflow(OverflowStrategy::BorrowFuture { max_ahead_ms: 10 })
+            .build()
+            .unwrap();
+
+        let ids: Vec<_> = generator.next_id_chunks(200).unwrap().collect();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(ids.iter().all(|id| id.sequence() >= 4_000));
+        let parts = |id: &SnowflakeId| (id.timestamp(), id.sequence());
+        assert_eq!(parts(&ids[0]), (1_000, 4_000));
+        assert_eq!(parts(&ids[96]), (1_001, 4_000));
+        assert_eq!(parts(&ids[199]), (1_002, 4_007));
+    }
+
+    #[test]
+    fn test_failed_checkpoint_on_borrow_reissues_nothing() {
+        use crate::checkpoint::Checkpoint;
+        use crate::OverflowStrategy;
+        use std::collections::HashSet;
+        use std::io;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        struct Flaky(Arc<AtomicBool>);
+
+        impl Checkpoint for Flaky {
+            fn load(&self) -> io::Result<Option<i64>> {
+                Ok(None)
+            }
+
+            fn store(&self, _millis: i64) -> io::Result<()> {
+                if self.0.load(Ordering::Relaxed) {
+                    Err(io::Error::other("disk full"))
+                } else {
+                    Ok(())
+                }
+            }
         }
 
-        ids
+        let failing = Arc::new(AtomicBool::new(false));
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(manual_clock())
+            .overflow(OverflowStrategy::BorrowFuture { max_ahead_ms: 2 })
+            .checkpoint(Flaky(Arc::clone(&failing)), Duration::from_millis(1))
+            .build()
+            .unwrap();
+
+        let mut seen: HashSet<_> = (0..=MAX_SEQUENCE)
+            .map(|_| generator.generate().unwrap())
+            .collect();
+        let last = *seen.iter().max().unwrap();
+
+        // Borrowing the next tick needs a new mark, which the store refuses
+        failing.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            generator.generate(),
+            Err(SnowflakeError::CheckpointFailed(_))
+        ));
+
+        failing.store(false, Ordering::Relaxed);
+        let id = generator.generate().unwrap();
+        assert_eq!((id.timestamp(), id.sequence()), (last.timestamp() + 1, 0));
+        assert!(seen.insert(id));
     }
 
-    fn current_timestamp() -> i64 {
-        Utc::now().timestamp_millis()
+    #[cfg(snowflake_loom)]
+    mod loom_tests {
+        use super::*;
+        use crate::generator::SnowflakeOperation;
+        use crate::SnowflakeGenerator128;
+        use loom::sync::Arc;
+        use loom::thread;
+
+        struct FixedClock;
+
+        impl Clock for FixedClock {
+            fn now_millis(&self) -> i64 {
+                SNOWFLAKE_ID_EPOCH + 1_000
+            }
+        }
+
+        fn ready(generator: &SnowflakeGenerator) -> SnowflakeId {
+            match generator.try_next_id() {
+                Ok(SnowflakeOperation::Ready(id)) => id,
+                _ => panic!("expected an id"),
+            }
+        }
+
+        #[test]
+        fn concurrent_ids_are_unique_and_ordered_per_thread() {
+            loom::model(|| {
+                let generator = Arc::new(
+                    SnowflakeGenerator::with_clock(1, Epoch::TWITTER, FixedClock).unwrap(),
+                );
+                let handles: Vec<_> = (0..2)
+                    .map(|_| {
+                        let generator = Arc::clone(&generator);
+                        thread::spawn(move || [ready(&generator), ready(&generator)])
+                    })
+                    .collect();
+                let mut ids = Vec::new();
+                for handle in handles {
+                    let pair = handle.join().unwrap();
+                    assert!(pair[0] < pair[1]);
+                    ids.extend(pair);
+                }
+                ids.sort();
+                ids.dedup();
+                assert_eq!(ids.len(), 4);
+            });
+        }
+
+        #[test]
+        fn bulk_generation_holds_the_lock_for_the_whole_batch() {
+            loom::model(|| {
+                let generator = Arc::new(
+                    SnowflakeGenerator::with_clock(1, Epoch::TWITTER, FixedClock).unwrap(),
+                );
+                let bulk = {
+                    let generator = Arc::clone(&generator);
+                    thread::spawn(move || generator.generate_bulk(3).unwrap())
+                };
+                let single = ready(&generator);
+                let batch = bulk.join().unwrap();
+                // The single ID lands before or after the batch, never inside it
+                let sequences: Vec<_> = batch.iter().map(|id| id.sequence()).collect();
+                assert!(sequences.windows(2).all(|pair| pair[1] == pair[0] + 1));
+                assert!(!batch.contains(&single));
+            });
+        }
+
+        #[test]
+        fn shared_128_bit_generator_is_unique() {
+            loom::model(|| {
+                let generator = Arc::new(SnowflakeGenerator128::with_clock(
+                    1,
+                    Epoch::TWITTER,
+                    FixedClock,
+                ));
+                let other = {
+                    let generator = Arc::clone(&generator);
+                    thread::spawn(move || generator.generate().unwrap())
+                };
+                let mine = generator.generate().unwrap();
+                assert_ne!(mine, other.join().unwrap());
+            });
+        }
     }
 }
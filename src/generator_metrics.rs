@@ -0,0 +1,170 @@
+//! Generator instrumentation through the [`metrics`] facade.
+//!
+//! With the `metrics` feature, the sync, async and sharded generators report
+//! to whichever recorder is installed, e.g. a Prometheus exporter. Every
+//! metric carries a `machine_id` label. Handles are registered when a
+//! generator is created, so install the recorder before building generators.
+
+use metrics::{counter, histogram, Counter, Histogram};
+use std::time::Duration;
+
+/// Counter of IDs handed out
+pub const IDS_GENERATED: &str = "snowflake_ids_generated_total";
+/// Counter of times a tick's sequence space ran out and generation had to
+/// wait for the next millisecond
+pub const SEQUENCE_EXHAUSTED: &str = "snowflake_sequence_exhausted_total";
+/// Counter of backwards clock movements, labelled `outcome="waited"` when
+/// within tolerance and `outcome="rejected"` when generation failed
+pub const CLOCK_BACKWARDS: &str = "snowflake_clock_backwards_total";
+/// Histogram of requested waits in seconds, labelled by `reason`
+/// (`sequence_exhausted` or `clock_backwards`)
+pub const WAIT_SECONDS: &str = "snowflake_wait_seconds";
+
+pub(crate) struct GeneratorMetrics {
+    generated: Counter,
+    exhausted: Counter,
+    backwards_waited: Counter,
+    backwards_rejected: Counter,
+    exhausted_wait: Histogram,
+    backwards_wait: Histogram,
+}
+
+impl GeneratorMetrics {
+    pub(crate) fn new(machine_id: u64) -> Self {
+        let machine_id = machine_id.to_string();
+        GeneratorMetrics {
+            generated: counter!(IDS_GENERATED, "machine_id" => machine_id.clone()),
+            exhausted: counter!(SEQUENCE_EXHAUSTED, "machine_id" => machine_id.clone()),
+            backwards_waited: counter!(
+                CLOCK_BACKWARDS,
+                "machine_id" => machine_id.clone(),
+                "outcome" => "waited"
+            ),
+            backwards_rejected: counter!(
+                CLOCK_BACKWARDS,
+                "machine_id" => machine_id.clone(),
+                "outcome" => "rejected"
+            ),
+            exhausted_wait: histogram!(
+                WAIT_SECONDS,
+                "machine_id" => machine_id.clone(),
+                "reason" => "sequence_exhausted"
+            ),
+            backwards_wait: histogram!(
+                WAIT_SECONDS,
+                "machine_id" => machine_id,
+                "reason" => "clock_backwards"
+            ),
+        }
+    }
+
+    /// Handles that report nowhere, for generators that only exist
+    /// internally
+    pub(crate) fn noop() -> Self {
+        GeneratorMetrics {
+            generated: Counter::noop(),
+            exhausted: Counter::noop(),
+            backwards_waited: Counter::noop(),
+            backwards_rejected: Counter::noop(),
+            exhausted_wait: Histogram::noop(),
+            backwards_wait: Histogram::noop(),
+        }
+    }
+
+    pub(crate) fn record_generated(&self) {
+        self.generated.increment(1);
+    }
+
+    pub(crate) fn record_exhaustion(&self, wait: Duration) {
+        self.exhausted.increment(1);
+        self.exhausted_wait.record(wait);
+    }
+
+    pub(crate) fn record_clock_wait(&self, wait: Duration) {
+        self.backwards_waited.increment(1);
+        self.backwards_wait.record(wait);
+    }
+
+    pub(crate) fn record_clock_rejected(&self) {
+        self.backwards_rejected.increment(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::manual_clock;
+    use crate::Epoch;
+    use crate::SnowflakeGenerator;
+    use crate::MAX_SEQUENCE;
+
+    #[test]
+    fn test_generator_metrics() {
+        use crate::generator::SnowflakeOperation;
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let clock = manual_clock();
+
+        metrics::with_local_recorder(&recorder, || {
+            let generator =
+                SnowflakeGenerator::with_clock(12, Epoch::TWITTER, clock.clone()).unwrap();
+            // Runs on a scratch generator that reports nothing
+            assert!(generator.self_test().passed());
+            for _ in 0..=MAX_SEQUENCE {
+                generator.try_next_id().unwrap();
+            }
+            assert!(matches!(
+                generator.try_next_id().unwrap(),
+                SnowflakeOperation::Pending(_)
+            ));
+
+            clock.rewind(Duration::from_millis(3));
+            assert!(matches!(
+                generator.try_next_id().unwrap(),
+                SnowflakeOperation::Pending(_)
+            ));
+            clock.rewind(Duration::from_millis(100));
+            assert!(generator.try_next_id().is_err());
+        });
+
+        let snapshot = snapshotter.snapshot().into_vec();
+        let value = |name: &str, label: Option<(&str, &str)>| {
+            snapshot
+                .iter()
+                .find(|(key, ..)| {
+                    let key = key.key();
+                    key.name() == name
+                        && key
+                            .labels()
+                            .any(|l| l.key() == "machine_id" && l.value() == "12")
+                        && label.is_none_or(|(k, v)| {
+                            key.labels().any(|l| l.key() == k && l.value() == v)
+                        })
+                })
+                .map(|(.., value)| value)
+                .unwrap()
+        };
+
+        assert_eq!(
+            value(IDS_GENERATED, None),
+            &DebugValue::Counter(MAX_SEQUENCE + 1)
+        );
+        assert_eq!(value(SEQUENCE_EXHAUSTED, None), &DebugValue::Counter(1));
+        assert_eq!(
+            value(CLOCK_BACKWARDS, Some(("outcome", "waited"))),
+            &DebugValue::Counter(1)
+        );
+        assert_eq!(
+            value(CLOCK_BACKWARDS, Some(("outcome", "rejected"))),
+            &DebugValue::Counter(1)
+        );
+        let DebugValue::Histogram(waits) = value(WAIT_SECONDS, Some(("reason", "clock_backwards")))
+        else {
+            panic!("expected a histogram");
+        };
+        assert_eq!(waits.len(), 1);
+        assert_eq!(waits[0].into_inner(), 0.003);
+    }
+}
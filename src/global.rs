@@ -0,0 +1,77 @@
+//! A process-wide default generator for applications that only need one.
+//!
+//! ```
+//! snowflake_id::init_global(1).unwrap();
+//!
+//! let a = snowflake_id::next_id();
+//! let b = snowflake_id::try_next_id().unwrap();
+//! assert!(a < b);
+//! ```
+
+use crate::error::SnowflakeError;
+use crate::{SnowflakeGenerator, SnowflakeId};
+use std::sync::OnceLock;
+
+static GLOBAL: OnceLock<SnowflakeGenerator> = OnceLock::new();
+
+/// Installs the process-wide generator for `machine_id`.
+///
+/// Returns [`SnowflakeError::AlreadyInitialized`] if a global generator is
+/// already installed, even for the same machine ID.
+pub fn init_global(machine_id: u64) -> Result<(), SnowflakeError> {
+    install(SnowflakeGenerator::new(machine_id)?)
+}
+
+/// Installs an already configured generator as the process-wide one, e.g.
+/// one with a custom epoch or clock
+pub fn install(generator: SnowflakeGenerator) -> Result<(), SnowflakeError> {
+    GLOBAL
+        .set(generator)
+        .map_err(|_| SnowflakeError::AlreadyInitialized)
+}
+
+/// Returns the process-wide generator, if one was installed
+pub fn global() -> Option<&'static SnowflakeGenerator> {
+    GLOBAL.get()
+}
+
+/// Generates an ID from the process-wide generator, waiting through pending
+/// ticks with the generator's [`WaitStrategy`](crate::wait::WaitStrategy)
+///
+/// # Panics
+/// Panics if [`init_global`] has not been called, or if generation fails.
+pub fn next_id() -> SnowflakeId {
+    try_next_id().expect("snowflake generation failed")
+}
+
+/// Fallible variant of [`next_id`]; returns
+/// [`SnowflakeError::NotInitialized`] if [`init_global`] has not been called
+pub fn try_next_id() -> Result<SnowflakeId, SnowflakeError> {
+    global().ok_or(SnowflakeError::NotInitialized)?.generate()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_generator() {
+        assert!(matches!(
+            crate::try_next_id(),
+            Err(SnowflakeError::NotInitialized)
+        ));
+        assert!(crate::global::global().is_none());
+
+        crate::init_global(4).unwrap();
+        assert!(matches!(
+            crate::init_global(4),
+            Err(SnowflakeError::AlreadyInitialized)
+        ));
+
+        let a = crate::next_id();
+        let b = crate::try_next_id().unwrap();
+        assert!(a < b);
+        assert_eq!(a.machine_id(), 4);
+        assert_eq!(crate::global::global().unwrap().machine_id(), 4);
+    }
+}
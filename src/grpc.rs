@@ -0,0 +1,413 @@
+//! A gRPC ID-issuing service for running the generator as a central daemon.
+//!
+//! [`IdServiceServer`] wraps an [`AsyncSnowflakeGenerator`] and implements
+//! the service below. The messages are hand-written prost types, so no
+//! `protoc` is needed to build; clients in other languages can generate
+//! their stubs from this definition:
+//!
+//! ```proto
+//! syntax = "proto3";
+//! package snowflake;
+//!
+//! service IdService {
+//!   rpc GetId(GetIdRequest) returns (GetIdResponse);
+//!   rpc GetIdBatch(GetIdBatchRequest) returns (GetIdBatchResponse);
+//!   rpc Decode(DecodeRequest) returns (DecodeResponse);
+//! }
+//!
+//! message GetIdRequest {}
+//! message GetIdResponse { int64 id = 1; }
+//! message GetIdBatchRequest {
+//!   uint32 count = 1;
+//!   string idempotency_token = 2;  // optional, see below
+//! }
+//! message GetIdBatchResponse { repeated int64 ids = 1; }
+//! message DecodeRequest { int64 id = 1; }
+//! message DecodeResponse {
+//!   int64 timestamp_ms = 1;  // Unix milliseconds, using the service's epoch
+//!   uint64 machine_id = 2;
+//!   uint64 sequence = 3;
+//! }
+//! ```
+//!
+//! A `GetIdBatch` call retried with the same non-empty `idempotency_token`
+//! within [`IdServiceServer::with_idempotency_ttl`] gets the batch issued the
+//! first time instead of a new one, so a lost response doesn't burn IDs.
+//!
+//! Invalid input, including a token reused for a different `count`, maps to
+//! `INVALID_ARGUMENT`, transient generator conditions to `UNAVAILABLE` and
+//! everything else to `INTERNAL`.
+//!
+//! # Example
+//! ```no_run
+//! use snowflake_id::grpc::IdServiceServer;
+//! use snowflake_id::AsyncSnowflakeGenerator;
+//! use std::sync::Arc;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let generator = Arc::new(AsyncSnowflakeGenerator::new(1)?);
+//! tonic::transport::Server::builder()
+//!     .add_service(IdServiceServer::new(generator))
+//!     .serve("0.0.0.0:50051".parse()?)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::SnowflakeError;
+use crate::idempotency::{BatchError, IdempotencyCache, MAX_TOKEN_LEN};
+use crate::snowflake::Snowflake;
+use crate::{AsyncSnowflakeGenerator, SnowflakeId};
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tonic::body::Body;
+use tonic::codegen::{http, Body as HttpBody, BoxFuture, Service, StdError};
+use tonic::server::{Grpc, NamedService, UnaryService};
+use tonic::{Code, Request, Response, Status};
+use tonic_prost::ProstCodec;
+
+/// Default for [`IdServiceServer::with_idempotency_ttl`]
+pub use crate::idempotency::DEFAULT_IDEMPOTENCY_TTL;
+
+/// Default for [`IdServiceServer::with_max_batch`]
+pub const DEFAULT_MAX_BATCH: u32 = 4096;
+
+#[derive(Clone, Copy, PartialEq, Eq, prost::Message)]
+pub struct GetIdRequest {}
+
+#[derive(Clone, Copy, PartialEq, Eq, prost::Message)]
+pub struct GetIdResponse {
+    #[prost(int64, tag = "1")]
+    pub id: i64,
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct GetIdBatchRequest {
+    #[prost(uint32, tag = "1")]
+    pub count: u32,
+    /// Makes retries return the first batch; empty for a fresh batch each time
+    #[prost(string, tag = "2")]
+    pub idempotency_token: String,
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct GetIdBatchResponse {
+    #[prost(int64, repeated, tag = "1")]
+    pub ids: Vec<i64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, prost::Message)]
+pub struct DecodeRequest {
+    #[prost(int64, tag = "1")]
+    pub id: i64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, prost::Message)]
+pub struct DecodeResponse {
+    /// Creation time in milliseconds since the Unix epoch
+    #[prost(int64, tag = "1")]
+    pub timestamp_ms: i64,
+    #[prost(uint64, tag = "2")]
+    pub machine_id: u64,
+    #[prost(uint64, tag = "3")]
+    pub sequence: u64,
+}
+
+impl From<SnowflakeError> for Status {
+    fn from(err: SnowflakeError) -> Self {
+        let code = match err.http_status() {
+            400 => Code::InvalidArgument,
+            503 => Code::Unavailable,
+            _ => Code::Internal,
+        };
+        Status::new(code, err.to_string())
+    }
+}
+
+/// The `snowflake.IdService` gRPC service, ready to add to a tonic server
+#[derive(Clone)]
+pub struct IdServiceServer {
+    generator: Arc<AsyncSnowflakeGenerator>,
+    max_batch: u32,
+    idempotency: Arc<IdempotencyCache>,
+}
+
+impl IdServiceServer {
+    pub fn new(generator: Arc<AsyncSnowflakeGenerator>) -> Self {
+        IdServiceServer {
+            generator,
+            max_batch: DEFAULT_MAX_BATCH,
+            idempotency: Arc::new(IdempotencyCache::new(DEFAULT_IDEMPOTENCY_TTL)),
+        }
+    }
+
+    /// Sets the largest `count` a `GetIdBatch` call may ask for
+    pub fn with_max_batch(mut self, max_batch: u32) -> Self {
+        self.max_batch = max_batch;
+        self
+    }
+
+    /// Sets how long a batch requested with an idempotency token is kept for
+    /// retries
+    pub fn with_idempotency_ttl(mut self, ttl: Duration) -> Self {
+        self.idempotency = Arc::new(IdempotencyCache::new(ttl));
+        self
+    }
+
+    async fn get_id(self, _: GetIdRequest) -> Result<GetIdResponse, Status> {
+        let id = self.generator.next_id_checked().await?;
+        Ok(GetIdResponse { id: id.into() })
+    }
+
+    async fn get_id_batch(self, request: GetIdBatchRequest) -> Result<GetIdBatchResponse, Status> {
+        if request.count > self.max_batch {
+            return Err(Status::invalid_argument(format!(
+                "count {} exceeds the maximum batch of {}",
+                request.count, self.max_batch
+            )));
+        }
+        let count = request.count as usize;
+        let issue = || self.generator.next_id_bulk_checked(count);
+        let ids = if request.idempotency_token.is_empty() {
+            issue().await?
+        } else {
+            self.idempotency
+                .get_or_issue(&request.idempotency_token, count, issue)
+                .await
+                .map_err(|err| match err {
+                    BatchError::Generator(err) => Status::from(err),
+                    BatchError::TokenReused { count } => Status::invalid_argument(format!(
+                        "idempotency token was already used for count {}",
+                        count
+                    )),
+                    BatchError::TokenTooLong => Status::invalid_argument(format!(
+                        "idempotency token is longer than {} bytes",
+                        MAX_TOKEN_LEN
+                    )),
+                })?
+        };
+        Ok(GetIdBatchResponse {
+            ids: ids.into_iter().map(i64::from).collect(),
+        })
+    }
+
+    async fn decode(self, request: DecodeRequest) -> Result<DecodeResponse, Status> {
+        let id = SnowflakeId::new(request.id)?;
+        Ok(DecodeResponse {
+            timestamp_ms: id.timestamp_with_epoch(self.generator.epoch()),
+            machine_id: Snowflake::machine_id(&id),
+            sequence: Snowflake::sequence(&id),
+        })
+    }
+}
+
+impl NamedService for IdServiceServer {
+    const NAME: &'static str = "snowflake.IdService";
+}
+
+impl<B> Service<http::Request<B>> for IdServiceServer
+where
+    B: HttpBody + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let service = self.clone();
+        match request.uri().path() {
+            "/snowflake.IdService/GetId" => Box::pin(async move {
+                let method = Unary(move |message| service.clone().get_id(message));
+                Ok(Grpc::new(ProstCodec::default())
+                    .unary(method, request)
+                    .await)
+            }),
+            "/snowflake.IdService/GetIdBatch" => Box::pin(async move {
+                let method = Unary(move |message| service.clone().get_id_batch(message));
+                Ok(Grpc::new(ProstCodec::default())
+                    .unary(method, request)
+                    .await)
+            }),
+            "/snowflake.IdService/Decode" => Box::pin(async move {
+                let method = Unary(move |message| service.clone().decode(message));
+                Ok(Grpc::new(ProstCodec::default())
+                    .unary(method, request)
+                    .await)
+            }),
+            path => {
+                let status = Status::unimplemented(format!("unknown method {}", path));
+                Box::pin(async move { Ok(status.into_http()) })
+            }
+        }
+    }
+}
+
+/// Adapts an async handler to tonic's unary method interface
+struct Unary<F>(F);
+
+impl<F, Fut, Req, Res> UnaryService<Req> for Unary<F>
+where
+    F: FnMut(Req) -> Fut,
+    Fut: Future<Output = Result<Res, Status>> + Send + 'static,
+{
+    type Response = Res;
+    type Future = BoxFuture<Response<Res>, Status>;
+
+    fn call(&mut self, request: Request<Req>) -> Self::Future {
+        let response = (self.0)(request.into_inner());
+        Box::pin(async move { response.await.map(Response::new) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SNOWFLAKE_ID_EPOCH;
+
+    use tonic::client::Grpc;
+
+    use tonic::codegen::http::uri::PathAndQuery;
+
+    use tonic::transport::server::TcpIncoming;
+
+    use tonic::transport::{Channel, Endpoint, Server};
+
+    async fn call<Req, Res>(
+        client: &mut Grpc<Channel>,
+        method: &'static str,
+        request: Req,
+    ) -> Result<Res, Status>
+    where
+        Req: prost::Message + Send + 'static,
+        Res: prost::Message + Default + Send + 'static,
+    {
+        client.ready().await.unwrap();
+        client
+            .unary(
+                Request::new(request),
+                PathAndQuery::from_static(method),
+                ProstCodec::default(),
+            )
+            .await
+            .map(|response| response.into_inner())
+    }
+
+    #[tokio::test]
+    async fn test_grpc_service() {
+        let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = incoming.local_addr().unwrap();
+        let generator = Arc::new(AsyncSnowflakeGenerator::new(9).unwrap());
+        tokio::spawn(
+            Server::builder()
+                .add_service(IdServiceServer::new(generator).with_max_batch(100))
+                .serve_with_incoming(incoming),
+        );
+
+        let channel = Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect()
+            .await
+            .unwrap();
+        let mut client = Grpc::new(channel);
+
+        let response: GetIdResponse =
+            call(&mut client, "/snowflake.IdService/GetId", GetIdRequest {})
+                .await
+                .unwrap();
+        let id = SnowflakeId::new(response.id).unwrap();
+        assert_eq!(id.machine_id(), 9);
+
+        let batch: GetIdBatchResponse = call(
+            &mut client,
+            "/snowflake.IdService/GetIdBatch",
+            GetIdBatchRequest {
+                count: 50,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(batch.ids.len(), 50);
+        assert!(batch.ids[0] > id.id());
+        assert!(batch.ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // Retrying with the same token returns the same batch
+        let idempotent = |count| GetIdBatchRequest {
+            count,
+            idempotency_token: "retry-me".to_string(),
+        };
+        let first: GetIdBatchResponse = call(
+            &mut client,
+            "/snowflake.IdService/GetIdBatch",
+            idempotent(5),
+        )
+        .await
+        .unwrap();
+        let retried: GetIdBatchResponse = call(
+            &mut client,
+            "/snowflake.IdService/GetIdBatch",
+            idempotent(5),
+        )
+        .await
+        .unwrap();
+        assert_eq!(retried, first);
+        assert!(first.ids[0] > batch.ids[49]);
+        let reused = call::<_, GetIdBatchResponse>(
+            &mut client,
+            "/snowflake.IdService/GetIdBatch",
+            idempotent(6),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(reused.code(), Code::InvalidArgument);
+
+        let too_many = call::<_, GetIdBatchResponse>(
+            &mut client,
+            "/snowflake.IdService/GetIdBatch",
+            GetIdBatchRequest {
+                count: 101,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(too_many.code(), Code::InvalidArgument);
+
+        let decoded: DecodeResponse = call(
+            &mut client,
+            "/snowflake.IdService/Decode",
+            DecodeRequest { id: id.id() },
+        )
+        .await
+        .unwrap();
+        assert_eq!(decoded.machine_id, 9);
+        assert_eq!(decoded.sequence, id.sequence());
+        assert_eq!(
+            decoded.timestamp_ms,
+            id.timestamp_with_epoch(SNOWFLAKE_ID_EPOCH)
+        );
+
+        let invalid = call::<_, DecodeResponse>(
+            &mut client,
+            "/snowflake.IdService/Decode",
+            DecodeRequest { id: -1 },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(invalid.code(), Code::InvalidArgument);
+
+        let unknown =
+            call::<_, GetIdResponse>(&mut client, "/snowflake.IdService/Missing", GetIdRequest {})
+                .await
+                .unwrap_err();
+        assert_eq!(unknown.code(), Code::Unimplemented);
+    }
+}
@@ -0,0 +1,268 @@
+//! A minimal HTTP ID service for running the generator as a sidecar.
+//!
+//! [`HttpIdService`] wraps an [`AsyncSnowflakeGenerator`] in an axum router
+//! with three JSON endpoints. IDs are always sent as strings so JavaScript
+//! clients don't lose precision:
+//!
+//! | Route | Response |
+//! |-------|----------|
+//! | `GET /id` | `{"id":"…"}` |
+//! | `GET /ids?count=n` | `{"ids":["…", …]}` |
+//! | `GET /decode/{id}` | `{"id":"…","timestamp_ms":…,"datetime":"…","machine_id":…,"sequence":…}` |
+//!
+//! A `/ids` request may carry an `Idempotency-Key` header. Retrying it with
+//! the same key within [`HttpIdService::with_idempotency_ttl`] returns the
+//! batch issued the first time instead of a new one, so a lost response
+//! doesn't burn IDs. Reusing a key for a different `count` is rejected with
+//! `422`.
+//!
+//! Malformed IDs and counts above the configured maximum are rejected with
+//! `400`; generator failures use [`SnowflakeError::status_code`].
+//!
+//! # Example
+//! ```no_run
+//! use snowflake_id::http_service::HttpIdService;
+//! use snowflake_id::AsyncSnowflakeGenerator;
+//! use std::sync::Arc;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let generator = Arc::new(AsyncSnowflakeGenerator::new(1).unwrap());
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
+//! HttpIdService::new(generator).serve(listener).await
+//! # }
+//! ```
+//!
+//! [`SnowflakeError::status_code`]: crate::error::SnowflakeError::status_code
+
+use crate::error::SnowflakeError;
+use crate::idempotency::{BatchError, IdempotencyCache, MAX_TOKEN_LEN};
+use crate::{AsyncSnowflakeGenerator, SnowflakeId};
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::SecondsFormat;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// Default for [`HttpIdService::with_idempotency_ttl`]
+pub use crate::idempotency::DEFAULT_IDEMPOTENCY_TTL;
+
+/// Default for [`HttpIdService::with_max_batch`]
+pub const DEFAULT_MAX_BATCH: usize = 4096;
+
+/// Header carrying a `/ids` request's idempotency token
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdResponse {
+    pub id: SnowflakeId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdsResponse {
+    pub ids: Vec<SnowflakeId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodeResponse {
+    pub id: SnowflakeId,
+    /// Creation time in milliseconds since the Unix epoch
+    pub timestamp_ms: i64,
+    /// Creation time as RFC 3339
+    pub datetime: String,
+    pub machine_id: u64,
+    pub sequence: u64,
+}
+
+#[derive(Deserialize)]
+struct CountParams {
+    count: usize,
+}
+
+/// The HTTP ID service, see the [module docs](self)
+#[derive(Clone)]
+pub struct HttpIdService {
+    generator: Arc<AsyncSnowflakeGenerator>,
+    max_batch: usize,
+    idempotency: Arc<IdempotencyCache>,
+}
+
+impl HttpIdService {
+    pub fn new(generator: Arc<AsyncSnowflakeGenerator>) -> Self {
+        HttpIdService {
+            generator,
+            max_batch: DEFAULT_MAX_BATCH,
+            idempotency: Arc::new(IdempotencyCache::new(DEFAULT_IDEMPOTENCY_TTL)),
+        }
+    }
+
+    /// Sets the largest `count` a `/ids` request may ask for
+    pub fn with_max_batch(mut self, max_batch: usize) -> Self {
+        self.max_batch = max_batch;
+        self
+    }
+
+    /// Sets how long a batch requested with an `Idempotency-Key` is kept
+    /// for retries
+    pub fn with_idempotency_ttl(mut self, ttl: Duration) -> Self {
+        self.idempotency = Arc::new(IdempotencyCache::new(ttl));
+        self
+    }
+
+    /// Returns the service's routes, ready to serve or nest in a larger app
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/id", get(get_id))
+            .route("/ids", get(get_ids))
+            .route("/decode/{id}", get(decode))
+            .with_state(self)
+    }
+
+    /// Serves the routes on `listener` until the process exits
+    pub async fn serve(self, listener: TcpListener) -> io::Result<()> {
+        axum::serve(listener, self.router()).await
+    }
+}
+
+async fn get_id(State(service): State<HttpIdService>) -> Result<Json<IdResponse>, SnowflakeError> {
+    let id = service.generator.next_id_checked().await?;
+    Ok(Json(IdResponse { id }))
+}
+
+async fn get_ids(
+    State(service): State<HttpIdService>,
+    Query(params): Query<CountParams>,
+    headers: HeaderMap,
+) -> Result<Json<IdsResponse>, Response> {
+    if params.count > service.max_batch {
+        let message = format!(
+            "count {} exceeds the maximum batch of {}",
+            params.count, service.max_batch
+        );
+        return Err((StatusCode::BAD_REQUEST, message).into_response());
+    }
+    let issue = || service.generator.next_id_bulk_checked(params.count);
+    let Some(token) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        let ids = issue().await.map_err(IntoResponse::into_response)?;
+        return Ok(Json(IdsResponse { ids }));
+    };
+    let token = token.to_str().map_err(|_| {
+        let message = "idempotency key must be visible ASCII";
+        (StatusCode::BAD_REQUEST, message).into_response()
+    })?;
+    match service
+        .idempotency
+        .get_or_issue(token, params.count, issue)
+        .await
+    {
+        Ok(ids) => Ok(Json(IdsResponse { ids })),
+        Err(BatchError::Generator(err)) => Err(err.into_response()),
+        Err(BatchError::TokenReused { count }) => {
+            let message = format!("idempotency key was already used for count {}", count);
+            Err((StatusCode::UNPROCESSABLE_ENTITY, message).into_response())
+        }
+        Err(BatchError::TokenTooLong) => {
+            let message = format!("idempotency key is longer than {} bytes", MAX_TOKEN_LEN);
+            Err((StatusCode::BAD_REQUEST, message).into_response())
+        }
+    }
+}
+
+async fn decode(
+    State(service): State<HttpIdService>,
+    Path(id): Path<SnowflakeId>,
+) -> Json<DecodeResponse> {
+    let parts = id.decompose(service.generator.epoch());
+    Json(DecodeResponse {
+        id,
+        timestamp_ms: parts.datetime.timestamp_millis(),
+        datetime: parts.datetime.to_rfc3339_opts(SecondsFormat::Millis, true),
+        machine_id: parts.machine_id,
+        sequence: parts.sequence,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::body::Body;
+
+    use axum::http::Request;
+
+    use http_body_util::BodyExt;
+
+    use tower::ServiceExt;
+
+    async fn call(uri: &str) -> (StatusCode, String) {
+        let generator = Arc::new(AsyncSnowflakeGenerator::new(5).unwrap());
+        let router = HttpIdService::new(generator).with_max_batch(100).router();
+        call_with(&router, Request::get(uri).body(Body::empty()).unwrap()).await
+    }
+
+    async fn call_with(router: &Router, request: Request<Body>) -> (StatusCode, String) {
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_http_service() {
+        let (status, body) = call("/id").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with(r#"{"id":""#));
+        let id = serde_json::from_str::<IdResponse>(&body).unwrap().id;
+        assert_eq!(id.machine_id(), 5);
+
+        let (status, body) = call("/ids?count=10").await;
+        assert_eq!(status, StatusCode::OK);
+        let ids = serde_json::from_str::<IdsResponse>(&body).unwrap().ids;
+        assert_eq!(ids.len(), 10);
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        assert_eq!(call("/ids?count=101").await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(call("/ids").await.0, StatusCode::BAD_REQUEST);
+
+        let (status, body) = call("/decode/175928847299117063").await;
+        assert_eq!(status, StatusCode::OK);
+        let decoded: DecodeResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(decoded.id.to_string(), "175928847299117063");
+        assert_eq!(decoded.machine_id, 32);
+        assert_eq!(decoded.sequence, 7);
+        assert_eq!(decoded.timestamp_ms, 1330779680453);
+        assert_eq!(decoded.datetime, "2012-03-03T13:01:20.453Z");
+
+        assert_eq!(call("/decode/abc").await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_http_service_idempotent_batches() {
+        let generator = Arc::new(AsyncSnowflakeGenerator::new(5).unwrap());
+        let router = HttpIdService::new(generator).router();
+        let request = |uri: &str, key: &str| {
+            Request::get(uri)
+                .header(IDEMPOTENCY_KEY_HEADER, key)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let (status, first) = call_with(&router, request("/ids?count=5", "retry-me")).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, retried) = call_with(&router, request("/ids?count=5", "retry-me")).await;
+        assert_eq!(retried, first);
+        let (_, other) = call_with(&router, request("/ids?count=5", "another")).await;
+        assert_ne!(other, first);
+
+        let (status, _) = call_with(&router, request("/ids?count=6", "retry-me")).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let long = "k".repeat(MAX_TOKEN_LEN + 1);
+        let (status, _) = call_with(&router, request("/ids?count=5", &long)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,356 @@
+//! 128-bit IDs with microsecond timestamps.
+//!
+//! [`SnowflakeId128`] packs a 64-bit timestamp in microseconds since the
+//! epoch, a 32-bit machine ID and a 32-bit sequence into a `u128`. The
+//! timestamp lasts for over half a million years and orders IDs to the
+//! microsecond, for systems where 41 bits of milliseconds are too coarse or
+//! too short-lived.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::{Epoch, SnowflakeGenerator128};
+//!
+//! let generator = SnowflakeGenerator128::with_epoch(70_000, Epoch::DISCORD);
+//! let id = generator.generate().unwrap();
+//! assert_eq!(id.machine_id(), 70_000);
+//! assert!(generator.generate().unwrap() > id);
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::defs::{CLOCK_BACKWARDS_TOLERANCE_MS, SNOWFLAKE_ID_EPOCH};
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::generator::SnowflakeOperation;
+use crate::sync::Mutex;
+use crate::wait::{BackoffWait, WaitStrategy};
+use ::serde::de::{self, Visitor};
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A 128-bit snowflake ID, see the [module docs](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnowflakeId128(u128);
+
+impl SnowflakeId128 {
+    pub const TIMESTAMP_BITS: u32 = 64;
+    pub const MACHINE_ID_BITS: u32 = 32;
+    pub const SEQUENCE_BITS: u32 = 32;
+
+    /// Builds an ID from a timestamp in microseconds since the epoch, a
+    /// machine ID and a sequence number
+    pub fn from_parts(timestamp_micros: u64, machine_id: u32, sequence: u32) -> Self {
+        SnowflakeId128(
+            ((timestamp_micros as u128) << (Self::MACHINE_ID_BITS + Self::SEQUENCE_BITS))
+                | ((machine_id as u128) << Self::SEQUENCE_BITS)
+                | sequence as u128,
+        )
+    }
+
+    pub fn id(&self) -> u128 {
+        self.0
+    }
+
+    /// Returns the timestamp offset in microseconds since the epoch
+    pub fn timestamp(&self) -> u64 {
+        (self.0 >> (Self::MACHINE_ID_BITS + Self::SEQUENCE_BITS)) as u64
+    }
+
+    /// Returns the timestamp in microseconds since the Unix epoch, using a
+    /// custom epoch
+    pub fn timestamp_with_epoch(&self, epoch: impl Into<Epoch>) -> i64 {
+        i64::try_from(self.timestamp())
+            .unwrap_or(i64::MAX)
+            .saturating_add(epoch.into().as_millis().saturating_mul(1_000))
+    }
+
+    pub fn machine_id(&self) -> u32 {
+        (self.0 >> Self::SEQUENCE_BITS) as u32
+    }
+
+    pub fn sequence(&self) -> u32 {
+        self.0 as u32
+    }
+
+    /// Returns the creation time of this ID as a `DateTime<Utc>`, using a
+    /// custom epoch
+    ///
+    /// # Panics
+    /// Panics if the resulting timestamp is outside the range supported by chrono.
+    pub fn datetime_with_epoch(&self, epoch: impl Into<Epoch>) -> DateTime<Utc> {
+        DateTime::from_timestamp_micros(self.timestamp_with_epoch(epoch))
+            .expect("snowflake timestamp out of range for DateTime")
+    }
+
+    /// Returns the ID as 16 big-endian bytes, which sort in the same order
+    /// as the IDs themselves
+    pub fn to_be_bytes(&self) -> [u8; 16] {
+        self.0.to_be_bytes()
+    }
+
+    pub fn from_be_bytes(bytes: [u8; 16]) -> Self {
+        SnowflakeId128(u128::from_be_bytes(bytes))
+    }
+}
+
+impl From<u128> for SnowflakeId128 {
+    fn from(value: u128) -> Self {
+        SnowflakeId128(value)
+    }
+}
+
+impl From<SnowflakeId128> for u128 {
+    fn from(id: SnowflakeId128) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for SnowflakeId128 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for SnowflakeId128 {
+    type Err = SnowflakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<u128>()
+            .map(SnowflakeId128)
+            .map_err(|e| SnowflakeError::Unparseable {
+                input: s.to_string(),
+                reason: e.to_string(),
+            })
+    }
+}
+
+impl Serialize for SnowflakeId128 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u128(self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SnowflakeId128 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IdVisitor;
+
+        impl Visitor<'_> for IdVisitor {
+            type Value = SnowflakeId128;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string or integer representing a 128-bit snowflake id")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<SnowflakeId128, E> {
+                Ok(SnowflakeId128(value as u128))
+            }
+
+            fn visit_u128<E: de::Error>(self, value: u128) -> Result<SnowflakeId128, E> {
+                Ok(SnowflakeId128(value))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<SnowflakeId128, E> {
+                u128::try_from(value)
+                    .map(SnowflakeId128)
+                    .map_err(|_| E::custom("snowflake id cannot be negative"))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<SnowflakeId128, E> {
+                value
+                    .parse()
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(IdVisitor)
+        } else {
+            deserializer.deserialize_u128(IdVisitor)
+        }
+    }
+}
+
+/// Generates [`SnowflakeId128`]s from a microsecond clock
+///
+/// A 32-bit sequence per microsecond never runs out in practice, so the
+/// generator only waits when the clock steps back within tolerance, using a
+/// [`BackoffWait`] unless set otherwise with
+/// [`SnowflakeGenerator128::with_wait`].
+pub struct SnowflakeGenerator128 {
+    machine_id: u32,
+    epoch: i64,
+    clock: Arc<dyn Clock>,
+    tolerance_micros: i64,
+    /// Last timestamp in microseconds since the Unix epoch, and its sequence
+    state: Mutex<(i64, u32)>,
+    wait: Arc<dyn WaitStrategy>,
+}
+
+impl SnowflakeGenerator128 {
+    pub fn new(machine_id: u32) -> Self {
+        Self::with_epoch(machine_id, SNOWFLAKE_ID_EPOCH)
+    }
+
+    pub fn with_epoch(machine_id: u32, epoch: impl Into<Epoch>) -> Self {
+        Self::with_clock(machine_id, epoch, SystemClock)
+    }
+
+    /// Creates a generator that reads time from `clock`'s
+    /// [`Clock::now_micros`]
+    pub fn with_clock(
+        machine_id: u32,
+        epoch: impl Into<Epoch>,
+        clock: impl Clock + 'static,
+    ) -> Self {
+        SnowflakeGenerator128 {
+            machine_id,
+            epoch: epoch.into().as_millis(),
+            clock: Arc::new(clock),
+            tolerance_micros: CLOCK_BACKWARDS_TOLERANCE_MS * 1_000,
+            state: Mutex::new((i64::MIN, 0)),
+            wait: Arc::new(BackoffWait::default()),
+        }
+    }
+
+    /// Sets how [`SnowflakeGenerator128::generate`] waits for the clock
+    pub fn with_wait(mut self, wait: impl WaitStrategy + 'static) -> Self {
+        self.wait = Arc::new(wait);
+        self
+    }
+
+    /// Returns the epoch in milliseconds since the Unix epoch
+    pub fn epoch(&self) -> i64 {
+        self.epoch
+    }
+
+    pub fn machine_id(&self) -> u32 {
+        self.machine_id
+    }
+
+    pub fn try_next_id(&self) -> Result<SnowflakeOperation<SnowflakeId128>, SnowflakeError> {
+        let mut state = self.state.lock();
+        let (last_timestamp, last_sequence) = *state;
+        let timestamp = self.clock.now_micros();
+
+        let sequence = if timestamp < last_timestamp {
+            let drift = last_timestamp - timestamp;
+            if drift <= self.tolerance_micros {
+                return Ok(SnowflakeOperation::Pending(Duration::from_micros(
+                    drift as u64,
+                )));
+            }
+            return Err(SnowflakeError::clock_moved_backwards(
+                Duration::from_micros(drift as u64),
+            ));
+        } else if timestamp == last_timestamp {
+            match last_sequence.checked_add(1) {
+                Some(sequence) => sequence,
+                None => return Ok(SnowflakeOperation::Pending(Duration::from_micros(1))),
+            }
+        } else {
+            0
+        };
+
+        let offset = timestamp
+            .checked_sub(self.epoch.saturating_mul(1_000))
+            .and_then(|offset| u64::try_from(offset).ok())
+            .ok_or(SnowflakeError::TimestampOverflow)?;
+        *state = (timestamp, sequence);
+        Ok(SnowflakeOperation::Ready(SnowflakeId128::from_parts(
+            offset,
+            self.machine_id,
+            sequence,
+        )))
+    }
+
+    /// Generates the next ID, waiting out small backwards clock steps
+    pub fn generate(&self) -> Result<SnowflakeId128, SnowflakeError> {
+        let mut attempt = 0;
+        loop {
+            match self.try_next_id()? {
+                SnowflakeOperation::Ready(id) => return Ok(id),
+                SnowflakeOperation::Pending(wait) => {
+                    self.wait.wait(wait, attempt);
+                    attempt = attempt.saturating_add(1);
+                }
+            }
+        }
+    }
+
+    pub fn generate_bulk(&self, count: usize) -> Result<Vec<SnowflakeId128>, SnowflakeError> {
+        (0..count).map(|_| self.generate()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snowflake_id_128() {
+        use crate::clock::ManualClock;
+        use crate::generator::SnowflakeOperation;
+
+        let id = SnowflakeId128::from_parts(u64::MAX, u32::MAX - 1, 7);
+        assert_eq!(
+            (id.timestamp(), id.machine_id(), id.sequence()),
+            (u64::MAX, u32::MAX - 1, 7)
+        );
+        assert_eq!(SnowflakeId128::from_be_bytes(id.to_be_bytes()), id);
+        assert_eq!(id.to_string().parse::<SnowflakeId128>().unwrap(), id);
+        assert!("-1".parse::<SnowflakeId128>().is_err());
+
+        let clock = ManualClock::new(Epoch::DISCORD.as_millis() + 5);
+        let generator = SnowflakeGenerator128::with_clock(1 << 20, Epoch::DISCORD, clock.clone());
+        let first = generator.generate().unwrap();
+        let second = generator.generate().unwrap();
+        assert_eq!((first.timestamp(), first.sequence()), (5_000, 0));
+        assert_eq!((second.timestamp(), second.sequence()), (5_000, 1));
+        assert_eq!(first.machine_id(), 1 << 20);
+        assert_eq!(
+            first.datetime_with_epoch(Epoch::DISCORD).timestamp_micros(),
+            (Epoch::DISCORD.as_millis() + 5) * 1_000
+        );
+
+        clock.advance(Duration::from_millis(1));
+        let third = generator.generate().unwrap();
+        assert_eq!((third.timestamp(), third.sequence()), (6_000, 0));
+        clock.rewind(Duration::from_millis(2));
+        assert!(matches!(
+            generator.try_next_id(),
+            Ok(SnowflakeOperation::Pending(wait)) if wait == Duration::from_millis(2)
+        ));
+        clock.rewind(Duration::from_millis(100));
+        assert!(matches!(
+            generator.try_next_id(),
+            Err(SnowflakeError::ClockMovedBackwards { .. })
+        ));
+
+        let real = SnowflakeGenerator128::new(3);
+        let ids = real.generate_bulk(1_000).unwrap();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let json = serde_json::to_string(&third).unwrap();
+        assert_eq!(json, format!("\"{}\"", third.id()));
+        assert_eq!(
+            serde_json::from_str::<SnowflakeId128>(&json).unwrap(),
+            third
+        );
+        assert_eq!(
+            serde_json::from_str::<SnowflakeId128>("42").unwrap().id(),
+            42
+        );
+        let bytes = postcard::to_allocvec(&third).unwrap();
+        assert_eq!(
+            postcard::from_bytes::<SnowflakeId128>(&bytes).unwrap(),
+            third
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Idempotent batches for the bundled ID services.
+//!
+//! A client that sends a token with its batch request gets the same IDs back
+//! when it retries with that token, so a response lost to the network
+//! doesn't burn ID space or hand one range out twice. Batches are kept for
+//! a TTL; the oldest are dropped early once too many are cached.
+
+use crate::error::SnowflakeError;
+use crate::SnowflakeId;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// Default for how long an idempotent batch is kept for retries
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(300);
+
+/// Batches cached at once before the oldest are dropped ahead of their TTL
+const MAX_CACHED_BATCHES: usize = 10_000;
+
+/// Longest token accepted, so tokens can't be used to fill memory
+pub(crate) const MAX_TOKEN_LEN: usize = 256;
+
+/// Why an idempotent batch couldn't be returned
+#[derive(Debug)]
+pub(crate) enum BatchError {
+    Generator(SnowflakeError),
+    /// The token was already used for a batch of `count` IDs
+    TokenReused {
+        count: usize,
+    },
+    TokenTooLong,
+}
+
+impl From<SnowflakeError> for BatchError {
+    fn from(err: SnowflakeError) -> Self {
+        BatchError::Generator(err)
+    }
+}
+
+struct CachedBatch {
+    created: Instant,
+    count: usize,
+    /// Filled by the first request; concurrent retries wait on it
+    ids: Arc<OnceCell<Vec<SnowflakeId>>>,
+}
+
+#[derive(Default)]
+struct Batches {
+    by_token: HashMap<String, CachedBatch>,
+    /// Tokens in the order they were cached, which is also expiry order
+    order: VecDeque<(Instant, String)>,
+}
+
+pub(crate) struct IdempotencyCache {
+    ttl: Duration,
+    batches: Mutex<Batches>,
+}
+
+impl IdempotencyCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        IdempotencyCache {
+            ttl,
+            batches: Mutex::new(Batches::default()),
+        }
+    }
+
+    /// Returns the batch cached for `token`, or calls `issue` to mint one.
+    ///
+    /// A batch that fails to mint isn't cached, so it can be retried.
+    pub(crate) async fn get_or_issue<F, Fut>(
+        &self,
+        token: &str,
+        count: usize,
+        issue: F,
+    ) -> Result<Vec<SnowflakeId>, BatchError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<SnowflakeId>, SnowflakeError>>,
+    {
+        if token.len() > MAX_TOKEN_LEN {
+            return Err(BatchError::TokenTooLong);
+        }
+        let ids = self.slot(token, count)?;
+        let ids = ids.get_or_try_init(issue).await?;
+        Ok(ids.clone())
+    }
+
+    fn slot(
+        &self,
+        token: &str,
+        count: usize,
+    ) -> Result<Arc<OnceCell<Vec<SnowflakeId>>>, BatchError> {
+        let now = Instant::now();
+        let mut batches = self.batches.lock().unwrap_or_else(|e| e.into_inner());
+        let Batches { by_token, order } = &mut *batches;
+
+        while let Some((created, oldest)) = order.front() {
+            if now.duration_since(*created) < self.ttl && order.len() < MAX_CACHED_BATCHES {
+                break;
+            }
+            // The token may have been cached again since
+            if by_token
+                .get(oldest)
+                .is_some_and(|batch| batch.created == *created)
+            {
+                by_token.remove(oldest);
+            }
+            order.pop_front();
+        }
+
+        let batch = by_token.entry(token.to_string()).or_insert_with(|| {
+            order.push_back((now, token.to_string()));
+            CachedBatch {
+                created: now,
+                count,
+                ids: Arc::new(OnceCell::new()),
+            }
+        });
+        if batch.count != count {
+            return Err(BatchError::TokenReused { count: batch.count });
+        }
+        Ok(batch.ids.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AsyncSnowflakeGenerator;
+
+    #[tokio::test]
+    async fn test_idempotency_cache() {
+        let generator = AsyncSnowflakeGenerator::new(1).unwrap();
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        let issue = |count| {
+            let generator = &generator;
+            move || generator.next_id_bulk_checked(count)
+        };
+
+        let first = cache.get_or_issue("a", 3, issue(3)).await.unwrap();
+        assert_eq!(first.len(), 3);
+        assert_eq!(cache.get_or_issue("a", 3, issue(3)).await.unwrap(), first);
+        let other = cache.get_or_issue("b", 3, issue(3)).await.unwrap();
+        assert!(other[0] > first[2]);
+
+        assert!(matches!(
+            cache.get_or_issue("a", 4, issue(4)).await,
+            Err(BatchError::TokenReused { count: 3 })
+        ));
+        let long = "x".repeat(MAX_TOKEN_LEN + 1);
+        assert!(matches!(
+            cache.get_or_issue(&long, 1, issue(1)).await,
+            Err(BatchError::TokenTooLong)
+        ));
+
+        // Failures aren't cached
+        let failed = cache
+            .get_or_issue("c", 1, || async { Err(SnowflakeError::Overloaded) })
+            .await;
+        assert!(matches!(failed, Err(BatchError::Generator(_))));
+        assert_eq!(cache.get_or_issue("c", 1, issue(1)).await.unwrap().len(), 1);
+
+        // Expired batches are minted afresh
+        let expiring = IdempotencyCache::new(Duration::ZERO);
+        let first = expiring.get_or_issue("a", 1, issue(1)).await.unwrap();
+        assert_ne!(
+            expiring.get_or_issue("a", 1, issue(1)).await.unwrap(),
+            first
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! juniper scalar for [`SnowflakeId`].
+//!
+//! IDs are always output as strings, since GraphQL `Int` is 32-bit and JSON
+//! clients lose precision above 2^53. Input accepts strings and integer
+//! literals of any size; integers passed through JSON variables must fit in
+//! an `i32`, so clients should send larger IDs as strings.
+
+use crate::SnowflakeId;
+use juniper::parser::{ParseError, ScalarToken, Token};
+use juniper::{DefaultScalarValue, ParseScalarResult, Value};
+
+juniper::graphql_scalar!(SnowflakeId as "SnowflakeId" {
+    description: "A 64-bit snowflake ID, serialized as a decimal string"
+
+    resolve(&self) -> Value {
+        Value::scalar(self.0.to_string())
+    }
+
+    from_input_value(v: &InputValue) -> Option<SnowflakeId> {
+        match v.as_scalar_value::<String>() {
+            Some(value) => value.parse().ok(),
+            None => v
+                .as_scalar_value::<i32>()
+                .and_then(|&value| SnowflakeId::new(i64::from(value)).ok()),
+        }
+    }
+
+    from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a> {
+        match value {
+            // Keep integer literals as text so IDs beyond i32 aren't truncated
+            ScalarToken::String(text) | ScalarToken::Int(text) => {
+                Ok(DefaultScalarValue::String(text.to_owned()))
+            }
+            ScalarToken::Float(_) => Err(ParseError::UnexpectedToken(Token::Scalar(value))),
+        }
+    }
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_juniper_scalar() {
+        use juniper::{graphql_value, EmptyMutation, RootNode, Variables};
+
+        struct Query;
+
+        #[juniper::object]
+        impl Query {
+            fn echo(id: SnowflakeId) -> SnowflakeId {
+                id
+            }
+        }
+
+        let schema = RootNode::new(Query, EmptyMutation::<()>::new());
+        // Returns the data, or None if the query was rejected
+        let run = |query: &str, variables: &Variables| {
+            juniper::execute(query, None, &schema, variables, &())
+                .ok()
+                .map(|(value, errors)| {
+                    assert!(errors.is_empty(), "{:?}", errors);
+                    value
+                })
+        };
+
+        let value = run(
+            r#"{ a: echo(id: "123456789012345678") b: echo(id: 123456789012345678) c: echo(id: 42) }"#,
+            &Variables::new(),
+        );
+        assert_eq!(
+            value,
+            Some(graphql_value!({
+                "a": "123456789012345678",
+                "b": "123456789012345678",
+                "c": "42",
+            }))
+        );
+
+        let mut variables = Variables::new();
+        variables.insert("id".to_owned(), juniper::InputValue::scalar(7));
+        let value = run("query ($id: SnowflakeId!) { echo(id: $id) }", &variables);
+        assert_eq!(value, Some(graphql_value!({ "echo": "7" })));
+
+        assert!(run(r#"{ echo(id: "-1") }"#, &Variables::new()).is_none());
+        assert!(run("{ echo(id: 1.5) }", &Variables::new()).is_none());
+    }
+}
@@ -0,0 +1,103 @@
+//! Partitioning and encoding for Kafka topics keyed by snowflake.
+//!
+//! [`SnowflakeId::to_partition`] picks a partition from the machine id and
+//! sequence bits only, mixed with the same fixed function as
+//! [`SnowflakeId::shard`], so the assignment is stable across processes,
+//! restarts and crate versions. A burst from one generator spreads evenly
+//! over the partitions, while a generator issuing fewer than one ID per tick
+//! mostly uses sequence `0` and so keeps landing on the same partition; key
+//! such traffic with [`SnowflakeId::shard`] instead.
+//!
+//! [`SnowflakeId::to_kafka_key_bytes`] encodes the ID as 8 big-endian bytes,
+//! which works both as a record key and as a header value. Producers that
+//! leave partitioning to Kafka's default partitioner hash these bytes
+//! themselves, so pass the result of `to_partition` explicitly to get the
+//! behaviour described here.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::SnowflakeId;
+//!
+//! let id = SnowflakeId::new(175928847299117063).unwrap();
+//! let partition = id.to_partition(12).unwrap();
+//! assert!(partition < 12);
+//!
+//! let key = id.to_kafka_key_bytes();
+//! assert_eq!(SnowflakeId::from_kafka_key_bytes(&key).unwrap(), id);
+//! ```
+
+use crate::defs::TIMESTAMP_SHIFT;
+use crate::error::SnowflakeError;
+use crate::routing::mix64;
+use crate::SnowflakeId;
+
+impl SnowflakeId {
+    /// Returns the Kafka partition out of `num_partitions` for this ID, see
+    /// the [module docs](crate::kafka)
+    ///
+    /// Returns [`SnowflakeError::InvalidShardCount`] if `num_partitions` is
+    /// zero.
+    pub fn to_partition(&self, num_partitions: u32) -> Result<u32, SnowflakeError> {
+        if num_partitions == 0 {
+            return Err(SnowflakeError::InvalidShardCount(0, u32::MAX as u64));
+        }
+        let bits = self.id() as u64 & ((1 << TIMESTAMP_SHIFT) - 1);
+        Ok((mix64(bits) % num_partitions as u64) as u32)
+    }
+
+    /// Encodes the ID as a Kafka record key or header value
+    pub fn to_kafka_key_bytes(&self) -> [u8; 8] {
+        self.to_be_bytes()
+    }
+
+    /// Decodes a key or header value written by
+    /// [`SnowflakeId::to_kafka_key_bytes`]
+    pub fn from_kafka_key_bytes(bytes: &[u8]) -> Result<Self, SnowflakeError> {
+        let bytes: [u8; 8] = bytes.try_into().map_err(|_| {
+            SnowflakeError::InvalidWireFormat(format!(
+                "Kafka key is {} bytes, expected 8",
+                bytes.len()
+            ))
+        })?;
+        Self::from_be_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+
+    #[test]
+    fn test_kafka_partition_and_key() {
+        let id = SnowflakeId::from_component_parts(1_000, 5, 42);
+        let later = SnowflakeId::from_component_parts(9_999_999, 5, 42);
+        assert_eq!(
+            id.to_partition(12).unwrap(),
+            later.to_partition(12).unwrap()
+        );
+        // Pinned so partition assignments never move between releases
+        assert_eq!(id.to_partition(12).unwrap(), 2);
+        assert_eq!(id.to_partition(1).unwrap(), 0);
+        assert!(matches!(
+            id.to_partition(0),
+            Err(SnowflakeError::InvalidShardCount(0, _))
+        ));
+
+        let generator = SnowflakeGenerator::new(3).unwrap();
+        let ids = generator.generate_bulk(4096).unwrap();
+        let mut counts = [0usize; 8];
+        for id in &ids {
+            counts[id.to_partition(8).unwrap() as usize] += 1;
+        }
+        assert!(counts.iter().all(|&n| n > 4096 / 8 / 2), "{:?}", counts);
+
+        let key = id.to_kafka_key_bytes();
+        assert_eq!(SnowflakeId::from_kafka_key_bytes(&key).unwrap(), id);
+        let Err(err) = SnowflakeId::from_kafka_key_bytes(&key[..7]) else {
+            panic!("short key accepted");
+        };
+        assert_eq!(err.code(), "invalid_wire_format");
+        assert!(SnowflakeId::from_kafka_key_bytes(&[0xff; 8]).is_err());
+    }
+}
@@ -1,29 +1,255 @@
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+// Lets code generated by `#[derive(Snowflake)]` name this crate in its own tests
+#[cfg(all(test, feature = "derive"))]
+extern crate self as snowflake_id;
+
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
 use std::convert::TryFrom;
 use std::fmt;
+use std::num::NonZeroI64;
+use std::ops::{Range, RangeInclusive};
 use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[cfg(feature = "sqlx")]
 use sqlx::Type;
 
+pub mod audit;
+pub mod block;
+pub mod builder;
+pub mod checkpoint;
+pub mod clock;
+pub mod clock_guard;
+pub mod config;
+pub mod consumer;
 pub mod defs;
+pub mod derived;
+pub mod display_cache;
+pub mod entity;
+pub mod epoch;
+pub mod epoch_bound;
 pub mod error;
 pub mod generator;
+pub mod id128;
+pub mod kafka;
+pub mod machine_id;
+pub mod non_nil;
+pub mod obfuscate;
+pub mod offline;
+pub mod ordering;
+pub mod pagination;
+pub mod parts;
+pub mod pool;
+pub mod range;
+pub mod registry;
+pub mod rotation;
+pub mod routing;
+pub mod self_test;
+pub mod serde;
+pub mod sharded;
+pub mod snapshot;
 pub mod snowflake;
+mod sync;
+#[cfg(test)]
+mod test_support;
+pub mod typed;
+pub mod validation;
+pub mod wait;
 
-#[cfg(feature = "tokio")]
+#[cfg(feature = "async")]
 pub mod async_generator;
 
+#[cfg(feature = "actix")]
+pub mod actix_extras;
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+
+#[cfg(feature = "axum")]
+mod axum_support;
+
+#[cfg(feature = "borsh")]
+mod borsh_support;
+
+#[cfg(feature = "bson")]
+mod bson_support;
+
+#[cfg(feature = "tokio")]
+pub mod buffered;
+
+#[cfg(feature = "clap")]
+pub mod clap_extras;
+
+#[cfg(feature = "config-file")]
+pub mod config_file;
+
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod derive_support;
+
+#[cfg(feature = "global")]
+pub mod global;
+
+#[cfg(feature = "server-grpc")]
+pub mod grpc;
+
+#[cfg(feature = "metrics")]
+pub mod generator_metrics;
+
+#[cfg(feature = "server-http")]
+pub mod http_service;
+
+#[cfg(any(feature = "server-grpc", feature = "server-http"))]
+mod idempotency;
+
+#[cfg(feature = "juniper")]
+mod juniper_support;
+
+#[cfg(feature = "prost")]
+pub mod proto;
+
+#[cfg(feature = "proptest")]
+mod proptest_support;
+
+#[cfg(feature = "python")]
+pub mod python;
+
+#[cfg(feature = "quickcheck")]
+mod quickcheck_support;
+
+#[cfg(feature = "rand")]
+mod rand_support;
+
+#[cfg(feature = "redis")]
+mod redis_support;
+
+#[cfg(feature = "rocket")]
+mod rocket_support;
+
+#[cfg(feature = "async")]
+mod rt;
+
+#[cfg(feature = "schemars")]
+mod schemars_support;
+
+#[cfg(feature = "sea-orm")]
+mod sea_orm_support;
+
+#[cfg(feature = "tower")]
+pub mod service;
+
+#[cfg(feature = "sqlx-extras")]
+pub mod sqlx_extras;
+
+#[cfg(feature = "stats")]
+pub mod stats;
+
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "utoipa")]
+mod utoipa_support;
+
+#[cfg(feature = "wire")]
+pub mod wire;
+
+pub use block::SnowflakeBlock;
+pub use builder::{GeneratorBuilder, OverflowStrategy, Profile, SequenceStart};
+pub use clock::{Clock, ClockHealth, MonotonicClock, SystemClock};
+pub use clock_guard::ClockGuard;
 pub use defs::*;
+pub use derived::DerivedId;
+pub use epoch::Epoch;
+pub use epoch_bound::EpochBound;
 use error::SnowflakeError;
+pub use id128::{SnowflakeGenerator128, SnowflakeId128};
+pub use non_nil::NonNilSnowflakeId;
+pub use parts::{decompose_batch, DecomposedColumns, SnowflakeParts};
+pub use range::SnowflakeRange;
 pub use snowflake::Snowflake;
+pub use typed::TypedSnowflakeId;
+use validation::{ValidationReport, ValidationRules};
 
 /// Type alias — the concrete generator is now the generic one parameterised on `SnowflakeId`.
 pub type SnowflakeGenerator = generator::SnowflakeGenerator<SnowflakeId>;
 
-#[cfg(feature = "tokio")]
+/// Thread-sharded generator for the default [`SnowflakeId`] layout
+pub type ShardedSnowflakeGenerator = sharded::ShardedSnowflakeGenerator<SnowflakeId>;
+
+// Generators are built to be shared across threads; fail the build if a
+// change costs any of them Send or Sync
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SnowflakeGenerator>();
+    assert_send_sync::<ShardedSnowflakeGenerator>();
+    assert_send_sync::<SnowflakeGenerator128>();
+    assert_send_sync::<offline::OfflineIssuer<SnowflakeId>>();
+    assert_send_sync::<rotation::RotatingGenerator>();
+    assert_send_sync::<ClockGuard>();
+    assert_send_sync::<pool::GeneratorPool<String, SnowflakeId>>();
+    #[cfg(feature = "async")]
+    assert_send_sync::<AsyncSnowflakeGenerator>();
+};
+
+#[cfg(feature = "async")]
+pub use async_generator::Priority;
+
+/// Derives [`Snowflake`] plus `Display`, `FromStr`, serde and integer
+/// conversions for an ID newtype
+///
+/// # Example
+/// ```
+/// use snowflake_id::Snowflake;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Snowflake)]
+/// #[snowflake(timestamp_bits = 42, machine_id_bits = 8, sequence_bits = 13)]
+/// struct OrderId(i64);
+///
+/// let generator = snowflake_id::generator::SnowflakeGenerator::<OrderId>::new(3).unwrap();
+/// let id = generator.generate().unwrap();
+/// assert_eq!(id.machine_id(), 3);
+/// assert_eq!(id.to_string().parse::<OrderId>().unwrap(), id);
+/// ```
+#[cfg(feature = "derive")]
+pub use snowflake_id_derive::Snowflake;
+
+#[cfg(feature = "global")]
+pub use global::{init_global, next_id, try_next_id};
+
+#[cfg(feature = "async")]
 pub type AsyncSnowflakeGenerator = async_generator::AsyncSnowflakeGenerator<SnowflakeId>;
 
+/// Creates a [`SnowflakeId`] constant, rejecting negative values at compile
+/// time
+///
+/// # Example
+/// ```
+/// use snowflake_id::{snowflake_id, SnowflakeId};
+///
+/// const FIXTURE: SnowflakeId = snowflake_id!(4_194_308_096);
+/// const SAME: SnowflakeId = SnowflakeId::from_component_parts(1_000, 1, 0);
+/// assert_eq!(FIXTURE.machine_id(), 1);
+///
+/// match SnowflakeId::new(4_194_308_096).unwrap() {
+///     SAME => {}
+///     _ => unreachable!(),
+/// }
+/// ```
+///
+/// ```compile_fail
+/// let id = snowflake_id::snowflake_id!(-1);
+/// ```
+#[macro_export]
+macro_rules! snowflake_id {
+    ($value:expr) => {{
+        const ID: $crate::SnowflakeId = $crate::SnowflakeId::new_const($value);
+        ID
+    }};
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "sqlx", derive(Type))]
 #[cfg_attr(feature = "sqlx", sqlx(type_name = "BIGINT"))]
@@ -36,10 +262,7 @@ pub struct SnowflakeId(i64);
 
 impl Snowflake for SnowflakeId {
     fn from_component_parts(timestamp_offset: u64, machine_id: u64, sequence: u64) -> Self {
-        let id = (timestamp_offset << Self::timestamp_shift())
-            | (machine_id << Self::sequence_bits())
-            | sequence;
-        SnowflakeId(id as i64)
+        SnowflakeId::from_component_parts(timestamp_offset, machine_id, sequence)
     }
 
     fn id(&self) -> u64 {
@@ -73,35 +296,307 @@ impl SnowflakeId {
         Ok(SnowflakeId(value))
     }
 
+    /// The nil ID, which no generator issues; usable as a "no ID" sentinel
+    pub const ZERO: SnowflakeId = SnowflakeId(0);
+
+    /// Returns true for [`SnowflakeId::ZERO`]
+    pub const fn is_nil(&self) -> bool {
+        self.0 == 0
+    }
+
     /// Creates a SnowflakeId without validation. Only use this if you're certain the value is valid.
     ///
     /// # Safety
     /// The caller must ensure that the value is non-negative.
-    pub fn new_unchecked(value: i64) -> Self {
+    pub const fn new_unchecked(value: i64) -> Self {
         SnowflakeId(value)
     }
 
-    pub fn id(&self) -> i64 {
+    /// Creates a SnowflakeId in `const` contexts, see [`snowflake_id!`]
+    ///
+    /// # Panics
+    /// Panics if `value` is negative, which fails compilation when evaluated
+    /// in a `const`.
+    pub const fn new_const(value: i64) -> Self {
+        assert!(value >= 0, "Snowflake ID cannot be negative");
+        SnowflakeId(value)
+    }
+
+    /// Builds an ID from its components, like
+    /// [`Snowflake::from_component_parts`] but usable in `const` contexts.
+    /// Components are not masked, so out-of-range values spill into the
+    /// neighbouring fields.
+    pub const fn from_component_parts(
+        timestamp_offset: u64,
+        machine_id: u64,
+        sequence: u64,
+    ) -> Self {
+        let id = (timestamp_offset << TIMESTAMP_SHIFT) | (machine_id << SEQUENCE_BITS) | sequence;
+        SnowflakeId(id as i64)
+    }
+
+    pub const fn id(&self) -> i64 {
         self.0
     }
 
+    pub const fn timestamp_mask() -> u64 {
+        (1u64 << TIMESTAMP_BITS) - 1
+    }
+
+    pub const fn machine_id_mask() -> u64 {
+        (1u64 << MACHINE_ID_BITS) - 1
+    }
+
+    pub const fn sequence_mask() -> u64 {
+        (1u64 << SEQUENCE_BITS) - 1
+    }
+
+    /// Returns the ID as 8 big-endian bytes, which sort in the same order as
+    /// the IDs themselves
+    pub const fn to_be_bytes(&self) -> [u8; 8] {
+        self.0.to_be_bytes()
+    }
+
+    /// Returns the ID as 8 little-endian bytes
+    pub const fn to_le_bytes(&self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+
+    /// Reads an ID from 8 big-endian bytes, rejecting negative values
+    pub fn from_be_bytes(bytes: [u8; 8]) -> Result<Self, SnowflakeError> {
+        Self::new(i64::from_be_bytes(bytes))
+    }
+
+    /// Reads an ID from 8 little-endian bytes, rejecting negative values
+    pub fn from_le_bytes(bytes: [u8; 8]) -> Result<Self, SnowflakeError> {
+        Self::new(i64::from_le_bytes(bytes))
+    }
+
     /// Returns the timestamp offset (in milliseconds) stored in this snowflake ID.
     /// This is NOT a Unix timestamp. To get the actual Unix timestamp, use `timestamp_with_epoch()`.
-    pub fn timestamp(&self) -> i64 {
-        <Self as Snowflake>::timestamp(self) as i64
+    pub const fn timestamp(&self) -> i64 {
+        ((self.0 as u64 >> TIMESTAMP_SHIFT) & Self::timestamp_mask()) as i64
     }
 
     /// Returns the timestamp in milliseconds since Unix epoch, using a custom epoch
-    pub fn timestamp_with_epoch(&self, epoch: i64) -> i64 {
+    pub fn timestamp_with_epoch(&self, epoch: impl Into<Epoch>) -> i64 {
         <Self as Snowflake>::timestamp_with_epoch(self, epoch)
     }
 
-    pub fn machine_id(&self) -> u64 {
-        <Self as Snowflake>::machine_id(self)
+    pub const fn machine_id(&self) -> u64 {
+        (self.0 as u64 >> SEQUENCE_BITS) & Self::machine_id_mask()
+    }
+
+    pub const fn sequence(&self) -> u64 {
+        self.0 as u64 & Self::sequence_mask()
+    }
+
+    /// Returns the upper [`DATACENTER_ID_BITS`] of the machine ID, for
+    /// Twitter-style datacenter and worker layouts
+    pub fn datacenter_id(&self) -> u64 {
+        self.machine_id() >> WORKER_ID_BITS
+    }
+
+    /// Returns the lower [`WORKER_ID_BITS`] of the machine ID, for
+    /// Twitter-style datacenter and worker layouts
+    pub fn worker_id(&self) -> u64 {
+        self.machine_id() & MAX_WORKER_ID
+    }
+
+    /// Returns the creation time of this ID as a `DateTime<Utc>`, using a custom epoch
+    ///
+    /// # Panics
+    /// Panics if the resulting timestamp is outside the range supported by chrono.
+    pub fn datetime_with_epoch(&self, epoch: impl Into<Epoch>) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.timestamp_with_epoch(epoch))
+            .expect("snowflake timestamp out of range for DateTime")
+    }
+
+    /// Returns the creation time of this ID in the time zone `tz`, using a custom epoch
+    ///
+    /// # Panics
+    /// Panics if the resulting timestamp is outside the range supported by chrono.
+    pub fn datetime_in<Tz: TimeZone>(&self, epoch: impl Into<Epoch>, tz: &Tz) -> DateTime<Tz> {
+        self.datetime_with_epoch(epoch).with_timezone(tz)
+    }
+
+    /// Formats the creation time of this ID as RFC 3339 in UTC with millisecond
+    /// precision, e.g. `2024-03-01T00:00:00.000Z`
+    pub fn to_rfc3339_with_epoch(&self, epoch: impl Into<Epoch>) -> String {
+        self.datetime_with_epoch(epoch)
+            .to_rfc3339_opts(SecondsFormat::Millis, true)
+    }
+
+    /// Formats the creation time of this ID as RFC 3339 in the operator's time
+    /// zone `tz`, e.g. `2024-03-01T09:00:00.000+09:00`
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::{Epoch, SnowflakeId};
+    ///
+    /// let id = SnowflakeId::first_for_timestamp(1709251200000, Epoch::TWITTER).unwrap();
+    /// assert_eq!(
+    ///     id.format_local(Epoch::TWITTER, chrono_tz::Asia::Tokyo),
+    ///     "2024-03-01T09:00:00.000+09:00"
+    /// );
+    /// ```
+    #[cfg(feature = "chrono-tz")]
+    pub fn format_local(&self, epoch: impl Into<Epoch>, tz: chrono_tz::Tz) -> String {
+        self.datetime_in(epoch, &tz)
+            .to_rfc3339_opts(SecondsFormat::Millis, false)
+    }
+
+    /// Returns the creation time of this ID as a `SystemTime`, using a custom epoch
+    pub fn system_time_with_epoch(&self, epoch: impl Into<Epoch>) -> SystemTime {
+        let millis = self.timestamp_with_epoch(epoch);
+        if millis >= 0 {
+            UNIX_EPOCH + Duration::from_millis(millis as u64)
+        } else {
+            UNIX_EPOCH - Duration::from_millis(millis.unsigned_abs())
+        }
+    }
+
+    /// Returns the time elapsed since this ID was created, using a custom
+    /// epoch, or zero if its timestamp is in the future
+    pub fn age_with_epoch(&self, epoch: impl Into<Epoch>) -> Duration {
+        let elapsed = SystemClock
+            .now_millis()
+            .saturating_sub(self.timestamp_with_epoch(epoch));
+        Duration::from_millis(elapsed.max(0) as u64)
+    }
+
+    /// Returns true if this ID was created more than `duration` ago, using a
+    /// custom epoch
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::{Epoch, SnowflakeId};
+    /// use std::time::Duration;
+    ///
+    /// let id = SnowflakeId::first_for_timestamp(1709251200000, Epoch::TWITTER).unwrap();
+    /// assert!(id.is_older_than(Duration::from_secs(3600), Epoch::TWITTER));
+    /// ```
+    pub fn is_older_than(&self, duration: Duration, epoch: impl Into<Epoch>) -> bool {
+        self.age_with_epoch(epoch) > duration
+    }
+
+    /// Checks this ID against `rules`, see [`crate::validation`]
+    pub fn validate(&self, rules: &ValidationRules) -> ValidationReport {
+        rules.check(*self)
+    }
+
+    /// Returns the creation time of this ID as a `time::OffsetDateTime` in UTC, using a custom epoch
+    ///
+    /// # Panics
+    /// Panics if the resulting timestamp is outside the range supported by the `time` crate.
+    #[cfg(feature = "time")]
+    pub fn offset_datetime_with_epoch(&self, epoch: impl Into<Epoch>) -> time::OffsetDateTime {
+        let nanos = self.timestamp_with_epoch(epoch) as i128 * 1_000_000;
+        time::OffsetDateTime::from_unix_timestamp_nanos(nanos)
+            .expect("snowflake timestamp out of range for OffsetDateTime")
+    }
+
+    /// Splits the ID into its fields, resolving the timestamp against `epoch`.
+    ///
+    /// # Panics
+    /// Panics if the resulting timestamp is outside the range supported by chrono.
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::{Epoch, SnowflakeId};
+    ///
+    /// let id = SnowflakeId::new(175928847299117063).unwrap();
+    /// let parts = id.decompose(Epoch::DISCORD);
+    /// assert_eq!(parts.datetime.timestamp_millis(), 1462015105796);
+    /// println!("{}", parts);
+    /// ```
+    pub fn decompose(&self, epoch: impl Into<Epoch>) -> SnowflakeParts {
+        SnowflakeParts {
+            datetime: self.datetime_with_epoch(epoch),
+            machine_id: self.machine_id(),
+            sequence: self.sequence(),
+            raw_offset: <Self as Snowflake>::timestamp(self),
+        }
+    }
+
+    /// Returns the smallest ID that can be generated at Unix timestamp `timestamp_ms`.
+    ///
+    /// Together with [`SnowflakeId::last_for_timestamp`] this turns a time window into
+    /// an indexed `BETWEEN` filter on the ID column.
+    pub fn first_for_timestamp(
+        timestamp_ms: i64,
+        epoch: impl Into<Epoch>,
+    ) -> Result<Self, SnowflakeError> {
+        let offset = Self::offset_for_timestamp(timestamp_ms, epoch.into())?;
+        Ok(<Self as Snowflake>::from_component_parts(offset, 0, 0))
+    }
+
+    /// Returns the largest ID that can be generated at Unix timestamp `timestamp_ms`.
+    pub fn last_for_timestamp(
+        timestamp_ms: i64,
+        epoch: impl Into<Epoch>,
+    ) -> Result<Self, SnowflakeError> {
+        let offset = Self::offset_for_timestamp(timestamp_ms, epoch.into())?;
+        Ok(<Self as Snowflake>::from_component_parts(
+            offset,
+            MAX_MACHINE_ID,
+            MAX_SEQUENCE,
+        ))
+    }
+
+    /// Returns the inclusive ID bounds covering every ID generated in the half-open
+    /// Unix timestamp window `range`.
+    ///
+    /// Fails with [`SnowflakeError::InvalidId`] if the window is empty or its
+    /// bounds are inverted, rather than returning bounds no ID falls between.
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::{Epoch, SnowflakeId};
+    ///
+    /// // 2024-03-01T00:00:00Z .. 2024-04-01T00:00:00Z
+    /// let bounds = SnowflakeId::range_for(1709251200000..1711929600000, Epoch::TWITTER).unwrap();
+    /// let (low, high) = (bounds.start().id(), bounds.end().id());
+    /// // SELECT * FROM rows WHERE id BETWEEN $low AND $high
+    /// assert!(low < high);
+    /// ```
+    pub fn range_for(
+        range: Range<i64>,
+        epoch: impl Into<Epoch>,
+    ) -> Result<RangeInclusive<Self>, SnowflakeError> {
+        if range.start > range.end {
+            return Err(SnowflakeError::InvalidId(format!(
+                "Timestamp range {}..{} is inverted",
+                range.start, range.end
+            )));
+        }
+        if range.is_empty() {
+            return Err(SnowflakeError::InvalidId(format!(
+                "Timestamp range {}..{} is empty",
+                range.start, range.end
+            )));
+        }
+        let epoch = epoch.into();
+        Ok(Self::first_for_timestamp(range.start, epoch)?
+            ..=Self::last_for_timestamp(range.end - 1, epoch)?)
+    }
+
+    fn offset_for_timestamp(timestamp_ms: i64, epoch: Epoch) -> Result<u64, SnowflakeError> {
+        let offset = timestamp_ms
+            .checked_sub(epoch.as_millis())
+            .ok_or(SnowflakeError::TimestampOverflow)?;
+        if !(0..=MAX_TIMESTAMP_MS).contains(&offset) {
+            return Err(SnowflakeError::TimestampOverflow);
+        }
+        Ok(offset as u64)
     }
 
-    pub fn sequence(&self) -> u64 {
-        <Self as Snowflake>::sequence(self)
+    /// Derives the `n`th deterministic child identifier of this ID.
+    ///
+    /// Useful for sub-entities (e.g. line items under an order) that must be
+    /// reconstructible without another generator call.
+    pub fn derive_child(&self, n: u32) -> DerivedId {
+        DerivedId::new(*self, n)
     }
 }
 
@@ -113,9 +608,10 @@ impl FromStr for SnowflakeId {
     type Err = SnowflakeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let value = s
-            .parse::<i64>()
-            .map_err(|e| SnowflakeError::InvalidId(format!("Failed to parse: {}", e)))?;
+        let value = s.parse::<i64>().map_err(|e| SnowflakeError::Unparseable {
+            input: s.to_string(),
+            reason: e.to_string(),
+        })?;
 
         if value < 0 {
             return Err(SnowflakeError::InvalidId(
@@ -151,6 +647,49 @@ impl From<SnowflakeId> for i64 {
     }
 }
 
+impl TryFrom<u64> for SnowflakeId {
+    type Error = SnowflakeError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        i64::try_from(value).map(SnowflakeId).map_err(|_| {
+            SnowflakeError::InvalidId(format!("{} does not fit in a snowflake ID", value))
+        })
+    }
+}
+
+impl From<SnowflakeId> for u64 {
+    fn from(id: SnowflakeId) -> Self {
+        id.0 as u64
+    }
+}
+
+impl TryFrom<&str> for SnowflakeId {
+    type Error = SnowflakeError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Fails for negative values, like [`SnowflakeId::new`]
+impl TryFrom<NonZeroI64> for SnowflakeId {
+    type Error = SnowflakeError;
+
+    fn try_from(value: NonZeroI64) -> Result<Self, Self::Error> {
+        SnowflakeId::new(value.get())
+    }
+}
+
+/// Fails for the zero ID, which no generator issues but parsing accepts
+impl TryFrom<SnowflakeId> for NonZeroI64 {
+    type Error = SnowflakeError;
+
+    fn try_from(id: SnowflakeId) -> Result<Self, Self::Error> {
+        NonZeroI64::new(id.0)
+            .ok_or_else(|| SnowflakeError::InvalidId("Snowflake ID is zero".to_string()))
+    }
+}
+
 impl Serialize for SnowflakeId {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -171,7 +710,7 @@ impl<'de> Deserialize<'de> for SnowflakeId {
     {
         struct SnowflakeIdVisitor;
 
-        impl<'de> serde::de::Visitor<'de> for SnowflakeIdVisitor {
+        impl<'de> ::serde::de::Visitor<'de> for SnowflakeIdVisitor {
             type Value = SnowflakeId;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
@@ -180,7 +719,7 @@ impl<'de> Deserialize<'de> for SnowflakeId {
 
             fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
             where
-                E: serde::de::Error,
+                E: ::serde::de::Error,
             {
                 if value > i64::MAX as u64 {
                     return Err(E::custom("snowflake id value exceeds i64::MAX"));
@@ -190,7 +729,7 @@ impl<'de> Deserialize<'de> for SnowflakeId {
 
             fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
             where
-                E: serde::de::Error,
+                E: ::serde::de::Error,
             {
                 if value < 0 {
                     Err(E::custom("snowflake id cannot be negative"))
@@ -201,11 +740,11 @@ impl<'de> Deserialize<'de> for SnowflakeId {
 
             fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
             where
-                E: serde::de::Error,
+                E: ::serde::de::Error,
             {
                 let parsed = value
                     .parse::<i64>()
-                    .map_err(|_| E::custom("invalid snowflake id string"))?;
+                    .map_err(|_| E::invalid_value(::serde::de::Unexpected::Str(value), &self))?;
                 if parsed < 0 {
                     return Err(E::custom("snowflake id cannot be negative"));
                 }
@@ -214,13 +753,19 @@ impl<'de> Deserialize<'de> for SnowflakeId {
 
             fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
             where
-                E: serde::de::Error,
+                E: ::serde::de::Error,
             {
                 self.visit_str(&value)
             }
         }
 
-        deserializer.deserialize_any(SnowflakeIdVisitor)
+        // Binary formats aren't self-describing, so ask for the i64 that
+        // `Serialize` writes for them
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(SnowflakeIdVisitor)
+        } else {
+            deserializer.deserialize_i64(SnowflakeIdVisitor)
+        }
     }
 }
 
@@ -231,19 +776,8 @@ impl<'de> Deserialize<'de> for SnowflakeId {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::str::FromStr;
     use std::thread;
 
-    #[test]
-    fn test_snowflake_generator() {
-        let generator = SnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
-        let id1 = generator.next_id(|_| thread::yield_now());
-        let id2 = generator.next_id(|_| thread::yield_now());
-
-        assert_ne!(id1, id2);
-        assert!(id1.id() < id2.id());
-    }
-
     #[test]
     fn test_snowflake_id_components() {
         let generator = SnowflakeGenerator::with_epoch(42, SNOWFLAKE_ID_EPOCH).unwrap();
@@ -257,15 +791,79 @@ mod tests {
     }
 
     #[test]
-    fn test_bulk_generation() {
-        let generator = SnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
-        let ids = generator.next_id_bulk(100, |_| thread::yield_now());
+    fn test_age() {
+        let now = Utc::now().timestamp_millis();
+        let id = SnowflakeId::first_for_timestamp(now - 10_000, Epoch::DISCORD).unwrap();
+        let age = id.age_with_epoch(Epoch::DISCORD);
+        assert!(age >= Duration::from_secs(10) && age < Duration::from_secs(60));
+        assert!(id.is_older_than(Duration::from_secs(5), Epoch::DISCORD));
+        assert!(!id.is_older_than(Duration::from_secs(60), Epoch::DISCORD));
+
+        let future = SnowflakeId::first_for_timestamp(now + 60_000, Epoch::DISCORD).unwrap();
+        assert_eq!(future.age_with_epoch(Epoch::DISCORD), Duration::ZERO);
+        assert!(!future.is_older_than(Duration::ZERO, Epoch::DISCORD));
+
+        let bound = EpochBound::new(id, Epoch::DISCORD);
+        assert!(bound.is_older_than(Duration::from_secs(5)));
+        assert!(!bound.is_older_than(Duration::from_secs(60)));
+    }
 
-        assert_eq!(ids.len(), 100);
+    #[test]
+    fn test_const_construction() {
+        const ID: SnowflakeId = crate::snowflake_id!(4_194_308_096);
+        const PARTS: SnowflakeId = SnowflakeId::from_component_parts(1_000, 1, 0);
+        const TIMESTAMP: i64 = ID.timestamp();
+        const BYTES: [u8; 8] = ID.to_be_bytes();
+
+        assert_eq!(ID, PARTS);
+        assert_eq!(TIMESTAMP, 1_000);
+        assert_eq!((ID.machine_id(), ID.sequence()), (1, 0));
+        assert_eq!(SnowflakeId::from_be_bytes(BYTES).unwrap(), ID);
+        assert_eq!(
+            SnowflakeId::machine_id_mask(),
+            <SnowflakeId as Snowflake>::machine_id_mask()
+        );
+
+        let id = SnowflakeGenerator::new(7).unwrap().generate().unwrap();
+        let rebuilt = SnowflakeId::from_component_parts(
+            id.timestamp() as u64,
+            id.machine_id(),
+            id.sequence(),
+        );
+        assert_eq!(rebuilt, id);
+    }
 
-        for i in 1..ids.len() {
-            assert!(ids[i - 1].id() < ids[i].id());
-        }
+    #[test]
+    fn test_byte_conversions() {
+        let id = SnowflakeId::new(0x0102_0304_0506_0708).unwrap();
+        assert_eq!(id.to_be_bytes(), [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(id.to_le_bytes(), [8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(SnowflakeId::from_be_bytes(id.to_be_bytes()).unwrap(), id);
+        assert_eq!(SnowflakeId::from_le_bytes(id.to_le_bytes()).unwrap(), id);
+
+        // Big-endian keys sort like the IDs
+        let later = SnowflakeId::new(0x0102_0304_0506_0800).unwrap();
+        assert!(later > id && later.to_be_bytes() > id.to_be_bytes());
+
+        assert!(SnowflakeId::from_be_bytes([0x80, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        assert!(SnowflakeId::from_le_bytes([0, 0, 0, 0, 0, 0, 0, 0xff]).is_err());
+    }
+
+    #[test]
+    fn test_timezone_formatting() {
+        use chrono::FixedOffset;
+
+        // 2024-03-01T00:00:00.250Z
+        let id = SnowflakeId::first_for_timestamp(1709251200250, Epoch::TWITTER).unwrap();
+        assert_eq!(
+            id.to_rfc3339_with_epoch(Epoch::TWITTER),
+            "2024-03-01T00:00:00.250Z"
+        );
+
+        let ist = FixedOffset::east_opt(5 * 3600 + 1800).unwrap();
+        let local = id.datetime_in(Epoch::TWITTER, &ist);
+        assert_eq!(local.to_rfc3339(), "2024-03-01T05:30:00.250+05:30");
+        assert_eq!(local, id.datetime_with_epoch(Epoch::TWITTER));
     }
 
     #[test]
@@ -288,81 +886,6 @@ mod tests {
         assert_eq!(format!("{}", id), "987654321098765432");
     }
 
-    #[test]
-    fn test_invalid_machine_id() {
-        let result = SnowflakeGenerator::with_epoch(MAX_MACHINE_ID + 1, SNOWFLAKE_ID_EPOCH);
-        assert!(result.is_err());
-    }
-
-    #[test]
-    fn test_custom_epoch() {
-        // Use Jan 1, 2024 as custom epoch
-        let custom_epoch = 1704067200000i64;
-        let generator = SnowflakeGenerator::with_epoch(5, custom_epoch).unwrap();
-
-        assert_eq!(generator.epoch(), custom_epoch);
-
-        let id = generator.next_id(|_| thread::yield_now());
-
-        // Verify machine ID is correct
-        assert_eq!(id.machine_id(), 5);
-
-        // Verify timestamp() returns offset (not Unix timestamp)
-        let offset = id.timestamp();
-        assert!(offset > 0);
-        // Offset should be reasonable (much less than MAX_TIMESTAMP_MS ~= 69 years)
-        assert!(offset < MAX_TIMESTAMP_MS);
-
-        // Verify timestamp_with_epoch() returns actual Unix timestamp
-        let timestamp = id.timestamp_with_epoch(custom_epoch);
-        assert!(timestamp >= custom_epoch);
-        // Verify it's not too far in the future (less than ~69 years from custom epoch)
-        assert!(timestamp < custom_epoch + MAX_TIMESTAMP_MS);
-
-        // Verify ID is positive
-        assert!(id.id() > 0);
-    }
-
-    #[test]
-    fn test_custom_epoch_vs_default() {
-        let custom_epoch = 1704067200000i64; // Jan 1, 2024
-        let generator_custom = SnowflakeGenerator::with_epoch(1, custom_epoch).unwrap();
-        let generator_default = SnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
-
-        let id_custom = generator_custom.next_id(|_| thread::yield_now());
-        let id_default = generator_default.next_id(|_| thread::yield_now());
-
-        // Both should be positive
-        assert!(id_custom.id() > 0);
-        assert!(id_default.id() > 0);
-
-        // Timestamps extracted with correct epochs should be recent
-        let ts_custom = id_custom.timestamp_with_epoch(custom_epoch);
-        let ts_default = id_default.timestamp_with_epoch(SNOWFLAKE_ID_EPOCH);
-
-        assert!(ts_custom >= custom_epoch);
-        assert!(ts_default >= SNOWFLAKE_ID_EPOCH);
-    }
-
-    #[test]
-    fn test_custom_epoch_uniqueness() {
-        let custom_epoch = 1704067200000i64;
-        let generator = SnowflakeGenerator::with_epoch(3, custom_epoch).unwrap();
-
-        let id1 = generator.next_id(|_| thread::yield_now());
-        let id2 = generator.next_id(|_| thread::yield_now());
-        let id3 = generator.next_id(|_| thread::yield_now());
-
-        // All IDs should be unique
-        assert_ne!(id1.id(), id2.id());
-        assert_ne!(id2.id(), id3.id());
-        assert_ne!(id1.id(), id3.id());
-
-        // IDs should be monotonically increasing
-        assert!(id1.id() < id2.id());
-        assert!(id2.id() < id3.id());
-    }
-
     #[test]
     fn test_from_str_rejects_negative() {
         let result = SnowflakeId::from_str("-123");
@@ -382,13 +905,41 @@ mod tests {
         assert!(result.is_err());
 
         match result {
-            Err(SnowflakeError::InvalidId(msg)) => {
-                assert!(msg.contains("Failed to parse"));
+            Err(SnowflakeError::Unparseable { input, reason }) => {
+                assert_eq!(input, "not_a_number");
+                assert!(reason.contains("invalid digit"));
             }
-            _ => panic!("Expected InvalidId error"),
+            _ => panic!("Expected Unparseable error"),
         }
     }
 
+    #[test]
+    fn test_standard_conversions() {
+        let id = SnowflakeId::new(175928847299117063).unwrap();
+        assert_eq!(SnowflakeId::try_from(175928847299117063u64).unwrap(), id);
+        assert_eq!(u64::from(id), 175928847299117063);
+        assert!(SnowflakeId::try_from(u64::MAX).is_err());
+        assert_eq!(
+            SnowflakeId::try_from(i64::MAX as u64).unwrap().id(),
+            i64::MAX
+        );
+
+        assert_eq!(SnowflakeId::try_from("175928847299117063").unwrap(), id);
+        assert!(SnowflakeId::try_from("-1").is_err());
+
+        let non_zero = NonZeroI64::try_from(id).unwrap();
+        assert_eq!(SnowflakeId::try_from(non_zero).unwrap(), id);
+        assert!(SnowflakeId::try_from(NonZeroI64::new(-5).unwrap()).is_err());
+        assert!(NonZeroI64::try_from(SnowflakeId::new(0).unwrap()).is_err());
+
+        fn round_trip<T: TryFrom<SnowflakeId> + TryInto<SnowflakeId>>(id: SnowflakeId) -> bool {
+            T::try_from(id).ok().and_then(|value| value.try_into().ok()) == Some(id)
+        }
+        assert!(round_trip::<u64>(id));
+        assert!(round_trip::<i64>(id));
+        assert!(round_trip::<NonZeroI64>(id));
+    }
+
     #[test]
     fn test_from_str_accepts_valid() {
         let result = SnowflakeId::from_str("123456789012345678");
@@ -437,45 +988,117 @@ mod tests {
         assert_eq!(id.id(), 123456789012345678);
     }
 
-    #[cfg(feature = "tokio")]
-    mod async_tests {
-        use super::*;
-        use crate::generator::SnowflakeOperation;
+    #[test]
+    fn test_datetime_conversions() {
+        let generator = SnowflakeGenerator::new(1).unwrap();
+        let id = generator.next_id(|_| thread::yield_now());
+        let millis = id.timestamp_with_epoch(SNOWFLAKE_ID_EPOCH);
+
+        let datetime = id.datetime_with_epoch(Epoch::TWITTER);
+        assert_eq!(datetime.timestamp_millis(), millis);
+        assert!((Utc::now() - datetime).num_seconds() < 5);
+
+        let system_time = id.system_time_with_epoch(SNOWFLAKE_ID_EPOCH);
+        let since_unix = system_time.duration_since(UNIX_EPOCH).unwrap();
+        assert_eq!(since_unix.as_millis() as i64, millis);
+
+        // Epochs before 1970 still produce the right instant
+        let early = SnowflakeId::new(5 << TIMESTAMP_SHIFT).unwrap();
+        let before_unix = UNIX_EPOCH
+            .duration_since(early.system_time_with_epoch(-10))
+            .unwrap();
+        assert_eq!(before_unix, Duration::from_millis(5));
+    }
 
-        #[tokio::test]
-        async fn test_async_generate() {
-            let generator = AsyncSnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
-            let id1 = generator.next_id().await;
-            let id2 = generator.next_id().await;
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_offset_datetime() {
+        let id = SnowflakeId::new(175928847299117063).unwrap();
+        let datetime = id.offset_datetime_with_epoch(Epoch::DISCORD);
+        assert_eq!(datetime.unix_timestamp_nanos(), 1462015105796 * 1_000_000);
+    }
 
-            assert_ne!(id1, id2);
-            assert!(id1.id() < id2.id());
-        }
+    #[test]
+    fn test_timestamp_bounds() {
+        let ts = SNOWFLAKE_ID_EPOCH + 1_000;
+        let first = SnowflakeId::first_for_timestamp(ts, SNOWFLAKE_ID_EPOCH).unwrap();
+        let last = SnowflakeId::last_for_timestamp(ts, SNOWFLAKE_ID_EPOCH).unwrap();
+
+        assert_eq!(first.timestamp(), 1_000);
+        assert_eq!((first.machine_id(), first.sequence()), (0, 0));
+        assert_eq!(last.timestamp(), 1_000);
+        assert_eq!(last.machine_id(), MAX_MACHINE_ID);
+        assert_eq!(last.sequence(), MAX_SEQUENCE);
+        assert_eq!(
+            SnowflakeId::first_for_timestamp(ts + 1, SNOWFLAKE_ID_EPOCH)
+                .unwrap()
+                .id(),
+            last.id() + 1
+        );
+
+        assert!(
+            SnowflakeId::first_for_timestamp(SNOWFLAKE_ID_EPOCH - 1, SNOWFLAKE_ID_EPOCH).is_err()
+        );
+        assert!(SnowflakeId::last_for_timestamp(i64::MAX, SNOWFLAKE_ID_EPOCH).is_err());
+        assert!(SnowflakeId::first_for_timestamp(i64::MIN, 1).is_err());
+    }
+
+    #[test]
+    fn test_range_for_contains_generated_ids() {
+        let generator = SnowflakeGenerator::new(17).unwrap();
+        let start = Utc::now().timestamp_millis();
+        let id = generator.next_id(|_| thread::yield_now());
+        let end = Utc::now().timestamp_millis() + 1;
 
-        #[tokio::test]
-        async fn test_async_generate_bulk() {
-            let generator = AsyncSnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
-            let ids = generator.next_id_bulk(100).await;
+        let bounds = SnowflakeId::range_for(start..end, SNOWFLAKE_ID_EPOCH).unwrap();
+        assert!(bounds.contains(&id));
 
-            assert_eq!(ids.len(), 100);
+        let before = SnowflakeId::range_for(start - 10..start, SNOWFLAKE_ID_EPOCH).unwrap();
+        assert!(!before.contains(&id));
 
-            for i in 1..ids.len() {
-                assert!(ids[i - 1].id() < ids[i].id());
-            }
-        }
+        assert!(SnowflakeId::range_for(start..start, SNOWFLAKE_ID_EPOCH).is_err());
+        let Err(err) = SnowflakeId::range_for(end..start, SNOWFLAKE_ID_EPOCH) else {
+            panic!("inverted range accepted");
+        };
+        assert!(err.to_string().contains("inverted"));
+    }
 
-        #[tokio::test]
-        async fn test_async_try_next_id() {
-            let generator = AsyncSnowflakeGenerator::with_epoch(1, SNOWFLAKE_ID_EPOCH).unwrap();
-            let result = generator.try_next_id().await.unwrap();
+    #[cfg(feature = "chrono-tz")]
+    #[test]
+    fn test_format_local() {
+        // 2024-07-01T12:34:56.789Z
+        let id = SnowflakeId::first_for_timestamp(1719837296789, Epoch::DISCORD).unwrap();
+        assert_eq!(
+            id.format_local(Epoch::DISCORD, chrono_tz::America::New_York),
+            "2024-07-01T08:34:56.789-04:00"
+        );
+        assert_eq!(
+            id.format_local(Epoch::DISCORD, chrono_tz::UTC),
+            "2024-07-01T12:34:56.789+00:00"
+        );
+    }
 
-            match result {
-                SnowflakeOperation::Ready(id) => {
-                    assert!(id.id() > 0);
-                    assert_eq!(id.machine_id(), 1);
-                }
-                SnowflakeOperation::Pending(_) => panic!("Expected Ready, got Pending"),
-            }
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    mod wasm_tests {
+        use super::*;
+        use wasm_bindgen_test::wasm_bindgen_test;
+
+        wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+        #[wasm_bindgen_test]
+        fn test_system_clock_reads_date_now() {
+            let before = js_sys::Date::now() as i64;
+            let now = SystemClock.now_millis();
+            assert!(now >= before && now - before < 1_000);
+        }
+
+        #[wasm_bindgen_test]
+        fn test_generate_in_browser() {
+            let generator = SnowflakeGenerator::new(1).unwrap();
+            let ids: Vec<SnowflakeId> = generator.iter().take(10_000).collect();
+            assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+            let minted = ids[0].timestamp_with_epoch(SNOWFLAKE_ID_EPOCH);
+            assert!((minted - SystemClock.now_millis()).abs() < 1_000);
         }
     }
 }
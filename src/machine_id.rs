@@ -0,0 +1,232 @@
+//! Deterministic machine ID assignment from hostnames.
+//!
+//! Hashing hostnames avoids a coordination service, but with 1024 machine
+//! IDs collisions become likely well before a fleet reaches that size.
+//! [`plan`] computes the assignment for a whole fleet up front, so a
+//! collision can be caught in a preflight check before it mints duplicate
+//! IDs in production.
+//!
+//! The hash is 64-bit FNV-1a over the lower-cased hostname. It is fixed and
+//! documented so that every process, language and crate version agrees on
+//! the mapping.
+
+use crate::defs::{MAX_DATACENTER_ID, MAX_MACHINE_ID, MAX_WORKER_ID, WORKER_ID_BITS};
+use crate::error::SnowflakeError;
+use std::collections::BTreeMap;
+use std::fmt;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Combines a datacenter and worker ID into a machine ID, as in Twitter's
+/// original layout where the datacenter takes the upper
+/// [`DATACENTER_ID_BITS`](crate::defs::DATACENTER_ID_BITS)
+///
+/// # Example
+/// ```
+/// use snowflake_id::machine_id::from_datacenter;
+///
+/// assert_eq!(from_datacenter(3, 7).unwrap(), (3 << 5) | 7);
+/// assert!(from_datacenter(32, 0).is_err());
+/// ```
+pub fn from_datacenter(datacenter_id: u64, worker_id: u64) -> Result<u64, SnowflakeError> {
+    if datacenter_id > MAX_DATACENTER_ID {
+        return Err(SnowflakeError::InvalidDatacenterId(
+            datacenter_id,
+            MAX_DATACENTER_ID,
+        ));
+    }
+    if worker_id > MAX_WORKER_ID {
+        return Err(SnowflakeError::InvalidWorkerId(worker_id, MAX_WORKER_ID));
+    }
+    Ok((datacenter_id << WORKER_ID_BITS) | worker_id)
+}
+
+/// Maps `hostname` onto a machine ID in `0..=max_machine_id`
+///
+/// # Example
+/// ```
+/// use snowflake_id::machine_id::hash_hostname;
+/// use snowflake_id::MAX_MACHINE_ID;
+///
+/// let id = hash_hostname("web-01.example.com", MAX_MACHINE_ID);
+/// assert_eq!(id, hash_hostname("WEB-01.example.com", MAX_MACHINE_ID));
+/// ```
+pub fn hash_hostname(hostname: &str, max_machine_id: u64) -> u64 {
+    let hash = hostname
+        .bytes()
+        .map(|byte| byte.to_ascii_lowercase())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        });
+    match max_machine_id.checked_add(1) {
+        Some(range) => hash % range,
+        None => hash,
+    }
+}
+
+/// Hostnames that hash to the same machine ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    pub machine_id: u64,
+    pub hostnames: Vec<String>,
+}
+
+/// Machine ID assignments for a fleet, see [`plan`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollisionReport {
+    /// Every hostname with its machine ID, in input order
+    pub assignments: Vec<(String, u64)>,
+    /// Machine IDs claimed by more than one hostname, in ascending order
+    pub collisions: Vec<Collision>,
+}
+
+impl CollisionReport {
+    /// Returns true if every hostname received a distinct machine ID
+    pub fn is_collision_free(&self) -> bool {
+        self.collisions.is_empty()
+    }
+
+    /// Returns the machine ID assigned to `hostname`, if it was planned
+    pub fn machine_id_for(&self, hostname: &str) -> Option<u64> {
+        self.assignments
+            .iter()
+            .find(|(host, _)| host.eq_ignore_ascii_case(hostname))
+            .map(|(_, machine_id)| *machine_id)
+    }
+}
+
+impl fmt::Display for CollisionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} hosts, {} collisions",
+            self.assignments.len(),
+            self.collisions.len()
+        )?;
+        for collision in &self.collisions {
+            write!(
+                f,
+                "\nmachine_id {}: {}",
+                collision.machine_id,
+                collision.hostnames.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes machine ID assignments for the default layout and reports any
+/// collisions
+///
+/// A hostname listed twice is reported as colliding with itself, since two
+/// processes on one host would share a machine ID.
+///
+/// # Example
+/// ```
+/// use snowflake_id::machine_id;
+///
+/// let fleet: Vec<String> = (0..8).map(|i| format!("web-{:02}", i)).collect();
+/// let report = machine_id::plan(&fleet);
+/// assert_eq!(report.assignments.len(), 8);
+/// println!("{}", report);
+/// ```
+pub fn plan(fleet_hostnames: &[String]) -> CollisionReport {
+    plan_with_max(fleet_hostnames, MAX_MACHINE_ID)
+}
+
+/// Like [`plan`], for layouts with a different machine ID range
+pub fn plan_with_max(fleet_hostnames: &[String], max_machine_id: u64) -> CollisionReport {
+    let assignments: Vec<(String, u64)> = fleet_hostnames
+        .iter()
+        .map(|hostname| (hostname.clone(), hash_hostname(hostname, max_machine_id)))
+        .collect();
+
+    let mut by_machine_id: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+    for (hostname, machine_id) in &assignments {
+        by_machine_id
+            .entry(*machine_id)
+            .or_default()
+            .push(hostname.clone());
+    }
+
+    let collisions = by_machine_id
+        .into_iter()
+        .filter(|(_, hostnames)| hostnames.len() > 1)
+        .map(|(machine_id, hostnames)| Collision {
+            machine_id,
+            hostnames,
+        })
+        .collect();
+
+    CollisionReport {
+        assignments,
+        collisions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Epoch;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+    use crate::DATACENTER_ID_BITS;
+    use crate::MACHINE_ID_BITS;
+
+    #[test]
+    fn test_datacenter_layout() {
+        let generator = SnowflakeGenerator::with_datacenter(31, 5, Epoch::TWITTER).unwrap();
+        let id = generator.generate().unwrap();
+        assert_eq!(id.datacenter_id(), 31);
+        assert_eq!(id.worker_id(), 5);
+        assert_eq!(id.machine_id(), (31 << WORKER_ID_BITS) | 5);
+        assert_eq!(DATACENTER_ID_BITS + WORKER_ID_BITS, MACHINE_ID_BITS);
+
+        // Matches IDs minted by Twitter's reference implementation
+        let twitter = SnowflakeId::from_component_parts(1, (2 << 5) | 9, 0);
+        assert_eq!((twitter.datacenter_id(), twitter.worker_id()), (2, 9));
+
+        assert!(matches!(
+            SnowflakeGenerator::with_datacenter(MAX_DATACENTER_ID + 1, 0, Epoch::TWITTER),
+            Err(SnowflakeError::InvalidDatacenterId(32, 31))
+        ));
+        assert!(matches!(
+            SnowflakeGenerator::with_datacenter(0, MAX_WORKER_ID + 1, Epoch::TWITTER),
+            Err(SnowflakeError::InvalidWorkerId(32, 31))
+        ));
+    }
+
+    #[test]
+    fn test_machine_id_plan() {
+        // FNV-1a is fixed, so assignments are stable across builds
+        assert_eq!(hash_hostname("", u64::MAX), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash_hostname("a", u64::MAX), 0xaf63_dc4c_8601_ec8c);
+        assert!(hash_hostname("web-01", MAX_MACHINE_ID) <= MAX_MACHINE_ID);
+
+        let fleet: Vec<String> = (0..4).map(|i| format!("web-{:02}", i)).collect();
+        let report = plan_with_max(&fleet, 1 << 20);
+        assert!(report.is_collision_free());
+        assert_eq!(
+            report.machine_id_for("WEB-02"),
+            Some(hash_hostname("web-02", 1 << 20))
+        );
+        assert_eq!(report.machine_id_for("db-01"), None);
+
+        // A single machine ID forces every host to collide
+        let report = plan_with_max(&fleet, 0);
+        assert_eq!(report.collisions.len(), 1);
+        assert_eq!(report.collisions[0].hostnames, fleet);
+        assert!(report.to_string().starts_with("4 hosts, 1 collisions"));
+
+        let duplicated = vec!["api-1".to_string(), "api-1".to_string()];
+        assert!(!plan(&duplicated).is_collision_free());
+
+        // With 2000 hosts and 1024 IDs, the pigeonhole guarantees collisions
+        let fleet: Vec<String> = (0..2000).map(|i| format!("node-{}", i)).collect();
+        let report = plan(&fleet);
+        assert!(!report.is_collision_free());
+        let colliding: usize = report.collisions.iter().map(|c| c.hostnames.len()).sum();
+        assert!(colliding >= 2000 - 1024);
+    }
+}
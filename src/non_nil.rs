@@ -0,0 +1,120 @@
+//! An ID that can't be [`SnowflakeId::ZERO`], for dense optional storage.
+//!
+//! [`NonNilSnowflakeId`] is backed by a [`NonZeroI64`], so the compiler uses
+//! the zero bit pattern as `None` and `Option<NonNilSnowflakeId>` takes 8
+//! bytes rather than 16. Indexes holding billions of optional IDs halve their
+//! footprint compared with `Option<SnowflakeId>`.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::{NonNilSnowflakeId, SnowflakeId};
+//!
+//! assert_eq!(std::mem::size_of::<Option<NonNilSnowflakeId>>(), 8);
+//!
+//! let id = SnowflakeId::new(42).unwrap();
+//! let slot = NonNilSnowflakeId::new(id);
+//! assert_eq!(slot.map(NonNilSnowflakeId::get), Some(id));
+//! assert_eq!(NonNilSnowflakeId::new(SnowflakeId::ZERO), None);
+//! ```
+
+use crate::error::SnowflakeError;
+use crate::SnowflakeId;
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::num::NonZeroI64;
+use std::str::FromStr;
+
+/// A [`SnowflakeId`] other than [`SnowflakeId::ZERO`], see the
+/// [module docs](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NonNilSnowflakeId(NonZeroI64);
+
+const _: () = assert!(std::mem::size_of::<Option<NonNilSnowflakeId>>() == 8);
+
+impl NonNilSnowflakeId {
+    /// Returns `None` for the nil ID
+    pub const fn new(id: SnowflakeId) -> Option<Self> {
+        match NonZeroI64::new(id.id()) {
+            Some(value) => Some(NonNilSnowflakeId(value)),
+            None => None,
+        }
+    }
+
+    pub const fn get(self) -> SnowflakeId {
+        SnowflakeId::new_unchecked(self.0.get())
+    }
+}
+
+impl From<NonNilSnowflakeId> for SnowflakeId {
+    fn from(id: NonNilSnowflakeId) -> Self {
+        id.get()
+    }
+}
+
+impl TryFrom<SnowflakeId> for NonNilSnowflakeId {
+    type Error = SnowflakeError;
+
+    fn try_from(id: SnowflakeId) -> Result<Self, Self::Error> {
+        NonNilSnowflakeId::new(id)
+            .ok_or_else(|| SnowflakeError::InvalidId("Snowflake ID is zero".to_string()))
+    }
+}
+
+impl fmt::Display for NonNilSnowflakeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl FromStr for NonNilSnowflakeId {
+    type Err = SnowflakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<SnowflakeId>()?.try_into()
+    }
+}
+
+impl Serialize for NonNilSnowflakeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for NonNilSnowflakeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SnowflakeId::deserialize(deserializer)?
+            .try_into()
+            .map_err(::serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+
+    #[test]
+    fn test_nil_and_non_nil_ids() {
+        assert!(SnowflakeId::ZERO.is_nil());
+        assert_eq!("0".parse::<SnowflakeId>().unwrap(), SnowflakeId::ZERO);
+
+        let generator = SnowflakeGenerator::new(0).unwrap();
+        let id = generator.generate().unwrap();
+        assert!(!id.is_nil());
+
+        let non_nil = NonNilSnowflakeId::new(id).unwrap();
+        assert_eq!(SnowflakeId::from(non_nil), id);
+        assert_eq!(non_nil.to_string(), id.to_string());
+        assert!(NonNilSnowflakeId::try_from(SnowflakeId::ZERO).is_err());
+        assert!("0".parse::<NonNilSnowflakeId>().is_err());
+        assert!("-3".parse::<NonNilSnowflakeId>().is_err());
+
+        let slots = [Some(non_nil), None];
+        let json = serde_json::to_string(&slots).unwrap();
+        assert_eq!(
+            serde_json::from_str::<[Option<NonNilSnowflakeId>; 2]>(&json).unwrap(),
+            slots
+        );
+        assert!(serde_json::from_str::<NonNilSnowflakeId>("0").is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! Reversible obfuscation for public-facing IDs.
+//!
+//! Snowflake IDs reveal when they were minted, and consecutive IDs reveal
+//! how many were minted in between. [`SnowflakeId::obfuscate`] scrambles an
+//! ID with a keyed permutation of the 63 non-negative bits, in the style of
+//! Knuth's multiplicative hashing: XOR and odd-multiplier rounds mod 2^63
+//! with xorshifts in between, every step invertible. The result is still a
+//! positive `i64`, so it fits the same columns and wire formats, and only
+//! holders of the key can map it back.
+//!
+//! This hides ordering and volume from casual observers; it is not
+//! encryption and shouldn't guard anything secret.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::obfuscate::ObfuscationKey;
+//! use snowflake_id::SnowflakeGenerator;
+//!
+//! let key = ObfuscationKey::new(0x5eed_1234);
+//! let id = SnowflakeGenerator::new(1).unwrap().generate().unwrap();
+//! let public = id.obfuscate(&key);
+//! assert_eq!(public.deobfuscate(&key), id);
+//! ```
+
+use crate::error::SnowflakeError;
+use crate::routing::mix64;
+use crate::SnowflakeId;
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+const MASK: u64 = (1 << 63) - 1;
+const SHIFTS: [u32; 2] = [31, 29];
+
+/// Secret parameters of the permutation, derived from a seed
+#[derive(Clone, PartialEq, Eq)]
+pub struct ObfuscationKey {
+    multipliers: [u64; 2],
+    inverses: [u64; 2],
+    xors: [u64; 2],
+}
+
+impl ObfuscationKey {
+    /// Derives a key from `secret`. Keep the secret stable: IDs obfuscated
+    /// under one secret can only be recovered with the same secret.
+    pub fn new(secret: u64) -> Self {
+        let mut state = secret;
+        let mut next = || splitmix64(&mut state);
+        let multipliers = [next() | 1, next() | 1];
+        ObfuscationKey {
+            multipliers,
+            inverses: multipliers.map(inverse),
+            xors: [next() & MASK, next() & MASK],
+        }
+    }
+
+    fn permute(&self, value: u64) -> u64 {
+        let mut x = value ^ self.xors[0];
+        x = x.wrapping_mul(self.multipliers[0]) & MASK;
+        x ^= x >> SHIFTS[0];
+        x = x.wrapping_mul(self.multipliers[1]) & MASK;
+        x ^= x >> SHIFTS[1];
+        x ^ self.xors[1]
+    }
+
+    fn unpermute(&self, value: u64) -> u64 {
+        let mut x = value ^ self.xors[1];
+        x = unshift(x, SHIFTS[1]);
+        x = x.wrapping_mul(self.inverses[1]) & MASK;
+        x = unshift(x, SHIFTS[0]);
+        x = x.wrapping_mul(self.inverses[0]) & MASK;
+        x ^ self.xors[0]
+    }
+}
+
+/// Leaves the parameters out, so keys don't end up in logs
+impl fmt::Debug for ObfuscationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ObfuscationKey { .. }")
+    }
+}
+
+/// A [`SnowflakeId`] scrambled with an [`ObfuscationKey`], see the
+/// [module docs](self)
+///
+/// Serializes like a [`SnowflakeId`]. Deliberately not ordered: the order of
+/// obfuscated IDs means nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObfuscatedSnowflakeId(i64);
+
+impl ObfuscatedSnowflakeId {
+    /// Wraps an obfuscated value received from a client, rejecting negative
+    /// values
+    pub fn new(value: i64) -> Result<Self, SnowflakeError> {
+        SnowflakeId::new(value).map(|id| ObfuscatedSnowflakeId(id.id()))
+    }
+
+    pub fn value(&self) -> i64 {
+        self.0
+    }
+
+    /// Recovers the original ID. With the wrong key this returns some other,
+    /// meaningless ID rather than an error.
+    pub fn deobfuscate(&self, key: &ObfuscationKey) -> SnowflakeId {
+        SnowflakeId::new_unchecked(key.unpermute(self.0 as u64) as i64)
+    }
+}
+
+impl SnowflakeId {
+    /// Scrambles this ID for public display, see [`crate::obfuscate`]
+    pub fn obfuscate(&self, key: &ObfuscationKey) -> ObfuscatedSnowflakeId {
+        ObfuscatedSnowflakeId(key.permute(self.id() as u64) as i64)
+    }
+}
+
+impl fmt::Display for ObfuscatedSnowflakeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ObfuscatedSnowflakeId {
+    type Err = SnowflakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<SnowflakeId>()
+            .map(|id| ObfuscatedSnowflakeId(id.id()))
+    }
+}
+
+impl Serialize for ObfuscatedSnowflakeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SnowflakeId::new_unchecked(self.0).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ObfuscatedSnowflakeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SnowflakeId::deserialize(deserializer).map(|id| ObfuscatedSnowflakeId(id.id()))
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    mix64(*state)
+}
+
+/// Returns the inverse of odd `m` modulo 2^64, which is also its inverse
+/// modulo 2^63
+fn inverse(m: u64) -> u64 {
+    // Each Newton step doubles the number of correct low bits, from 3
+    let mut inv = m;
+    for _ in 0..5 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(m.wrapping_mul(inv)));
+    }
+    inv
+}
+
+/// Inverts `x ^= x >> shift` on 63-bit values
+fn unshift(value: u64, shift: u32) -> u64 {
+    let mut x = value;
+    for _ in 0..63u32.div_ceil(shift) {
+        x = value ^ (x >> shift);
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+
+    #[test]
+    fn test_obfuscation() {
+        use std::collections::HashSet;
+
+        let key = ObfuscationKey::new(42);
+        let generator = SnowflakeGenerator::new(1).unwrap();
+        let ids = generator.generate_bulk(1_000).unwrap();
+        let public: Vec<_> = ids.iter().map(|id| id.obfuscate(&key)).collect();
+        assert!(public.iter().all(|id| id.value() >= 0));
+        assert_eq!(public.iter().collect::<HashSet<_>>().len(), ids.len());
+        for (id, obfuscated) in ids.iter().zip(&public) {
+            assert_eq!(obfuscated.deobfuscate(&key), *id);
+        }
+        // Consecutive IDs no longer look consecutive
+        assert!(public
+            .windows(2)
+            .any(|pair| pair[1].value() < pair[0].value()));
+
+        for raw in [0, 1, i64::MAX] {
+            let id = SnowflakeId::new(raw).unwrap();
+            assert_eq!(id.obfuscate(&key).deobfuscate(&key), id);
+        }
+        let other = ObfuscationKey::new(43);
+        assert_ne!(ids[0].obfuscate(&other), public[0]);
+        assert_ne!(public[0].deobfuscate(&other), ids[0]);
+        assert_eq!(format!("{:?}", key), "ObfuscationKey { .. }");
+
+        let json = serde_json::to_string(&public[0]).unwrap();
+        assert_eq!(json, format!("\"{}\"", public[0].value()));
+        assert_eq!(
+            serde_json::from_str::<ObfuscatedSnowflakeId>(&json).unwrap(),
+            public[0]
+        );
+        assert_eq!(
+            public[0]
+                .to_string()
+                .parse::<ObfuscatedSnowflakeId>()
+                .unwrap(),
+            public[0]
+        );
+        assert!(ObfuscatedSnowflakeId::new(-1).is_err());
+    }
+}
@@ -0,0 +1,431 @@
+//! Pre-issued ID pools for offline-first clients.
+//!
+//! A client without network access, or without a clock the server trusts,
+//! can't run its own generator. Instead a server reserves a machine ID for
+//! offline use and hands each client an [`OfflinePool`]: a slice of that
+//! machine's future ID space the client draws from locally. The server keeps
+//! its own copy of each pool it issues, and when the client syncs, checks the
+//! IDs it sends back against that copy with [`OfflinePool::validate`].
+//!
+//! The reserved machine ID must not be used by any online generator, and all
+//! pools for it must come from a single [`OfflineIssuer`], which never hands
+//! out the same tick twice.
+
+use crate::builder::GeneratorBuilder;
+use crate::clock::{Clock, SystemClock};
+use crate::defs::SNOWFLAKE_ID_EPOCH;
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::generator::GeneratorCore;
+use crate::snowflake::Snowflake;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A batch of IDs reserved for one offline client, see [`OfflineIssuer::issue`]
+///
+/// The pool is serializable so it can be sent to the client and persisted
+/// there; it remembers how many IDs have been drawn. Deserializing rejects
+/// pools whose cursor is past their end or whose IDs don't fit the layout.
+///
+/// # Example
+/// ```
+/// use snowflake_id::offline::OfflineIssuer;
+/// use snowflake_id::SnowflakeId;
+///
+/// let issuer = OfflineIssuer::<SnowflakeId>::new(1023).unwrap();
+/// let issued = issuer.issue(100).unwrap();
+/// let mut pool = issued.clone();
+///
+/// // On the client, no clock required
+/// let ids: Vec<SnowflakeId> = (0..3).filter_map(|_| pool.next_id()).collect();
+/// assert_eq!(pool.remaining(), 97);
+///
+/// // Back on the server, against its own copy
+/// assert!(issued.validate(&ids).is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    bound(serialize = "", deserialize = "S: Snowflake"),
+    try_from = "PoolFields"
+)]
+pub struct OfflinePool<S> {
+    machine_id: u64,
+    epoch: i64,
+    /// Length of one timestamp unit in microseconds
+    tick_micros: i64,
+    first_offset: u64,
+    len: u64,
+    /// Index of the next ID to hand out
+    cursor: u64,
+    #[serde(skip)]
+    _marker: PhantomData<S>,
+}
+
+impl<S: Snowflake> OfflinePool<S> {
+    pub fn machine_id(&self) -> u64 {
+        self.machine_id
+    }
+
+    /// Returns the epoch the pool's IDs are relative to
+    pub fn epoch(&self) -> i64 {
+        self.epoch
+    }
+
+    /// Returns the length of one unit of the pool's ID timestamps
+    pub fn tick(&self) -> Duration {
+        Duration::from_micros(self.tick_micros as u64)
+    }
+
+    /// Returns how many IDs the pool was issued with
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns how many IDs are still available to draw
+    pub fn remaining(&self) -> u64 {
+        self.len.saturating_sub(self.cursor)
+    }
+
+    /// Returns the smallest ID in the pool
+    pub fn first(&self) -> Option<S> {
+        (self.len > 0).then(|| self.id_at(0))
+    }
+
+    /// Returns the largest ID in the pool
+    pub fn last(&self) -> Option<S> {
+        self.len.checked_sub(1).map(|index| self.id_at(index))
+    }
+
+    /// Returns true if `id` was issued as part of this pool, whether or not it
+    /// has been drawn yet
+    pub fn contains(&self, id: &S) -> bool {
+        if id.machine_id() != self.machine_id || id.timestamp() < self.first_offset {
+            return false;
+        }
+        let tick = id.timestamp() - self.first_offset;
+        tick.checked_mul(S::max_sequence() + 1)
+            .and_then(|index| index.checked_add(id.sequence()))
+            .is_some_and(|index| index < self.len)
+    }
+
+    /// Checks IDs returned by a client: every one must belong to this pool,
+    /// and none may appear twice
+    ///
+    /// Call it on the server's copy of the pool as [`OfflineIssuer::issue`]
+    /// returned it, never on one sent back by the client, which could have
+    /// been widened to cover any ID.
+    pub fn validate(&self, ids: &[S]) -> Result<(), SnowflakeError> {
+        let mut seen = HashSet::with_capacity(ids.len());
+        for id in ids {
+            if !self.contains(id) {
+                return Err(SnowflakeError::InvalidId(format!(
+                    "{} was not issued in this offline pool",
+                    id.id()
+                )));
+            }
+            if !seen.insert(id.id()) {
+                return Err(SnowflakeError::InvalidId(format!(
+                    "{} was returned more than once",
+                    id.id()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the next ID, or returns `None` once the pool is used up
+    pub fn next_id(&mut self) -> Option<S> {
+        if self.cursor >= self.len {
+            return None;
+        }
+        let id = self.id_at(self.cursor);
+        self.cursor += 1;
+        Some(id)
+    }
+
+    fn id_at(&self, index: u64) -> S {
+        let per_tick = S::max_sequence() + 1;
+        S::from_component_parts(
+            self.first_offset + index / per_tick,
+            self.machine_id,
+            index % per_tick,
+        )
+    }
+}
+
+/// The serialized form of an [`OfflinePool`], checked before it is trusted
+#[derive(Deserialize)]
+struct PoolFields {
+    machine_id: u64,
+    epoch: i64,
+    /// Pools persisted before the tick was recorded were all on millisecond
+    /// ticks
+    #[serde(default = "millisecond_tick")]
+    tick_micros: i64,
+    first_offset: u64,
+    len: u64,
+    cursor: u64,
+}
+
+fn millisecond_tick() -> i64 {
+    1_000
+}
+
+impl<S: Snowflake> TryFrom<PoolFields> for OfflinePool<S> {
+    type Error = String;
+
+    fn try_from(fields: PoolFields) -> Result<Self, String> {
+        if fields.cursor > fields.len {
+            return Err(format!(
+                "cursor {} is past the end of a pool of {}",
+                fields.cursor, fields.len
+            ));
+        }
+        if fields.machine_id > S::max_machine_id() {
+            return Err(format!("machine ID {} is out of range", fields.machine_id));
+        }
+        if fields.tick_micros <= 0 {
+            return Err(format!("tick of {}us is not positive", fields.tick_micros));
+        }
+        let last_tick = fields.len.saturating_sub(1) / (S::max_sequence() + 1);
+        if fields
+            .first_offset
+            .checked_add(last_tick)
+            .is_none_or(|last| last > S::max_timestamp() as u64)
+        {
+            return Err("pool extends past the layout's timestamp range".to_string());
+        }
+        Ok(OfflinePool {
+            machine_id: fields.machine_id,
+            epoch: fields.epoch,
+            tick_micros: fields.tick_micros,
+            first_offset: fields.first_offset,
+            len: fields.len,
+            cursor: fields.cursor,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// Issues non-overlapping [`OfflinePool`]s for a machine ID reserved for
+/// offline clients
+///
+/// Each pool starts at the current tick or just after the previous pool,
+/// whichever is later, so pools never overlap and IDs drawn from them sort
+/// after everything minted before they were issued.
+pub struct OfflineIssuer<S: Snowflake> {
+    core: GeneratorCore<S>,
+    /// First absolute tick not yet covered by an issued pool
+    next_tick: Mutex<i64>,
+}
+
+impl<S: Snowflake> OfflineIssuer<S> {
+    /// Creates an issuer for `reserved_machine_id` using the default epoch
+    pub fn new(reserved_machine_id: u64) -> Result<Self, SnowflakeError> {
+        Self::with_epoch(reserved_machine_id, SNOWFLAKE_ID_EPOCH)
+    }
+
+    /// Creates an issuer with a custom epoch
+    pub fn with_epoch(
+        reserved_machine_id: u64,
+        epoch: impl Into<Epoch>,
+    ) -> Result<Self, SnowflakeError> {
+        Self::with_clock(reserved_machine_id, epoch, SystemClock)
+    }
+
+    /// Creates an issuer that reads time from `clock`
+    pub fn with_clock(
+        reserved_machine_id: u64,
+        epoch: impl Into<Epoch>,
+        clock: impl Clock + 'static,
+    ) -> Result<Self, SnowflakeError> {
+        Ok(OfflineIssuer {
+            core: GeneratorCore::new(reserved_machine_id, epoch.into(), Arc::new(clock))?,
+            next_tick: Mutex::new(i64::MIN),
+        })
+    }
+
+    /// Creates an issuer with the machine ID, epoch, tick and clock of
+    /// `builder`; its other settings don't apply to issuing pools
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::offline::OfflineIssuer;
+    /// use snowflake_id::{GeneratorBuilder, SnowflakeId};
+    /// use std::time::Duration;
+    ///
+    /// let builder = GeneratorBuilder::new(1023).tick(Duration::from_millis(10));
+    /// let issuer = OfflineIssuer::<SnowflakeId>::from_builder(builder).unwrap();
+    /// assert_eq!(issuer.issue(10).unwrap().tick(), Duration::from_millis(10));
+    /// ```
+    pub fn from_builder(builder: GeneratorBuilder<S>) -> Result<Self, SnowflakeError> {
+        Ok(OfflineIssuer {
+            core: builder.core()?,
+            next_tick: Mutex::new(i64::MIN),
+        })
+    }
+
+    /// Skips every tick up to and including the one holding `millis`, e.g.
+    /// the [`Self::reserved_until`] value saved by a previous issuer for the
+    /// same machine ID
+    pub fn resume_after(self, millis: i64) -> Self {
+        let last = self
+            .core
+            .tick_at(millis.saturating_mul(1_000).saturating_add(999));
+        *self.next_tick.lock().unwrap_or_else(|e| e.into_inner()) = last.saturating_add(1);
+        self
+    }
+
+    pub fn machine_id(&self) -> u64 {
+        self.core.machine_id
+    }
+
+    /// Returns the end of the last tick covered by an issued pool, as its
+    /// last millisecond since the Unix epoch. Persist it across restarts so a
+    /// new issuer doesn't hand out ticks a long-running pool still holds.
+    pub fn reserved_until(&self) -> Option<i64> {
+        let next_tick = *self.next_tick.lock().unwrap_or_else(|e| e.into_inner());
+        (next_tick != i64::MIN)
+            .then(|| (self.core.tick_start_micros(next_tick) - 1).div_euclid(1_000))
+    }
+
+    /// Reserves a pool of `count` IDs
+    ///
+    /// Returns [`SnowflakeError::TimestampOverflow`] if the pool would extend
+    /// past the layout's timestamp range.
+    pub fn issue(&self, count: u64) -> Result<OfflinePool<S>, SnowflakeError> {
+        let per_tick = S::max_sequence() + 1;
+        let ticks = count.div_ceil(per_tick).max(1) as i64;
+
+        let mut next_tick = self.next_tick.lock().unwrap_or_else(|e| e.into_inner());
+        let start = self.core.current_timestamp().max(*next_tick);
+        let end = start
+            .checked_add(ticks - 1)
+            .ok_or(SnowflakeError::TimestampOverflow)?;
+
+        // Both ends must be representable
+        let first = self.core.compose(start, 0)?;
+        self.core.compose(end, 0)?;
+        *next_tick = end + 1;
+
+        Ok(OfflinePool {
+            machine_id: self.core.machine_id,
+            epoch: self.core.epoch,
+            tick_micros: self.core.tick_micros,
+            first_offset: first.timestamp(),
+            len: count,
+            cursor: 0,
+            _marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::manual_clock;
+    use crate::SnowflakeId;
+    use crate::MAX_SEQUENCE;
+
+    #[test]
+    fn test_offline_pool() {
+        let clock = manual_clock();
+        let issuer =
+            OfflineIssuer::<SnowflakeId>::with_clock(1023, Epoch::TWITTER, clock.clone()).unwrap();
+        assert!(OfflineIssuer::<SnowflakeId>::new(1024).is_err());
+
+        let count = MAX_SEQUENCE + 10;
+        let mut pool = issuer.issue(count).unwrap();
+        let issued = pool.clone();
+        let second = issuer.issue(5).unwrap();
+        assert_eq!(issuer.reserved_until(), Some(SNOWFLAKE_ID_EPOCH + 1_002));
+
+        // Pools are carved from consecutive ticks and never overlap
+        assert_eq!(pool.first().unwrap().timestamp(), 1_000);
+        assert_eq!(pool.last().unwrap().timestamp(), 1_001);
+        assert_eq!(second.first().unwrap().timestamp(), 1_002);
+        assert!(!pool.contains(&second.first().unwrap()));
+
+        // The client persists the pool between sessions
+        let json = serde_json::to_string(&pool).unwrap();
+        pool = serde_json::from_str::<OfflinePool<SnowflakeId>>(&json).unwrap();
+
+        let ids: Vec<SnowflakeId> = std::iter::from_fn(|| pool.next_id()).collect();
+        assert_eq!(ids.len() as u64, count);
+        assert_eq!(pool.remaining(), 0);
+        assert_eq!(pool.next_id(), None);
+        for i in 1..ids.len() {
+            assert!(ids[i - 1] < ids[i]);
+        }
+        assert!(issued.validate(&ids).is_ok());
+
+        assert!(issued.validate(&[ids[0], ids[0]]).is_err());
+        assert!(issued
+            .validate(&second.first().into_iter().collect::<Vec<_>>())
+            .is_err());
+        let foreign = SnowflakeId::from_component_parts(1_000, 1, 0);
+        assert!(issued.validate(&[foreign]).is_err());
+
+        // A restarted issuer picks up where the last one left off
+        let restarted =
+            OfflineIssuer::<SnowflakeId>::with_clock(1023, Epoch::TWITTER, clock.clone())
+                .unwrap()
+                .resume_after(SNOWFLAKE_ID_EPOCH + 1_002);
+        assert_eq!(
+            restarted.issue(1).unwrap().first().unwrap().timestamp(),
+            1_003
+        );
+
+        // Pools persisted before the tick was recorded are on milliseconds
+        let legacy = json.replace(",\"tick_micros\":1000", "");
+        assert_ne!(legacy, json);
+        let legacy = serde_json::from_str::<OfflinePool<SnowflakeId>>(&legacy).unwrap();
+        assert_eq!(legacy.tick(), Duration::from_millis(1));
+
+        // A tampered or corrupted copy is refused rather than underflowing
+        let overrun = json.replace("\"cursor\":0", &format!("\"cursor\":{}", count + 1));
+        assert_ne!(overrun, json);
+        assert!(serde_json::from_str::<OfflinePool<SnowflakeId>>(&overrun).is_err());
+        let widened = json.replace(
+            &format!("\"len\":{}", count),
+            "\"len\":18446744073709551615",
+        );
+        assert!(serde_json::from_str::<OfflinePool<SnowflakeId>>(&widened).is_err());
+    }
+
+    #[test]
+    fn test_offline_pool_custom_tick() {
+        use crate::builder::GeneratorBuilder;
+
+        let clock = manual_clock();
+        let builder = GeneratorBuilder::new(1023)
+            .clock(clock.clone())
+            .tick(Duration::from_millis(10));
+        let issuer = OfflineIssuer::<SnowflakeId>::from_builder(builder).unwrap();
+
+        let pool = issuer.issue(MAX_SEQUENCE + 2).unwrap();
+        assert_eq!(pool.tick(), Duration::from_millis(10));
+        assert_eq!(pool.first().unwrap().timestamp(), 100);
+        assert_eq!(pool.last().unwrap().timestamp(), 101);
+        // The end of the second 10ms tick
+        assert_eq!(issuer.reserved_until(), Some(SNOWFLAKE_ID_EPOCH + 1_019));
+
+        let builder = GeneratorBuilder::new(1023)
+            .clock(clock.clone())
+            .tick(Duration::from_millis(10));
+        let restarted = OfflineIssuer::<SnowflakeId>::from_builder(builder)
+            .unwrap()
+            .resume_after(issuer.reserved_until().unwrap());
+        assert_eq!(
+            restarted.issue(1).unwrap().first().unwrap().timestamp(),
+            102
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! Ordering IDs by creation time alone.
+//!
+//! Raw IDs order by timestamp, then machine ID, then sequence, so IDs minted
+//! in the same tick on different machines come out grouped by machine. When
+//! merging streams from several machines, comparing only the timestamps
+//! keeps same-tick IDs in the order they arrived instead.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::ordering::sort_by_creation;
+//! use snowflake_id::SnowflakeId;
+//!
+//! let a = SnowflakeId::from_component_parts(10, 9, 0);
+//! let b = SnowflakeId::from_component_parts(10, 1, 0);
+//! let c = SnowflakeId::from_component_parts(5, 9, 0);
+//!
+//! let mut ids = vec![a, b, c];
+//! sort_by_creation(&mut ids);
+//! assert_eq!(ids, [c, a, b]);
+//! ```
+
+use crate::snowflake::Snowflake;
+use crate::SnowflakeId;
+use std::cmp::Ordering;
+
+impl SnowflakeId {
+    /// Returns a sort key holding only the timestamp bits
+    pub const fn timestamp_ordering_key(&self) -> u64 {
+        self.timestamp() as u64
+    }
+
+    /// Compares creation times, ignoring machine ID and sequence
+    pub fn cmp_by_time(&self, other: &SnowflakeId) -> Ordering {
+        self.timestamp_ordering_key()
+            .cmp(&other.timestamp_ordering_key())
+    }
+}
+
+/// Sorts `ids` by creation time. The sort is stable, so IDs from the same
+/// tick keep their relative order.
+pub fn sort_by_creation<S: Snowflake>(ids: &mut [S]) {
+    ids.sort_by_key(|id| id.timestamp());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_creation_ordering() {
+        use std::cmp::Ordering;
+
+        let early = SnowflakeId::from_component_parts(5, 9, 7);
+        let late_high = SnowflakeId::from_component_parts(10, 9, 0);
+        let late_low = SnowflakeId::from_component_parts(10, 1, 3);
+
+        assert_eq!(late_high.cmp_by_time(&late_low), Ordering::Equal);
+        assert_eq!(early.cmp_by_time(&late_low), Ordering::Less);
+        assert_eq!(late_low.timestamp_ordering_key(), 10);
+
+        let mut ids = vec![late_high, early, late_low];
+        sort_by_creation(&mut ids);
+        assert_eq!(ids, [early, late_high, late_low]);
+        ids.sort();
+        assert_eq!(ids, [early, late_low, late_high]);
+    }
+}
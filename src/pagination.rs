@@ -0,0 +1,203 @@
+//! Keyset pagination with snowflake IDs as cursors.
+//!
+//! IDs grow with creation time, so "the page after this ID" is an indexed
+//! range scan instead of an `OFFSET`. A [`Cursor`] remembers the last ID a
+//! client saw and which way it is paging, and encodes to a short opaque
+//! base64url token for APIs. The token is only an encoding, not a signature:
+//! clients can craft their own.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::pagination::{Cursor, Direction};
+//! use snowflake_id::SnowflakeId;
+//!
+//! let last_seen = SnowflakeId::new(175928847299117063).unwrap();
+//! let token = Cursor::after(last_seen).encode();
+//!
+//! // Next request
+//! let cursor = Cursor::decode(&token).unwrap();
+//! assert_eq!(cursor.direction, Direction::After);
+//! // SELECT * FROM events WHERE id BETWEEN $1 AND $2 ORDER BY id ASC LIMIT 50
+//! let (low, high) = cursor.bounds().to_sql_bounds();
+//! assert_eq!(low, last_seen.id() + 1);
+//! assert_eq!(high, i64::MAX);
+//! ```
+
+use crate::error::SnowflakeError;
+use crate::range::SnowflakeRange;
+use crate::SnowflakeId;
+use std::fmt;
+use std::str::FromStr;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+/// One direction byte and eight ID bytes, unpadded
+const ENCODED_LEN: usize = 12;
+
+/// Which side of the cursor ID the next page lies on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Newer IDs, in ascending order
+    After,
+    /// Older IDs, in descending order
+    Before,
+}
+
+impl Direction {
+    /// Returns the `ORDER BY id` direction that pages away from the cursor
+    pub fn sql_order(&self) -> &'static str {
+        match self {
+            Direction::After => "ASC",
+            Direction::Before => "DESC",
+        }
+    }
+}
+
+/// Position in a listing, see the [module docs](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cursor {
+    pub id: SnowflakeId,
+    pub direction: Direction,
+}
+
+impl Cursor {
+    /// Pages forward from `id`, excluding it
+    pub fn after(id: SnowflakeId) -> Self {
+        Cursor {
+            id,
+            direction: Direction::After,
+        }
+    }
+
+    /// Pages backward from `id`, excluding it
+    pub fn before(id: SnowflakeId) -> Self {
+        Cursor {
+            id,
+            direction: Direction::Before,
+        }
+    }
+
+    /// Returns the IDs the next page may contain, empty if there can't be
+    /// any
+    pub fn bounds(&self) -> SnowflakeRange {
+        let (start, end) = match self.direction {
+            Direction::After => (self.id.id().checked_add(1), Some(i64::MAX)),
+            Direction::Before => (Some(0), self.id.id().checked_sub(1)),
+        };
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end => SnowflakeRange::new(
+                SnowflakeId::new_unchecked(start),
+                SnowflakeId::new_unchecked(end),
+            ),
+            _ => SnowflakeRange::new(
+                SnowflakeId::new_unchecked(i64::MAX),
+                SnowflakeId::new_unchecked(0),
+            ),
+        }
+    }
+
+    /// Encodes the cursor as a URL-safe token
+    pub fn encode(&self) -> String {
+        let mut bytes = [0u8; 9];
+        bytes[0] = match self.direction {
+            Direction::After => b'a',
+            Direction::Before => b'b',
+        };
+        bytes[1..].copy_from_slice(&self.id.to_be_bytes());
+
+        let mut token = String::with_capacity(ENCODED_LEN);
+        for chunk in bytes.chunks(3) {
+            let group = (chunk[0] as u32) << 16 | (chunk[1] as u32) << 8 | chunk[2] as u32;
+            for shift in [18, 12, 6, 0] {
+                token.push(ALPHABET[(group >> shift) as usize & 0x3f] as char);
+            }
+        }
+        token
+    }
+
+    /// Decodes a token from [`Cursor::encode`], failing with
+    /// [`SnowflakeError::InvalidId`] if it is malformed
+    ///
+    /// Tokens aren't signed, so a well-formed token for another ID decodes
+    /// fine. Check that the caller may see the rows it selects as usual.
+    pub fn decode(token: &str) -> Result<Self, SnowflakeError> {
+        let invalid = || SnowflakeError::InvalidId(format!("Invalid cursor: {:?}", token));
+        if token.len() != ENCODED_LEN {
+            return Err(invalid());
+        }
+
+        let mut bytes = [0u8; 9];
+        for (chunk, out) in token.as_bytes().chunks(4).zip(bytes.chunks_mut(3)) {
+            let mut group = 0u32;
+            for &c in chunk {
+                let value = ALPHABET.iter().position(|&a| a == c).ok_or_else(invalid)?;
+                group = group << 6 | value as u32;
+            }
+            out.copy_from_slice(&group.to_be_bytes()[1..]);
+        }
+
+        let direction = match bytes[0] {
+            b'a' => Direction::After,
+            b'b' => Direction::Before,
+            _ => return Err(invalid()),
+        };
+        let id =
+            SnowflakeId::from_be_bytes(bytes[1..].try_into().unwrap()).map_err(|_| invalid())?;
+        Ok(Cursor { id, direction })
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.encode())
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = SnowflakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::decode(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+
+    #[test]
+    fn test_pagination_cursor() {
+        let id = SnowflakeGenerator::new(1).unwrap().generate().unwrap();
+        for cursor in [Cursor::after(id), Cursor::before(id)] {
+            let token = cursor.encode();
+            assert_eq!(token.len(), 12);
+            assert!(token
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+            assert_eq!(Cursor::decode(&token).unwrap(), cursor);
+            assert_eq!(token.parse::<Cursor>().unwrap().to_string(), token);
+            assert!(!cursor.bounds().contains(id));
+        }
+
+        let after = Cursor::after(id).bounds();
+        assert_eq!(after.start.id(), id.id() + 1);
+        assert_eq!(Cursor::after(id).direction.sql_order(), "ASC");
+        let before = Cursor::before(id).bounds();
+        assert_eq!(before.to_sql_bounds(), (0, id.id() - 1));
+        assert_eq!(Direction::Before.sql_order(), "DESC");
+
+        assert!(Cursor::before(SnowflakeId::new(0).unwrap())
+            .bounds()
+            .is_empty());
+        assert!(Cursor::after(SnowflakeId::new(i64::MAX).unwrap())
+            .bounds()
+            .is_empty());
+
+        let token = Cursor::after(id).encode();
+        assert!(Cursor::decode(&token[1..]).is_err());
+        assert!(Cursor::decode("!!!!!!!!!!!!").is_err());
+        // A negative ID or unknown direction byte is rejected
+        assert!(Cursor::decode("Yf__________").is_err());
+        assert!(Cursor::decode("eAAAAAAAAAAA").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::epoch::Epoch;
+use crate::SnowflakeId;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt;
+
+/// The decoded fields of a snowflake ID, as returned by
+/// [`SnowflakeId::decompose`](crate::SnowflakeId::decompose).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SnowflakeParts {
+    /// Creation time, resolved against the epoch passed to `decompose`
+    pub datetime: DateTime<Utc>,
+    pub machine_id: u64,
+    pub sequence: u64,
+    /// Timestamp offset from the epoch as stored in the ID, in ticks of the
+    /// generator (milliseconds unless configured otherwise)
+    pub raw_offset: u64,
+}
+
+impl fmt::Display for SnowflakeParts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "datetime={} machine_id={} sequence={} raw_offset={}",
+            self.datetime.to_rfc3339_opts(SecondsFormat::Millis, true),
+            self.machine_id,
+            self.sequence,
+            self.raw_offset
+        )
+    }
+}
+
+/// Fields of many IDs decoded column by column, as returned by
+/// [`decompose_batch`]. Row `i` of each column belongs to the `i`th input ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DecomposedColumns {
+    /// Creation times in milliseconds since the Unix epoch
+    pub timestamps: Vec<i64>,
+    pub machine_ids: Vec<u64>,
+    pub sequences: Vec<u64>,
+}
+
+impl DecomposedColumns {
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+}
+
+/// Decodes `ids` into columns, resolving timestamps against `epoch`.
+///
+/// Each column is filled by its own branch-free pass over the input, which
+/// the compiler can vectorise, so this is much faster than calling
+/// [`SnowflakeId::decompose`] per ID when feeding analytics pipelines.
+///
+/// # Example
+/// ```
+/// use snowflake_id::{decompose_batch, Epoch, SnowflakeId};
+///
+/// let ids = [SnowflakeId::new(175928847299117063).unwrap()];
+/// let columns = decompose_batch(&ids, Epoch::DISCORD);
+/// assert_eq!(columns.timestamps, [1462015105796]);
+/// assert_eq!(columns.machine_ids, [ids[0].machine_id()]);
+/// assert_eq!(columns.sequences, [7]);
+/// ```
+pub fn decompose_batch(ids: &[SnowflakeId], epoch: impl Into<Epoch>) -> DecomposedColumns {
+    let epoch = epoch.into().as_millis();
+    DecomposedColumns {
+        timestamps: ids.iter().map(|id| id.timestamp() + epoch).collect(),
+        machine_ids: ids.iter().map(SnowflakeId::machine_id).collect(),
+        sequences: ids.iter().map(SnowflakeId::sequence).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+    use crate::SEQUENCE_BITS;
+    use crate::TIMESTAMP_SHIFT;
+    use std::thread;
+
+    #[test]
+    fn test_decompose_batch() {
+        let generator = SnowflakeGenerator::new(37).unwrap();
+        let ids = generator.generate_bulk(5000).unwrap();
+        let columns = decompose_batch(&ids, Epoch::TWITTER);
+        assert_eq!(columns.len(), ids.len());
+        for (i, id) in ids.iter().enumerate() {
+            let parts = id.decompose(Epoch::TWITTER);
+            assert_eq!(columns.timestamps[i], parts.datetime.timestamp_millis());
+            assert_eq!(columns.machine_ids[i], parts.machine_id);
+            assert_eq!(columns.sequences[i], parts.sequence);
+        }
+        assert!(decompose_batch(&[], Epoch::TWITTER).is_empty());
+    }
+
+    #[test]
+    fn test_decompose() {
+        let generator = SnowflakeGenerator::with_epoch(9, Epoch::DISCORD).unwrap();
+        let id = generator.next_id(|_| thread::yield_now());
+
+        let parts = id.decompose(Epoch::DISCORD);
+        assert_eq!(parts.machine_id, 9);
+        assert_eq!(parts.sequence, id.sequence());
+        assert_eq!(parts.raw_offset as i64, id.timestamp());
+        assert_eq!(
+            parts.datetime.timestamp_millis(),
+            id.timestamp_with_epoch(Epoch::DISCORD)
+        );
+    }
+
+    #[test]
+    fn test_decompose_display() {
+        let id = SnowflakeId::new((1000 << TIMESTAMP_SHIFT) | (3 << SEQUENCE_BITS) | 5).unwrap();
+        let parts = id.decompose(Epoch::UNIX);
+        assert_eq!(
+            parts.to_string(),
+            "datetime=1970-01-01T00:00:01.000Z machine_id=3 sequence=5 raw_offset=1000"
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! One generator per tenant, namespace or other key, created on first use.
+//!
+//! A [`GeneratorPool`] gives each key its own [`SnowflakeGenerator`], either
+//! on a machine id leased from a sub-range, so IDs stay unique across keys,
+//! or on a per-key [`Epoch`], so each key gets an independent ID space on a
+//! shared machine id.
+//! Generators for keys that go quiet can be evicted to bound the pool; their
+//! machine ids return to the free list for new keys.
+//!
+//! A key's new generator never reuses a tick an evicted generator may have
+//! issued from: it waits for the clock to pass the last tick of any
+//! generator evicted before it, which costs at most a tick right after an
+//! eviction.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::pool::{Eviction, GeneratorPool};
+//! use snowflake_id::SnowflakeId;
+//!
+//! let pool = GeneratorPool::<&str, SnowflakeId>::with_machine_ids(100..=199)
+//!     .unwrap()
+//!     .eviction(Eviction::LeastRecentlyUsed(64));
+//!
+//! let a = pool.next_id(&"acme").unwrap();
+//! let b = pool.next_id(&"globex").unwrap();
+//! assert_eq!(a.machine_id(), 100);
+//! assert_eq!(b.machine_id(), 101);
+//! ```
+
+use crate::builder::GeneratorBuilder;
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::generator::SnowflakeGenerator;
+use crate::snowflake::Snowflake;
+use crate::sync::Mutex;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+type Configure<S> = Box<dyn Fn(GeneratorBuilder<S>) -> GeneratorBuilder<S> + Send + Sync>;
+type EpochFor<K> = Box<dyn Fn(&K) -> Epoch + Send + Sync>;
+
+/// When a [`GeneratorPool`] drops generators
+///
+/// A generator is only evicted while no [`Arc`] handed out by
+/// [`GeneratorPool::generator`] is still alive, so a key in use is never
+/// given a second generator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Eviction {
+    /// Keep every generator for the life of the pool
+    #[default]
+    Never,
+    /// Keep at most this many generators, evicting the least recently used
+    /// to make room for a new key
+    LeastRecentlyUsed(usize),
+    /// Evict generators unused for this long when a new key arrives
+    Idle(Duration),
+}
+
+enum Assignment<K> {
+    MachineIds {
+        range: RangeInclusive<u64>,
+        free: BTreeSet<u64>,
+    },
+    Epochs {
+        machine_id: u64,
+        epoch_for: EpochFor<K>,
+        /// Epochs of the generators currently in the pool
+        in_use: HashSet<i64>,
+    },
+}
+
+struct Entry<S: Snowflake> {
+    generator: Arc<SnowflakeGenerator<S>>,
+    machine_id: u64,
+    last_used: Instant,
+}
+
+struct Inner<K, S: Snowflake> {
+    assignment: Assignment<K>,
+    entries: HashMap<K, Entry<S>>,
+    /// Latest end of a tick issued from by an evicted generator
+    evicted_until: Option<i64>,
+}
+
+/// Lazily created generators keyed by tenant or namespace, see the
+/// [module docs](self)
+pub struct GeneratorPool<K, S: Snowflake> {
+    inner: Mutex<Inner<K, S>>,
+    eviction: Eviction,
+    configure: Configure<S>,
+}
+
+impl<K: Eq + Hash + Clone, S: Snowflake> GeneratorPool<K, S> {
+    /// Gives each key its own machine id from `machine_ids`, lowest free
+    /// first.
+    ///
+    /// Creating a generator for a new key fails with
+    /// [`SnowflakeError::MachineIdsExhausted`] while every machine id in the
+    /// range is held by a generator that can't be evicted.
+    pub fn with_machine_ids(machine_ids: RangeInclusive<u64>) -> Result<Self, SnowflakeError> {
+        let (start, end) = (*machine_ids.start(), *machine_ids.end());
+        if start > end {
+            return Err(SnowflakeError::InvalidConfig(format!(
+                "machine id range {}..={} is empty",
+                start, end
+            )));
+        }
+        if end > S::max_machine_id() {
+            return Err(SnowflakeError::InvalidMachineId(end, S::max_machine_id()));
+        }
+        Ok(Self::from_assignment(Assignment::MachineIds {
+            free: machine_ids.clone().collect(),
+            range: machine_ids,
+        }))
+    }
+
+    /// Gives every key a generator on `machine_id` with the epoch returned
+    /// by `epoch_for`.
+    ///
+    /// Every key shares the machine id, so two generators on one epoch would
+    /// mint the same IDs: creating a generator whose epoch another key's
+    /// generator in the pool already uses fails with
+    /// [`SnowflakeError::EpochInUse`]. An epoch frees up again when that
+    /// generator is evicted. IDs from different keys can still coincide, as
+    /// the same bits mean a different instant under each epoch, so they are
+    /// only unique within a key.
+    pub fn with_epochs(
+        machine_id: u64,
+        epoch_for: impl Fn(&K) -> Epoch + Send + Sync + 'static,
+    ) -> Result<Self, SnowflakeError> {
+        if machine_id > S::max_machine_id() {
+            return Err(SnowflakeError::InvalidMachineId(
+                machine_id,
+                S::max_machine_id(),
+            ));
+        }
+        Ok(Self::from_assignment(Assignment::Epochs {
+            machine_id,
+            epoch_for: Box::new(epoch_for),
+            in_use: HashSet::new(),
+        }))
+    }
+
+    fn from_assignment(assignment: Assignment<K>) -> Self {
+        GeneratorPool {
+            inner: Mutex::new(Inner {
+                assignment,
+                entries: HashMap::new(),
+                evicted_until: None,
+            }),
+            eviction: Eviction::Never,
+            configure: Box::new(|builder| builder),
+        }
+    }
+
+    /// Sets when generators are evicted
+    pub fn eviction(mut self, eviction: Eviction) -> Self {
+        self.eviction = eviction;
+        self
+    }
+
+    /// Applies further settings, such as a clock or wait strategy, to every
+    /// generator the pool creates. The builder arrives with the key's machine
+    /// id, and epoch if assigned per key, already set.
+    pub fn configure(
+        mut self,
+        configure: impl Fn(GeneratorBuilder<S>) -> GeneratorBuilder<S> + Send + Sync + 'static,
+    ) -> Self {
+        self.configure = Box::new(configure);
+        self
+    }
+
+    /// Generates the next ID for `key`, creating its generator if needed
+    pub fn next_id(&self, key: &K) -> Result<S, SnowflakeError> {
+        self.generator(key)?.generate()
+    }
+
+    /// Returns the generator for `key`, creating it if needed.
+    ///
+    /// The generator isn't evicted while the returned handle is alive.
+    pub fn generator(&self, key: &K) -> Result<Arc<SnowflakeGenerator<S>>, SnowflakeError> {
+        let mut inner = self.inner.lock();
+        let now = Instant::now();
+        if let Some(entry) = inner.entries.get_mut(key) {
+            entry.last_used = now;
+            return Ok(entry.generator.clone());
+        }
+
+        self.evict(&mut inner, now);
+        let (machine_id, builder) = match &mut inner.assignment {
+            Assignment::MachineIds { range, free } => {
+                let machine_id = free
+                    .pop_first()
+                    .ok_or(SnowflakeError::MachineIdsExhausted(*range.end()))?;
+                (machine_id, GeneratorBuilder::new(machine_id))
+            }
+            Assignment::Epochs {
+                machine_id,
+                epoch_for,
+                ..
+            } => (
+                *machine_id,
+                GeneratorBuilder::new(*machine_id).epoch(epoch_for(key)),
+            ),
+        };
+
+        let generator = match (self.configure)(builder).build() {
+            Ok(generator) => Arc::new(generator),
+            Err(err) => {
+                inner.release(machine_id);
+                return Err(err);
+            }
+        };
+        if let Assignment::Epochs { in_use, .. } = &mut inner.assignment {
+            // Checked on the built generator, in case `configure` set the epoch
+            if !in_use.insert(generator.epoch()) {
+                return Err(SnowflakeError::EpochInUse(generator.epoch()));
+            }
+        }
+        if let Some(until) = inner.evicted_until {
+            generator.resume_after_micros(until);
+        }
+        inner.entries.insert(
+            key.clone(),
+            Entry {
+                generator: generator.clone(),
+                machine_id,
+                last_used: now,
+            },
+        );
+        Ok(generator)
+    }
+
+    /// Drops the generator for `key` now, returning whether there was one to
+    /// drop. Fails to drop one that is still in use.
+    pub fn evict_key(&self, key: &K) -> bool {
+        let mut inner = self.inner.lock();
+        match inner.entries.get(key) {
+            Some(entry) if Arc::strong_count(&entry.generator) == 1 => {
+                inner.remove(key);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns how many generators the pool holds
+    pub fn len(&self) -> usize {
+        self.inner.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn evict(&self, inner: &mut Inner<K, S>, now: Instant) {
+        match self.eviction {
+            Eviction::Never => {}
+            Eviction::LeastRecentlyUsed(capacity) => {
+                while inner.entries.len() >= capacity.max(1) {
+                    let oldest = inner
+                        .evictable()
+                        .min_by_key(|(_, entry)| entry.last_used)
+                        .map(|(key, _)| key.clone());
+                    match oldest {
+                        Some(key) => inner.remove(&key),
+                        None => break,
+                    }
+                }
+            }
+            Eviction::Idle(idle) => {
+                let stale: Vec<K> = inner
+                    .evictable()
+                    .filter(|(_, entry)| now.duration_since(entry.last_used) >= idle)
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                for key in stale {
+                    inner.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+impl<K: Eq + Hash, S: Snowflake> Inner<K, S> {
+    /// Entries whose generator no caller holds
+    fn evictable(&self) -> impl Iterator<Item = (&K, &Entry<S>)> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| Arc::strong_count(&entry.generator) == 1)
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some(entry) = self.entries.remove(key) {
+            if let Some(until) = entry.generator.issued_until_micros() {
+                self.evicted_until = self.evicted_until.max(Some(until));
+            }
+            self.release(entry.machine_id);
+            if let Assignment::Epochs { in_use, .. } = &mut self.assignment {
+                in_use.remove(&entry.generator.epoch());
+            }
+        }
+    }
+
+    fn release(&mut self, machine_id: u64) {
+        if let Assignment::MachineIds { free, .. } = &mut self.assignment {
+            free.insert(machine_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::manual_clock;
+    use crate::SnowflakeId;
+    use crate::SNOWFLAKE_ID_EPOCH;
+
+    #[test]
+    fn test_generator_pool() {
+        use crate::generator::SnowflakeOperation;
+
+        let clock = manual_clock();
+        let pool_clock = clock.clone();
+        let pool = GeneratorPool::<&str, SnowflakeId>::with_machine_ids(3..=4)
+            .unwrap()
+            .configure(move |builder| builder.clock(pool_clock.clone()));
+
+        let acme = pool.next_id(&"acme").unwrap();
+        assert_eq!(acme.machine_id(), 3);
+        assert_eq!(pool.next_id(&"globex").unwrap().machine_id(), 4);
+        assert!(pool.next_id(&"acme").unwrap() > acme);
+        assert!(matches!(
+            pool.next_id(&"initech"),
+            Err(SnowflakeError::MachineIdsExhausted(4))
+        ));
+        assert_eq!(pool.len(), 2);
+
+        // A held generator can't be evicted; a free one hands its machine id on
+        let held = pool.generator(&"globex").unwrap();
+        assert!(!pool.evict_key(&"globex"));
+        assert!(pool.evict_key(&"acme"));
+        drop(held);
+
+        // The new generator waits out the evicted one's last tick
+        let initech = pool.generator(&"initech").unwrap();
+        assert_eq!(initech.machine_id(), 3);
+        assert!(matches!(
+            initech.try_next_id().unwrap(),
+            SnowflakeOperation::Pending(_)
+        ));
+        clock.advance(Duration::from_millis(1));
+        let id = pool.next_id(&"initech").unwrap();
+        assert!(id.timestamp() > acme.timestamp());
+        drop(initech);
+
+        let lru = GeneratorPool::<u32, SnowflakeId>::with_machine_ids(0..=9)
+            .unwrap()
+            .eviction(Eviction::LeastRecentlyUsed(2));
+        for tenant in [1, 2, 1, 3] {
+            lru.next_id(&tenant).unwrap();
+        }
+        assert_eq!(lru.len(), 2);
+        // Tenant 2 was least recently used, so 3 took its machine id
+        assert_eq!(lru.next_id(&3).unwrap().machine_id(), 1);
+        assert_eq!(lru.next_id(&1).unwrap().machine_id(), 0);
+
+        let epochs = GeneratorPool::<u8, SnowflakeId>::with_epochs(7, |&tenant| {
+            Epoch::custom(SNOWFLAKE_ID_EPOCH + i64::from(tenant) * 1_000)
+        })
+        .unwrap();
+        assert_eq!(
+            epochs.generator(&2).unwrap().epoch(),
+            SNOWFLAKE_ID_EPOCH + 2_000
+        );
+        assert_eq!(epochs.next_id(&5).unwrap().machine_id(), 7);
+
+        // Two live keys on one epoch would share every ID
+        let shared = GeneratorPool::<u8, SnowflakeId>::with_epochs(7, |&tenant| {
+            Epoch::custom(SNOWFLAKE_ID_EPOCH + i64::from(tenant / 2) * 1_000)
+        })
+        .unwrap();
+        shared.next_id(&2).unwrap();
+        let Err(err) = shared.next_id(&3) else {
+            panic!("two keys shared an epoch");
+        };
+        assert!(
+            matches!(err, SnowflakeError::EpochInUse(epoch) if epoch == SNOWFLAKE_ID_EPOCH + 1_000)
+        );
+        assert!(shared.evict_key(&2));
+        assert_eq!(shared.next_id(&3).unwrap().machine_id(), 7);
+
+        let (start, end) = (5, 4);
+        assert!(GeneratorPool::<u8, SnowflakeId>::with_machine_ids(start..=end).is_err());
+        assert!(matches!(
+            GeneratorPool::<u8, SnowflakeId>::with_machine_ids(0..=1024),
+            Err(SnowflakeError::InvalidMachineId(1024, 1023))
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+//! proptest `Arbitrary` for [`SnowflakeId`] and [`SnowflakeParts`].
+//!
+//! IDs are built from independently drawn timestamp, machine ID and sequence
+//! fields, so failing cases shrink each field toward zero.
+
+use crate::defs::{MAX_MACHINE_ID, MAX_SEQUENCE, MAX_TIMESTAMP_MS};
+use crate::epoch::Epoch;
+use crate::parts::SnowflakeParts;
+use crate::SnowflakeId;
+use proptest::arbitrary::Arbitrary;
+use proptest::strategy::{Map, Strategy};
+use std::ops::RangeInclusive;
+
+type FieldRanges = (
+    RangeInclusive<u64>,
+    RangeInclusive<u64>,
+    RangeInclusive<u64>,
+);
+
+impl Arbitrary for SnowflakeId {
+    type Parameters = ();
+    type Strategy = Map<FieldRanges, fn((u64, u64, u64)) -> SnowflakeId>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            0..=MAX_TIMESTAMP_MS as u64,
+            0..=MAX_MACHINE_ID,
+            0..=MAX_SEQUENCE,
+        )
+            .prop_map(|(timestamp, machine_id, sequence)| {
+                SnowflakeId::from_component_parts(timestamp, machine_id, sequence)
+            })
+    }
+}
+
+/// Parameterized by the epoch the parts are resolved against
+impl Arbitrary for SnowflakeParts {
+    type Parameters = Epoch;
+    type Strategy = Map<
+        (
+            <SnowflakeId as Arbitrary>::Strategy,
+            proptest::strategy::Just<Epoch>,
+        ),
+        fn((SnowflakeId, Epoch)) -> SnowflakeParts,
+    >;
+
+    fn arbitrary_with(epoch: Epoch) -> Self::Strategy {
+        (SnowflakeId::arbitrary(), proptest::strategy::Just(epoch))
+            .prop_map(|(id, epoch)| id.decompose(epoch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Snowflake;
+    use std::str::FromStr;
+
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_arbitrary_ids_roundtrip(id: SnowflakeId) {
+            prop_assert!(id.is_valid());
+            prop_assert_eq!(SnowflakeId::from_str(&id.to_string()).unwrap(), id);
+        }
+
+        #[test]
+        fn test_arbitrary_parts(parts in any_with::<SnowflakeParts>(Epoch::DISCORD)) {
+            let id = SnowflakeId::from_component_parts(
+                parts.raw_offset,
+                parts.machine_id,
+                parts.sequence,
+            );
+            prop_assert_eq!(id.decompose(Epoch::DISCORD), parts);
+        }
+    }
+
+    #[test]
+    fn test_shrinks_toward_zero() {
+        use proptest::strategy::ValueTree;
+        use proptest::test_runner::TestRunner;
+
+        let mut runner = TestRunner::deterministic();
+        let mut tree = any::<SnowflakeId>().new_tree(&mut runner).unwrap();
+        while tree.simplify() {}
+        assert_eq!(tree.current(), SnowflakeId::new(0).unwrap());
+    }
+}
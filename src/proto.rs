@@ -0,0 +1,132 @@
+//! Helpers for carrying [`SnowflakeId`] in prost-generated messages.
+//!
+//! prost generates plain `i64` and `String` fields, so IDs cross the message
+//! boundary through conversions that validate on the way in:
+//!
+//! ```proto
+//! message Event {
+//!   int64 id = 1;                  // SnowflakeId::try_from(event.id)?
+//!   string parent_id = 2;          // event.parent_id.parse::<SnowflakeId>()?
+//!   snowflake.SnowflakeId ref = 3; // proto::required(event.r#ref, "ref")?
+//! }
+//! ```
+//!
+//! Prefer `int64` fields; use `string` only for consumers such as JavaScript
+//! that cannot represent 64-bit integers. [`SnowflakeIdValue`] is
+//! wire-compatible with `message SnowflakeId { int64 value = 1; }` and can be
+//! mapped onto that message with prost-build's `extern_path`, which also
+//! gives optional ID fields explicit presence.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::proto::{self, SnowflakeIdValue};
+//! use snowflake_id::SnowflakeId;
+//!
+//! #[derive(Clone, PartialEq, prost::Message)]
+//! struct Event {
+//!     #[prost(int64, tag = "1")]
+//!     id: i64,
+//!     #[prost(message, optional, tag = "3")]
+//!     r#ref: Option<SnowflakeIdValue>,
+//! }
+//!
+//! let id = SnowflakeId::new(123456789).unwrap();
+//! let event = Event { id: id.into(), r#ref: Some(id.into()) };
+//!
+//! assert_eq!(SnowflakeId::try_from(event.id).unwrap(), id);
+//! assert_eq!(proto::required(event.r#ref, "ref").unwrap(), id);
+//! ```
+
+use crate::error::SnowflakeError;
+use crate::SnowflakeId;
+
+/// Message wrapper for an ID, wire-compatible with
+/// `message SnowflakeId { int64 value = 1; }`
+#[derive(Clone, Copy, PartialEq, Eq, Hash, prost::Message)]
+pub struct SnowflakeIdValue {
+    #[prost(int64, tag = "1")]
+    pub value: i64,
+}
+
+impl From<SnowflakeId> for SnowflakeIdValue {
+    fn from(id: SnowflakeId) -> Self {
+        SnowflakeIdValue { value: id.into() }
+    }
+}
+
+impl TryFrom<SnowflakeIdValue> for SnowflakeId {
+    type Error = SnowflakeError;
+
+    fn try_from(value: SnowflakeIdValue) -> Result<Self, Self::Error> {
+        SnowflakeId::new(value.value)
+    }
+}
+
+/// Validates an optional message field, treating absence as `None`
+pub fn optional(field: Option<SnowflakeIdValue>) -> Result<Option<SnowflakeId>, SnowflakeError> {
+    field.map(SnowflakeId::try_from).transpose()
+}
+
+/// Validates a message field that must be present, naming it in the error
+pub fn required(
+    field: Option<SnowflakeIdValue>,
+    name: &str,
+) -> Result<SnowflakeId, SnowflakeError> {
+    optional(field)?.ok_or_else(|| SnowflakeError::InvalidId(format!("missing field {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+    use std::thread;
+
+    #[test]
+    fn test_prost_roundtrip() {
+        use crate::proto::{self, SnowflakeIdValue};
+        use prost::Message;
+
+        #[derive(Clone, PartialEq, prost::Message)]
+        struct Event {
+            #[prost(int64, tag = "1")]
+            id: i64,
+            #[prost(string, tag = "2")]
+            parent_id: String,
+            #[prost(message, optional, tag = "3")]
+            causation: Option<SnowflakeIdValue>,
+            #[prost(message, optional, tag = "4")]
+            correlation: Option<SnowflakeIdValue>,
+        }
+
+        let generator = SnowflakeGenerator::new(3).unwrap();
+        let id = generator.next_id(|_| thread::yield_now());
+        let parent = generator.next_id(|_| thread::yield_now());
+
+        let event = Event {
+            id: id.into(),
+            parent_id: parent.to_string(),
+            causation: Some(parent.into()),
+            correlation: None,
+        };
+        let decoded = Event::decode(event.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded, event);
+
+        assert_eq!(SnowflakeId::try_from(decoded.id).unwrap(), id);
+        assert_eq!(decoded.parent_id.parse::<SnowflakeId>().unwrap(), parent);
+        assert_eq!(
+            proto::required(decoded.causation, "causation").unwrap(),
+            parent
+        );
+        assert_eq!(proto::optional(decoded.correlation).unwrap(), None);
+        assert!(matches!(
+            proto::required(decoded.correlation, "correlation"),
+            Err(SnowflakeError::InvalidId(msg)) if msg.contains("correlation")
+        ));
+
+        // Negative values survive the wire but fail validation
+        let bad = SnowflakeIdValue { value: -1 };
+        let decoded = SnowflakeIdValue::decode(bad.encode_to_vec().as_slice()).unwrap();
+        assert!(SnowflakeId::try_from(decoded).is_err());
+        assert!(SnowflakeId::try_from(-1_i64).is_err());
+    }
+}
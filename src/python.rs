@@ -0,0 +1,252 @@
+//! PyO3 bindings for [`SnowflakeId`] and [`SnowflakeGenerator`].
+//!
+//! The classes behave like their Rust counterparts, so IDs minted or parsed
+//! from Python match the ones produced by Rust services bit for bit. Add them
+//! to an extension module with [`register`]:
+//!
+//! ```no_run
+//! use pyo3::prelude::*;
+//!
+//! #[pymodule]
+//! fn snowflake_id(m: &Bound<'_, PyModule>) -> PyResult<()> {
+//!     ::snowflake_id::python::register(m)
+//! }
+//! ```
+//!
+//! ```python
+//! from snowflake_id import SnowflakeGenerator, SnowflakeId
+//!
+//! generator = SnowflakeGenerator(7)
+//! id = generator.next_id()
+//! assert SnowflakeId.parse(str(id)) == id
+//! print(int(id), id.decompose().datetime)
+//! ```
+//!
+//! Invalid input raises `ValueError`; clock and generator failures raise
+//! `RuntimeError`.
+
+use crate::defs::SNOWFLAKE_ID_EPOCH;
+use crate::error::SnowflakeError;
+use crate::parts::SnowflakeParts;
+use crate::{SnowflakeGenerator, SnowflakeId};
+use chrono::{DateTime, Utc};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+impl From<SnowflakeError> for PyErr {
+    fn from(err: SnowflakeError) -> Self {
+        match err {
+            SnowflakeError::InvalidMachineId(..)
+            | SnowflakeError::InvalidId(_)
+            | SnowflakeError::Unparseable { .. }
+            | SnowflakeError::InvalidRoute { .. }
+            | SnowflakeError::InvalidWireFormat(_)
+            | SnowflakeError::InvalidShardCount(..)
+            | SnowflakeError::InvalidSequenceStart(..)
+            | SnowflakeError::InvalidDatacenterId(..)
+            | SnowflakeError::InvalidWorkerId(..)
+            | SnowflakeError::InvalidConfig(_)
+            | SnowflakeError::InvalidTick(_) => PyValueError::new_err(err.to_string()),
+            _ => PyRuntimeError::new_err(err.to_string()),
+        }
+    }
+}
+
+/// Python `SnowflakeId`: hashable, ordered, and convertible with `int()`
+#[pyclass(
+    name = "SnowflakeId",
+    module = "snowflake_id",
+    frozen,
+    eq,
+    ord,
+    hash,
+    from_py_object
+)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PySnowflakeId(pub SnowflakeId);
+
+#[pymethods]
+impl PySnowflakeId {
+    #[new]
+    fn new(value: i64) -> PyResult<Self> {
+        Ok(PySnowflakeId(SnowflakeId::new(value)?))
+    }
+
+    /// Parses an ID from its decimal string form
+    #[staticmethod]
+    fn parse(value: &str) -> PyResult<Self> {
+        Ok(PySnowflakeId(value.parse()?))
+    }
+
+    fn __int__(&self) -> i64 {
+        self.0.id()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("SnowflakeId({})", self.0)
+    }
+
+    /// Timestamp offset in milliseconds from the epoch, as stored in the ID
+    #[getter]
+    fn timestamp(&self) -> i64 {
+        self.0.timestamp()
+    }
+
+    #[getter]
+    fn machine_id(&self) -> u64 {
+        self.0.machine_id()
+    }
+
+    #[getter]
+    fn sequence(&self) -> u64 {
+        self.0.sequence()
+    }
+
+    /// Returns the creation time in milliseconds since the Unix epoch
+    #[pyo3(signature = (epoch = SNOWFLAKE_ID_EPOCH))]
+    fn timestamp_with_epoch(&self, epoch: i64) -> i64 {
+        self.0.timestamp_with_epoch(epoch)
+    }
+
+    /// Splits the ID into its fields, resolving the timestamp against `epoch`
+    #[pyo3(signature = (epoch = SNOWFLAKE_ID_EPOCH))]
+    fn decompose(&self, epoch: i64) -> PySnowflakeParts {
+        PySnowflakeParts(self.0.decompose(epoch))
+    }
+}
+
+/// Python `SnowflakeParts`, as returned by `SnowflakeId.decompose()`
+#[pyclass(
+    name = "SnowflakeParts",
+    module = "snowflake_id",
+    frozen,
+    eq,
+    from_py_object
+)]
+#[derive(Clone, Copy, PartialEq)]
+pub struct PySnowflakeParts(pub SnowflakeParts);
+
+#[pymethods]
+impl PySnowflakeParts {
+    /// Creation time as a timezone-aware `datetime`
+    #[getter]
+    fn datetime(&self) -> DateTime<Utc> {
+        self.0.datetime
+    }
+
+    #[getter]
+    fn machine_id(&self) -> u64 {
+        self.0.machine_id
+    }
+
+    #[getter]
+    fn sequence(&self) -> u64 {
+        self.0.sequence
+    }
+
+    #[getter]
+    fn raw_offset(&self) -> u64 {
+        self.0.raw_offset
+    }
+
+    fn __repr__(&self) -> String {
+        format!("SnowflakeParts({})", self.0)
+    }
+}
+
+/// Python `SnowflakeGenerator`, safe to share between Python threads
+#[pyclass(name = "SnowflakeGenerator", module = "snowflake_id", frozen)]
+pub struct PySnowflakeGenerator(pub SnowflakeGenerator);
+
+#[pymethods]
+impl PySnowflakeGenerator {
+    #[new]
+    #[pyo3(signature = (machine_id, epoch = SNOWFLAKE_ID_EPOCH))]
+    fn new(machine_id: u64, epoch: i64) -> PyResult<Self> {
+        Ok(PySnowflakeGenerator(SnowflakeGenerator::with_epoch(
+            machine_id, epoch,
+        )?))
+    }
+
+    #[getter]
+    fn machine_id(&self) -> u64 {
+        self.0.machine_id()
+    }
+
+    #[getter]
+    fn epoch(&self) -> i64 {
+        self.0.epoch()
+    }
+
+    /// Generates the next ID, releasing the GIL while waiting for the clock
+    fn next_id(&self, py: Python<'_>) -> PyResult<PySnowflakeId> {
+        let id = py.detach(|| self.0.generate())?;
+        Ok(PySnowflakeId(id))
+    }
+
+    /// Generates `count` IDs in one call
+    fn next_ids(&self, py: Python<'_>, count: usize) -> PyResult<Vec<PySnowflakeId>> {
+        let ids = py.detach(|| self.0.generate_bulk(count))?;
+        Ok(ids.into_iter().map(PySnowflakeId).collect())
+    }
+}
+
+/// Adds the `SnowflakeId`, `SnowflakeParts` and `SnowflakeGenerator` classes
+/// to `module`
+pub fn register(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PySnowflakeId>()?;
+    module.add_class::<PySnowflakeParts>()?;
+    module.add_class::<PySnowflakeGenerator>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_bindings() {
+        use pyo3::prelude::*;
+        use pyo3::types::{PyDict, PyModule};
+
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "snowflake_id").unwrap();
+            register(&module).unwrap();
+            let globals = PyDict::new(py);
+            globals.set_item("sf", module).unwrap();
+            py.run(
+                c"
+generator = sf.SnowflakeGenerator(7)
+a, b = generator.next_id(), generator.next_id()
+assert a < b and a != b and sorted([b, a]) == [a, b]
+assert str(a) == str(int(a)) and repr(a) == f'SnowflakeId({a})'
+assert sf.SnowflakeId.parse(str(a)) == a
+assert hash(sf.SnowflakeId(int(a))) == hash(a)
+assert len(set(generator.next_ids(100))) == 100
+
+parts = a.decompose()
+assert parts.machine_id == a.machine_id == 7
+assert parts.sequence == a.sequence and parts.raw_offset == a.timestamp
+assert parts.datetime.tzinfo is not None
+assert a.decompose(0).raw_offset == parts.raw_offset
+
+for bad in (lambda: sf.SnowflakeId.parse('abc'), lambda: sf.SnowflakeGenerator(4096)):
+    try:
+        bad()
+    except ValueError:
+        pass
+    else:
+        raise AssertionError('expected ValueError')
+",
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+        });
+    }
+}
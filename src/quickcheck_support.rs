@@ -0,0 +1,81 @@
+//! quickcheck `Arbitrary` for [`SnowflakeId`] and [`SnowflakeParts`].
+//!
+//! Shrinking reduces the timestamp first, then the sequence, then the
+//! machine ID, each toward zero.
+
+use crate::epoch::Epoch;
+use crate::parts::SnowflakeParts;
+use crate::snowflake::Snowflake;
+use crate::SnowflakeId;
+use quickcheck::{Arbitrary, Gen};
+
+impl Arbitrary for SnowflakeId {
+    fn arbitrary(g: &mut Gen) -> Self {
+        SnowflakeId::from_component_parts(
+            u64::arbitrary(g) & SnowflakeId::timestamp_mask(),
+            u64::arbitrary(g) & SnowflakeId::machine_id_mask(),
+            u64::arbitrary(g) & SnowflakeId::sequence_mask(),
+        )
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let timestamp = <Self as Snowflake>::timestamp(self);
+        let machine_id = self.machine_id();
+        let sequence = self.sequence();
+
+        let timestamps = timestamp
+            .shrink()
+            .map(move |t| SnowflakeId::from_component_parts(t, machine_id, sequence));
+        let sequences = sequence
+            .shrink()
+            .map(move |s| SnowflakeId::from_component_parts(timestamp, machine_id, s));
+        let machine_ids = machine_id
+            .shrink()
+            .map(move |m| SnowflakeId::from_component_parts(timestamp, m, sequence));
+        Box::new(timestamps.chain(sequences).chain(machine_ids))
+    }
+}
+
+/// Parts are resolved against the default epoch
+impl Arbitrary for SnowflakeParts {
+    fn arbitrary(g: &mut Gen) -> Self {
+        SnowflakeId::arbitrary(g).decompose(Epoch::default())
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let id = SnowflakeId::from_component_parts(self.raw_offset, self.machine_id, self.sequence);
+        let epoch = Epoch::custom(self.datetime.timestamp_millis() - self.raw_offset as i64);
+        Box::new(id.shrink().map(move |id| id.decompose(epoch)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use quickcheck::QuickCheck;
+
+    #[test]
+    fn test_arbitrary_ids_are_valid() {
+        fn prop(id: SnowflakeId) -> bool {
+            id.is_valid() && SnowflakeId::from_str(&id.to_string()).unwrap() == id
+        }
+        QuickCheck::new().quickcheck(prop as fn(SnowflakeId) -> bool);
+    }
+
+    #[test]
+    fn test_shrinks_toward_zero() {
+        let id = SnowflakeId::from_component_parts(1_000, 7, 30);
+        let shrunk: Vec<SnowflakeId> = id.shrink().collect();
+        assert!(shrunk.contains(&SnowflakeId::from_component_parts(0, 7, 30)));
+        assert!(shrunk.contains(&SnowflakeId::from_component_parts(1_000, 7, 0)));
+        assert!(shrunk.iter().all(|candidate| candidate.is_valid()));
+        assert!(SnowflakeId::new(0).unwrap().shrink().next().is_none());
+
+        let parts = SnowflakeParts::arbitrary(&mut Gen::new(100));
+        assert!(parts
+            .shrink()
+            .all(|smaller| smaller.raw_offset <= parts.raw_offset));
+    }
+}
@@ -0,0 +1,86 @@
+//! rand integration for minting realistic [`SnowflakeId`]s without a
+//! generator, e.g. for test fixtures and load generators.
+
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::snowflake::Snowflake;
+use crate::SnowflakeId;
+use rand::distr::{Distribution, StandardUniform};
+use rand::Rng;
+use std::ops::Range;
+
+/// Samples uniformly over every valid ID: any timestamp, machine ID and
+/// sequence the layout can hold
+impl Distribution<SnowflakeId> for StandardUniform {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> SnowflakeId {
+        SnowflakeId::new_unchecked((rng.random::<u64>() & SnowflakeId::valid_mask()) as i64)
+    }
+}
+
+impl SnowflakeId {
+    /// Returns a random ID minted during `range` (Unix milliseconds, end
+    /// exclusive), drawn from the thread-local RNG
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::{Epoch, SnowflakeId};
+    ///
+    /// // Sometime in March 2024
+    /// let range = 1709251200000..1711929600000;
+    /// let id = SnowflakeId::random_in_range(range.clone(), Epoch::TWITTER).unwrap();
+    /// assert!(range.contains(&id.timestamp_with_epoch(Epoch::TWITTER)));
+    /// ```
+    pub fn random_in_range(
+        range: Range<i64>,
+        epoch: impl Into<Epoch>,
+    ) -> Result<Self, SnowflakeError> {
+        Self::random_in_range_with(&mut rand::rng(), range, epoch)
+    }
+
+    /// Like [`Self::random_in_range`], drawing from `rng` so fixtures can be
+    /// reproduced from a seed
+    pub fn random_in_range_with<R: Rng + ?Sized>(
+        rng: &mut R,
+        range: Range<i64>,
+        epoch: impl Into<Epoch>,
+    ) -> Result<Self, SnowflakeError> {
+        let bounds = Self::range_for(range, epoch)?;
+        Ok(Self::new_unchecked(
+            rng.random_range(bounds.start().id()..=bounds.end().id()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SNOWFLAKE_ID_EPOCH;
+
+    #[test]
+    fn test_rand_distribution() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..1000 {
+            let id: SnowflakeId = rng.random();
+            assert!(id.id() >= 0);
+        }
+
+        let start = SNOWFLAKE_ID_EPOCH + 1_000_000;
+        let range = start..start + 60_000;
+        for _ in 0..1000 {
+            let id =
+                SnowflakeId::random_in_range_with(&mut rng, range.clone(), Epoch::TWITTER).unwrap();
+            assert!(range.contains(&id.timestamp_with_epoch(Epoch::TWITTER)));
+        }
+
+        // Same seed, same fixtures
+        let a = SnowflakeId::random_in_range_with(&mut StdRng::seed_from_u64(1), range.clone(), 0);
+        let b = SnowflakeId::random_in_range_with(&mut StdRng::seed_from_u64(1), range.clone(), 0);
+        assert_eq!(a.unwrap(), b.unwrap());
+
+        assert!(SnowflakeId::random_in_range(start..start, Epoch::TWITTER).is_err());
+        assert!(SnowflakeId::random_in_range(0..10, Epoch::TWITTER).is_err());
+    }
+}
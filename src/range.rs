@@ -0,0 +1,121 @@
+//! Inclusive ranges of IDs, for time-window filters and ID pagination.
+//!
+//! # Example
+//! ```
+//! use chrono::{Duration, Utc};
+//! use snowflake_id::{Epoch, SnowflakeGenerator, SnowflakeRange};
+//!
+//! let id = SnowflakeGenerator::new(1).unwrap().generate().unwrap();
+//! let now = Utc::now();
+//! let (start, end) = (now - Duration::hours(1), now + Duration::seconds(1));
+//! let window = SnowflakeRange::from_time_window(start, end, Epoch::TWITTER).unwrap();
+//! assert!(window.contains(id));
+//!
+//! // SELECT * FROM events WHERE id BETWEEN $1 AND $2
+//! let (low, high) = window.to_sql_bounds();
+//! assert!(low <= id.id() && id.id() <= high);
+//! ```
+
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::SnowflakeId;
+use chrono::{DateTime, Utc};
+use std::ops::RangeInclusive;
+
+/// IDs from `start` to `end`, both inclusive. Empty when `start > end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SnowflakeRange {
+    pub start: SnowflakeId,
+    pub end: SnowflakeId,
+}
+
+impl SnowflakeRange {
+    pub fn new(start: SnowflakeId, end: SnowflakeId) -> Self {
+        SnowflakeRange { start, end }
+    }
+
+    /// Returns the range covering every ID minted in the half-open window
+    /// `start..end`, on any machine
+    pub fn from_time_window(
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        epoch: impl Into<Epoch>,
+    ) -> Result<Self, SnowflakeError> {
+        SnowflakeId::range_for(start.timestamp_millis()..end.timestamp_millis(), epoch)
+            .map(Self::from)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start > self.end
+    }
+
+    pub fn contains(&self, id: SnowflakeId) -> bool {
+        self.start <= id && id <= self.end
+    }
+
+    /// Returns the IDs in both ranges, or `None` if they don't overlap
+    pub fn intersect(&self, other: &SnowflakeRange) -> Option<SnowflakeRange> {
+        let range = SnowflakeRange {
+            start: self.start.max(other.start),
+            end: self.end.min(other.end),
+        };
+        (!range.is_empty()).then_some(range)
+    }
+
+    /// Returns the raw bounds for `WHERE id BETWEEN $1 AND $2`
+    pub fn to_sql_bounds(&self) -> (i64, i64) {
+        (self.start.id(), self.end.id())
+    }
+
+    /// Iterates over every ID value in the range in ascending order, whether
+    /// or not it was ever minted
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = SnowflakeId> {
+        (self.start.id()..=self.end.id()).map(SnowflakeId::new_unchecked)
+    }
+}
+
+impl From<RangeInclusive<SnowflakeId>> for SnowflakeRange {
+    fn from(range: RangeInclusive<SnowflakeId>) -> Self {
+        let (start, end) = range.into_inner();
+        SnowflakeRange { start, end }
+    }
+}
+
+/// For binding with [`BindIdRange`](crate::sqlx_extras::BindIdRange)
+impl From<SnowflakeRange> for RangeInclusive<SnowflakeId> {
+    fn from(range: SnowflakeRange) -> Self {
+        range.start..=range.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snowflake_range() {
+        use chrono::DateTime;
+
+        let id = |value| SnowflakeId::new(value).unwrap();
+        let range = SnowflakeRange::new(id(10), id(20));
+        assert!(range.contains(id(10)) && range.contains(id(20)));
+        assert!(!range.contains(id(21)));
+        assert_eq!(range.iter().count(), 11);
+        assert_eq!(range.to_sql_bounds(), (10, 20));
+
+        let other = SnowflakeRange::new(id(15), id(30));
+        assert_eq!(
+            range.intersect(&other),
+            Some(SnowflakeRange::new(id(15), id(20)))
+        );
+        assert_eq!(range.intersect(&SnowflakeRange::new(id(21), id(30))), None);
+        assert!(SnowflakeRange::new(id(2), id(1)).is_empty());
+
+        let start = DateTime::from_timestamp_millis(1709251200000).unwrap();
+        let end = DateTime::from_timestamp_millis(1709251201000).unwrap();
+        let window = SnowflakeRange::from_time_window(start, end, Epoch::TWITTER).unwrap();
+        let bounds = SnowflakeId::range_for(1709251200000..1709251201000, Epoch::TWITTER).unwrap();
+        assert_eq!(std::ops::RangeInclusive::from(window), bounds);
+        assert!(SnowflakeRange::from_time_window(end, start, Epoch::TWITTER).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+//! redis conversions, so IDs can be passed straight to commands as keys or
+//! values and read back from replies.
+//!
+//! IDs are written as decimal integers, which redis stores compactly and
+//! which work with `INCR`-style and sorted-set commands. Replies are read
+//! like an `i64`, accepting integers and digit strings, and then rejected if
+//! negative.
+
+use crate::SnowflakeId;
+use redis::{
+    FromRedisValue, NumericBehavior, ParsingError, RedisWrite, ToRedisArgs, ToSingleRedisArg, Value,
+};
+
+impl ToRedisArgs for SnowflakeId {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        self.0.write_redis_args(out)
+    }
+
+    fn describe_numeric_behavior(&self) -> NumericBehavior {
+        NumericBehavior::NumberIsInteger
+    }
+}
+
+impl ToSingleRedisArg for SnowflakeId {}
+
+impl FromRedisValue for SnowflakeId {
+    fn from_redis_value_ref(v: &Value) -> Result<Self, ParsingError> {
+        SnowflakeId::new(i64::from_redis_value_ref(v)?).map_err(|e| e.to_string().into())
+    }
+
+    fn from_redis_value(v: Value) -> Result<Self, ParsingError> {
+        Self::from_redis_value_ref(&v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redis_values() {
+        use redis::{FromRedisValue, ToRedisArgs, Value};
+
+        let id = SnowflakeId::new(175928847299117063).unwrap();
+        assert_eq!(id.to_redis_args(), vec![b"175928847299117063".to_vec()]);
+
+        assert_eq!(
+            SnowflakeId::from_redis_value(Value::Int(id.id())).unwrap(),
+            id
+        );
+        let bulk = Value::BulkString(b"175928847299117063".to_vec());
+        assert_eq!(SnowflakeId::from_redis_value(bulk).unwrap(), id);
+        assert_eq!(
+            Option::<SnowflakeId>::from_redis_value(Value::Nil).unwrap(),
+            None
+        );
+        assert!(SnowflakeId::from_redis_value(Value::Int(-1)).is_err());
+        assert!(SnowflakeId::from_redis_value(Value::BulkString(b"abc".to_vec())).is_err());
+    }
+}
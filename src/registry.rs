@@ -0,0 +1,418 @@
+//! Claiming machine IDs from a shared registry.
+//!
+//! A [`MachineIdRegistry`] hands out machine IDs under time-limited leases,
+//! so processes that start and stop freely never share an ID. Hold a lease
+//! for as long as the generator runs, renew it well within its time to live,
+//! and stop generating if renewal fails: the ID may have been handed to
+//! another process.
+//!
+//! Registries reached over the network can implement
+//! [`AsyncMachineIdRegistry`] instead (`async` feature), so async services
+//! don't block their executor on a claim or renewal.
+//!
+//! [`FileLockRegistry`] keeps leases as lock files in a shared directory,
+//! local or on NFS, so small deployments need no Redis or etcd. Expiry is
+//! judged by wall-clock time, so hosts sharing a directory need reasonably
+//! synchronised clocks.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::registry::{FileLockRegistry, MachineIdRegistry};
+//! use snowflake_id::SnowflakeGenerator;
+//! use std::time::Duration;
+//!
+//! let dir = std::env::temp_dir().join("snowflake-doc-registry");
+//! let registry = FileLockRegistry::new(&dir, Duration::from_secs(60)).unwrap();
+//! let lease = registry.claim().unwrap();
+//! let generator = SnowflakeGenerator::new(lease.machine_id()).unwrap();
+//!
+//! // Periodically, e.g. every 20 seconds:
+//! registry.renew(&lease).unwrap();
+//!
+//! registry.release(lease).unwrap();
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::defs::MAX_MACHINE_ID;
+use crate::error::SnowflakeError;
+use std::collections::hash_map::RandomState;
+use std::fs::{self, File, OpenOptions};
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A claimed machine ID, valid until it expires or is released
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct MachineIdLease {
+    machine_id: u64,
+    /// Identifies this claim, so a lease taken over after expiring can't be
+    /// renewed or released by its previous holder
+    token: String,
+}
+
+impl MachineIdLease {
+    /// Creates a lease on `machine_id`, for [`MachineIdRegistry`]
+    /// implementations. `token` must be unique to this claim.
+    pub fn new(machine_id: u64, token: impl Into<String>) -> Self {
+        MachineIdLease {
+            machine_id,
+            token: token.into(),
+        }
+    }
+
+    pub fn machine_id(&self) -> u64 {
+        self.machine_id
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+/// A source of leased machine IDs, see the [module docs](self)
+pub trait MachineIdRegistry: Send + Sync {
+    /// Claims a free machine ID, failing with
+    /// [`SnowflakeError::MachineIdsExhausted`] if every ID is leased
+    fn claim(&self) -> Result<MachineIdLease, SnowflakeError>;
+
+    /// Extends `lease`, failing with [`SnowflakeError::LeaseLost`] if it
+    /// expired and was claimed by someone else
+    fn renew(&self, lease: &MachineIdLease) -> Result<(), SnowflakeError>;
+
+    /// Gives `lease` up so its machine ID can be claimed again
+    fn release(&self, lease: MachineIdLease) -> Result<(), SnowflakeError>;
+}
+
+/// Future returned by [`AsyncMachineIdRegistry`]'s methods
+#[cfg(feature = "async")]
+pub type RegistryFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<T, SnowflakeError>> + Send + 'a>>;
+
+/// A [`MachineIdRegistry`] reached over the network, such as etcd or Redis,
+/// so async services can claim and renew leases without blocking their
+/// executor
+#[cfg(feature = "async")]
+pub trait AsyncMachineIdRegistry: Send + Sync {
+    /// Claims a free machine ID, failing with
+    /// [`SnowflakeError::MachineIdsExhausted`] if every ID is leased
+    fn claim(&self) -> RegistryFuture<'_, MachineIdLease>;
+
+    /// Extends `lease`, failing with [`SnowflakeError::LeaseLost`] if it
+    /// expired and was claimed by someone else
+    fn renew<'a>(&'a self, lease: &'a MachineIdLease) -> RegistryFuture<'a, ()>;
+
+    /// Gives `lease` up so its machine ID can be claimed again
+    fn release(&self, lease: MachineIdLease) -> RegistryFuture<'_, ()>;
+}
+
+/// Leases machine IDs as lock files named `<machine_id>.lock` in a shared
+/// directory
+///
+/// Each file holds the lease's token and expiry time. Every claim, renewal
+/// and release runs under an exclusive lock on the directory's `.guard`
+/// file, so checking a lease and replacing it happen as one step: only one
+/// process can take over a stale lease, and a renewal can't overwrite a
+/// lease claimed since it was checked. The operating system drops the lock
+/// if its holder dies. New lock files are written aside and renamed into
+/// place, so they're never seen half-written.
+///
+/// On NFS this relies on the server supporting file locks, as NFSv4 and
+/// Linux's NFSv3 client do.
+#[derive(Debug, Clone)]
+pub struct FileLockRegistry {
+    dir: PathBuf,
+    ttl: Duration,
+    max_machine_id: u64,
+}
+
+impl FileLockRegistry {
+    /// Creates a registry in `dir`, creating the directory if needed, whose
+    /// leases expire `ttl` after being claimed or renewed
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Result<Self, SnowflakeError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(registry_error)?;
+        Ok(FileLockRegistry {
+            dir,
+            ttl,
+            max_machine_id: MAX_MACHINE_ID,
+        })
+    }
+
+    /// Limits claims to `0..=max_machine_id`, for layouts other than the default
+    pub fn with_max_machine_id(mut self, max_machine_id: u64) -> Self {
+        self.max_machine_id = max_machine_id;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn lock_path(&self, machine_id: u64) -> PathBuf {
+        self.dir.join(format!("{}.lock", machine_id))
+    }
+
+    fn expires_at(&self) -> i64 {
+        let ttl = i64::try_from(self.ttl.as_millis()).unwrap_or(i64::MAX);
+        SystemClock.now_millis().saturating_add(ttl)
+    }
+
+    /// Locks the registry against other processes until the returned file
+    /// is dropped
+    fn guard(&self) -> io::Result<File> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.dir.join(".guard"))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    /// Tries to take `machine_id`, replacing its lock file if it has expired;
+    /// the caller must hold the [guard](Self::guard)
+    fn try_claim(&self, machine_id: u64, token: &str) -> io::Result<bool> {
+        let path = self.lock_path(machine_id);
+        if let Some((_, expires_at)) = read_lock(&path)? {
+            if expires_at > SystemClock.now_millis() {
+                return Ok(false);
+            }
+        }
+        self.write_lease(machine_id, token, &path)?;
+        Ok(true)
+    }
+
+    /// Writes a lease aside and renames it over `path`
+    fn write_lease(&self, machine_id: u64, token: &str, path: &Path) -> io::Result<()> {
+        let temp = self.dir.join(format!("{}.lock.{}.tmp", machine_id, token));
+        write_lock(&temp, token, self.expires_at())?;
+        fs::rename(&temp, path)
+    }
+
+    /// Checks `lease` is still current; the caller must hold the
+    /// [guard](Self::guard)
+    fn check_held(&self, lease: &MachineIdLease) -> Result<PathBuf, SnowflakeError> {
+        let path = self.lock_path(lease.machine_id);
+        match read_lock(&path).map_err(registry_error)? {
+            Some((token, _)) if token == lease.token => Ok(path),
+            _ => Err(SnowflakeError::LeaseLost(lease.machine_id)),
+        }
+    }
+}
+
+impl MachineIdRegistry for FileLockRegistry {
+    fn claim(&self) -> Result<MachineIdLease, SnowflakeError> {
+        let token = new_token();
+        let _guard = self.guard().map_err(registry_error)?;
+        for machine_id in 0..=self.max_machine_id {
+            if self.try_claim(machine_id, &token).map_err(registry_error)? {
+                return Ok(MachineIdLease { machine_id, token });
+            }
+        }
+        Err(SnowflakeError::MachineIdsExhausted(self.max_machine_id))
+    }
+
+    fn renew(&self, lease: &MachineIdLease) -> Result<(), SnowflakeError> {
+        let _guard = self.guard().map_err(registry_error)?;
+        let path = self.check_held(lease)?;
+        self.write_lease(lease.machine_id, &lease.token, &path)
+            .map_err(registry_error)
+    }
+
+    fn release(&self, lease: MachineIdLease) -> Result<(), SnowflakeError> {
+        let _guard = self.guard().map_err(registry_error)?;
+        let path = self.check_held(&lease)?;
+        fs::remove_file(path).map_err(registry_error)
+    }
+}
+
+/// Reads a lock file's token and expiry, or `None` if it doesn't exist
+fn read_lock(path: &Path) -> io::Result<Option<(String, i64)>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let mut lines = contents.lines();
+    let token = lines.next().unwrap_or_default().to_string();
+    // Only a corrupted file lacks an expiry; treat it as expired
+    let expires_at = lines
+        .next()
+        .and_then(|line| line.parse().ok())
+        .unwrap_or(i64::MIN);
+    Ok(Some((token, expires_at)))
+}
+
+fn write_lock(path: &Path, token: &str, expires_at: i64) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    writeln!(file, "{}\n{}", token, expires_at)?;
+    file.sync_all()
+}
+
+fn new_token() -> String {
+    let seed = (std::process::id(), SystemClock.now_millis());
+    format!(
+        "{:016x}{:016x}",
+        RandomState::new().hash_one(seed),
+        RandomState::new().hash_one(seed)
+    )
+}
+
+fn registry_error(err: io::Error) -> SnowflakeError {
+    SnowflakeError::RegistryFailed(Arc::new(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_lock_registry() {
+        let dir = std::env::temp_dir().join(format!("snowflake-registry-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let registry = FileLockRegistry::new(&dir, Duration::from_secs(60))
+            .unwrap()
+            .with_max_machine_id(2);
+        let other = registry.clone();
+
+        let a = registry.claim().unwrap();
+        let b = other.claim().unwrap();
+        let c = registry.claim().unwrap();
+        assert_eq!([a.machine_id(), b.machine_id(), c.machine_id()], [0, 1, 2]);
+        assert!(matches!(
+            other.claim(),
+            Err(SnowflakeError::MachineIdsExhausted(2))
+        ));
+
+        registry.renew(&b).unwrap();
+        other.release(b).unwrap();
+        let d = registry.claim().unwrap();
+        assert_eq!(d.machine_id(), 1);
+
+        // Expired leases are taken over, and their old holder loses them
+        let expiring = FileLockRegistry::new(&dir, Duration::ZERO).unwrap();
+        let stale = expiring.claim().unwrap();
+        assert_eq!(stale.machine_id(), 3);
+        let taken = expiring.claim().unwrap();
+        assert_eq!(taken.machine_id(), 3);
+        assert!(matches!(
+            expiring.renew(&stale),
+            Err(SnowflakeError::LeaseLost(3))
+        ));
+        assert!(matches!(
+            expiring.release(stale),
+            Err(SnowflakeError::LeaseLost(3))
+        ));
+        expiring.release(taken).unwrap();
+
+        for lease in [a, c, d] {
+            registry.release(lease).unwrap();
+        }
+        let left: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(left, [".guard"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_lock_registry_racing_takeover() {
+        let dir = std::env::temp_dir().join(format!("snowflake-takeover-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let expiring = FileLockRegistry::new(&dir, Duration::ZERO)
+            .unwrap()
+            .with_max_machine_id(0);
+        let stale = expiring.claim().unwrap();
+
+        // Of many processes taking over the same stale lease, exactly one
+        // gets it and nobody overwrites the winner
+        let registry = FileLockRegistry::new(&dir, Duration::from_secs(60))
+            .unwrap()
+            .with_max_machine_id(0);
+        let claims: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8).map(|_| scope.spawn(|| registry.claim())).collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let mut won: Vec<_> = claims.into_iter().filter_map(Result::ok).collect();
+        assert_eq!(won.len(), 1);
+        let winner = won.pop().unwrap();
+        registry.renew(&winner).unwrap();
+        assert!(matches!(
+            expiring.renew(&stale),
+            Err(SnowflakeError::LeaseLost(0))
+        ));
+        registry.release(winner).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async_registry() {
+        use crate::sync::Mutex;
+        use std::collections::HashMap;
+
+        /// Leases machine IDs `0..=1` from a map, as a client of a remote
+        /// registry would
+        struct MemoryRegistry {
+            leases: Mutex<HashMap<u64, String>>,
+        }
+
+        impl AsyncMachineIdRegistry for MemoryRegistry {
+            fn claim(&self) -> RegistryFuture<'_, MachineIdLease> {
+                let mut leases = self.leases.lock();
+                let claimed = (0..=1).find(|id| !leases.contains_key(id)).map(|id| {
+                    let lease = MachineIdLease::new(id, format!("token-{}", id));
+                    leases.insert(id, lease.token().to_string());
+                    lease
+                });
+                Box::pin(std::future::ready(
+                    claimed.ok_or(SnowflakeError::MachineIdsExhausted(1)),
+                ))
+            }
+
+            fn renew<'a>(&'a self, lease: &'a MachineIdLease) -> RegistryFuture<'a, ()> {
+                let held = self
+                    .leases
+                    .lock()
+                    .get(&lease.machine_id())
+                    .map(String::as_str)
+                    == Some(lease.token());
+                Box::pin(std::future::ready(if held {
+                    Ok(())
+                } else {
+                    Err(SnowflakeError::LeaseLost(lease.machine_id()))
+                }))
+            }
+
+            fn release(&self, lease: MachineIdLease) -> RegistryFuture<'_, ()> {
+                self.leases.lock().remove(&lease.machine_id());
+                Box::pin(std::future::ready(Ok(())))
+            }
+        }
+
+        let registry: Box<dyn AsyncMachineIdRegistry> = Box::new(MemoryRegistry {
+            leases: Mutex::new(HashMap::new()),
+        });
+        let a = registry.claim().await.unwrap();
+        let b = registry.claim().await.unwrap();
+        assert_eq!((a.machine_id(), b.machine_id()), (0, 1));
+        assert!(matches!(
+            registry.claim().await,
+            Err(SnowflakeError::MachineIdsExhausted(1))
+        ));
+        registry.renew(&a).await.unwrap();
+        assert!(matches!(
+            registry.renew(&MachineIdLease::new(1, "stale")).await,
+            Err(SnowflakeError::LeaseLost(1))
+        ));
+        registry.release(b).await.unwrap();
+        assert_eq!(registry.claim().await.unwrap().machine_id(), 1);
+    }
+}
@@ -0,0 +1,86 @@
+//! Rocket integration for [`SnowflakeId`].
+//!
+//! Route signatures can take IDs directly as path segments (`FromParam`) and
+//! query or form fields (`FromFormField`), and `uri!` renders them back with
+//! `UriDisplay`. Malformed IDs fail the guard with the [`SnowflakeError`]
+//! from parsing, so Rocket forwards or rejects the request as usual.
+//!
+//! [`SnowflakeError`]: crate::error::SnowflakeError
+
+use crate::error::SnowflakeError;
+use crate::SnowflakeId;
+use rocket::form::{self, FromFormField, ValueField};
+use rocket::http::impl_from_uri_param_identity;
+use rocket::http::uri::fmt::{Formatter, Part, UriDisplay};
+use rocket::request::FromParam;
+use std::fmt::{self, Write};
+
+impl<'a> FromParam<'a> for SnowflakeId {
+    type Error = SnowflakeError;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        param.parse()
+    }
+}
+
+impl<'v> FromFormField<'v> for SnowflakeId {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        field
+            .value
+            .parse()
+            .map_err(|e: SnowflakeError| form::Error::custom(e).into())
+    }
+}
+
+impl<P: Part> UriDisplay<P> for SnowflakeId {
+    fn fmt(&self, f: &mut Formatter<'_, P>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl_from_uri_param_identity!(SnowflakeId);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rocket::http::Status;
+
+    use rocket::local::blocking::Client;
+
+    use rocket::{get, routes, uri};
+
+    #[get("/events/<id>")]
+    fn event(id: SnowflakeId) -> String {
+        id.to_string()
+    }
+
+    #[get("/events?<after>")]
+    fn events(after: Option<SnowflakeId>) -> String {
+        format!("{:?}", after)
+    }
+
+    #[test]
+    fn test_rocket_guards() {
+        let rocket = rocket::build().mount("/", routes![event, events]);
+        let client = Client::untracked(rocket).unwrap();
+
+        let response = client.get("/events/123456789012345678").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().unwrap(), "123456789012345678");
+
+        let response = client.get("/events?after=42").dispatch();
+        assert_eq!(response.into_string().unwrap(), "Some(SnowflakeId(42))");
+
+        // A malformed segment fails the guard, so no route matches
+        let response = client.get("/events/abc").dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        // Rocket maps invalid optional fields to `None`
+        let response = client.get("/events?after=-1").dispatch();
+        assert_eq!(response.into_string().unwrap(), "None");
+
+        let id = SnowflakeId::new(99).unwrap();
+        assert_eq!(uri!(event(id)).to_string(), "/events/99");
+        assert_eq!(uri!(events(Some(id))).to_string(), "/events?after=99");
+    }
+}
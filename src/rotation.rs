@@ -0,0 +1,302 @@
+//! Migrating a fleet from one epoch to another without downtime.
+//!
+//! Once a cutover time is reached, generators switch to the new epoch and
+//! set a reserved *era bit* in the machine ID. A decoder that knows the
+//! rotation reads that bit to pick the right epoch, so IDs from both eras
+//! can be decoded side by side for as long as old IDs are in circulation.
+//!
+//! The era bit is the second-highest machine ID bit by default, leaving the
+//! highest one to [`Snowflake::tombstone_of`], so rotating IDs can still be
+//! turned into tombstones and a tombstone is never read as a new-era ID.
+//! Machine IDs must leave both bits clear, which leaves a quarter of the
+//! machine ID range. The era bit is set in every ID minted after the
+//! cutover, for good.
+//!
+//! Because new-era IDs carry smaller timestamp offsets, raw ID order is only
+//! chronological within an era; compare [`EpochRotation::timestamp`] values
+//! to order IDs across the cutover.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::rotation::{Era, EpochRotation};
+//! use snowflake_id::Epoch;
+//! use std::thread;
+//!
+//! // Cut over to a 2024 epoch at 2024-06-01T00:00:00Z
+//! let rotation = EpochRotation::new(Epoch::TWITTER, Epoch::custom(1704067200000), 1717200000000)
+//!     .unwrap();
+//! let generator = rotation.generator(7).unwrap();
+//!
+//! let id = generator.next_id(thread::sleep);
+//! assert_eq!(rotation.era_of(id), Era::New);
+//! assert_eq!(rotation.machine_id(id), 7);
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::defs::{MACHINE_ID_BITS, MAX_MACHINE_ID, SEQUENCE_BITS};
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::generator::SnowflakeOperation;
+use crate::snowflake::Snowflake;
+use crate::{SnowflakeGenerator, SnowflakeId};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Which side of an [`EpochRotation`] cutover an ID was minted on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Era {
+    Old,
+    New,
+}
+
+/// A planned switch from one epoch to another at a fixed cutover time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpochRotation {
+    old: Epoch,
+    new: Epoch,
+    cutover_ms: i64,
+    era_mask: u64,
+}
+
+impl EpochRotation {
+    /// Plans a rotation from `old` to `new` at Unix timestamp `cutover_ms`,
+    /// reserving the second-highest machine ID bit as the era bit.
+    ///
+    /// The new epoch must not be later than the cutover, or the first
+    /// new-era IDs would have negative timestamp offsets.
+    pub fn new(old: Epoch, new: Epoch, cutover_ms: i64) -> Result<Self, SnowflakeError> {
+        if new.as_millis() > cutover_ms {
+            return Err(SnowflakeError::TimestampOverflow);
+        }
+
+        Ok(EpochRotation {
+            old,
+            new,
+            cutover_ms,
+            era_mask: 1 << (MACHINE_ID_BITS - 2),
+        })
+    }
+
+    /// Uses machine ID bit `bit` as the era bit instead of the
+    /// second-highest one. The highest bit is the tombstone bit and can't be
+    /// used.
+    pub fn with_era_bit(mut self, bit: u32) -> Result<Self, SnowflakeError> {
+        if u64::from(bit) >= MACHINE_ID_BITS {
+            return Err(SnowflakeError::InvalidMachineId(
+                1u64.checked_shl(bit).unwrap_or(u64::MAX),
+                MAX_MACHINE_ID,
+            ));
+        }
+        if 1 << bit == tombstone_bit() {
+            return Err(SnowflakeError::InvalidConfig(format!(
+                "machine ID bit {} is the tombstone bit",
+                bit
+            )));
+        }
+        self.era_mask = 1 << bit;
+        Ok(self)
+    }
+
+    pub fn old_epoch(&self) -> Epoch {
+        self.old
+    }
+
+    pub fn new_epoch(&self) -> Epoch {
+        self.new
+    }
+
+    /// Returns the cutover time in milliseconds since the Unix epoch
+    pub fn cutover_ms(&self) -> i64 {
+        self.cutover_ms
+    }
+
+    /// Returns the machine ID bit that marks new-era IDs
+    pub fn era_mask(&self) -> u64 {
+        self.era_mask
+    }
+
+    /// Returns the era the ID was minted in
+    pub fn era_of(&self, id: SnowflakeId) -> Era {
+        if id.machine_id() & self.era_mask != 0 {
+            Era::New
+        } else {
+            Era::Old
+        }
+    }
+
+    /// Returns the epoch the ID's timestamp is relative to
+    pub fn epoch_of(&self, id: SnowflakeId) -> Epoch {
+        match self.era_of(id) {
+            Era::Old => self.old,
+            Era::New => self.new,
+        }
+    }
+
+    /// Returns the ID's creation time in milliseconds since the Unix epoch
+    pub fn timestamp(&self, id: SnowflakeId) -> i64 {
+        id.timestamp_with_epoch(self.epoch_of(id))
+    }
+
+    /// Returns the ID's creation time as a `DateTime<Utc>`
+    ///
+    /// # Panics
+    /// Panics if the resulting timestamp is outside the range supported by chrono.
+    pub fn datetime(&self, id: SnowflakeId) -> DateTime<Utc> {
+        id.datetime_with_epoch(self.epoch_of(id))
+    }
+
+    /// Returns the ID's machine ID with the era bit cleared
+    pub fn machine_id(&self, id: SnowflakeId) -> u64 {
+        id.machine_id() & !self.era_mask
+    }
+
+    /// Creates a generator that mints old-era IDs until the cutover and
+    /// new-era IDs from then on
+    pub fn generator(&self, machine_id: u64) -> Result<RotatingGenerator, SnowflakeError> {
+        self.generator_with_clock(machine_id, SystemClock)
+    }
+
+    /// Like [`Self::generator`], reading time from `clock`
+    pub fn generator_with_clock(
+        &self,
+        machine_id: u64,
+        clock: impl Clock + 'static,
+    ) -> Result<RotatingGenerator, SnowflakeError> {
+        if machine_id & self.era_mask != 0 || machine_id > MAX_MACHINE_ID {
+            return Err(SnowflakeError::InvalidMachineId(
+                machine_id,
+                MAX_MACHINE_ID & !self.era_mask & !tombstone_bit(),
+            ));
+        }
+
+        // The builder rejects machine IDs on the tombstone bit in either era
+        let clock: Arc<dyn Clock> = Arc::new(clock);
+        let build = |machine_id, epoch| {
+            SnowflakeGenerator::builder(machine_id)
+                .epoch(epoch)
+                .clock(clock.clone())
+                .reserve_tombstone_bit()
+                .build()
+        };
+        Ok(RotatingGenerator {
+            old: build(machine_id, self.old)?,
+            new: build(machine_id | self.era_mask, self.new)?,
+            cutover_ms: self.cutover_ms,
+            clock,
+        })
+    }
+}
+
+/// The tombstone bit as a machine ID mask
+fn tombstone_bit() -> u64 {
+    SnowflakeId::tombstone_mask() >> SEQUENCE_BITS
+}
+
+/// A generator that switches epochs at an [`EpochRotation`]'s cutover
+pub struct RotatingGenerator {
+    old: SnowflakeGenerator,
+    new: SnowflakeGenerator,
+    cutover_ms: i64,
+    clock: Arc<dyn Clock>,
+}
+
+impl RotatingGenerator {
+    /// Returns the era IDs are currently minted in
+    pub fn current_era(&self) -> Era {
+        if self.clock.now_millis() >= self.cutover_ms {
+            Era::New
+        } else {
+            Era::Old
+        }
+    }
+
+    pub fn try_next_id(&self) -> Result<SnowflakeOperation<SnowflakeId>, SnowflakeError> {
+        match self.current_era() {
+            Era::Old => self.old.try_next_id(),
+            Era::New => self.new.try_next_id(),
+        }
+    }
+
+    /// Generates the next ID, calling `on_pending` whenever the generator has
+    /// to wait for the clock
+    ///
+    /// # Panics
+    /// Panics if generation fails, see [`Self::next_id_checked`].
+    pub fn next_id(&self, on_pending: impl FnMut(Duration)) -> SnowflakeId {
+        self.next_id_checked(on_pending)
+            .expect("snowflake generation failed")
+    }
+
+    /// Generates the next ID, returning an error instead of panicking on
+    /// clock failures
+    pub fn next_id_checked(
+        &self,
+        mut on_pending: impl FnMut(Duration),
+    ) -> Result<SnowflakeId, SnowflakeError> {
+        loop {
+            match self.try_next_id()? {
+                SnowflakeOperation::Ready(id) => return Ok(id),
+                SnowflakeOperation::Pending(wait) => on_pending(wait),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SNOWFLAKE_ID_EPOCH;
+
+    #[test]
+    fn test_epoch_rotation() {
+        use crate::clock::ManualClock;
+
+        let new_epoch = Epoch::custom(SNOWFLAKE_ID_EPOCH + 1_000_000);
+        let cutover = SNOWFLAKE_ID_EPOCH + 2_000_000;
+        let rotation = EpochRotation::new(Epoch::TWITTER, new_epoch, cutover).unwrap();
+        assert!(EpochRotation::new(Epoch::TWITTER, Epoch::custom(cutover + 1), cutover).is_err());
+
+        let clock = ManualClock::new(cutover - 10);
+        let generator = rotation.generator_with_clock(7, clock.clone()).unwrap();
+        // The era bit, then the tombstone bit
+        assert!(rotation.generator(256).is_err());
+        assert!(rotation.generator(512).is_err());
+
+        assert_eq!(generator.current_era(), Era::Old);
+        let old = generator.next_id(|_| clock.advance(Duration::from_millis(1)));
+        clock.set(cutover + 5);
+        assert_eq!(generator.current_era(), Era::New);
+        let new = generator.next_id(|_| clock.advance(Duration::from_millis(1)));
+
+        assert_eq!(rotation.era_of(old), Era::Old);
+        assert_eq!(rotation.era_of(new), Era::New);
+        assert_eq!(rotation.machine_id(old), 7);
+        assert_eq!(rotation.machine_id(new), 7);
+        assert_eq!(rotation.timestamp(old), cutover - 10);
+        assert_eq!(rotation.timestamp(new), cutover + 5);
+        // Raw ordering doesn't survive the cutover, decoded timestamps do
+        assert!(new < old);
+        assert!(rotation.datetime(new) > rotation.datetime(old));
+
+        // Era and tombstone bits are independent
+        assert_eq!(rotation.era_mask(), 256);
+        assert!(!new.is_tombstone());
+        let tombstone = SnowflakeId::tombstone_of(new).unwrap();
+        assert_eq!(rotation.era_of(tombstone), Era::New);
+        assert_eq!(tombstone.live_id(), Some(new));
+        assert_eq!(
+            rotation.era_of(SnowflakeId::tombstone_of(old).unwrap()),
+            Era::Old
+        );
+        assert!(matches!(
+            rotation.with_era_bit(9),
+            Err(SnowflakeError::InvalidConfig(_))
+        ));
+
+        let low_bit = rotation.with_era_bit(0).unwrap();
+        assert_eq!(low_bit.era_mask(), 1);
+        assert!(low_bit.generator(7).is_err());
+        assert!(rotation.with_era_bit(10).is_err());
+    }
+}
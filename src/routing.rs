@@ -0,0 +1,289 @@
+use crate::error::SnowflakeError;
+use crate::snowflake::Snowflake;
+use crate::SnowflakeId;
+use std::ops::RangeInclusive;
+
+struct Route<E> {
+    start: u64,
+    end: u64,
+    endpoint: E,
+}
+
+/// Maps machine-id ranges to service endpoints, so a gateway can find the
+/// cluster that owns an entity from its ID alone.
+///
+/// # Example
+/// ```
+/// use snowflake_id::routing::RoutingTable;
+/// use snowflake_id::SnowflakeId;
+///
+/// let mut table = RoutingTable::new();
+/// table.insert(0..=511, "eu-west").unwrap();
+/// table.insert(512..=1023, "us-east").unwrap();
+///
+/// // machine id 600
+/// let id = SnowflakeId::new((600 << 12) | 7).unwrap();
+/// assert_eq!(table.route(&id), Some(&"us-east"));
+/// ```
+pub struct RoutingTable<E> {
+    routes: Vec<Route<E>>,
+    fallback: Option<E>,
+}
+
+impl<E> RoutingTable<E> {
+    pub fn new() -> Self {
+        RoutingTable {
+            routes: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    /// Sets the endpoint returned for machine ids not covered by any range
+    pub fn with_fallback(mut self, endpoint: E) -> Self {
+        self.fallback = Some(endpoint);
+        self
+    }
+
+    /// Routes an inclusive range of machine ids to `endpoint`.
+    ///
+    /// Ranges may not overlap an existing route; an empty or overlapping
+    /// range fails with [`SnowflakeError::InvalidRoute`].
+    pub fn insert(
+        &mut self,
+        machine_ids: RangeInclusive<u64>,
+        endpoint: E,
+    ) -> Result<(), SnowflakeError> {
+        let (start, end) = machine_ids.into_inner();
+        if start > end {
+            return Err(SnowflakeError::InvalidRoute { start, end });
+        }
+
+        let index = self.routes.partition_point(|route| route.start < start);
+        let overlaps_prev = index > 0 && self.routes[index - 1].end >= start;
+        let overlaps_next = index < self.routes.len() && self.routes[index].start <= end;
+        if overlaps_prev || overlaps_next {
+            return Err(SnowflakeError::InvalidRoute { start, end });
+        }
+
+        self.routes.insert(
+            index,
+            Route {
+                start,
+                end,
+                endpoint,
+            },
+        );
+        Ok(())
+    }
+
+    /// Routes every machine id whose top `prefix_bits` bits equal `region`.
+    ///
+    /// `machine_id_bits` is the width of the machine id field in the layout,
+    /// e.g. [`MACHINE_ID_BITS`](crate::defs::MACHINE_ID_BITS). A region that
+    /// doesn't fit in `prefix_bits` fails with
+    /// [`SnowflakeError::InvalidConfig`].
+    pub fn insert_region(
+        &mut self,
+        machine_id_bits: u64,
+        prefix_bits: u64,
+        region: u64,
+        endpoint: E,
+    ) -> Result<(), SnowflakeError> {
+        if prefix_bits == 0 || prefix_bits > machine_id_bits || region >= (1 << prefix_bits) {
+            return Err(SnowflakeError::InvalidConfig(format!(
+                "region {} doesn't fit in {} prefix bits of a {}-bit machine id",
+                region, prefix_bits, machine_id_bits
+            )));
+        }
+
+        let shift = machine_id_bits - prefix_bits;
+        let start = region << shift;
+        let end = start | ((1 << shift) - 1);
+        self.insert(start..=end, endpoint)
+    }
+
+    /// Returns the endpoint owning the machine that generated `id`
+    pub fn route<S: Snowflake>(&self, id: &S) -> Option<&E> {
+        self.route_machine_id(id.machine_id())
+    }
+
+    /// Returns the endpoint owning `machine_id`
+    pub fn route_machine_id(&self, machine_id: u64) -> Option<&E> {
+        let index = self
+            .routes
+            .partition_point(|route| route.start <= machine_id);
+        if index > 0 {
+            let route = &self.routes[index - 1];
+            if machine_id <= route.end {
+                return Some(&route.endpoint);
+            }
+        }
+        self.fallback.as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+impl<E> Default for RoutingTable<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which bits of an ID pick its shard in [`SnowflakeId::shard_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ShardStrategy {
+    /// The machine ID, keeping everything one generator minted together
+    MachineId,
+    /// The sequence number, spreading a burst from one machine evenly
+    Sequence,
+    /// A fixed mix of all the bits. Spreads well whatever the traffic
+    /// pattern, and is stable across processes and crate versions.
+    #[default]
+    Hash,
+}
+
+impl SnowflakeId {
+    /// Returns the shard out of `shards` that owns this ID, using
+    /// [`ShardStrategy::Hash`]
+    ///
+    /// Returns [`SnowflakeError::InvalidShardCount`] if `shards` is zero.
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::SnowflakeGenerator;
+    ///
+    /// let id = SnowflakeGenerator::new(1).unwrap().generate().unwrap();
+    /// let shard = id.shard(16).unwrap();
+    /// assert!(shard < 16);
+    /// assert_eq!(id.shard(16).unwrap(), shard);
+    /// ```
+    pub fn shard(&self, shards: usize) -> Result<usize, SnowflakeError> {
+        self.shard_with(shards, ShardStrategy::Hash)
+    }
+
+    /// Returns the shard out of `shards` that owns this ID, choosing it from
+    /// the bits selected by `strategy`
+    ///
+    /// Returns [`SnowflakeError::InvalidShardCount`] if `shards` is zero.
+    pub fn shard_with(
+        &self,
+        shards: usize,
+        strategy: ShardStrategy,
+    ) -> Result<usize, SnowflakeError> {
+        if shards == 0 {
+            return Err(SnowflakeError::InvalidShardCount(0, usize::MAX as u64));
+        }
+        let key = match strategy {
+            ShardStrategy::MachineId => self.machine_id(),
+            ShardStrategy::Sequence => self.sequence(),
+            ShardStrategy::Hash => mix64(self.id() as u64),
+        };
+        Ok((key % shards as u64) as usize)
+    }
+}
+
+/// The splitmix64 finalizer. Fixed so shard assignments never move.
+pub(crate) fn mix64(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+    use crate::MACHINE_ID_BITS;
+    use crate::MAX_MACHINE_ID;
+    use std::thread;
+
+    #[test]
+    fn test_shard_distribution() {
+        let spread = |ids: &[SnowflakeId], strategy: ShardStrategy| {
+            let mut counts = [0usize; 16];
+            for id in ids {
+                counts[id.shard_with(16, strategy).unwrap()] += 1;
+            }
+            let mean = ids.len() / 16;
+            counts
+                .iter()
+                .all(|&count| count.abs_diff(mean) <= mean / 10)
+        };
+
+        // Consecutive IDs from one machine, the worst case for the raw bits
+        let burst = SnowflakeGenerator::new(1)
+            .unwrap()
+            .generate_bulk(64_000)
+            .unwrap();
+        assert!(spread(&burst, ShardStrategy::Hash));
+        assert!(spread(&burst, ShardStrategy::Sequence));
+        assert!(!spread(&burst, ShardStrategy::MachineId));
+
+        let fleet: Vec<_> = (0..64_000u64)
+            .map(|n| SnowflakeId::from_component_parts(n / 1_024, n % 1_024, 0))
+            .collect();
+        assert!(spread(&fleet, ShardStrategy::Hash));
+        assert!(spread(&fleet, ShardStrategy::MachineId));
+
+        // Assignments are fixed, not seeded per process
+        let id = SnowflakeId::new(175928847299117063).unwrap();
+        assert_eq!(id.shard(1_000).unwrap(), 608);
+        assert_eq!(id.shard_with(1_000, ShardStrategy::Hash).unwrap(), 608);
+        assert_eq!(id.shard(1).unwrap(), 0);
+        assert_eq!(id.shard_with(1_024, ShardStrategy::MachineId).unwrap(), 32);
+        assert!(matches!(
+            id.shard(0),
+            Err(SnowflakeError::InvalidShardCount(0, _))
+        ));
+    }
+
+    #[test]
+    fn test_routing_table() {
+        let mut table = RoutingTable::new().with_fallback("default");
+        table.insert(0..=99, "a").unwrap();
+        table.insert(100..=199, "b").unwrap();
+        assert!(matches!(
+            table.insert(150..=250, "c"),
+            Err(SnowflakeError::InvalidRoute {
+                start: 150,
+                end: 250
+            })
+        ));
+        assert!(table.insert(50..=50, "c").is_err());
+        assert_eq!(table.len(), 2);
+
+        let generator = SnowflakeGenerator::new(120).unwrap();
+        let id = generator.next_id(|_| thread::yield_now());
+        assert_eq!(table.route(&id), Some(&"b"));
+        assert_eq!(table.route_machine_id(99), Some(&"a"));
+        assert_eq!(table.route_machine_id(500), Some(&"default"));
+    }
+
+    #[test]
+    fn test_routing_table_regions() {
+        let mut table = RoutingTable::new();
+        // Top two machine id bits select one of four regions
+        for region in 0..4 {
+            table
+                .insert_region(MACHINE_ID_BITS, 2, region, region)
+                .unwrap();
+        }
+        assert!(matches!(
+            table.insert_region(MACHINE_ID_BITS, 2, 4, 4),
+            Err(SnowflakeError::InvalidConfig(_))
+        ));
+
+        assert_eq!(table.route_machine_id(0), Some(&0));
+        assert_eq!(table.route_machine_id(255), Some(&0));
+        assert_eq!(table.route_machine_id(256), Some(&1));
+        assert_eq!(table.route_machine_id(MAX_MACHINE_ID), Some(&3));
+    }
+}
@@ -0,0 +1,93 @@
+//! The few async runtime services the async generator needs, so it runs on
+//! tokio, async-std or smol.
+//!
+//! The lock comes from `async-lock`, which works on any executor. Sleeps use
+//! tokio's timer when called inside a tokio runtime, and otherwise the timer
+//! of async-std or smol (`async-io`), which run their own reactor and work
+//! from any executor. With no usable timer a sleep yields to the executor
+//! until the duration has passed.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+pub(crate) use async_lock::Mutex;
+
+/// Sleeps for `duration`, see the [module docs](self)
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(feature = "tokio")]
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return tokio::time::sleep(duration).await;
+    }
+
+    #[cfg(feature = "async-std")]
+    async_std::task::sleep(duration).await;
+
+    #[cfg(all(feature = "smol", not(feature = "async-std")))]
+    async_io::Timer::after(duration).await;
+
+    #[cfg(not(any(feature = "async-std", feature = "smol")))]
+    {
+        // A duration too long for an `Instant` never ends
+        let deadline = std::time::Instant::now().checked_add(duration);
+        while deadline.is_none_or(|deadline| std::time::Instant::now() < deadline) {
+            yield_now().await;
+        }
+    }
+}
+
+/// Returns control to the executor once before completing
+pub(crate) fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+pub(crate) struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(any(feature = "smol", feature = "async-std"))]
+    use crate::AsyncSnowflakeGenerator;
+
+    #[cfg(not(any(feature = "async-std", feature = "smol")))]
+    #[test]
+    fn test_fallback_sleep_longer_than_instant() {
+        use futures_util::FutureExt;
+        use std::time::Duration;
+
+        // Outside any runtime, so this takes the yielding fallback
+        assert!(super::sleep(Duration::MAX).now_or_never().is_none());
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn test_async_generator_on_smol() {
+        let generator = AsyncSnowflakeGenerator::new(1).unwrap();
+        // Spans several ticks, so the generator has to sleep on smol's timer
+        let ids = async_io::block_on(generator.next_id_bulk_checked(10_000)).unwrap();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn test_async_generator_on_async_std() {
+        let generator = AsyncSnowflakeGenerator::new(1).unwrap();
+        let ids = async_std::task::block_on(generator.next_id_bulk_checked(10_000)).unwrap();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
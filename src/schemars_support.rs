@@ -0,0 +1,45 @@
+//! schemars `JsonSchema` for [`SnowflakeId`].
+//!
+//! Human-readable formats serialize IDs as decimal strings, so the schema is
+//! a string carrying the `int64` format and a digits-only pattern.
+
+use crate::SnowflakeId;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use std::borrow::Cow;
+
+impl JsonSchema for SnowflakeId {
+    fn schema_name() -> Cow<'static, str> {
+        "SnowflakeId".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "snowflake_id::SnowflakeId".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "format": "int64",
+            "pattern": "^[0-9]{1,19}$",
+            "description": "A 64-bit snowflake ID, serialized as a decimal string",
+            "examples": ["1541815603606036480"],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+
+    #[test]
+    fn test_json_schema() {
+        let schema = serde_json::to_value(schemars::schema_for!(SnowflakeId)).unwrap();
+        assert_eq!(schema["type"], "string");
+        assert_eq!(schema["format"], "int64");
+        assert_eq!(schema["pattern"], "^[0-9]{1,19}$");
+
+        let id = SnowflakeGenerator::new(1).unwrap().generate().unwrap();
+        assert!(serde_json::to_value(id).unwrap().is_string());
+    }
+}
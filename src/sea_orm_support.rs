@@ -0,0 +1,112 @@
+//! SeaORM conversions so entities can use [`SnowflakeId`] columns, including
+//! primary keys, stored as `BIGINT`.
+
+use crate::SnowflakeId;
+use sea_orm::sea_query::{ArrayType, ColumnType, Nullable, Value, ValueType, ValueTypeErr};
+use sea_orm::{ColIdx, DbErr, QueryResult, TryFromU64, TryGetError, TryGetable};
+
+impl From<SnowflakeId> for Value {
+    fn from(id: SnowflakeId) -> Self {
+        Value::BigInt(Some(id.0))
+    }
+}
+
+impl Nullable for SnowflakeId {
+    fn null() -> Value {
+        Value::BigInt(None)
+    }
+}
+
+impl ValueType for SnowflakeId {
+    fn try_from(v: Value) -> Result<Self, ValueTypeErr> {
+        match v {
+            Value::BigInt(Some(value)) => SnowflakeId::new(value).map_err(|_| ValueTypeErr),
+            _ => Err(ValueTypeErr),
+        }
+    }
+
+    fn type_name() -> String {
+        "SnowflakeId".to_owned()
+    }
+
+    fn array_type() -> ArrayType {
+        ArrayType::BigInt
+    }
+
+    fn column_type() -> ColumnType {
+        ColumnType::BigInteger
+    }
+}
+
+impl TryGetable for SnowflakeId {
+    fn try_get_by<I: ColIdx>(res: &QueryResult, index: I) -> Result<Self, TryGetError> {
+        let value = i64::try_get_by(res, index)?;
+        SnowflakeId::new(value).map_err(|e| TryGetError::DbErr(DbErr::Type(e.to_string())))
+    }
+}
+
+impl TryFromU64 for SnowflakeId {
+    fn try_from_u64(n: u64) -> Result<Self, DbErr> {
+        <i64 as TryFrom<u64>>::try_from(n)
+            .map_err(|_| DbErr::Type(format!("{} does not fit in a snowflake id", n)))
+            .and_then(|value| SnowflakeId::new(value).map_err(|e| DbErr::Type(e.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sea_orm::entity::prelude::*;
+
+    use sea_orm::{DbBackend, MockDatabase, QueryTrait};
+
+    mod event {
+        use crate::SnowflakeId;
+        use sea_orm::entity::prelude::*;
+
+        #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+        #[sea_orm(table_name = "events")]
+        pub struct Model {
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub id: SnowflakeId,
+            pub parent: Option<SnowflakeId>,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    #[test]
+    fn test_value_conversion() {
+        let id = SnowflakeId::new(123456789012345678).unwrap();
+        let value: Value = id.into();
+        assert_eq!(value, Value::BigInt(Some(123456789012345678)));
+        assert_eq!(<SnowflakeId as ValueType>::try_from(value).unwrap(), id);
+        assert!(<SnowflakeId as ValueType>::try_from(Value::BigInt(Some(-1))).is_err());
+        assert!(<SnowflakeId as ValueType>::try_from(Value::BigInt(None)).is_err());
+        assert_eq!(SnowflakeId::try_from_u64(42).unwrap().id(), 42);
+        assert!(SnowflakeId::try_from_u64(u64::MAX).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_entity_primary_key() {
+        let model = event::Model {
+            id: SnowflakeId::new(987654321).unwrap(),
+            parent: None,
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![model.clone()]])
+            .into_connection();
+
+        let found = event::Entity::find_by_id(model.id).one(&db).await.unwrap();
+        assert_eq!(found, Some(model));
+
+        let sql = event::Entity::find_by_id(SnowflakeId::new(5).unwrap())
+            .build(DbBackend::Postgres)
+            .to_string();
+        assert!(sql.ends_with(r#"WHERE "events"."id" = 5"#), "{}", sql);
+    }
+}
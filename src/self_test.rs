@@ -0,0 +1,319 @@
+use crate::clock::ManualClock;
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::generator::{GeneratorCore, GeneratorState, SnowflakeGenerator, SnowflakeOperation};
+use crate::snowflake::Snowflake;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Outcome of a single self-test check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    /// Explanation of the first failure, if any
+    pub detail: Option<String>,
+}
+
+/// Structured result of [`SnowflakeGenerator::self_test`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestReport {
+    pub checks: Vec<SelfTestCheck>,
+    /// Number of IDs minted on the simulated clock during the test
+    pub ids_generated: usize,
+    /// The generator's [`SnowflakeGenerator::fingerprint`]
+    pub fingerprint: [u8; 32],
+}
+
+impl SelfTestReport {
+    /// Returns true if every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// Returns the checks that failed
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = if check.passed { "ok" } else { "FAILED" };
+            write!(f, "{}: {}", check.name, status)?;
+            if let Some(detail) = &check.detail {
+                write!(f, " ({})", detail)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "{} ids generated", self.ids_generated)?;
+        write!(f, "fingerprint ")?;
+        for byte in &self.fingerprint {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: Snowflake> SnowflakeGenerator<S> {
+    /// Exercises this generator's layout, epoch and machine ID against a
+    /// simulated clock and reports whether generation behaves correctly.
+    ///
+    /// The test runs on a scratch generator, so it never consumes sequence
+    /// numbers from (or blocks) this one, nor shows up in its metrics. It is cheap enough to run at boot
+    /// as a guard against misconfigured custom layouts.
+    ///
+    /// # Example
+    /// ```
+    /// use snowflake_id::SnowflakeGenerator;
+    ///
+    /// let generator = SnowflakeGenerator::new(1).unwrap();
+    /// let report = generator.self_test();
+    /// assert!(report.passed(), "{}", report);
+    /// ```
+    pub fn self_test(&self) -> SelfTestReport {
+        let mut checks = vec![
+            check_layout_roundtrip::<S>(self.core.machine_id),
+            check_tombstone_bit::<S>(),
+        ];
+
+        // Starts at the real clock's tick, kept clear of the layout's end so
+        // the exhaustion check can move on to the next one
+        let offset = (self.core.current_timestamp() - self.core.epoch_ticks())
+            .clamp(0, (S::max_timestamp() - 1).max(0));
+        let clock = Arc::new(ManualClock::new(0));
+        let mut core = GeneratorCore::<S>::unmetered(
+            self.core.machine_id,
+            Epoch::custom(self.core.epoch),
+            clock.clone(),
+        )
+        .expect("machine id was already validated");
+        core.tick_micros = self.core.tick_micros;
+        core.tolerance_ms = self.core.tolerance_ms;
+        let start = core.tick_start_micros(core.epoch_ticks() + offset);
+        clock.advance(Duration::from_micros(u64::try_from(start).unwrap_or(0)));
+        let mut state = GeneratorState::new();
+        let mut ids = Vec::new();
+
+        checks.push(run_check("same_tick_exhaustion", || {
+            check_exhaustion(&core, &clock, &mut state, &mut ids)
+        }));
+        checks.push(run_check("clock_drift_tolerance", || {
+            check_drift(&core, &clock, &mut state, &mut ids)
+        }));
+        checks.push(run_check("uniqueness_and_monotonicity", || {
+            match ids.windows(2).position(|pair| pair[0] >= pair[1]) {
+                Some(i) => Err(format!("id {} is not greater than {:?}", i + 1, ids[i])),
+                None => Ok(()),
+            }
+        }));
+
+        SelfTestReport {
+            checks,
+            ids_generated: ids.len(),
+            fingerprint: self.fingerprint(),
+        }
+    }
+}
+
+fn run_check(name: &'static str, check: impl FnOnce() -> Result<(), String>) -> SelfTestCheck {
+    let result = check();
+    SelfTestCheck {
+        name,
+        passed: result.is_ok(),
+        detail: result.err(),
+    }
+}
+
+fn check_tombstone_bit<S: Snowflake>() -> SelfTestCheck {
+    run_check("tombstone_bit", || {
+        let mask = S::tombstone_mask();
+        let machine_bits = S::machine_id_mask() << S::sequence_bits();
+        if mask.count_ones() != 1 || mask & machine_bits == 0 {
+            return Err(format!(
+                "tombstone mask {:#x} is not a single machine id bit",
+                mask
+            ));
+        }
+        Ok(())
+    })
+}
+
+fn check_layout_roundtrip<S: Snowflake>(machine_id: u64) -> SelfTestCheck {
+    run_check("layout_roundtrip", || {
+        let timestamps = [0, 1, S::max_timestamp() as u64];
+        let machine_ids = [0, machine_id, S::max_machine_id()];
+        let sequences = [0, 1, S::max_sequence()];
+
+        for &timestamp in &timestamps {
+            for &machine_id in &machine_ids {
+                for &sequence in &sequences {
+                    let id = S::from_component_parts(timestamp, machine_id, sequence);
+                    let decoded = (id.timestamp(), id.machine_id(), id.sequence());
+                    if decoded != (timestamp, machine_id, sequence) || !id.is_valid() {
+                        return Err(format!(
+                            "parts {:?} decoded as {:?}",
+                            (timestamp, machine_id, sequence),
+                            decoded
+                        ));
+                    }
+                    if id.id() >> 63 != 0 {
+                        return Err(format!(
+                            "parts {:?} set the sign bit",
+                            (timestamp, machine_id, sequence)
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+fn next_ready<S: Snowflake>(
+    core: &GeneratorCore<S>,
+    state: &mut GeneratorState,
+) -> Result<S, String> {
+    match core.try_next(state) {
+        Ok(SnowflakeOperation::Ready(id)) => Ok(id),
+        Ok(SnowflakeOperation::Pending(wait)) => {
+            Err(format!("unexpected pending wait of {:?}", wait))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn check_exhaustion<S: Snowflake>(
+    core: &GeneratorCore<S>,
+    clock: &ManualClock,
+    state: &mut GeneratorState,
+    ids: &mut Vec<S>,
+) -> Result<(), String> {
+    for _ in 0..=S::max_sequence() {
+        ids.push(next_ready(core, state)?);
+    }
+
+    match core.try_next(state) {
+        Ok(SnowflakeOperation::Pending(_)) => {}
+        Ok(SnowflakeOperation::Ready(id)) => {
+            return Err(format!("sequence space exhausted but got {:?}", id))
+        }
+        Err(e) => return Err(e.to_string()),
+    }
+
+    clock.advance(core.ticks_duration(1));
+    let id = next_ready(core, state)?;
+    if id.sequence() != 0 {
+        return Err(format!(
+            "sequence did not reset on new tick: {}",
+            id.sequence()
+        ));
+    }
+    ids.push(id);
+    Ok(())
+}
+
+fn check_drift<S: Snowflake>(
+    core: &GeneratorCore<S>,
+    clock: &ManualClock,
+    state: &mut GeneratorState,
+    ids: &mut Vec<S>,
+) -> Result<(), String> {
+    let tolerance = core.ticks_duration(core.tolerance_ticks());
+
+    if !tolerance.is_zero() {
+        clock.rewind(tolerance);
+        match core.try_next(state) {
+            Ok(SnowflakeOperation::Pending(wait)) if wait == tolerance => {}
+            other => {
+                return Err(format!(
+                    "drift within tolerance not deferred: {}",
+                    describe(other)
+                ))
+            }
+        }
+    }
+
+    clock.rewind(core.ticks_duration(1));
+    match core.try_next(state) {
+        Err(SnowflakeError::ClockMovedBackwards { .. }) => {}
+        other => {
+            return Err(format!(
+                "drift beyond tolerance not rejected: {}",
+                describe(other)
+            ))
+        }
+    }
+
+    clock.advance(tolerance + core.ticks_duration(1));
+    ids.push(next_ready(core, state)?);
+    Ok(())
+}
+
+fn describe<S: Snowflake>(result: Result<SnowflakeOperation<S>, SnowflakeError>) -> String {
+    match result {
+        Ok(SnowflakeOperation::Ready(id)) => format!("ready {:?}", id),
+        Ok(SnowflakeOperation::Pending(wait)) => format!("pending {:?}", wait),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+    use crate::MAX_MACHINE_ID;
+    use crate::MAX_SEQUENCE;
+
+    #[test]
+    fn test_self_test_passes() {
+        let generator = SnowflakeGenerator::with_epoch(MAX_MACHINE_ID, Epoch::DISCORD).unwrap();
+        let report = generator.self_test();
+
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.checks.len(), 5);
+        assert!(report.ids_generated as u64 > MAX_SEQUENCE);
+        assert_eq!(report.failures().count(), 0);
+    }
+
+    #[test]
+    fn test_self_test_with_custom_ticks() {
+        for tick in [Duration::from_micros(100), Duration::from_millis(10)] {
+            let generator = SnowflakeGenerator::builder(1).tick(tick).build().unwrap();
+            let report = generator.self_test();
+            assert!(report.passed(), "{:?}: {}", tick, report);
+        }
+    }
+
+    #[test]
+    fn test_self_test_detects_broken_layout() {
+        // Sequence bits overlap the machine id field
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        struct Broken(u64);
+
+        impl Snowflake for Broken {
+            fn from_component_parts(timestamp: u64, machine_id: u64, sequence: u64) -> Self {
+                Broken((timestamp << 22) | (machine_id << 10) | sequence)
+            }
+            fn id(&self) -> u64 {
+                self.0
+            }
+            fn timestamp_bits() -> u64 {
+                41
+            }
+            fn machine_id_bits() -> u64 {
+                10
+            }
+            fn sequence_bits() -> u64 {
+                12
+            }
+        }
+
+        let generator = crate::generator::SnowflakeGenerator::<Broken>::new(5).unwrap();
+        let report = generator.self_test();
+        assert!(!report.passed());
+        assert_eq!(report.failures().next().unwrap().name, "layout_roundtrip");
+    }
+}
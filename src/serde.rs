@@ -0,0 +1,474 @@
+//! Serde helper modules for pinning how a [`SnowflakeId`] field is represented.
+//!
+//! Use them with `#[serde(with = "...")]` on individual fields.
+
+use crate::SnowflakeId;
+use ::serde::de::{self, MapAccess, Visitor};
+use ::serde::{Deserializer, Serializer};
+use std::fmt;
+
+/// Serializes the ID as an exact JSON integer rather than a string.
+///
+/// The value is written through `serialize_i64`, so every serializer that
+/// supports 64-bit integers stores it without loss; it never goes through an
+/// `f64`. Deserialization accepts integers and digit strings, and rejects any
+/// float that could have been rounded on its way in. With `serde_json`'s
+/// `arbitrary_precision` feature, which hands numbers over as their literal
+/// text, the same numbers are accepted, and decimals such as `42.0` are read
+/// exactly.
+///
+/// # Example
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use snowflake_id::SnowflakeId;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Event {
+///     #[serde(with = "snowflake_id::serde::exact_number")]
+///     id: SnowflakeId,
+/// }
+///
+/// let event = Event { id: SnowflakeId::new(123456789012345678).unwrap() };
+/// assert_eq!(serde_json::to_string(&event).unwrap(), r#"{"id":123456789012345678}"#);
+/// ```
+pub mod exact_number {
+    use super::*;
+
+    /// The largest integer magnitude an `f64` represents without gaps (2^53)
+    const MAX_EXACT_FLOAT: f64 = 9_007_199_254_740_992.0;
+    /// Field name serde_json uses for arbitrary-precision numbers
+    const JSON_NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+    pub fn serialize<S>(id: &SnowflakeId, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i64(id.id())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SnowflakeId, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ExactNumberVisitor)
+    }
+
+    struct ExactNumberVisitor;
+
+    impl<'de> Visitor<'de> for ExactNumberVisitor {
+        type Value = SnowflakeId;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an exact integer snowflake id")
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            SnowflakeId::new(value).map_err(E::custom)
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            let value = i64::try_from(value)
+                .map_err(|_| E::custom("snowflake id value exceeds i64::MAX"))?;
+            self.visit_i64(value)
+        }
+
+        fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            if value.fract() != 0.0 || value.abs() > MAX_EXACT_FLOAT {
+                return Err(E::custom(
+                    "snowflake id float is not exactly representable; send an integer",
+                ));
+            }
+            self.visit_i64(value as i64)
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            value.parse::<SnowflakeId>().map_err(E::custom)
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            match map.next_key::<String>()? {
+                Some(key) if key == JSON_NUMBER_TOKEN => {
+                    let number: String = map.next_value()?;
+                    self.visit_number_token(&number)
+                }
+                _ => Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+            }
+        }
+    }
+
+    impl ExactNumberVisitor {
+        /// Reads the verbatim JSON number `serde_json` passes along under
+        /// `arbitrary_precision`, accepting whatever the plain number path
+        /// would: integers, and floats with an exact integer value
+        fn visit_number_token<E>(self, number: &str) -> Result<SnowflakeId, E>
+        where
+            E: de::Error,
+        {
+            // The text is exact, so `42.000` needs no trip through an f64
+            let integer = match number.split_once('.') {
+                Some((integer, fraction)) if fraction.bytes().all(|b| b == b'0') => integer,
+                _ => number,
+            };
+            if let Ok(value) = integer.parse::<i64>() {
+                return self.visit_i64(value);
+            }
+            if let Ok(value) = integer.parse::<u64>() {
+                return self.visit_u64(value);
+            }
+            match number.parse::<f64>() {
+                Ok(value) => self.visit_f64(value),
+                Err(_) => Err(E::invalid_value(de::Unexpected::Str(number), &self)),
+            }
+        }
+    }
+}
+
+/// Always serializes the ID as an `i64`, regardless of whether the format is
+/// human-readable.
+///
+/// This is the mode to use for BSON, where the default string form would
+/// store IDs as strings instead of native `Int64` values. Deserialization
+/// accepts both integers and digit strings, so the same struct can be read
+/// from a JSON API that sends string IDs.
+///
+/// # Example
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use snowflake_id::SnowflakeId;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Document {
+///     #[serde(with = "snowflake_id::serde::as_i64")]
+///     id: SnowflakeId,
+/// }
+///
+/// let doc: Document = serde_json::from_str(r#"{"id":"123456789"}"#).unwrap();
+/// assert_eq!(serde_json::to_string(&doc).unwrap(), r#"{"id":123456789}"#);
+/// ```
+pub mod as_i64 {
+    use super::*;
+
+    pub fn serialize<S>(id: &SnowflakeId, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i64(id.id())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SnowflakeId, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(IntegerOrStringVisitor)
+    }
+}
+
+/// Always serializes the ID as a decimal string, regardless of whether the
+/// format is human-readable.
+///
+/// Deserialization accepts both digit strings and integers.
+///
+/// # Example
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use snowflake_id::SnowflakeId;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Event {
+///     #[serde(with = "snowflake_id::serde::as_string")]
+///     id: SnowflakeId,
+/// }
+///
+/// let event: Event = serde_json::from_str(r#"{"id":123456789}"#).unwrap();
+/// assert_eq!(serde_json::to_string(&event).unwrap(), r#"{"id":"123456789"}"#);
+/// ```
+pub mod as_string {
+    use super::*;
+
+    pub fn serialize<S>(id: &SnowflakeId, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(id)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SnowflakeId, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(IntegerOrStringVisitor)
+    }
+}
+
+/// A [`SnowflakeId`] that always serializes as a decimal string, for places
+/// a `#[serde(with)]` attribute can't reach, such as `Vec` elements, map
+/// keys or `Option` fields.
+///
+/// Deserialization accepts both digit strings and integers, like
+/// [`as_string`].
+///
+/// # Example
+/// ```
+/// use snowflake_id::serde::StringSnowflake;
+/// use snowflake_id::SnowflakeId;
+///
+/// let ids = vec![StringSnowflake(SnowflakeId::new(1).unwrap()); 2];
+/// assert_eq!(serde_json::to_string(&ids).unwrap(), r#"["1","1"]"#);
+///
+/// let parent: Option<StringSnowflake> = serde_json::from_str("42").unwrap();
+/// assert_eq!(parent.map(SnowflakeId::from), SnowflakeId::new(42).ok());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StringSnowflake(pub SnowflakeId);
+
+impl ::serde::Serialize for StringSnowflake {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        as_string::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> ::serde::Deserialize<'de> for StringSnowflake {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        as_string::deserialize(deserializer).map(StringSnowflake)
+    }
+}
+
+impl From<SnowflakeId> for StringSnowflake {
+    fn from(id: SnowflakeId) -> Self {
+        StringSnowflake(id)
+    }
+}
+
+impl From<StringSnowflake> for SnowflakeId {
+    fn from(id: StringSnowflake) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for StringSnowflake {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Serializes the ID as its 8 little-endian bytes, regardless of format.
+///
+/// Compact binary formats such as postcard otherwise varint-encode integers,
+/// so an ID's size on the wire depends on its value. With this helper every
+/// ID takes exactly 8 bytes, for protocols that need fixed-width records.
+///
+/// # Example
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use snowflake_id::SnowflakeId;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Record {
+///     #[serde(with = "snowflake_id::serde::fixed_le")]
+///     id: SnowflakeId,
+/// }
+/// ```
+pub mod fixed_le {
+    use super::*;
+    use ::serde::{Deserialize, Serialize};
+
+    pub fn serialize<S>(id: &SnowflakeId, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        id.to_le_bytes().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SnowflakeId, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = <[u8; 8]>::deserialize(deserializer)?;
+        SnowflakeId::from_le_bytes(bytes).map_err(de::Error::custom)
+    }
+}
+
+/// Accepts an ID as an integer or a digit string, but never as a float
+struct IntegerOrStringVisitor;
+
+impl<'de> Visitor<'de> for IntegerOrStringVisitor {
+    type Value = SnowflakeId;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a snowflake id as an integer or a string")
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        SnowflakeId::new(value).map_err(E::custom)
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let value =
+            i64::try_from(value).map_err(|_| E::custom("snowflake id value exceeds i64::MAX"))?;
+        self.visit_i64(value)
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse::<SnowflakeId>().map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::serde::{Deserialize, Serialize};
+
+    #[test]
+    fn test_serde_as_i64_and_as_string() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Row {
+            #[serde(with = "crate::serde::as_i64")]
+            id: SnowflakeId,
+            #[serde(with = "crate::serde::as_string")]
+            text: SnowflakeId,
+        }
+
+        let id = SnowflakeId::new(123456789012345678).unwrap();
+        let row = Row { id, text: id };
+        let json = serde_json::to_string(&row).unwrap();
+        assert_eq!(
+            json,
+            r#"{"id":123456789012345678,"text":"123456789012345678"}"#
+        );
+        assert_eq!(serde_json::from_str::<Row>(&json).unwrap(), row);
+
+        // Either mode accepts the other's representation
+        let swapped = r#"{"id":"123456789012345678","text":123456789012345678}"#;
+        assert_eq!(serde_json::from_str::<Row>(swapped).unwrap(), row);
+
+        assert!(serde_json::from_str::<Row>(r#"{"id":1.0,"text":"1"}"#).is_err());
+        assert!(serde_json::from_str::<Row>(r#"{"id":-5,"text":"1"}"#).is_err());
+        assert!(serde_json::from_str::<Row>(r#"{"id":1,"text":"abc"}"#).is_err());
+    }
+
+    #[test]
+    fn test_string_snowflake() {
+        use std::collections::BTreeMap;
+
+        let id = SnowflakeId::new(123456789012345678).unwrap();
+        let wrapped = StringSnowflake::from(id);
+        assert_eq!(wrapped.to_string(), "123456789012345678");
+
+        let tags = BTreeMap::from([(wrapped, vec![wrapped])]);
+        let json = serde_json::to_string(&tags).unwrap();
+        assert_eq!(json, r#"{"123456789012345678":["123456789012345678"]}"#);
+        assert_eq!(
+            serde_json::from_str::<BTreeMap<StringSnowflake, Vec<StringSnowflake>>>(&json).unwrap(),
+            tags
+        );
+
+        // Integers are still accepted on input
+        let parsed: StringSnowflake = serde_json::from_str("123456789012345678").unwrap();
+        assert_eq!(SnowflakeId::from(parsed), id);
+
+        assert!(serde_json::from_str::<Option<StringSnowflake>>("null")
+            .unwrap()
+            .is_none());
+        assert!(serde_json::from_str::<StringSnowflake>(r#""-1""#).is_err());
+    }
+
+    #[test]
+    fn test_serde_fixed_le() {
+        #[derive(::serde::Serialize, ::serde::Deserialize, Debug, PartialEq)]
+        struct Record {
+            #[serde(with = "crate::serde::fixed_le")]
+            id: SnowflakeId,
+        }
+
+        for value in [0, 1, 300, i64::MAX] {
+            let record = Record {
+                id: SnowflakeId::new(value).unwrap(),
+            };
+            let bytes = postcard::to_allocvec(&record).unwrap();
+            assert_eq!(bytes, value.to_le_bytes());
+            assert_eq!(postcard::from_bytes::<Record>(&bytes).unwrap(), record);
+        }
+
+        let negative = (-1_i64).to_le_bytes();
+        assert!(postcard::from_bytes::<Record>(&negative).is_err());
+    }
+
+    #[test]
+    fn test_exact_number_serde() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Event {
+            #[serde(with = "crate::serde::exact_number")]
+            id: SnowflakeId,
+        }
+
+        let event = Event {
+            id: SnowflakeId::new(i64::MAX).unwrap(),
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(json, r#"{"id":9223372036854775807}"#);
+        assert_eq!(serde_json::from_str::<Event>(&json).unwrap(), event);
+
+        let from_str: Event = serde_json::from_str(r#"{"id":"42"}"#).unwrap();
+        assert_eq!(from_str.id.id(), 42);
+        let from_float: Event = serde_json::from_str(r#"{"id":42.0}"#).unwrap();
+        assert_eq!(from_float.id.id(), 42);
+        let from_token: Event =
+            serde_json::from_str(r#"{"id":{"$serde_json::private::Number":"123456789012345678"}}"#)
+                .unwrap();
+        assert_eq!(from_token.id.id(), 123456789012345678);
+
+        // serde_json's `arbitrary_precision` passes every number through as
+        // text, and it should be judged as the number it spells
+        let token = |number: &str| {
+            serde_json::from_str::<Event>(&format!(
+                r#"{{"id":{{"$serde_json::private::Number":"{}"}}}}"#,
+                number
+            ))
+            .map(|event| event.id.id())
+        };
+        assert_eq!(token("42.0").unwrap(), 42);
+        assert_eq!(token("9007199254740993.00").unwrap(), 9007199254740993);
+        assert_eq!(token("4.2e1").unwrap(), 42);
+        assert!(token("1.5").is_err());
+        assert!(token("1e300").is_err());
+        assert!(token("-1").is_err());
+        assert!(token("9223372036854775808").is_err());
+        assert!(token("").is_err());
+
+        assert!(serde_json::from_str::<Event>(r#"{"id":1.5}"#).is_err());
+        assert!(serde_json::from_str::<Event>(r#"{"id":1e300}"#).is_err());
+        assert!(serde_json::from_str::<Event>(r#"{"id":-1}"#).is_err());
+        assert!(serde_json::from_str::<Event>(r#"{"id":"1.5"}"#).is_err());
+        assert!(serde_json::from_str::<Event>(r#"{"id":{"other":"1"}}"#).is_err());
+    }
+}
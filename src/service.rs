@@ -0,0 +1,178 @@
+//! A tower [`Service`] for issuing IDs, so the async generator can sit in a
+//! service stack behind rate limits, timeouts or metrics middleware.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::service::{IdRequest, IdService};
+//! use snowflake_id::AsyncSnowflakeGenerator;
+//! use std::sync::Arc;
+//! use tower::{Service, ServiceExt};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let generator = Arc::new(AsyncSnowflakeGenerator::new(1).unwrap());
+//! let mut service = IdService::new(generator);
+//!
+//! let ids = service.ready().await.unwrap().call(IdRequest::batch(3)).await.unwrap();
+//! assert_eq!(ids.len(), 3);
+//! # }
+//! ```
+
+use crate::async_generator::{AsyncSnowflakeGenerator, Priority};
+use crate::error::SnowflakeError;
+use crate::snowflake::Snowflake;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_service::Service;
+
+/// A request for one or more IDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IdRequest {
+    pub count: usize,
+    pub priority: Priority,
+}
+
+impl IdRequest {
+    /// Requests a single ID at high priority
+    pub fn one() -> Self {
+        Self::batch(1)
+    }
+
+    /// Requests `count` IDs at high priority
+    pub fn batch(count: usize) -> Self {
+        IdRequest {
+            count,
+            priority: Priority::High,
+        }
+    }
+
+    /// Sets the request's priority, see
+    /// [`AsyncSnowflakeGenerator::with_load_shedding`]
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+impl Default for IdRequest {
+    fn default() -> Self {
+        Self::one()
+    }
+}
+
+/// Issues IDs from a shared [`AsyncSnowflakeGenerator`]
+///
+/// The service is always ready; waits for the clock happen inside the
+/// response future. Each response holds the requested IDs in ascending order.
+pub struct IdService<S: Snowflake> {
+    generator: Arc<AsyncSnowflakeGenerator<S>>,
+}
+
+impl<S: Snowflake> IdService<S> {
+    pub fn new(generator: Arc<AsyncSnowflakeGenerator<S>>) -> Self {
+        IdService { generator }
+    }
+
+    pub fn generator(&self) -> &Arc<AsyncSnowflakeGenerator<S>> {
+        &self.generator
+    }
+}
+
+impl<S: Snowflake> Clone for IdService<S> {
+    fn clone(&self) -> Self {
+        IdService {
+            generator: self.generator.clone(),
+        }
+    }
+}
+
+impl<S: Snowflake + Send + Sync + 'static> Service<IdRequest> for IdService<S> {
+    type Response = Vec<S>;
+    type Error = SnowflakeError;
+    type Future = Pin<Box<dyn Future<Output = Result<Vec<S>, SnowflakeError>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: IdRequest) -> Self::Future {
+        let generator = self.generator.clone();
+        Box::pin(async move {
+            match request.priority {
+                Priority::High => generator.next_id_bulk_checked(request.count).await,
+                // Checked per ID so a low-priority batch is shed as soon as
+                // the generator saturates
+                Priority::Low => {
+                    let mut ids = Vec::with_capacity(request.count);
+                    for _ in 0..request.count {
+                        ids.push(generator.next_id_with_priority(Priority::Low).await?);
+                    }
+                    Ok(ids)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::manual_clock;
+    use crate::AsyncSnowflakeGenerator;
+    use crate::Epoch;
+    use crate::MAX_SEQUENCE;
+    use std::time::Duration;
+
+    use crate::generator::SnowflakeOperation;
+
+    use tower::{ServiceBuilder, ServiceExt};
+
+    #[tokio::test]
+    async fn test_id_service() {
+        let generator = Arc::new(AsyncSnowflakeGenerator::new(3).unwrap());
+        let service = ServiceBuilder::new()
+            .concurrency_limit(4)
+            .service(IdService::new(generator));
+
+        let ids = service
+            .clone()
+            .oneshot(IdRequest::batch(100))
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 100);
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let next = service.oneshot(IdRequest::one()).await.unwrap();
+        assert_eq!(next.len(), 1);
+        assert!(next[0] > ids[99]);
+    }
+
+    #[tokio::test]
+    async fn test_id_service_sheds_low_priority() {
+        let clock = manual_clock();
+        let generator = AsyncSnowflakeGenerator::with_clock(1, Epoch::TWITTER, clock.clone())
+            .unwrap()
+            .with_load_shedding(2);
+        let service = IdService::new(Arc::new(generator));
+
+        // Exhaust the sequence space on three consecutive ticks
+        for _ in 0..3 {
+            let batch = IdRequest::batch(MAX_SEQUENCE as usize + 1);
+            service.clone().oneshot(batch).await.unwrap();
+            assert!(matches!(
+                service.generator().try_next_id().await.unwrap(),
+                SnowflakeOperation::Pending(_)
+            ));
+            clock.advance(Duration::from_millis(1));
+        }
+
+        let low = IdRequest::batch(2).with_priority(Priority::Low);
+        assert!(matches!(
+            service.clone().oneshot(low).await,
+            Err(SnowflakeError::Overloaded)
+        ));
+        assert!(service.oneshot(IdRequest::one()).await.is_ok());
+    }
+}
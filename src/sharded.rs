@@ -0,0 +1,452 @@
+use crate::clock::{Clock, SystemClock};
+use crate::defs::SNOWFLAKE_ID_EPOCH;
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::generator::{GeneratorCore, GeneratorState, SequenceSlice, SnowflakeOperation};
+use crate::snowflake::Snowflake;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+/// Source of unique keys for per-thread shard lookups
+static NEXT_GENERATOR_KEY: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static LOCAL_SHARDS: RefCell<HashMap<u64, LocalShard>> = RefCell::new(HashMap::new());
+}
+
+struct ShardState {
+    shard: u64,
+    /// Claims from the shard's slice of the sequence space
+    state: GeneratorState,
+}
+
+type ShardPool = Mutex<Vec<ShardState>>;
+
+/// A thread's hold on one generator's shards
+struct LocalShard {
+    /// The shard leased to this thread, or `None` if it shares the
+    /// generator's fallback shard
+    state: Option<ShardState>,
+    /// Weak, so that a dropped generator's entries can be recognised and
+    /// pruned
+    pool: Weak<ShardPool>,
+}
+
+impl LocalShard {
+    fn is_live(&self) -> bool {
+        self.pool.strong_count() > 0
+    }
+}
+
+impl Drop for LocalShard {
+    fn drop(&mut self) {
+        // The last timestamp travels with the shard, so a thread picking it up
+        // later cannot reissue sequence numbers from the same tick
+        if let (Some(state), Some(pool)) = (self.state.take(), self.pool.upgrade()) {
+            pool.lock().unwrap_or_else(|e| e.into_inner()).push(state);
+        }
+    }
+}
+
+/// A generator that splits the sequence space into `shards` equal slices and
+/// leases one slice to each thread, so generation never takes a shared lock.
+///
+/// Each thread keeps its own shard for as long as it lives; the shard goes
+/// back to the pool when the thread exits. With `n` shards, each thread can
+/// mint `(max_sequence + 1) / n` IDs per millisecond. The last shard is kept
+/// back as a fallback: once the other `n - 1` are leased, further threads
+/// share it under a lock rather than failing.
+///
+/// IDs are unique across threads and monotonic within a thread, but not
+/// globally ordered within a tick.
+///
+/// # Example
+/// ```
+/// use snowflake_id::ShardedSnowflakeGenerator;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let generator = Arc::new(ShardedSnowflakeGenerator::new(1, 4).unwrap());
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let generator = generator.clone();
+///         thread::spawn(move || generator.next_id(thread::sleep))
+///     })
+///     .collect();
+/// for worker in workers {
+///     worker.join().unwrap();
+/// }
+/// ```
+pub struct ShardedSnowflakeGenerator<S: Snowflake> {
+    core: GeneratorCore<S>,
+    key: u64,
+    shards: u64,
+    shard_size: u64,
+    pool: Arc<ShardPool>,
+    /// The last shard, shared by threads that found the pool empty
+    shared: Mutex<ShardState>,
+}
+
+impl<S: Snowflake> ShardedSnowflakeGenerator<S> {
+    /// Creates a sharded generator using the default epoch
+    ///
+    /// # Arguments
+    /// * `machine_id` - Unique machine/datacenter ID (0-1023)
+    /// * `shards` - Number of slices to split the sequence space into,
+    ///   between 1 and `max_sequence + 1`; all but one are leased to threads
+    ///   of their own
+    pub fn new(machine_id: u64, shards: usize) -> Result<Self, SnowflakeError> {
+        Self::with_epoch(machine_id, shards, SNOWFLAKE_ID_EPOCH)
+    }
+
+    /// Creates a sharded generator with a custom epoch
+    pub fn with_epoch(
+        machine_id: u64,
+        shards: usize,
+        epoch: impl Into<Epoch>,
+    ) -> Result<Self, SnowflakeError> {
+        Self::with_clock(machine_id, shards, epoch, SystemClock)
+    }
+
+    /// Creates a sharded generator that reads time from `clock`
+    pub fn with_clock(
+        machine_id: u64,
+        shards: usize,
+        epoch: impl Into<Epoch>,
+        clock: impl Clock + 'static,
+    ) -> Result<Self, SnowflakeError> {
+        let core = GeneratorCore::new(machine_id, epoch.into(), Arc::new(clock))?;
+        Self::from_core(core, shards)
+    }
+
+    /// Creates a sharded generator on `core`, taking its clock policy,
+    /// overflow strategy, sequence start and checkpoint. Each shard keeps
+    /// its own sequence within its slice.
+    pub(crate) fn from_core(core: GeneratorCore<S>, shards: usize) -> Result<Self, SnowflakeError> {
+        let space = S::max_sequence() + 1;
+        let shards_u64 = shards as u64;
+        if shards_u64 == 0 || shards_u64 > space {
+            return Err(SnowflakeError::InvalidShardCount(shards, space));
+        }
+
+        let state = |shard| ShardState {
+            shard,
+            state: core.initial_state(),
+        };
+        // Reversed so that shard 0 is handed out first
+        let pool = (0..shards_u64 - 1).rev().map(state).collect();
+        let shared = Mutex::new(state(shards_u64 - 1));
+
+        Ok(ShardedSnowflakeGenerator {
+            core,
+            key: NEXT_GENERATOR_KEY.fetch_add(1, Ordering::Relaxed),
+            shards: shards_u64,
+            shard_size: space / shards_u64,
+            pool: Arc::new(Mutex::new(pool)),
+            shared,
+        })
+    }
+
+    /// Returns the epoch being used by this generator
+    pub fn epoch(&self) -> i64 {
+        self.core.epoch
+    }
+
+    /// Returns the machine ID embedded in generated IDs
+    pub fn machine_id(&self) -> u64 {
+        self.core.machine_id
+    }
+
+    /// Returns the number of shards the sequence space is split into
+    pub fn shards(&self) -> usize {
+        self.shards as usize
+    }
+
+    /// Returns how many IDs each thread can generate per millisecond
+    pub fn shard_size(&self) -> u64 {
+        self.shard_size
+    }
+
+    /// Attempts to generate one ID from the calling thread's shard, leasing a
+    /// shard first if this thread doesn't hold one yet. A thread that finds
+    /// every shard leased uses the shared fallback shard from then on.
+    pub fn try_next_id(&self) -> Result<SnowflakeOperation<S>, SnowflakeError> {
+        LOCAL_SHARDS.with(|local| {
+            let mut local = local.borrow_mut();
+            if !local.contains_key(&self.key) {
+                // Entries of generators dropped since this thread last leased
+                // a shard are cleared out here, or when the thread exits
+                local.retain(|_, shard| shard.is_live());
+            }
+            let shard = local.entry(self.key).or_insert_with(|| self.lease());
+            match &mut shard.state {
+                Some(state) => self.advance(state),
+                None => self.advance(&mut self.shared.lock().unwrap_or_else(|e| e.into_inner())),
+            }
+        })
+    }
+
+    /// Generates an ID, calling `on_pending` whenever it has to wait
+    ///
+    /// # Panics
+    /// Panics if generation fails, see [`Self::next_id_checked`].
+    pub fn next_id(&self, on_pending: impl FnMut(Duration)) -> S {
+        self.next_id_checked(on_pending)
+            .expect("snowflake generation failed")
+    }
+
+    /// Generates an ID, calling `on_pending` whenever it has to wait, and
+    /// returns an error instead of panicking
+    pub fn next_id_checked(
+        &self,
+        mut on_pending: impl FnMut(Duration),
+    ) -> Result<S, SnowflakeError> {
+        loop {
+            match self.try_next_id()? {
+                SnowflakeOperation::Ready(id) => return Ok(id),
+                SnowflakeOperation::Pending(wait) => on_pending(wait),
+            }
+        }
+    }
+
+    fn lease(&self) -> LocalShard {
+        LocalShard {
+            state: self.pool.lock().unwrap_or_else(|e| e.into_inner()).pop(),
+            pool: Arc::downgrade(&self.pool),
+        }
+    }
+
+    fn advance(&self, shard: &mut ShardState) -> Result<SnowflakeOperation<S>, SnowflakeError> {
+        let slice = SequenceSlice {
+            start: shard.shard * self.shard_size,
+            last: self.shard_size - 1,
+        };
+        let id = match self.core.try_claim_in(&mut shard.state, 1, slice)? {
+            SnowflakeOperation::Ready(claim) => claim.first,
+            SnowflakeOperation::Pending(wait) => return Ok(SnowflakeOperation::Pending(wait)),
+        };
+        #[cfg(feature = "metrics")]
+        self.core.metrics.record_generated();
+        Ok(SnowflakeOperation::Ready(id))
+    }
+}
+
+impl<S: Snowflake> Drop for ShardedSnowflakeGenerator<S> {
+    fn drop(&mut self) {
+        // Only this thread's entry can be reached from here; other threads
+        // prune theirs on their next lease
+        let _ = LOCAL_SHARDS.try_with(|local| {
+            if let Ok(mut local) = local.try_borrow_mut() {
+                local.remove(&self.key);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ShardedSnowflakeGenerator;
+    use crate::SnowflakeId;
+    use crate::MAX_SEQUENCE;
+    use std::thread;
+
+    #[test]
+    fn test_sharded_generator_uniqueness() {
+        use std::collections::HashSet;
+        use std::sync::Arc;
+
+        let generator = Arc::new(ShardedSnowflakeGenerator::new(2, 8).unwrap());
+        assert_eq!(generator.shard_size(), (MAX_SEQUENCE + 1) / 8);
+
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                let generator = generator.clone();
+                thread::spawn(move || {
+                    let ids: Vec<SnowflakeId> = (0..20_000)
+                        .map(|_| generator.next_id(|_| thread::yield_now()))
+                        .collect();
+                    for pair in ids.windows(2) {
+                        assert!(pair[0] < pair[1]);
+                    }
+                    ids
+                })
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        for worker in workers {
+            for id in worker.join().unwrap() {
+                assert_eq!(id.machine_id(), 2);
+                assert!(seen.insert(id), "duplicate id {:?}", id);
+            }
+        }
+        assert_eq!(seen.len(), 8 * 20_000);
+    }
+
+    #[test]
+    fn test_sharded_generator_more_threads_than_shards() {
+        use std::collections::HashSet;
+        use std::sync::Arc;
+
+        let generator = Arc::new(ShardedSnowflakeGenerator::new(2, 2).unwrap());
+        let workers: Vec<_> = (0..6)
+            .map(|_| {
+                let generator = generator.clone();
+                thread::spawn(move || {
+                    (0..5_000)
+                        .map(|_| generator.next_id(|_| thread::yield_now()))
+                        .collect::<Vec<SnowflakeId>>()
+                })
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        for worker in workers {
+            let ids = worker.join().unwrap();
+            assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+            seen.extend(ids);
+        }
+        assert_eq!(seen.len(), 6 * 5_000);
+    }
+
+    #[test]
+    fn test_sharded_generator_releases_thread_entries() {
+        use std::sync::{mpsc, Arc};
+
+        let keys = || LOCAL_SHARDS.with(|local| local.borrow().keys().copied().collect::<Vec<_>>());
+
+        // Dropping a generator clears the dropping thread's entry at once
+        let generator = ShardedSnowflakeGenerator::new(1, 2).unwrap();
+        generator.next_id(|_| thread::yield_now());
+        let key = generator.key;
+        assert!(keys().contains(&key));
+        drop(generator);
+        assert!(!keys().contains(&key));
+
+        // Other threads clear theirs, returning nothing to the dead pool, on
+        // their next lease
+        let generator = Arc::new(ShardedSnowflakeGenerator::new(1, 2).unwrap());
+        let (used, dropped) = (mpsc::channel(), mpsc::channel());
+        let worker = {
+            let generator = generator.clone();
+            thread::spawn(move || {
+                generator.next_id(|_| thread::yield_now());
+                let key = generator.key;
+                drop(generator);
+                used.0.send(()).unwrap();
+                dropped.1.recv().unwrap();
+                assert!(keys().contains(&key));
+
+                let other = ShardedSnowflakeGenerator::new(1, 2).unwrap();
+                other.next_id(|_| thread::yield_now());
+                assert_eq!(keys(), [other.key]);
+            })
+        };
+        used.1.recv().unwrap();
+        drop(generator);
+        dropped.0.send(()).unwrap();
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn test_sharded_generator_leases() {
+        use std::sync::{Arc, Barrier};
+
+        assert!(matches!(
+            ShardedSnowflakeGenerator::new(1, 0),
+            Err(SnowflakeError::InvalidShardCount(0, _))
+        ));
+        assert!(ShardedSnowflakeGenerator::new(1, MAX_SEQUENCE as usize + 2).is_err());
+
+        // One shard is leased and the other is the fallback, so of three
+        // threads holding on at once, two share the fallback
+        let generator = Arc::new(ShardedSnowflakeGenerator::new(1, 2).unwrap());
+        let barrier = Arc::new(Barrier::new(3));
+        let shards: Vec<_> = (0..3)
+            .map(|_| {
+                let (generator, barrier) = (generator.clone(), barrier.clone());
+                thread::spawn(move || {
+                    let id = generator.next_id(|_| thread::yield_now());
+                    // Hold the shard until every thread has tried to lease one
+                    barrier.wait();
+                    id.sequence() / generator.shard_size()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(shards.iter().filter(|&&shard| shard == 1).count(), 2);
+
+        // Shards are returned when their threads exit
+        for _ in 0..4 {
+            let generator = generator.clone();
+            thread::spawn(move || generator.next_id(|_| thread::yield_now()))
+                .join()
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_sharded_generator_borrows_future_ticks() {
+        use crate::test_support::manual_clock;
+        use crate::OverflowStrategy;
+        use crate::SnowflakeGenerator;
+
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(manual_clock())
+            .overflow(OverflowStrategy::BorrowFuture { max_ahead_ms: 10 })
+            .build_sharded(2)
+            .unwrap();
+
+        // Runs ten ticks ahead of the clock before it has to wait
+        let mut last = None;
+        for _ in 0..generator.shard_size() * 11 {
+            let Ok(SnowflakeOperation::Ready(id)) = generator.try_next_id() else {
+                panic!("expected an id");
+            };
+            assert!(Some(id) > last);
+            last = Some(id);
+        }
+        assert_eq!(last.unwrap().timestamp(), 1_010);
+        assert!(matches!(
+            generator.try_next_id(),
+            Ok(SnowflakeOperation::Pending(wait)) if wait == Duration::from_millis(1)
+        ));
+    }
+
+    #[test]
+    fn test_sharded_generator_from_builder() {
+        use crate::test_support::{manual_clock, MemoryCheckpoint};
+        use crate::SnowflakeGenerator;
+
+        let clock = manual_clock();
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .checkpoint(
+                MemoryCheckpoint::new(SNOWFLAKE_ID_EPOCH + 1_010),
+                Duration::from_millis(100),
+            )
+            .build_sharded(4)
+            .unwrap();
+        assert_eq!(generator.shards(), 4);
+
+        // Shards wait out a restored checkpoint like the plain generator
+        assert!(matches!(
+            generator.try_next_id(),
+            Ok(SnowflakeOperation::Pending(wait)) if wait == Duration::from_millis(11)
+        ));
+        clock.advance(Duration::from_millis(11));
+        let id = generator.next_id(|_| panic!("unexpected wait"));
+        assert_eq!((id.timestamp(), id.machine_id()), (1_011, 1));
+
+        assert!(matches!(
+            SnowflakeGenerator::builder(1).build_sharded(0),
+            Err(SnowflakeError::InvalidShardCount(0, _))
+        ));
+    }
+}
@@ -0,0 +1,168 @@
+//! Saving a generator's state across a controlled restart.
+//!
+//! A [`GeneratorSnapshot`] captures the generator's layout parameters and the
+//! last tick and sequence it issued. Restoring it on startup carries on
+//! exactly where the old process stopped: the next ID continues the sequence
+//! within the same tick, or waits for the clock if it is behind the saved
+//! tick, so IDs stay monotonic across the restart. Unlike a
+//! [`Checkpoint`](crate::checkpoint::Checkpoint), nothing is written while
+//! generating; take the snapshot once the old generator has stopped issuing.
+//! [`GeneratorBuilder::restore`] restores onto a builder, for generators
+//! with settings beyond the snapshot's layout.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::snapshot::GeneratorSnapshot;
+//! use snowflake_id::SnowflakeGenerator;
+//!
+//! let generator = SnowflakeGenerator::new(1).unwrap();
+//! let last = generator.generate().unwrap();
+//! let saved = serde_json::to_string(&generator.snapshot()).unwrap();
+//!
+//! let snapshot: GeneratorSnapshot = serde_json::from_str(&saved).unwrap();
+//! let restored = SnowflakeGenerator::restore(snapshot).unwrap();
+//! assert!(restored.generate().unwrap() > last);
+//! ```
+
+use crate::builder::GeneratorBuilder;
+use crate::clock::{Clock, SystemClock};
+use crate::error::SnowflakeError;
+use crate::generator::SnowflakeGenerator;
+use crate::snowflake::Snowflake;
+use ::serde::{Deserialize, Serialize};
+
+/// A generator's state at a point in time, see the [module docs](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GeneratorSnapshot {
+    /// Epoch in milliseconds since the Unix epoch
+    pub epoch: i64,
+    pub machine_id: u64,
+    /// Length of one timestamp unit in microseconds
+    pub tick_micros: u64,
+    /// Last tick issued from, as the generator counts them; Unix
+    /// milliseconds with the default tick, or `0` if nothing was issued
+    pub last_timestamp: i64,
+    /// Sequence of the last ID issued
+    pub sequence: u64,
+}
+
+impl<S: Snowflake> SnowflakeGenerator<S> {
+    /// Captures the epoch, machine id, tick and last issued position
+    pub fn snapshot(&self) -> GeneratorSnapshot {
+        let state = self.lock_state();
+        GeneratorSnapshot {
+            epoch: self.core.epoch,
+            machine_id: self.core.machine_id,
+            tick_micros: self.core.tick_micros as u64,
+            last_timestamp: state.last_timestamp,
+            sequence: state.sequence,
+        }
+    }
+
+    /// Rebuilds a generator from `snapshot` that continues after the last ID
+    /// it recorded
+    pub fn restore(snapshot: GeneratorSnapshot) -> Result<Self, SnowflakeError> {
+        Self::restore_with_clock(snapshot, SystemClock)
+    }
+
+    /// Like [`SnowflakeGenerator::restore`], reading time from `clock`.
+    ///
+    /// Both use default settings otherwise; to restore with a checkpoint,
+    /// overflow strategy or other settings, use [`GeneratorBuilder::restore`].
+    pub fn restore_with_clock(
+        snapshot: GeneratorSnapshot,
+        clock: impl Clock + 'static,
+    ) -> Result<Self, SnowflakeError> {
+        GeneratorBuilder::restore(snapshot).clock(clock).build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epoch::Epoch;
+    use crate::test_support::manual_clock;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+    use crate::MAX_SEQUENCE;
+    use crate::SNOWFLAKE_ID_EPOCH;
+    use std::time::Duration;
+
+    #[test]
+    fn test_snapshot_restore() {
+        use crate::generator::SnowflakeOperation;
+
+        let clock = manual_clock();
+        let generator = SnowflakeGenerator::with_clock(5, Epoch::TWITTER, clock.clone()).unwrap();
+        generator.generate_bulk(2).unwrap();
+        let last = generator.generate().unwrap();
+
+        let snapshot = generator.snapshot();
+        assert_eq!(
+            snapshot,
+            GeneratorSnapshot {
+                epoch: Epoch::TWITTER.as_millis(),
+                machine_id: 5,
+                tick_micros: 1_000,
+                last_timestamp: SNOWFLAKE_ID_EPOCH + 1_000,
+                sequence: 2,
+            }
+        );
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let snapshot: GeneratorSnapshot = serde_json::from_str(&json).unwrap();
+
+        // Same tick: the sequence carries on
+        let restored = SnowflakeGenerator::restore_with_clock(snapshot, clock.clone()).unwrap();
+        let next = restored.generate().unwrap();
+        assert!(next > last);
+        assert_eq!(next.sequence(), 3);
+
+        // Clock behind the saved tick: wait instead of reissuing
+        clock.rewind(Duration::from_millis(2));
+        let restored = SnowflakeGenerator::restore_with_clock(snapshot, clock.clone()).unwrap();
+        assert!(matches!(
+            restored.try_next_id().unwrap(),
+            SnowflakeOperation::Pending(_)
+        ));
+
+        let bad = GeneratorSnapshot {
+            sequence: MAX_SEQUENCE + 1,
+            ..snapshot
+        };
+        assert!(matches!(
+            SnowflakeGenerator::restore(bad),
+            Err(SnowflakeError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_builder_restore_keeps_settings() {
+        use crate::generator::SnowflakeOperation;
+        use crate::OverflowStrategy;
+
+        let clock = manual_clock();
+        let generator = SnowflakeGenerator::with_clock(5, Epoch::TWITTER, clock.clone()).unwrap();
+        generator.generate_bulk(MAX_SEQUENCE as usize + 1).unwrap();
+        let snapshot = generator.snapshot();
+        assert_eq!(snapshot.sequence, MAX_SEQUENCE);
+
+        // The saved tick is used up, so borrowing the next one beats waiting
+        let restored = GeneratorBuilder::<SnowflakeId>::restore(snapshot)
+            .clock(clock.clone())
+            .overflow(OverflowStrategy::BorrowFuture { max_ahead_ms: 5 })
+            .build()
+            .unwrap();
+        let Ok(SnowflakeOperation::Ready(id)) = restored.try_next_id() else {
+            panic!("expected an id");
+        };
+        assert_eq!(id.timestamp(), 1_001);
+        assert_eq!(id.sequence(), 0);
+
+        assert!(matches!(
+            GeneratorBuilder::<SnowflakeId>::restore(snapshot)
+                .epoch(Epoch::DISCORD)
+                .build(),
+            Err(SnowflakeError::InvalidConfig(_))
+        ));
+    }
+}
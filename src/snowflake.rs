@@ -1,4 +1,7 @@
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
 use core::hash::Hash;
+use std::time::Duration;
 
 pub trait Snowflake:
     Copy + Clone + PartialOrd + Ord + PartialEq + Eq + Hash + std::fmt::Debug
@@ -9,14 +12,101 @@ pub trait Snowflake:
         (self.id() >> Self::timestamp_shift()) & Self::timestamp_mask()
     }
 
-    fn timestamp_with_epoch(&self, epoch: i64) -> i64 {
-        (self.timestamp() as i64) + epoch
+    fn timestamp_with_epoch(&self, epoch: impl Into<Epoch>) -> i64 {
+        (self.timestamp() as i64) + epoch.into().as_millis()
+    }
+
+    /// Returns the time elapsed between the epoch and this ID's timestamp
+    fn timestamp_duration(&self) -> Duration {
+        Duration::from_millis(self.timestamp())
+    }
+
+    /// Builds an ID from a [`Duration`] since the epoch, truncated to whole
+    /// milliseconds, rejecting any part that doesn't fit the layout
+    fn from_duration_parts(
+        offset: Duration,
+        machine_id: u64,
+        sequence: u64,
+    ) -> Result<Self, SnowflakeError> {
+        let timestamp_offset = u64::try_from(offset.as_millis())
+            .ok()
+            .filter(|&millis| millis <= Self::timestamp_mask())
+            .ok_or(SnowflakeError::TimestampOverflow)?;
+        if machine_id > Self::max_machine_id() {
+            return Err(SnowflakeError::InvalidMachineId(
+                machine_id,
+                Self::max_machine_id(),
+            ));
+        }
+        if sequence > Self::max_sequence() {
+            return Err(SnowflakeError::InvalidId(format!(
+                "sequence {} exceeds maximum {}",
+                sequence,
+                Self::max_sequence()
+            )));
+        }
+
+        Ok(Self::from_component_parts(
+            timestamp_offset,
+            machine_id,
+            sequence,
+        ))
     }
 
     fn machine_id(&self) -> u64 {
         (self.id() >> Self::sequence_bits()) & Self::machine_id_mask()
     }
 
+    /// Returns the bit of the machine ID space that marks tombstones, as a
+    /// mask over the whole ID. Defaults to the highest machine ID bit.
+    ///
+    /// Live IDs must leave this bit clear, which halves the usable machine ID
+    /// range; see [`crate::builder::GeneratorBuilder::reserve_tombstone_bit`].
+    fn tombstone_mask() -> u64 {
+        1u64 << (Self::timestamp_shift() - 1)
+    }
+
+    /// Returns true if this ID marks the deletion of another, see
+    /// [`Snowflake::tombstone_of`]
+    fn is_tombstone(&self) -> bool {
+        self.id() & Self::tombstone_mask() != 0
+    }
+
+    /// Returns the tombstone for `id`: the same ID with the tombstone bit set,
+    /// so it shares the keyspace. Being a machine ID bit, it sorts the
+    /// tombstone after every live ID from the same tick and before any ID
+    /// from a later one.
+    ///
+    /// Fails if `id` already has the tombstone bit set, meaning it is a
+    /// tombstone itself or was minted by a machine ID that doesn't reserve it.
+    fn tombstone_of(id: Self) -> Result<Self, SnowflakeError> {
+        if id.is_tombstone() {
+            return Err(SnowflakeError::InvalidId(format!(
+                "{} already has the tombstone bit set",
+                id.id()
+            )));
+        }
+        let raw = id.id() | Self::tombstone_mask();
+        Ok(Self::from_component_parts(
+            (raw >> Self::timestamp_shift()) & Self::timestamp_mask(),
+            (raw >> Self::sequence_bits()) & Self::machine_id_mask(),
+            raw & Self::sequence_mask(),
+        ))
+    }
+
+    /// Returns the ID a tombstone deletes, or `None` for a live ID
+    fn live_id(&self) -> Option<Self> {
+        if !self.is_tombstone() {
+            return None;
+        }
+        let raw = self.id() & !Self::tombstone_mask();
+        Some(Self::from_component_parts(
+            (raw >> Self::timestamp_shift()) & Self::timestamp_mask(),
+            (raw >> Self::sequence_bits()) & Self::machine_id_mask(),
+            raw & Self::sequence_mask(),
+        ))
+    }
+
     fn sequence(&self) -> u64 {
         self.id() & Self::sequence_mask()
     }
@@ -65,3 +155,67 @@ pub trait Snowflake:
         Self::sequence_mask()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+    use crate::MAX_MACHINE_ID;
+    use crate::MAX_SEQUENCE;
+
+    #[test]
+    fn test_duration_parts() {
+        let offset = Duration::from_millis(1_234_567);
+        let id = SnowflakeId::from_duration_parts(offset, 9, 42).unwrap();
+        assert_eq!(id.timestamp_duration(), offset);
+        assert_eq!(id.machine_id(), 9);
+        assert_eq!(id.sequence(), 42);
+
+        // Sub-millisecond precision is truncated
+        let id = SnowflakeId::from_duration_parts(Duration::from_micros(1_500), 0, 0).unwrap();
+        assert_eq!(id.timestamp_duration(), Duration::from_millis(1));
+
+        let max = Duration::from_millis(SnowflakeId::max_timestamp() as u64);
+        assert!(SnowflakeId::from_duration_parts(max, 0, 0).is_ok());
+        assert!(matches!(
+            SnowflakeId::from_duration_parts(max + Duration::from_millis(1), 0, 0),
+            Err(SnowflakeError::TimestampOverflow)
+        ));
+        assert!(matches!(
+            SnowflakeId::from_duration_parts(offset, MAX_MACHINE_ID + 1, 0),
+            Err(SnowflakeError::InvalidMachineId(_, _))
+        ));
+        assert!(matches!(
+            SnowflakeId::from_duration_parts(offset, 0, MAX_SEQUENCE + 1),
+            Err(SnowflakeError::InvalidId(_))
+        ));
+    }
+
+    #[test]
+    fn test_tombstones() {
+        let generator = SnowflakeGenerator::builder(MAX_MACHINE_ID >> 1)
+            .reserve_tombstone_bit()
+            .build()
+            .unwrap();
+        assert!(SnowflakeGenerator::builder(MAX_MACHINE_ID)
+            .reserve_tombstone_bit()
+            .build()
+            .is_err());
+
+        let id = generator.generate().unwrap();
+        assert!(!id.is_tombstone());
+        assert_eq!(id.live_id(), None);
+
+        let tombstone = SnowflakeId::tombstone_of(id).unwrap();
+        assert!(tombstone.is_tombstone());
+        assert_eq!(tombstone.live_id(), Some(id));
+        assert_eq!(tombstone.timestamp(), id.timestamp());
+        assert_eq!(tombstone.sequence(), id.sequence());
+        assert!(tombstone > id);
+
+        assert!(SnowflakeId::tombstone_of(tombstone).is_err());
+        let unreserved = SnowflakeId::from_component_parts(1, MAX_MACHINE_ID, 0);
+        assert!(SnowflakeId::tombstone_of(unreserved).is_err());
+    }
+}
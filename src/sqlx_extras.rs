@@ -0,0 +1,302 @@
+//! Additional sqlx integration beyond the transparent `BIGINT` mapping.
+//!
+//! # Schema
+//!
+//! IDs are always positive `i64`s, so a signed 64-bit column holds them on
+//! every database and keeps `ORDER BY id` chronological:
+//!
+//! ```sql
+//! -- Postgres
+//! CREATE TABLE events (
+//!     id BIGINT PRIMARY KEY,
+//!     payload TEXT NOT NULL
+//! );
+//!
+//! -- MySQL
+//! CREATE TABLE events (
+//!     id BIGINT NOT NULL PRIMARY KEY,
+//!     payload TEXT NOT NULL
+//! );
+//! ```
+//!
+//! Columns that must store the ID as text (for example when it is shared
+//! with JavaScript clients) should bind and read it through
+//! [`TextSnowflakeId`].
+//!
+//! # Queries
+//!
+//! ```no_run
+//! # async fn run(pool: sqlx::AnyPool) -> Result<(), Box<dyn std::error::Error>> {
+//! use chrono::{Duration, Utc};
+//! use snowflake_id::sqlx_extras::BindIdRange;
+//! use snowflake_id::{Epoch, SnowflakeId};
+//!
+//! #[derive(sqlx::FromRow)]
+//! struct Event {
+//!     id: SnowflakeId,
+//!     payload: String,
+//! }
+//!
+//! let now = Utc::now();
+//! let recent: Vec<Event> =
+//!     sqlx::query_as("SELECT id, payload FROM events WHERE id BETWEEN $1 AND $2")
+//!         .bind_time_range(now - Duration::hours(1)..now, Epoch::TWITTER)?
+//!         .fetch_all(&pool)
+//!         .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The same query works against MySQL with `?` placeholders.
+
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::SnowflakeId;
+use chrono::{DateTime, Utc};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::query::{Query, QueryAs, QueryScalar};
+use sqlx::{Database, Decode, Encode, Type};
+use std::fmt;
+use std::ops::{Range, RangeInclusive};
+
+/// A [`SnowflakeId`] stored in a text column as its decimal string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextSnowflakeId(pub SnowflakeId);
+
+impl From<SnowflakeId> for TextSnowflakeId {
+    fn from(id: SnowflakeId) -> Self {
+        TextSnowflakeId(id)
+    }
+}
+
+impl From<TextSnowflakeId> for SnowflakeId {
+    fn from(id: TextSnowflakeId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for TextSnowflakeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<DB: Database> Type<DB> for TextSnowflakeId
+where
+    str: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <str as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <str as Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'q, DB: Database> Encode<'q, DB> for TextSnowflakeId
+where
+    String: Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as Database>::ArgumentBuffer<'q>,
+    ) -> Result<IsNull, BoxDynError> {
+        <String as Encode<'q, DB>>::encode(self.0.to_string(), buf)
+    }
+}
+
+impl<'r, DB: Database> Decode<'r, DB> for TextSnowflakeId
+where
+    &'r str: Decode<'r, DB>,
+{
+    fn decode(value: <DB as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let text = <&'r str as Decode<'r, DB>>::decode(value)?;
+        Ok(TextSnowflakeId(text.parse()?))
+    }
+}
+
+/// Returns the inclusive ID bounds covering every ID minted during `range`
+pub fn time_range_bounds(
+    range: Range<DateTime<Utc>>,
+    epoch: impl Into<Epoch>,
+) -> Result<RangeInclusive<SnowflakeId>, SnowflakeError> {
+    SnowflakeId::range_for(
+        range.start.timestamp_millis()..range.end.timestamp_millis(),
+        epoch,
+    )
+}
+
+/// Binds the bounds of a time window as two consecutive ID parameters, for
+/// `WHERE id BETWEEN $1 AND $2` filters that hit the primary key index
+pub trait BindIdRange: Sized {
+    /// Binds the first and last ID of `range` as the next two parameters
+    fn bind_id_range(self, range: RangeInclusive<SnowflakeId>) -> Self;
+
+    /// Binds the ID bounds covering every ID minted during `range`
+    fn bind_time_range(
+        self,
+        range: Range<DateTime<Utc>>,
+        epoch: impl Into<Epoch>,
+    ) -> Result<Self, SnowflakeError> {
+        Ok(self.bind_id_range(time_range_bounds(range, epoch)?))
+    }
+}
+
+impl<'q, DB> BindIdRange for Query<'q, DB, <DB as Database>::Arguments<'q>>
+where
+    DB: Database,
+    SnowflakeId: Encode<'q, DB> + Type<DB>,
+{
+    fn bind_id_range(self, range: RangeInclusive<SnowflakeId>) -> Self {
+        let (start, end) = range.into_inner();
+        self.bind(start).bind(end)
+    }
+}
+
+impl<'q, DB, O> BindIdRange for QueryAs<'q, DB, O, <DB as Database>::Arguments<'q>>
+where
+    DB: Database,
+    SnowflakeId: Encode<'q, DB> + Type<DB>,
+{
+    fn bind_id_range(self, range: RangeInclusive<SnowflakeId>) -> Self {
+        let (start, end) = range.into_inner();
+        self.bind(start).bind(end)
+    }
+}
+
+impl<'q, DB, O> BindIdRange for QueryScalar<'q, DB, O, <DB as Database>::Arguments<'q>>
+where
+    DB: Database,
+    SnowflakeId: Encode<'q, DB> + Type<DB>,
+{
+    fn bind_id_range(self, range: RangeInclusive<SnowflakeId>) -> Self {
+        let (start, end) = range.into_inner();
+        self.bind(start).bind(end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SNOWFLAKE_ID_EPOCH;
+
+    use chrono::TimeZone;
+
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[derive(sqlx::FromRow)]
+    struct Event {
+        id: SnowflakeId,
+        text_id: TextSnowflakeId,
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_roundtrip_and_time_range() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE events (id BIGINT PRIMARY KEY, text_id TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let at = |secs: i64| Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap();
+        let ids: Vec<SnowflakeId> = (0..3)
+            .map(|hour| {
+                let millis = at(hour * 3600).timestamp_millis() - SNOWFLAKE_ID_EPOCH;
+                SnowflakeId::from_component_parts(millis as u64, 1, 0)
+            })
+            .collect();
+        for &id in &ids {
+            sqlx::query("INSERT INTO events (id, text_id) VALUES (?, ?)")
+                .bind(id)
+                .bind(TextSnowflakeId(id))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let events: Vec<Event> =
+            sqlx::query_as("SELECT id, text_id FROM events WHERE id BETWEEN ? AND ? ORDER BY id")
+                .bind_time_range(at(1800)..at(3 * 3600), Epoch::TWITTER)
+                .unwrap()
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        let found: Vec<SnowflakeId> = events.iter().map(|event| event.id).collect();
+        assert_eq!(found, &ids[1..]);
+        assert!(events.iter().all(|event| event.text_id.0 == event.id));
+
+        let bounds = time_range_bounds(at(0)..at(1), Epoch::TWITTER).unwrap();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM events WHERE id BETWEEN ? AND ?")
+            .bind_id_range(bounds)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let bad: Result<TextSnowflakeId, _> = sqlx::query_scalar("SELECT 'not-an-id'")
+            .fetch_one(&pool)
+            .await;
+        assert!(bad.is_err());
+    }
+
+    #[test]
+    fn test_postgres_and_mysql_encoding() {
+        use sqlx::mysql::MySql;
+        use sqlx::postgres::{types::Oid, PgArgumentBuffer, PgTypeInfo, Postgres};
+        use sqlx::TypeInfo;
+
+        let id = SnowflakeId::from_component_parts(1 << 30, 7, 3);
+        let raw = i64::from(id);
+
+        // Postgres sends BIGINT as big-endian INT8 (oid 20) and text as TEXT
+        assert_eq!(
+            <SnowflakeId as Type<Postgres>>::type_info().oid(),
+            Some(Oid(20))
+        );
+        assert!(<SnowflakeId as Type<Postgres>>::compatible(
+            &PgTypeInfo::with_oid(Oid(20))
+        ));
+        assert!(<TextSnowflakeId as Type<Postgres>>::compatible(
+            &PgTypeInfo::with_oid(Oid(25))
+        ));
+        let mut buf = PgArgumentBuffer::default();
+        assert!(matches!(
+            Encode::<Postgres>::encode_by_ref(&id, &mut buf),
+            Ok(IsNull::No)
+        ));
+        assert_eq!(&buf[..], raw.to_be_bytes());
+        let mut buf = PgArgumentBuffer::default();
+        assert!(matches!(
+            Encode::<Postgres>::encode_by_ref(&TextSnowflakeId(id), &mut buf),
+            Ok(IsNull::No)
+        ));
+        assert_eq!(&buf[..], id.to_string().as_bytes());
+
+        // MySQL sends BIGINT little-endian and text length-prefixed
+        assert_eq!(<SnowflakeId as Type<MySql>>::type_info().name(), "BIGINT");
+        assert_eq!(
+            <TextSnowflakeId as Type<MySql>>::type_info().name(),
+            "VARCHAR"
+        );
+        let mut buf = Vec::new();
+        assert!(matches!(
+            Encode::<MySql>::encode_by_ref(&id, &mut buf),
+            Ok(IsNull::No)
+        ));
+        assert_eq!(buf, raw.to_le_bytes());
+        let mut buf = Vec::new();
+        assert!(matches!(
+            Encode::<MySql>::encode_by_ref(&TextSnowflakeId(id), &mut buf),
+            Ok(IsNull::No)
+        ));
+        let text = id.to_string();
+        assert_eq!(buf[0] as usize, text.len());
+        assert_eq!(&buf[1..], text.as_bytes());
+    }
+}
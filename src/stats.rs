@@ -0,0 +1,174 @@
+use hdrhistogram::Histogram;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Generation latency percentiles, including any time spent waiting for the
+/// clock or for the next tick after sequence exhaustion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub count: u64,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub p999: Duration,
+    pub max: Duration,
+}
+
+/// A point-in-time snapshot of a generator's statistics
+#[derive(Debug, Clone)]
+pub struct GeneratorStats {
+    latency: Histogram<u64>,
+    lock_wait: Histogram<u64>,
+    lock_hold: Histogram<u64>,
+}
+
+impl GeneratorStats {
+    /// Returns latency percentiles over every recorded `next_id` call
+    pub fn latency_percentiles(&self) -> LatencyPercentiles {
+        percentiles(&self.latency)
+    }
+
+    /// Returns the underlying HDR histogram of latencies in nanoseconds
+    pub fn latency_histogram(&self) -> &Histogram<u64> {
+        &self.latency
+    }
+
+    /// Returns percentiles of the time spent waiting to acquire the
+    /// generator's state lock.
+    ///
+    /// Only the sync [`SnowflakeGenerator`](crate::generator::SnowflakeGenerator)
+    /// records lock statistics. Long waits relative to
+    /// [`Self::lock_hold_percentiles`] mean threads are queueing on the mutex,
+    /// and a sharded generator is likely to help.
+    pub fn lock_wait_percentiles(&self) -> LatencyPercentiles {
+        percentiles(&self.lock_wait)
+    }
+
+    /// Returns percentiles of how long the generator's state lock was held
+    pub fn lock_hold_percentiles(&self) -> LatencyPercentiles {
+        percentiles(&self.lock_hold)
+    }
+
+    /// Returns the underlying HDR histogram of lock wait times in nanoseconds
+    pub fn lock_wait_histogram(&self) -> &Histogram<u64> {
+        &self.lock_wait
+    }
+
+    /// Returns the underlying HDR histogram of lock hold times in nanoseconds
+    pub fn lock_hold_histogram(&self) -> &Histogram<u64> {
+        &self.lock_hold
+    }
+}
+
+fn percentiles(histogram: &Histogram<u64>) -> LatencyPercentiles {
+    let at = |quantile: f64| Duration::from_nanos(histogram.value_at_quantile(quantile));
+    LatencyPercentiles {
+        count: histogram.len(),
+        p50: at(0.5),
+        p90: at(0.9),
+        p99: at(0.99),
+        p999: at(0.999),
+        max: Duration::from_nanos(histogram.max()),
+    }
+}
+
+pub(crate) struct StatsRecorder {
+    latency: Mutex<Histogram<u64>>,
+    lock_wait: Mutex<Histogram<u64>>,
+    lock_hold: Mutex<Histogram<u64>>,
+}
+
+impl StatsRecorder {
+    pub(crate) fn new() -> Self {
+        StatsRecorder {
+            latency: Mutex::new(new_histogram()),
+            lock_wait: Mutex::new(new_histogram()),
+            lock_hold: Mutex::new(new_histogram()),
+        }
+    }
+
+    pub(crate) fn record_latency(&self, elapsed: Duration) {
+        record(&self.latency, elapsed);
+    }
+
+    pub(crate) fn record_lock_wait(&self, elapsed: Duration) {
+        record(&self.lock_wait, elapsed);
+    }
+
+    pub(crate) fn record_lock_hold(&self, elapsed: Duration) {
+        record(&self.lock_hold, elapsed);
+    }
+
+    pub(crate) fn snapshot(&self) -> GeneratorStats {
+        GeneratorStats {
+            latency: lock(&self.latency).clone(),
+            lock_wait: lock(&self.lock_wait).clone(),
+            lock_hold: lock(&self.lock_hold).clone(),
+        }
+    }
+}
+
+fn new_histogram() -> Histogram<u64> {
+    // Three significant digits, auto-resizing to fit long waits
+    Histogram::new(3).expect("valid histogram precision")
+}
+
+fn lock(histogram: &Mutex<Histogram<u64>>) -> MutexGuard<'_, Histogram<u64>> {
+    histogram.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn record(histogram: &Mutex<Histogram<u64>>, elapsed: Duration) {
+    let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+    lock(histogram).saturating_record(nanos);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SnowflakeGenerator;
+    use std::thread;
+
+    #[test]
+    fn test_latency_stats() {
+        let generator = SnowflakeGenerator::new(1).unwrap();
+        for _ in 0..100 {
+            generator.next_id(|_| thread::yield_now());
+        }
+        generator
+            .next_id_bulk_checked(10, |_| thread::yield_now())
+            .unwrap();
+
+        let percentiles = generator.stats().latency_percentiles();
+        assert_eq!(percentiles.count, 101);
+        assert!(percentiles.p50 <= percentiles.p99);
+        assert!(percentiles.p99 <= percentiles.max);
+    }
+
+    #[test]
+    fn test_lock_contention_stats() {
+        use std::sync::Arc;
+
+        let generator = Arc::new(SnowflakeGenerator::new(1).unwrap());
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let generator = generator.clone();
+                thread::spawn(move || {
+                    for _ in 0..500 {
+                        generator.next_id(|_| thread::yield_now());
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let stats = generator.stats();
+        let wait = stats.lock_wait_percentiles();
+        let hold = stats.lock_hold_percentiles();
+        // Every attempt, including ones that came back pending, takes the lock
+        assert!(wait.count >= 2000);
+        assert_eq!(wait.count, hold.count);
+        assert!(hold.p50 <= hold.max);
+        assert_eq!(stats.latency_percentiles().count, 2000);
+    }
+}
@@ -0,0 +1,72 @@
+//! Locks guarding generator state.
+//!
+//! [`Mutex`] never reports poisoning. The fields that have to agree, the last
+//! tick and the sequence issued in it, are assigned back to back once the
+//! clock, the checkpoint store and every other call that could panic have
+//! returned; the remaining fields are independent marks that are each valid
+//! on their own. A thread panicking while holding the lock leaves the state
+//! as the last issued ID left it, so the next caller just carries on. The
+//! lock is `parking_lot`'s with the `parking_lot` feature and std's
+//! otherwise.
+//!
+//! Under `cfg(snowflake_loom)` it is loom's instead, so the model checker can
+//! explore every interleaving of the generators. The cfg is crate-specific
+//! because a global `--cfg loom` switches tokio and other dependencies to
+//! loom too. Run the models with
+//! `RUSTFLAGS="--cfg snowflake_loom" cargo test --lib loom_tests`.
+
+#[cfg(snowflake_loom)]
+use loom::sync as imp;
+#[cfg(all(not(snowflake_loom), feature = "parking_lot"))]
+use parking_lot as imp;
+#[cfg(all(not(snowflake_loom), not(feature = "parking_lot")))]
+use std::sync as imp;
+
+pub(crate) type MutexGuard<'a, T> = imp::MutexGuard<'a, T>;
+
+pub(crate) struct Mutex<T>(imp::Mutex<T>);
+
+impl<T> Mutex<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Mutex(imp::Mutex::new(value))
+    }
+
+    #[cfg(all(not(snowflake_loom), feature = "parking_lot"))]
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        self.0.lock()
+    }
+
+    #[cfg(any(snowflake_loom, not(feature = "parking_lot")))]
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::manual_clock;
+    use crate::SnowflakeGenerator;
+    use crate::SNOWFLAKE_ID_EPOCH;
+    use std::time::Duration;
+
+    #[test]
+    fn test_generator_survives_panic_while_locked() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let clock = manual_clock();
+        let generator =
+            SnowflakeGenerator::with_clock(1, SNOWFLAKE_ID_EPOCH, clock.clone()).unwrap();
+        let first = generator.generate().unwrap();
+
+        // Runs out of sequence space and calls back with the lock held
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            generator.reserve_block(5_000, |_| panic!("caller gave up"))
+        }));
+        assert!(result.is_err());
+
+        clock.advance(Duration::from_millis(1));
+        let next = generator.generate().unwrap();
+        assert!(next > first);
+        assert_eq!(next.timestamp(), first.timestamp() + 1);
+    }
+}
@@ -0,0 +1,49 @@
+//! Fixtures shared by the unit tests.
+
+use crate::checkpoint::Checkpoint;
+#[cfg(feature = "async")]
+use crate::checkpoint::{AsyncCheckpoint, CheckpointFuture};
+use crate::clock::ManualClock;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// A manual clock one second past the default epoch, where most tests start
+pub(crate) fn manual_clock() -> ManualClock {
+    ManualClock::at_epoch_offset(1_000)
+}
+
+/// A checkpoint kept in memory; clones share the stored mark
+#[derive(Clone, Default)]
+pub(crate) struct MemoryCheckpoint(Arc<Mutex<Option<i64>>>);
+
+impl MemoryCheckpoint {
+    pub(crate) fn new(mark: i64) -> Self {
+        MemoryCheckpoint(Arc::new(Mutex::new(Some(mark))))
+    }
+
+    pub(crate) fn mark(&self) -> Option<i64> {
+        *self.0.lock().unwrap()
+    }
+}
+
+impl Checkpoint for MemoryCheckpoint {
+    fn load(&self) -> io::Result<Option<i64>> {
+        Ok(self.mark())
+    }
+
+    fn store(&self, millis: i64) -> io::Result<()> {
+        *self.0.lock().unwrap() = Some(millis);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncCheckpoint for MemoryCheckpoint {
+    fn load(&self) -> CheckpointFuture<'_, Option<i64>> {
+        Box::pin(std::future::ready(Checkpoint::load(self)))
+    }
+
+    fn store(&self, millis: i64) -> CheckpointFuture<'_, ()> {
+        Box::pin(std::future::ready(Checkpoint::store(self, millis)))
+    }
+}
@@ -0,0 +1,138 @@
+//! Deterministic clocks and assertions for testing code built on the
+//! generators.
+//!
+//! Enable the `test-util` feature in `[dev-dependencies]`. A
+//! [`ManualClock`] only moves when the test moves it, so clock-backwards
+//! handling, tolerance waits and sequence exhaustion can be exercised
+//! exactly, without sleeping or depending on the host clock.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::test_util::{assert_pending, assert_rejected, assert_ready, ManualClock};
+//! use snowflake_id::SnowflakeGenerator;
+//! use std::time::Duration;
+//!
+//! let clock = ManualClock::at_epoch_offset(1_000);
+//! let generator = SnowflakeGenerator::builder(1).clock(clock.clone()).build().unwrap();
+//!
+//! let first = assert_ready(&generator);
+//! clock.rewind(Duration::from_millis(3));
+//! assert_eq!(assert_pending(&generator), Duration::from_millis(3));
+//! clock.rewind(Duration::from_secs(1));
+//! assert_rejected(&generator);
+//! clock.advance(Duration::from_secs(2));
+//! assert!(assert_ready(&generator) > first);
+//! ```
+
+use crate::error::SnowflakeError;
+use crate::generator::{SnowflakeGenerator, SnowflakeOperation};
+use crate::snowflake::Snowflake;
+use std::time::Duration;
+
+pub use crate::clock::ManualClock;
+
+/// Asserts the generator issues an ID right away, and returns it
+///
+/// # Panics
+/// Panics if the generator asks to wait or fails.
+#[track_caller]
+pub fn assert_ready<S: Snowflake>(generator: &SnowflakeGenerator<S>) -> S {
+    match generator.try_next_id() {
+        Ok(SnowflakeOperation::Ready(id)) => id,
+        Ok(SnowflakeOperation::Pending(wait)) => {
+            panic!("expected an id, but the generator asked to wait {:?}", wait)
+        }
+        Err(err) => panic!("expected an id, but generation failed: {}", err),
+    }
+}
+
+/// Asserts the generator asks to wait, and returns for how long
+///
+/// # Panics
+/// Panics if the generator issues an ID or fails.
+#[track_caller]
+pub fn assert_pending<S: Snowflake>(generator: &SnowflakeGenerator<S>) -> Duration {
+    match generator.try_next_id() {
+        Ok(SnowflakeOperation::Pending(wait)) => wait,
+        Ok(SnowflakeOperation::Ready(id)) => panic!("expected a wait, but got {:?}", id),
+        Err(err) => panic!("expected a wait, but generation failed: {}", err),
+    }
+}
+
+/// Asserts the generator refuses to generate because the clock moved
+/// backwards beyond its tolerance
+///
+/// # Panics
+/// Panics on any other outcome.
+#[track_caller]
+pub fn assert_rejected<S: Snowflake>(generator: &SnowflakeGenerator<S>) {
+    match generator.try_next_id() {
+        Err(SnowflakeError::ClockMovedBackwards { .. }) => {}
+        Err(err) => panic!("expected a clock rejection, but got error: {}", err),
+        Ok(SnowflakeOperation::Pending(wait)) => {
+            panic!("expected a clock rejection, but got a wait of {:?}", wait)
+        }
+        Ok(SnowflakeOperation::Ready(id)) => {
+            panic!("expected a clock rejection, but got {:?}", id)
+        }
+    }
+}
+
+/// Drains the rest of the current tick's sequence space, returning the IDs
+/// issued, so the next call has to wait for the clock
+///
+/// # Panics
+/// Panics if generation fails before the tick runs out.
+#[track_caller]
+pub fn exhaust_tick<S: Snowflake>(generator: &SnowflakeGenerator<S>) -> Vec<S> {
+    let mut ids = Vec::new();
+    loop {
+        match generator.try_next_id() {
+            Ok(SnowflakeOperation::Ready(id)) => ids.push(id),
+            Ok(SnowflakeOperation::Pending(_)) => return ids,
+            Err(err) => panic!("generation failed while exhausting the tick: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MAX_SEQUENCE;
+    use crate::SNOWFLAKE_ID_EPOCH;
+
+    #[test]
+    fn test_simulated_clock() {
+        use crate::SnowflakeGenerator;
+
+        let clock = ManualClock::at_epoch_offset(10);
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+        let first = assert_ready(&generator);
+        assert_eq!(first.timestamp(), 10);
+        assert_eq!(exhaust_tick(&generator).len() as u64, MAX_SEQUENCE);
+        assert_eq!(assert_pending(&generator), Duration::from_millis(1));
+
+        clock.advance(Duration::from_micros(999));
+        assert_pending(&generator);
+        clock.advance(Duration::from_micros(1));
+        assert_eq!(assert_ready(&generator).timestamp(), 11);
+
+        clock.rewind(Duration::from_millis(5));
+        assert_eq!(assert_pending(&generator), Duration::from_millis(5));
+        clock.rewind(Duration::from_millis(1));
+        assert_rejected(&generator);
+        clock.set(SNOWFLAKE_ID_EPOCH + 20);
+        assert_eq!(assert_ready(&generator).timestamp(), 20);
+
+        let micros = SnowflakeGenerator::builder(1)
+            .tick(Duration::from_micros(1))
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+        clock.advance(Duration::from_micros(7));
+        assert_eq!(assert_ready(&micros).timestamp(), 20_007);
+    }
+}
@@ -0,0 +1,166 @@
+//! Test helpers for custom layouts and ID newtypes.
+//!
+//! Enable the `testing` feature in `[dev-dependencies]` to run the crate's
+//! own serialization compatibility matrix against your types.
+
+use crate::snowflake::Snowflake;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+/// Number of pseudo-random IDs checked on top of the boundary values
+pub const RANDOM_CASES: usize = 256;
+
+/// Asserts that IDs of type `S` survive JSON, bincode, postcard and
+/// MessagePack round-trips unchanged.
+///
+/// Covers every combination of zero, one and maximum timestamp, machine ID
+/// and sequence, plus [`RANDOM_CASES`] IDs from a fixed-seed generator so
+/// failures are reproducible.
+///
+/// # Panics
+/// Panics naming the format and value on the first failed round-trip.
+///
+/// # Example
+/// ```
+/// use snowflake_id::testing::assert_serde_roundtrip;
+/// use snowflake_id::SnowflakeId;
+///
+/// assert_serde_roundtrip::<SnowflakeId>();
+/// ```
+pub fn assert_serde_roundtrip<S>()
+where
+    S: Snowflake + Serialize + DeserializeOwned,
+{
+    for id in boundary_values::<S>()
+        .into_iter()
+        .chain(random_values::<S>())
+    {
+        assert_roundtrip_value(&id);
+    }
+}
+
+/// Asserts that a single value survives every format in the matrix
+pub fn assert_roundtrip_value<T>(value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let json = serde_json::to_string(value).unwrap_or_else(|e| fail("json", value, e));
+    check("json", value, serde_json::from_str(&json));
+
+    let bytes = bincode::serialize(value).unwrap_or_else(|e| fail("bincode", value, e));
+    check("bincode", value, bincode::deserialize(&bytes));
+
+    let bytes = postcard::to_allocvec(value).unwrap_or_else(|e| fail("postcard", value, e));
+    check("postcard", value, postcard::from_bytes(&bytes));
+
+    let bytes = rmp_serde::to_vec(value).unwrap_or_else(|e| fail("messagepack", value, e));
+    check("messagepack", value, rmp_serde::from_slice(&bytes));
+}
+
+fn boundary_values<S: Snowflake>() -> Vec<S> {
+    let timestamps = [0, 1, S::max_timestamp() as u64];
+    let machine_ids = [0, 1, S::max_machine_id()];
+    let sequences = [0, 1, S::max_sequence()];
+
+    let mut values = Vec::new();
+    for &timestamp in &timestamps {
+        for &machine_id in &machine_ids {
+            for &sequence in &sequences {
+                values.push(S::from_component_parts(timestamp, machine_id, sequence));
+            }
+        }
+    }
+    values
+}
+
+fn random_values<S: Snowflake>() -> impl Iterator<Item = S> {
+    // xorshift64 with a fixed seed keeps failures reproducible
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    std::iter::repeat_with(move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    })
+    .take(RANDOM_CASES)
+    .map(|bits| {
+        S::from_component_parts(
+            (bits >> S::timestamp_shift()) & S::timestamp_mask(),
+            (bits >> S::sequence_bits()) & S::machine_id_mask(),
+            bits & S::sequence_mask(),
+        )
+    })
+}
+
+fn check<T, E>(format: &str, expected: &T, decoded: Result<T, E>)
+where
+    T: PartialEq + Debug,
+    E: Debug,
+{
+    match decoded {
+        Ok(decoded) => assert_eq!(
+            &decoded, expected,
+            "{} round-trip changed {:?}",
+            format, expected
+        ),
+        Err(e) => fail(format, expected, e),
+    }
+}
+
+fn fail<T: Debug, E: Debug>(format: &str, value: &T, error: E) -> ! {
+    panic!("{} round-trip failed for {:?}: {:?}", format, value, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeId;
+    use ::serde::{Deserialize, Deserializer, Serializer};
+
+    #[test]
+    fn test_serde_roundtrip_matrix() {
+        assert_serde_roundtrip::<SnowflakeId>();
+        assert_roundtrip_value(&Some(SnowflakeId::new(42).unwrap()));
+    }
+
+    #[test]
+    #[should_panic(expected = "round-trip")]
+    fn test_serde_roundtrip_detects_lossy_types() {
+        // Serializes through f64, so large IDs lose precision
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        struct Lossy(u64);
+
+        impl Serialize for Lossy {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_f64(self.0 as f64)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Lossy {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                f64::deserialize(deserializer).map(|value| Lossy(value as u64))
+            }
+        }
+
+        impl Snowflake for Lossy {
+            fn from_component_parts(timestamp: u64, machine_id: u64, sequence: u64) -> Self {
+                Lossy((timestamp << 22) | (machine_id << 12) | sequence)
+            }
+            fn id(&self) -> u64 {
+                self.0
+            }
+            fn timestamp_bits() -> u64 {
+                41
+            }
+            fn machine_id_bits() -> u64 {
+                10
+            }
+            fn sequence_bits() -> u64 {
+                12
+            }
+        }
+
+        assert_serde_roundtrip::<Lossy>();
+    }
+}
@@ -0,0 +1,205 @@
+//! IDs tagged with the entity they identify.
+//!
+//! [`TypedSnowflakeId<T>`] wraps a [`SnowflakeId`] with a zero-sized marker,
+//! so a `TypedSnowflakeId<User>` can't be passed where a
+//! `TypedSnowflakeId<Order>` is expected, while both come from one generator
+//! and serialize exactly like a plain [`SnowflakeId`].
+//!
+//! # Example
+//! ```
+//! use snowflake_id::{SnowflakeGenerator, TypedSnowflakeId};
+//!
+//! enum User {}
+//! enum Order {}
+//!
+//! let generator = SnowflakeGenerator::new(1).unwrap();
+//! let user: TypedSnowflakeId<User> = generator.next_typed().unwrap();
+//! let order = generator.next_typed::<Order>().unwrap();
+//! assert!(order.untyped() > user.untyped());
+//! ```
+
+use crate::error::SnowflakeError;
+use crate::snowflake::Snowflake;
+use crate::SnowflakeId;
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::any::type_name;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+/// A [`SnowflakeId`] tagged with the type `T` it identifies, see the
+/// [module docs](self)
+///
+/// `T` is only a marker: it needn't implement anything, and uninhabited
+/// enums make good tags.
+pub struct TypedSnowflakeId<T> {
+    id: SnowflakeId,
+    // fn() -> T keeps the wrapper Send, Sync and covariant whatever T is
+    _tag: PhantomData<fn() -> T>,
+}
+
+impl<T> TypedSnowflakeId<T> {
+    pub fn new(id: SnowflakeId) -> Self {
+        TypedSnowflakeId {
+            id,
+            _tag: PhantomData,
+        }
+    }
+
+    /// Returns the untagged ID
+    pub fn untyped(&self) -> SnowflakeId {
+        self.id
+    }
+}
+
+impl<T> Clone for TypedSnowflakeId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TypedSnowflakeId<T> {}
+
+impl<T> PartialEq for TypedSnowflakeId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for TypedSnowflakeId<T> {}
+
+impl<T> PartialOrd for TypedSnowflakeId<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for TypedSnowflakeId<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl<T> Hash for TypedSnowflakeId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for TypedSnowflakeId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TypedSnowflakeId<{}>({})", type_name::<T>(), self.id)
+    }
+}
+
+impl<T> fmt::Display for TypedSnowflakeId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.id, f)
+    }
+}
+
+impl<T> FromStr for TypedSnowflakeId<T> {
+    type Err = SnowflakeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self::new)
+    }
+}
+
+impl<T> From<SnowflakeId> for TypedSnowflakeId<T> {
+    fn from(id: SnowflakeId) -> Self {
+        Self::new(id)
+    }
+}
+
+impl<T> From<TypedSnowflakeId<T>> for SnowflakeId {
+    fn from(id: TypedSnowflakeId<T>) -> Self {
+        id.id
+    }
+}
+
+impl<T> Snowflake for TypedSnowflakeId<T> {
+    fn from_component_parts(timestamp_offset: u64, machine_id: u64, sequence: u64) -> Self {
+        Self::new(SnowflakeId::from_component_parts(
+            timestamp_offset,
+            machine_id,
+            sequence,
+        ))
+    }
+
+    fn id(&self) -> u64 {
+        Snowflake::id(&self.id)
+    }
+
+    fn timestamp_bits() -> u64 {
+        SnowflakeId::timestamp_bits()
+    }
+
+    fn machine_id_bits() -> u64 {
+        SnowflakeId::machine_id_bits()
+    }
+
+    fn sequence_bits() -> u64 {
+        SnowflakeId::sequence_bits()
+    }
+}
+
+impl<T> Serialize for TypedSnowflakeId<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.id.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for TypedSnowflakeId<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SnowflakeId::deserialize(deserializer).map(Self::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+
+    #[test]
+    fn test_typed_ids() {
+        use std::collections::HashSet;
+
+        enum User {}
+        enum Order {}
+
+        let generator = SnowflakeGenerator::new(9).unwrap();
+        let user = generator.next_typed::<User>().unwrap();
+        let order: TypedSnowflakeId<Order> = generator.next_typed().unwrap();
+        assert!(order.untyped() > user.untyped());
+        assert_eq!(user.machine_id(), 9);
+        assert_eq!(SnowflakeId::from(user), user.untyped());
+        assert_eq!(user.to_string(), user.untyped().to_string());
+        assert_eq!(
+            user.to_string().parse::<TypedSnowflakeId<User>>().unwrap(),
+            user
+        );
+        assert!(format!("{:?}", user).contains("User"));
+        assert_eq!(HashSet::from([user, user]).len(), 1);
+
+        let json = serde_json::to_string(&order).unwrap();
+        assert_eq!(json, serde_json::to_string(&order.untyped()).unwrap());
+        assert_eq!(
+            serde_json::from_str::<TypedSnowflakeId<Order>>(&json).unwrap(),
+            order
+        );
+        let bytes = postcard::to_allocvec(&order).unwrap();
+        assert_eq!(
+            postcard::from_bytes::<TypedSnowflakeId<Order>>(&bytes).unwrap(),
+            order
+        );
+
+        let tagged = crate::generator::SnowflakeGenerator::<TypedSnowflakeId<User>>::new(4)
+            .unwrap()
+            .generate()
+            .unwrap();
+        assert_eq!(tagged.machine_id(), 4);
+    }
+}
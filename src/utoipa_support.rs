@@ -0,0 +1,41 @@
+//! utoipa `ToSchema` for [`SnowflakeId`].
+//!
+//! Matches the JSON representation: a decimal string with the `int64` format,
+//! so generated clients don't parse IDs into lossy JavaScript numbers.
+
+use crate::SnowflakeId;
+use utoipa::openapi::schema::{KnownFormat, ObjectBuilder, Schema, SchemaFormat, Type};
+use utoipa::openapi::RefOr;
+use utoipa::{PartialSchema, ToSchema};
+
+impl PartialSchema for SnowflakeId {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int64)))
+            .pattern(Some("^[0-9]{1,19}$"))
+            .description(Some(
+                "A 64-bit snowflake ID, serialized as a decimal string",
+            ))
+            .examples(["1541815603606036480"])
+            .into()
+    }
+}
+
+impl ToSchema for SnowflakeId {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_schema() {
+        use utoipa::{PartialSchema, ToSchema};
+
+        assert_eq!(SnowflakeId::name(), "SnowflakeId");
+        let schema = serde_json::to_value(SnowflakeId::schema()).unwrap();
+        assert_eq!(schema["type"], "string");
+        assert_eq!(schema["format"], "int64");
+        assert_eq!(schema["pattern"], "^[0-9]{1,19}$");
+    }
+}
@@ -0,0 +1,269 @@
+//! Checking untrusted IDs against what this deployment could have minted.
+//!
+//! Any positive `i64` parses as a [`SnowflakeId`](crate::SnowflakeId), so
+//! an ID arriving at an API boundary may be forged or corrupted. A
+//! [`ValidationRules`] describes what real IDs look like: the machine IDs in
+//! use and the span of time IDs can come from. Checking an ID returns a
+//! [`ValidationReport`] naming every rule it breaks.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::validation::ValidationRules;
+//! use snowflake_id::{Epoch, SnowflakeGenerator};
+//! use std::time::Duration;
+//!
+//! let rules = ValidationRules::new(Epoch::TWITTER)
+//!     .machine_ids([1, 2, 3])
+//!     .not_before(1704067200000)
+//!     .max_future(Duration::from_secs(5));
+//!
+//! let id = SnowflakeGenerator::new(2).unwrap().generate().unwrap();
+//! assert!(id.validate(&rules).passed());
+//!
+//! let forged = SnowflakeGenerator::new(9).unwrap().generate().unwrap();
+//! let report = forged.validate(&rules);
+//! assert!(!report.passed());
+//! println!("{}", report);
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::epoch::Epoch;
+use crate::error::SnowflakeError;
+use crate::snowflake::Snowflake;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::Duration;
+
+/// What a genuine ID looks like, see the [module docs](self)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationRules {
+    epoch: Epoch,
+    machine_ids: Option<BTreeSet<u64>>,
+    not_before: Option<i64>,
+    not_after: Option<i64>,
+    max_future: Option<Duration>,
+}
+
+impl ValidationRules {
+    /// Starts with rules that only check the bit layout, decoding
+    /// timestamps against `epoch`
+    pub fn new(epoch: impl Into<Epoch>) -> Self {
+        ValidationRules {
+            epoch: epoch.into(),
+            machine_ids: None,
+            not_before: None,
+            not_after: None,
+            max_future: None,
+        }
+    }
+
+    /// Accepts only IDs minted by one of `machine_ids`
+    pub fn machine_ids(mut self, machine_ids: impl IntoIterator<Item = u64>) -> Self {
+        self.machine_ids = Some(machine_ids.into_iter().collect());
+        self
+    }
+
+    /// Rejects IDs created before `millis` since the Unix epoch, e.g. the
+    /// service's launch
+    pub fn not_before(mut self, millis: i64) -> Self {
+        self.not_before = Some(millis);
+        self
+    }
+
+    /// Rejects IDs created after `millis` since the Unix epoch
+    pub fn not_after(mut self, millis: i64) -> Self {
+        self.not_after = Some(millis);
+        self
+    }
+
+    /// Rejects IDs whose timestamp is more than `skew` ahead of the system
+    /// clock at the time of checking
+    pub fn max_future(mut self, skew: Duration) -> Self {
+        self.max_future = Some(skew);
+        self
+    }
+
+    /// Checks `id` against every rule
+    pub fn check<S: Snowflake>(&self, id: S) -> ValidationReport {
+        let mut checks = vec![run_check("layout", || {
+            if id.is_valid() {
+                Ok(())
+            } else {
+                Err(format!("{:#x} sets bits outside the layout", id.id()))
+            }
+        })];
+
+        if let Some(machine_ids) = &self.machine_ids {
+            checks.push(run_check("machine_id", || {
+                if machine_ids.contains(&id.machine_id()) {
+                    Ok(())
+                } else {
+                    Err(format!("machine id {} is not allowed", id.machine_id()))
+                }
+            }));
+        }
+
+        let timestamp = id.timestamp_with_epoch(self.epoch);
+        if self.not_before.is_some() || self.not_after.is_some() {
+            checks.push(run_check("timestamp_range", || {
+                match (self.not_before, self.not_after) {
+                    (Some(min), _) if timestamp < min => {
+                        Err(format!("timestamp {} is before {}", timestamp, min))
+                    }
+                    (_, Some(max)) if timestamp > max => {
+                        Err(format!("timestamp {} is after {}", timestamp, max))
+                    }
+                    _ => Ok(()),
+                }
+            }));
+        }
+
+        if let Some(skew) = self.max_future {
+            checks.push(run_check("not_in_future", || {
+                let ahead = timestamp.saturating_sub(SystemClock.now_millis());
+                if ahead <= i64::try_from(skew.as_millis()).unwrap_or(i64::MAX) {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "timestamp {} is {}ms in the future",
+                        timestamp, ahead
+                    ))
+                }
+            }));
+        }
+
+        ValidationReport { checks }
+    }
+}
+
+/// Outcome of a single validation rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    /// Why the rule failed, if it did
+    pub detail: Option<String>,
+}
+
+/// Structured result of checking an ID against [`ValidationRules`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub checks: Vec<ValidationCheck>,
+}
+
+impl ValidationReport {
+    /// Returns true if every rule passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// Returns the rules that failed
+    pub fn failures(&self) -> impl Iterator<Item = &ValidationCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+
+    /// Converts a failed report into [`SnowflakeError::InvalidId`] listing
+    /// the broken rules
+    pub fn into_result(self) -> Result<(), SnowflakeError> {
+        if self.passed() {
+            return Ok(());
+        }
+        let reasons: Vec<_> = self
+            .failures()
+            .map(|check| check.detail.as_deref().unwrap_or(check.name))
+            .collect();
+        Err(SnowflakeError::InvalidId(reasons.join("; ")))
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, check) in self.checks.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let status = if check.passed { "ok" } else { "FAILED" };
+            write!(f, "{}: {}", check.name, status)?;
+            if let Some(detail) = &check.detail {
+                write!(f, " ({})", detail)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn run_check(name: &'static str, check: impl FnOnce() -> Result<(), String>) -> ValidationCheck {
+    let result = check();
+    ValidationCheck {
+        name,
+        passed: result.is_ok(),
+        detail: result.err(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeId;
+    use chrono::Utc;
+
+    #[test]
+    fn test_validation() {
+        let now = Utc::now().timestamp_millis();
+        let rules = ValidationRules::new(Epoch::DISCORD)
+            .machine_ids([4, 5])
+            .not_before(now - 60_000)
+            .max_future(Duration::from_secs(5));
+        let at = |millis: i64, machine_id: u64| {
+            let first = SnowflakeId::first_for_timestamp(millis, Epoch::DISCORD).unwrap();
+            SnowflakeId::from_component_parts(first.timestamp() as u64, machine_id, 0)
+        };
+
+        let report = at(now, 4).validate(&rules);
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.checks.len(), 4);
+        assert!(report.into_result().is_ok());
+
+        let forged = at(now - 120_000, 9).validate(&rules);
+        let failed: Vec<_> = forged.failures().map(|check| check.name).collect();
+        assert_eq!(failed, ["machine_id", "timestamp_range"]);
+        assert!(matches!(
+            forged.into_result(),
+            Err(SnowflakeError::InvalidId(reason)) if reason.contains("machine id 9")
+        ));
+
+        let future = at(now + 60_000, 5).validate(&rules);
+        assert_eq!(
+            future
+                .failures()
+                .map(|check| check.name)
+                .collect::<Vec<_>>(),
+            ["not_in_future"]
+        );
+        let capped = ValidationRules::new(Epoch::DISCORD).not_after(now - 1);
+        assert!(!at(now, 5).validate(&capped).passed());
+
+        // Bits outside a narrow layout
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        struct Narrow(u64);
+        impl Snowflake for Narrow {
+            fn from_component_parts(timestamp: u64, machine_id: u64, sequence: u64) -> Self {
+                Narrow((timestamp << 8) | (machine_id << 4) | sequence)
+            }
+            fn id(&self) -> u64 {
+                self.0
+            }
+            fn timestamp_bits() -> u64 {
+                20
+            }
+            fn machine_id_bits() -> u64 {
+                4
+            }
+            fn sequence_bits() -> u64 {
+                4
+            }
+        }
+        let report = ValidationRules::new(Epoch::UNIX).check(Narrow(1 << 40));
+        assert_eq!(report.failures().next().unwrap().name, "layout");
+    }
+}
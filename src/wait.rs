@@ -0,0 +1,232 @@
+//! How generators wait for the clock.
+//!
+//! A generator has to wait when a tick's sequence space runs out or the clock
+//! steps back within tolerance. The [`WaitStrategy`] set on the
+//! [`GeneratorBuilder`](crate::builder::GeneratorBuilder) decides how: by
+//! [`SnowflakeGenerator::generate`], [`SnowflakeGenerator::generate_bulk`]
+//! and [`SnowflakeGenerator::iter`] on the sync generator, and by every
+//! method of the async generator.
+//!
+//! Any `Fn(Duration)` closure is also a strategy.
+//!
+//! # Example
+//! ```
+//! use snowflake_id::wait::BackoffWait;
+//! use snowflake_id::SnowflakeGenerator;
+//!
+//! let generator = SnowflakeGenerator::builder(1)
+//!     .wait(BackoffWait::new(100, 10))
+//!     .build()
+//!     .unwrap();
+//! let ids = generator.generate_bulk(10_000).unwrap();
+//! ```
+//!
+//! [`SnowflakeGenerator::generate`]: crate::generator::SnowflakeGenerator::generate
+//! [`SnowflakeGenerator::generate_bulk`]: crate::generator::SnowflakeGenerator::generate_bulk
+//! [`SnowflakeGenerator::iter`]: crate::generator::SnowflakeGenerator::iter
+
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::time::Duration;
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+use std::time::Instant;
+
+/// Future returned by [`WaitStrategy::wait_async`]
+#[cfg(feature = "async")]
+pub type WaitFuture<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// Waits for the clock on behalf of a generator.
+///
+/// `wait` is how long the generator expects to wait before the clock allows
+/// the next ID, and `attempt` counts the waits so far in the current call,
+/// starting at zero. Returning early is fine: the generator checks the clock
+/// again and asks for another wait if needed.
+pub trait WaitStrategy: Send + Sync {
+    /// Blocks the current thread
+    fn wait(&self, wait: Duration, attempt: u32);
+
+    /// Waits without blocking the async runtime. Defaults to the runtime's
+    /// timer.
+    #[cfg(feature = "async")]
+    fn wait_async(&self, wait: Duration, attempt: u32) -> WaitFuture<'_> {
+        let _ = attempt;
+        Box::pin(crate::rt::sleep(wait))
+    }
+}
+
+impl<F: Fn(Duration) + Send + Sync> WaitStrategy for F {
+    fn wait(&self, wait: Duration, _attempt: u32) {
+        self(wait)
+    }
+}
+
+/// Busy-spins until the wait is over, for the lowest latency at the cost of
+/// a core. Yields to the runtime instead when waiting asynchronously, as
+/// spinning would stall other tasks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpinWait;
+
+impl WaitStrategy for SpinWait {
+    fn wait(&self, wait: Duration, _attempt: u32) {
+        spin(wait);
+    }
+
+    #[cfg(feature = "async")]
+    fn wait_async(&self, _wait: Duration, _attempt: u32) -> WaitFuture<'_> {
+        Box::pin(crate::rt::yield_now())
+    }
+}
+
+/// Yields the thread or task and retries as soon as the scheduler comes back
+#[derive(Debug, Clone, Copy, Default)]
+pub struct YieldWait;
+
+impl WaitStrategy for YieldWait {
+    fn wait(&self, _wait: Duration, _attempt: u32) {
+        std::thread::yield_now();
+    }
+
+    #[cfg(feature = "async")]
+    fn wait_async(&self, _wait: Duration, _attempt: u32) -> WaitFuture<'_> {
+        Box::pin(crate::rt::yield_now())
+    }
+}
+
+/// Sleeps for the whole wait; the default.
+///
+/// With the `wasm` feature on `wasm32` targets the sync wait returns straight
+/// away to poll the clock again, as the browser main thread can't sleep.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SleepWait;
+
+impl WaitStrategy for SleepWait {
+    fn wait(&self, wait: Duration, _attempt: u32) {
+        #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+        std::thread::sleep(wait);
+
+        #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+        let _ = wait;
+    }
+}
+
+/// Spins for the first few waits of a call, then yields for a few more, then
+/// sleeps, so short stalls are ridden out quickly without burning a core
+/// through long ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BackoffWait {
+    spins: u32,
+    yields: u32,
+}
+
+impl BackoffWait {
+    /// Spins for the first `spins` waits and yields for the next `yields`
+    pub fn new(spins: u32, yields: u32) -> Self {
+        BackoffWait { spins, yields }
+    }
+}
+
+impl Default for BackoffWait {
+    fn default() -> Self {
+        Self::new(16, 16)
+    }
+}
+
+impl WaitStrategy for BackoffWait {
+    fn wait(&self, wait: Duration, attempt: u32) {
+        if attempt < self.spins {
+            spin(wait);
+        } else if attempt - self.spins < self.yields {
+            YieldWait.wait(wait, attempt);
+        } else {
+            SleepWait.wait(wait, attempt);
+        }
+    }
+
+    #[cfg(feature = "async")]
+    fn wait_async(&self, wait: Duration, attempt: u32) -> WaitFuture<'_> {
+        if attempt < self.spins.saturating_add(self.yields) {
+            Box::pin(crate::rt::yield_now())
+        } else {
+            Box::pin(crate::rt::sleep(wait))
+        }
+    }
+}
+
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+fn spin(wait: Duration) {
+    let deadline = Instant::now() + wait;
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+/// `Instant` panics in the browser, so this spins on `Date.now()`, which
+/// only has millisecond resolution
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+fn spin(wait: Duration) {
+    let deadline = js_sys::Date::now() + wait.as_secs_f64() * 1_000.0;
+    while js_sys::Date::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::manual_clock;
+    use crate::SnowflakeGenerator;
+    use crate::SnowflakeId;
+    use crate::MAX_SEQUENCE;
+
+    #[test]
+    fn test_wait_strategies() {
+        use crate::clock::ManualClock;
+        use std::sync::{Arc, Mutex};
+
+        // Each wait advances the clock, and attempts count from zero per call
+        struct Recording(ManualClock, Arc<Mutex<Vec<u32>>>);
+        impl WaitStrategy for Recording {
+            fn wait(&self, _wait: Duration, attempt: u32) {
+                self.1.lock().unwrap().push(attempt);
+                self.0.advance(Duration::from_millis(1));
+            }
+        }
+
+        let clock = manual_clock();
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .wait(Recording(clock.clone(), attempts.clone()))
+            .build()
+            .unwrap();
+        let per_tick = MAX_SEQUENCE as usize + 1;
+        let ids = generator.generate_bulk(2 * per_tick + 1).unwrap();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(*attempts.lock().unwrap(), [0, 1]);
+        for _ in 1..per_tick {
+            generator.generate().unwrap();
+        }
+        generator.generate().unwrap();
+        assert_eq!(*attempts.lock().unwrap(), [0, 1, 0]);
+
+        // Closures work as strategies too
+        let generator = SnowflakeGenerator::builder(1)
+            .clock(clock.clone())
+            .wait(move |_| clock.advance(Duration::from_millis(1)))
+            .build()
+            .unwrap();
+        assert_eq!(
+            generator.generate_bulk(2 * per_tick).unwrap().len(),
+            2 * per_tick
+        );
+
+        let generator = SnowflakeGenerator::builder(1)
+            .wait(BackoffWait::new(2, 2))
+            .build()
+            .unwrap();
+        let ids: Vec<SnowflakeId> = generator.iter().take(20_000).collect();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
@@ -0,0 +1,370 @@
+//! Compact binary encoding for batches of snowflake IDs.
+//!
+//! Batches produced by a single generator are nearly sorted, so storing each
+//! ID as a delta from its predecessor keeps most entries to one or two bytes
+//! instead of the ~20 bytes of a quoted JSON string.
+//!
+//! # Format (version 1)
+//!
+//! All multi-byte fixed-width fields are little-endian.
+//!
+//! | Offset | Size     | Field                                              |
+//! |--------|----------|----------------------------------------------------|
+//! | 0      | 4        | Magic bytes `b"SNFK"`                              |
+//! | 4      | 1        | Format version, currently `1`                      |
+//! | 5      | 1        | Flags, see below                                   |
+//! | 6      | 4        | Number of IDs (`u32`)                              |
+//! | 10     | 32       | Generator fingerprint, only if flag bit 0 is set   |
+//! | ...    | variable | First ID as an unsigned LEB128 varint              |
+//! | ...    | variable | Each following ID as a zigzag-encoded LEB128 delta |
+//!
+//! Flag bit 0 ([`FLAG_FINGERPRINT`]) marks a batch that carries the
+//! [`SnowflakeGenerator::fingerprint`] of the generator that minted it; the
+//! other bits are reserved and must be `0`.
+//!
+//! An empty batch is just the 10-byte header. Deltas are computed with
+//! wrapping arithmetic, so unsorted input round-trips exactly.
+//!
+//! [`SnowflakeGenerator::fingerprint`]: crate::generator::SnowflakeGenerator::fingerprint
+//!
+//! The format is stable: a decoder for version 1 will keep accepting any
+//! payload produced by this encoder.
+
+use crate::error::SnowflakeError;
+use crate::SnowflakeId;
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// Magic bytes at the start of every encoded batch
+pub const MAGIC: [u8; 4] = *b"SNFK";
+/// Current format version
+pub const VERSION: u8 = 1;
+/// Size of the fixed header in bytes
+pub const HEADER_LEN: usize = 10;
+/// Flag marking a batch whose header is followed by a generator fingerprint
+pub const FLAG_FINGERPRINT: u8 = 0b0000_0001;
+/// Size of the optional fingerprint in bytes
+pub const FINGERPRINT_LEN: usize = 32;
+
+const MAX_VARINT_LEN: usize = 10;
+
+/// Encodes a batch of IDs into the compact wire format
+///
+/// # Panics
+/// Panics if the batch holds more than `u32::MAX` IDs.
+///
+/// # Example
+/// ```
+/// use snowflake_id::{wire, SnowflakeId};
+///
+/// let ids = vec![SnowflakeId::new(100).unwrap(), SnowflakeId::new(101).unwrap()];
+/// let encoded = wire::encode_batch(&ids);
+/// assert_eq!(wire::decode_batch(&encoded).unwrap(), ids);
+/// ```
+pub fn encode_batch(ids: &[SnowflakeId]) -> Bytes {
+    encode(ids, None)
+}
+
+/// Encodes a batch of IDs along with the fingerprint of the generator that
+/// minted them, so the receiver can check it decodes them the same way
+///
+/// # Panics
+/// Panics if the batch holds more than `u32::MAX` IDs.
+pub fn encode_batch_with_fingerprint(ids: &[SnowflakeId], fingerprint: &[u8; 32]) -> Bytes {
+    encode(ids, Some(fingerprint))
+}
+
+fn encode(ids: &[SnowflakeId], fingerprint: Option<&[u8; 32]>) -> Bytes {
+    let count = u32::try_from(ids.len()).expect("batch exceeds u32::MAX ids");
+
+    let mut buf = BytesMut::with_capacity(HEADER_LEN + FINGERPRINT_LEN + ids.len() * 2);
+    buf.put_slice(&MAGIC);
+    buf.put_u8(VERSION);
+    buf.put_u8(if fingerprint.is_some() {
+        FLAG_FINGERPRINT
+    } else {
+        0
+    });
+    buf.put_u32_le(count);
+    if let Some(fingerprint) = fingerprint {
+        buf.put_slice(fingerprint);
+    }
+
+    let mut prev: Option<i64> = None;
+    for id in ids {
+        let value = id.id();
+        match prev {
+            None => put_varint(&mut buf, value as u64),
+            Some(prev) => put_varint(&mut buf, zigzag_encode(value.wrapping_sub(prev))),
+        }
+        prev = Some(value);
+    }
+
+    buf.freeze()
+}
+
+/// Decodes a batch previously produced by [`encode_batch`] or
+/// [`encode_batch_with_fingerprint`], ignoring any fingerprint
+pub fn decode_batch(input: &[u8]) -> Result<Vec<SnowflakeId>, SnowflakeError> {
+    decode_batch_with_fingerprint(input).map(|(_, ids)| ids)
+}
+
+/// Decodes a batch along with its generator fingerprint, if it carries one
+pub fn decode_batch_with_fingerprint(
+    input: &[u8],
+) -> Result<(Option<[u8; 32]>, Vec<SnowflakeId>), SnowflakeError> {
+    let Header {
+        fingerprint,
+        count,
+        payload: mut input,
+    } = decode_header(input)?;
+
+    // Every entry takes at least one byte, which bounds the allocation below
+    if count > input.len() {
+        return Err(wire_error("count exceeds payload length"));
+    }
+
+    let mut ids = Vec::with_capacity(count);
+    let mut prev: Option<i64> = None;
+    for _ in 0..count {
+        let raw = get_varint(&mut input)?;
+        let value = match prev {
+            None => {
+                if raw > i64::MAX as u64 {
+                    return Err(wire_error("id exceeds i64::MAX"));
+                }
+                raw as i64
+            }
+            Some(prev) => prev.wrapping_add(zigzag_decode(raw)),
+        };
+        if value < 0 {
+            return Err(wire_error("negative id"));
+        }
+        ids.push(SnowflakeId::new_unchecked(value));
+        prev = Some(value);
+    }
+
+    if !input.is_empty() {
+        return Err(wire_error("trailing bytes after batch"));
+    }
+
+    Ok((fingerprint, ids))
+}
+
+/// Reads only the generator fingerprint from an encoded batch's header,
+/// without decoding the IDs
+pub fn batch_fingerprint(input: &[u8]) -> Result<Option<[u8; 32]>, SnowflakeError> {
+    decode_header(input).map(|header| header.fingerprint)
+}
+
+struct Header<'a> {
+    fingerprint: Option<[u8; 32]>,
+    count: usize,
+    /// Everything after the header and fingerprint
+    payload: &'a [u8],
+}
+
+fn decode_header(input: &[u8]) -> Result<Header<'_>, SnowflakeError> {
+    if input.len() < HEADER_LEN {
+        return Err(wire_error("truncated header"));
+    }
+    if input[..4] != MAGIC {
+        return Err(wire_error("bad magic bytes"));
+    }
+    if input[4] != VERSION {
+        return Err(wire_error(format!("unsupported version {}", input[4])));
+    }
+    let flags = input[5];
+    if flags & !FLAG_FINGERPRINT != 0 {
+        return Err(wire_error("reserved flags must be zero"));
+    }
+
+    let count = u32::from_le_bytes([input[6], input[7], input[8], input[9]]) as usize;
+    let rest = &input[HEADER_LEN..];
+
+    if flags & FLAG_FINGERPRINT == 0 {
+        return Ok(Header {
+            fingerprint: None,
+            count,
+            payload: rest,
+        });
+    }
+    match rest.split_first_chunk::<FINGERPRINT_LEN>() {
+        Some((fingerprint, payload)) => Ok(Header {
+            fingerprint: Some(*fingerprint),
+            count,
+            payload,
+        }),
+        None => Err(wire_error("truncated fingerprint")),
+    }
+}
+
+fn wire_error(msg: impl Into<String>) -> SnowflakeError {
+    SnowflakeError::InvalidWireFormat(msg.into())
+}
+
+fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn zigzag_decode(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn put_varint(buf: &mut BytesMut, mut value: u64) {
+    while value >= 0x80 {
+        buf.put_u8((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.put_u8(value as u8);
+}
+
+fn get_varint(input: &mut &[u8]) -> Result<u64, SnowflakeError> {
+    let mut value = 0u64;
+    for i in 0..MAX_VARINT_LEN {
+        let Some((&byte, rest)) = input.split_first() else {
+            return Err(wire_error("truncated varint"));
+        };
+        *input = rest;
+
+        // The tenth byte may only contribute the final bit of a u64
+        if i == MAX_VARINT_LEN - 1 && byte > 1 {
+            return Err(wire_error("varint overflow"));
+        }
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(wire_error("varint overflow"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SnowflakeGenerator;
+    use std::thread;
+
+    // Small deterministic PRNG so the fuzz-style tests are reproducible
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_wire_roundtrip_generated() {
+        let generator = SnowflakeGenerator::new(7).unwrap();
+        let ids = generator
+            .next_id_bulk_checked(1000, |_| thread::yield_now())
+            .unwrap();
+
+        let encoded = encode_batch(&ids);
+        assert!(encoded.len() < ids.len() * 4);
+        assert_eq!(decode_batch(&encoded).unwrap(), ids);
+    }
+
+    #[test]
+    fn test_wire_empty_batch() {
+        let encoded = encode_batch(&[]);
+        assert_eq!(encoded.len(), HEADER_LEN);
+        assert!(decode_batch(&encoded).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_wire_fingerprint() {
+        let generator = SnowflakeGenerator::new(7).unwrap();
+        let ids = generator
+            .next_id_bulk_checked(10, |_| thread::yield_now())
+            .unwrap();
+        let fingerprint = generator.fingerprint();
+
+        let encoded = encode_batch_with_fingerprint(&ids, &fingerprint);
+        assert_eq!(batch_fingerprint(&encoded).unwrap(), Some(fingerprint));
+        assert_eq!(
+            decode_batch_with_fingerprint(&encoded).unwrap(),
+            (Some(fingerprint), ids.clone())
+        );
+        assert_eq!(decode_batch(&encoded).unwrap(), ids);
+
+        let plain = encode_batch(&ids);
+        assert_eq!(batch_fingerprint(&plain).unwrap(), None);
+        assert_eq!(encoded.len(), plain.len() + FINGERPRINT_LEN);
+
+        assert!(batch_fingerprint(&encoded[..HEADER_LEN + 5]).is_err());
+    }
+
+    #[test]
+    fn test_wire_fuzz_roundtrip() {
+        let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..500 {
+            let len = (rng.next() % 64) as usize;
+            let ids: Vec<SnowflakeId> = (0..len)
+                .map(|_| SnowflakeId::new_unchecked((rng.next() >> 1) as i64))
+                .collect();
+            assert_eq!(decode_batch(&encode_batch(&ids)).unwrap(), ids);
+        }
+
+        let extremes = vec![
+            SnowflakeId::new_unchecked(i64::MAX),
+            SnowflakeId::new_unchecked(0),
+            SnowflakeId::new_unchecked(i64::MAX),
+        ];
+        assert_eq!(decode_batch(&encode_batch(&extremes)).unwrap(), extremes);
+    }
+
+    #[test]
+    fn test_wire_fuzz_corrupted_input() {
+        let mut rng = XorShift(0xDEAD_BEEF_CAFE_F00D);
+        let ids: Vec<SnowflakeId> = (0..32)
+            .map(|_| SnowflakeId::new_unchecked((rng.next() >> 1) as i64))
+            .collect();
+        let encoded = encode_batch(&ids);
+
+        for _ in 0..2000 {
+            let mut corrupted = encoded.to_vec();
+            match rng.next() % 3 {
+                0 => corrupted.truncate((rng.next() as usize) % corrupted.len()),
+                1 => {
+                    let i = (rng.next() as usize) % corrupted.len();
+                    corrupted[i] ^= (rng.next() as u8) | 1;
+                }
+                _ => corrupted.push(rng.next() as u8),
+            }
+            // Must never panic; any successful decode must be non-negative
+            if let Ok(decoded) = decode_batch(&corrupted) {
+                assert!(decoded.iter().all(|id| id.id() >= 0));
+            }
+        }
+
+        for _ in 0..2000 {
+            let len = (rng.next() % 48) as usize;
+            let garbage: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+            let _ = decode_batch(&garbage);
+        }
+    }
+
+    #[test]
+    fn test_wire_rejects_bad_header() {
+        let encoded = encode_batch(&[SnowflakeId::new_unchecked(1)]).to_vec();
+
+        let mut bad_magic = encoded.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(
+            decode_batch(&bad_magic),
+            Err(SnowflakeError::InvalidWireFormat(_))
+        ));
+
+        let mut bad_version = encoded.clone();
+        bad_version[4] = 2;
+        assert!(decode_batch(&bad_version).is_err());
+
+        let mut huge_count = encoded;
+        huge_count[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(decode_batch(&huge_count).is_err());
+    }
+}
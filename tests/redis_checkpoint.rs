@@ -0,0 +1,34 @@
+//! Stores a generator checkpoint in a live Redis server.
+//!
+//! Needs a server, so this only builds when asked for:
+//! `REDIS_URL=redis://... RUSTFLAGS="--cfg snowflake_redis" cargo test --features redis --test redis_checkpoint`.
+#![cfg(all(feature = "redis", snowflake_redis))]
+
+use snowflake_id::checkpoint::{Checkpoint, RedisCheckpoint};
+use snowflake_id::SnowflakeGenerator;
+use std::time::Duration;
+
+#[test]
+fn test_redis_checkpoint() {
+    let url = std::env::var("REDIS_URL").unwrap();
+    let key = format!("snowflake-test-checkpoint-{}", std::process::id());
+    let client = redis::Client::open(url).unwrap();
+    let checkpoint = RedisCheckpoint::new(client.clone(), key);
+    assert_eq!(checkpoint.load().unwrap(), None);
+
+    let generator = SnowflakeGenerator::builder(1)
+        .checkpoint(checkpoint.clone(), Duration::from_secs(1))
+        .build()
+        .unwrap();
+    let id = generator.generate().unwrap();
+    let mark = checkpoint.load().unwrap().unwrap();
+    assert!(mark >= id.timestamp_with_epoch(generator.epoch()) + 1_000);
+
+    checkpoint.store(mark + 1).unwrap();
+    assert_eq!(checkpoint.load().unwrap(), Some(mark + 1));
+    let mut connection = client.get_connection().unwrap();
+    redis::cmd("DEL")
+        .arg(checkpoint.key())
+        .exec(&mut connection)
+        .unwrap();
+}
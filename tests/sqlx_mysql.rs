@@ -0,0 +1,86 @@
+//! Runs the `sqlx_extras` schema and queries against a live MySQL server.
+//!
+//! `query_as!` checks its queries against `DATABASE_URL` at compile time, so
+//! these only build when asked for:
+//! `DATABASE_URL=mysql://... RUSTFLAGS="--cfg snowflake_sqlx_mysql" cargo test --features sqlx-extras --test sqlx_mysql`.
+//! The `events` table must exist when compiling, created with the schema
+//! from the [`sqlx_extras`](snowflake_id::sqlx_extras) docs plus a
+//! `text_id TEXT NOT NULL` column.
+#![cfg(all(feature = "sqlx-extras", snowflake_sqlx_mysql))]
+
+use chrono::{TimeZone, Utc};
+use snowflake_id::sqlx_extras::{BindIdRange, TextSnowflakeId};
+use snowflake_id::{Epoch, SnowflakeId, SNOWFLAKE_ID_EPOCH};
+use sqlx::mysql::MySqlPoolOptions;
+
+#[derive(Debug, PartialEq, sqlx::FromRow)]
+struct Event {
+    id: SnowflakeId,
+    text_id: TextSnowflakeId,
+    payload: String,
+}
+
+#[tokio::test]
+async fn test_mysql_primary_key() {
+    let url = std::env::var("DATABASE_URL").unwrap();
+    let pool = MySqlPoolOptions::new()
+        .max_connections(1)
+        .connect(&url)
+        .await
+        .unwrap();
+    let mut tx = pool.begin().await.unwrap();
+    sqlx::query("DELETE FROM events")
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+
+    let at = |hours: i64| Utc.timestamp_opt(1_700_000_000 + hours * 3600, 0).unwrap();
+    let ids: Vec<SnowflakeId> = (0..3)
+        .map(|hour| {
+            let millis = at(hour).timestamp_millis() - SNOWFLAKE_ID_EPOCH;
+            SnowflakeId::from_component_parts(millis as u64, 1, 0)
+        })
+        .collect();
+    for &id in &ids {
+        sqlx::query!(
+            "INSERT INTO events (id, text_id, payload) VALUES (?, ?, ?)",
+            id as SnowflakeId,
+            TextSnowflakeId(id) as TextSnowflakeId,
+            "hello",
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    }
+
+    let first = sqlx::query_as!(
+        Event,
+        r#"SELECT id AS "id: SnowflakeId", text_id AS "text_id: TextSnowflakeId", payload
+           FROM events WHERE id = ?"#,
+        ids[0] as SnowflakeId,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .unwrap();
+    assert_eq!(
+        first,
+        Event {
+            id: ids[0],
+            text_id: TextSnowflakeId(ids[0]),
+            payload: "hello".to_string(),
+        }
+    );
+
+    let recent: Vec<Event> = sqlx::query_as(
+        "SELECT id, text_id, payload FROM events WHERE id BETWEEN ? AND ? ORDER BY id",
+    )
+    .bind_time_range(at(1)..at(3), Epoch::TWITTER)
+    .unwrap()
+    .fetch_all(&mut *tx)
+    .await
+    .unwrap();
+    let found: Vec<SnowflakeId> = recent.iter().map(|event| event.id).collect();
+    assert_eq!(found, &ids[1..]);
+
+    tx.rollback().await.unwrap();
+}